@@ -0,0 +1,66 @@
+# A tiny stack-based calculator
+#
+# A program is a list of (opcode, argument) instructions run against a
+# fixed-size stack. Two numbers are read from stdin and can be pushed with
+# LOAD (argument 0 or 1). Each PRINT pops and prints the top of the stack.
+#
+# Opcodes: 0 PUSH, 1 LOAD, 2 ADD, 3 SUB, 4 MUL, 5 FLOORDIV, 6 FACT,
+#          7 DUP, 8 PRINT, 9 JUMP_IF_ZERO (to the argument's index)
+
+def factorial(n):
+    if n <= 1:
+        return 1
+    return n * factorial(n - 1)
+
+def run(program, inputs):
+    stack = [0, 0, 0, 0, 0, 0, 0, 0]
+    sp = 0
+    pc = 0
+    while pc < len(program):
+        op, arg = program[pc]
+        pc += 1
+        if op == 0:
+            stack[sp] = arg
+            sp += 1
+        elif op == 1:
+            stack[sp] = inputs[arg]
+            sp += 1
+        elif op == 7:
+            stack[sp] = stack[sp - 1]
+            sp += 1
+        elif op == 6:
+            stack[sp - 1] = factorial(stack[sp - 1])
+        elif op == 8:
+            sp -= 1
+            print(stack[sp])
+        elif op == 9:
+            sp -= 1
+            if stack[sp] == 0:
+                pc = arg
+        else:
+            sp -= 1
+            b = stack[sp]
+            a = stack[sp - 1]
+            if op == 2:
+                stack[sp - 1] = a + b
+            elif op == 3:
+                stack[sp - 1] = a - b
+            elif op == 4:
+                stack[sp - 1] = a * b
+            else:
+                stack[sp - 1] = a // b
+    return sp
+
+x = input()
+y = input()
+inputs = [x, y]
+
+# (x + y) * 2
+run([(1, 0), (1, 1), (2, 0), (0, 2), (4, 0), (8, 0)], inputs)
+
+# factorial(y) // x
+run([(1, 1), (6, 0), (1, 0), (5, 0), (8, 0)], inputs)
+
+# Count down from x to 1 with a loop
+left = run([(1, 0), (7, 0), (8, 0), (0, 1), (3, 0), (7, 0), (9, 9), (0, 0), (9, 1)], inputs)
+print(left)
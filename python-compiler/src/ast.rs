@@ -5,12 +5,14 @@ pub enum BinOp {
     Sub,
     Mul,
     Div,
-    Mod,    // %
-    BitAnd, // &
-    BitOr,  // |
-    BitXor, // ^
-    LShift, // <<
-    RShift, // >>
+    FloorDiv, // //
+    Mod,      // %
+    BitAnd,   // &
+    BitOr,    // |
+    BitXor,   // ^
+    LShift,   // <<
+    RShift,   // >>
+    Pow,      // **
 }
 
 /// The set of supported comparison operators.
@@ -22,6 +24,17 @@ pub enum CmpOp {
     Gt,    // >
     LtE,   // <=
     GtE,   // >=
+    Is,    // is (identity; only meaningful for None)
+    IsNot, // is not
+    In,    // in (membership in a string, list, tuple or dict)
+    NotIn, // not in
+}
+
+/// The set of supported boolean operators.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BoolOp {
+    And, // and
+    Or,  // or
 }
 
 /// The set of supported unary operators.
@@ -33,6 +46,44 @@ pub enum UnaryOp {
     USub,   // -x (unary minus)
 }
 
+/// A piece of an f-string.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FStringPart {
+    /// Literal text between replacement fields.
+    Literal(String),
+    /// A replacement field `{value}` or `{value:spec}`.
+    Value {
+        value: IRExpr,
+        spec: Option<FormatSpec>,
+    },
+}
+
+/// A format spec `[<|>][0][width][.precision][type]` of an f-string replacement
+/// field, where the type is one of `d`, `f`, `e`, `g` or `s`. Without a type the
+/// value is converted like `str()`: strings align left and other values right.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FormatSpec {
+    /// An explicit alignment, `'<'` or `'>'`.
+    pub align: Option<char>,
+    /// Pad numbers with zeros instead of spaces (`0` flag).
+    pub zero_pad: bool,
+    /// The minimum field width.
+    pub width: Option<usize>,
+    /// Digits after the decimal point, or the maximum length of a string.
+    pub precision: Option<usize>,
+    /// The presentation type.
+    pub kind: Option<char>,
+}
+
+/// What a list comprehension iterates over.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ComprehensionSource {
+    /// `range(end)` or `range(start, end)`, counting up by one.
+    Range { start: IRExpr, end: IRExpr },
+    /// The elements of a list or tuple, or the characters of a string.
+    Iterable(IRExpr),
+}
+
 /// A simplified Intermediate Representation for expressions.
 #[derive(Debug, Clone, PartialEq)]
 pub enum IRExpr {
@@ -50,8 +101,12 @@ pub enum IRExpr {
         left: Box<IRExpr>,
         right: Box<IRExpr>,
     },
-    /// A function call.
-    Call { func: String, args: Vec<IRExpr> },
+    /// A function call with positional and keyword (`name=value`) arguments.
+    Call {
+        func: String,
+        args: Vec<IRExpr>,
+        keywords: Vec<(String, IRExpr)>,
+    },
     /// An input() call to read from stdin.
     Input,
     /// A len() call to get the length of a value.
@@ -64,31 +119,132 @@ pub enum IRExpr {
     },
     /// A string literal.
     StringLiteral(String),
+    /// An f-string: literal text interleaved with formatted values, producing a new
+    /// string. One without replacement fields lowers to a plain string literal.
+    FString(Vec<FStringPart>),
+    /// The None literal.
+    NoneLiteral,
+    /// A conditional expression `then_value if condition else else_value`.
+    IfExp {
+        condition: Box<IRExpr>,
+        then_value: Box<IRExpr>,
+        else_value: Box<IRExpr>,
+    },
+    /// A short-circuiting `and` / `or` over two or more values. Like Python, it yields
+    /// the operand that decided the result rather than a bool.
+    BoolOp { op: BoolOp, values: Vec<IRExpr> },
+    /// An assignment expression `target := value`, which stores and yields the value.
+    NamedExpr { target: String, value: Box<IRExpr> },
+    /// A tuple literal, e.g. `a, b` or `(1, 2)`.
+    Tuple(Vec<IRExpr>),
     /// A unary operation.
     UnaryOp { op: UnaryOp, operand: Box<IRExpr> },
     /// A list literal.
     List(Vec<IRExpr>),
+    /// A list comprehension `[element for var in source if condition ...]` with a
+    /// single `for` clause. The element is evaluated afresh on every iteration, and
+    /// `var` is local to the comprehension.
+    ListComp {
+        element: Box<IRExpr>,
+        var: String,
+        source: Box<ComprehensionSource>,
+        conditions: Vec<IRExpr>,
+    },
+    /// A dict literal `{key: value, ...}`, entries in insertion order.
+    Dict(Vec<(IRExpr, IRExpr)>),
+    /// A set literal `{element, ...}`; repeated elements are kept once.
+    Set(Vec<IRExpr>),
     /// List indexing.
     Index {
         list: Box<IRExpr>,
         index: Box<IRExpr>,
     },
+    /// A reversed() call, producing a new list with the elements in reverse order.
+    Reversed(Box<IRExpr>),
+    /// A sorted() call, producing a new list of numbers in ascending order, or of any
+    /// elements ordered by the numbers the named `key` function returns for them.
+    Sorted {
+        list: Box<IRExpr>,
+        key: Option<String>,
+    },
+    /// A zip() call pairing up the elements of two lists, producing a new list of
+    /// 2-tuples as long as the shorter list.
+    Zip(Box<IRExpr>, Box<IRExpr>),
+    /// A hex(), oct() or bin() call: the digits of an integer in base 16, 8 or 2
+    /// after a `0x`, `0o` or `0b` prefix (and a `-` for negatives), as a new string.
+    IntToBase { base: u32, value: Box<IRExpr> },
+    /// A type() call: the `<class '...'>` text naming the type of its argument, as a
+    /// new string (there are no type objects to return).
+    TypeName(Box<IRExpr>),
+    /// A sum() call adding up the numbers in a list or tuple: an int unless one of
+    /// them is a float.
+    Sum(Box<IRExpr>),
+    /// An abs() call: the magnitude of a number, an int for ints and bools and a
+    /// float for floats.
+    Abs(Box<IRExpr>),
+    /// An int() call converting a number to an int. Floats are truncated toward zero
+    /// (`int(-2.9)` is -2), unlike `//`, which floors.
+    Int(Box<IRExpr>),
+    /// A min() or max() call over its arguments, or over the elements of a list or
+    /// tuple when given one argument. The result is the first smallest (or largest)
+    /// value itself, so it keeps its type.
+    MinMax { is_max: bool, args: Vec<IRExpr> },
+    /// An isclose() call: whether two numbers are equal within a relative tolerance
+    /// of 1e-9, like `math.isclose` with its default tolerances.
+    IsClose(Box<IRExpr>, Box<IRExpr>),
+    /// A map() call applying the named function to each element, producing a new list.
+    Map { func: String, list: Box<IRExpr> },
+    /// A filter() call keeping the elements for which the named function is truthy,
+    /// producing a new list.
+    Filter { func: String, list: Box<IRExpr> },
+    /// An open() call, producing a file object. The mode defaults to `"r"`.
+    Open {
+        path: Box<IRExpr>,
+        mode: Box<IRExpr>,
+    },
+    /// A method call `object.method(args...)`.
+    MethodCall {
+        object: Box<IRExpr>,
+        method: String,
+        args: Vec<IRExpr>,
+    },
 }
 
 /// A simplified Intermediate Representation for statements.
 #[derive(Debug, Clone, PartialEq)]
 pub enum IRStmt {
-    /// A print statement.
-    Print(Vec<IRExpr>),
+    /// A print statement. `sep` and `end` hold the `sep=` / `end=` keyword values,
+    /// which must evaluate to strings (or None); `None` means Python's defaults (a
+    /// space and a newline). `stderr` is set by `file=sys.stderr`.
+    Print {
+        args: Vec<IRExpr>,
+        sep: Option<IRExpr>,
+        end: Option<IRExpr>,
+        stderr: bool,
+    },
     /// An assignment statement.
     Assign { target: String, value: IRExpr },
+    /// An indexed assignment: `list[index] = value`.
+    /// The list may itself be an index expression (`grid[i][j] = v`).
+    IndexAssign {
+        list: IRExpr,
+        index: IRExpr,
+        value: IRExpr,
+    },
+    /// A tuple-unpacking assignment: `a, b = value` (value is a tuple or list).
+    UnpackAssign { targets: Vec<String>, value: IRExpr },
     /// An expression statement (evaluates an expression and discards the result).
     ExprStmt(IRExpr),
     /// A function definition.
+    /// Keyword-only parameters (declared after `*`) follow the positional ones, and
+    /// `kwarg` names the `**kwargs` dict collecting any other keyword arguments.
     FunctionDef {
         name: String,
         params: Vec<String>,
         defaults: Vec<Option<IRExpr>>,
+        kwonly_params: Vec<String>,
+        kwonly_defaults: Vec<Option<IRExpr>>,
+        kwarg: Option<String>,
         body: Vec<IRStmt>,
     },
     /// A return statement.
@@ -99,20 +255,184 @@ pub enum IRStmt {
         then_body: Vec<IRStmt>,
         else_body: Vec<IRStmt>,
     },
-    /// A while loop.
+    /// A while loop. The else body runs when the loop ends without `break`.
     While {
         condition: IRExpr,
         body: Vec<IRStmt>,
+        else_body: Vec<IRStmt>,
     },
-    /// A for loop (range-based only).
+    /// A for loop (range-based only). The else body runs when the loop ends without `break`.
+    /// `step` is evaluated once, before the first iteration.
     For {
         var: String,
         start: IRExpr,
         end: IRExpr,
+        step: IRExpr,
+        body: Vec<IRStmt>,
+        else_body: Vec<IRStmt>,
+    },
+    /// A for-each loop over the elements of a list or the characters of a string.
+    /// The else body runs when the loop ends without `break`.
+    ForEach {
+        var: String,
+        iter: IRExpr,
         body: Vec<IRStmt>,
+        else_body: Vec<IRStmt>,
     },
+    /// A try/except statement. The handler runs for a runtime error raised in the
+    /// body whose type is one of `exceptions` (any error when it is empty, as for a
+    /// bare `except:`); other errors propagate. The else body runs when the body
+    /// completes without an error.
+    Try {
+        body: Vec<IRStmt>,
+        exceptions: Vec<String>,
+        handler: Vec<IRStmt>,
+        else_body: Vec<IRStmt>,
+    },
+    /// A `global` declaration: inside a function, the names refer to module-level
+    /// variables rather than locals.
+    Global(Vec<String>),
+    /// A `del` statement unbinding variables; reading one afterwards (including
+    /// through augmented assignment) is an undefined-variable error until it is
+    /// assigned again.
+    Delete(Vec<String>),
+    /// A raise statement: `raise Name` or `raise Name(message)`. A bare `raise`
+    /// (no exception) re-raises the most recent runtime error.
+    Raise {
+        exception: Option<String>,
+        message: Option<IRExpr>,
+    },
+    /// An `assert condition` or `assert condition, message` statement, raising an
+    /// AssertionError when the condition is falsy. Compiling with asserts disabled
+    /// drops it without evaluating either expression.
+    Assert {
+        condition: IRExpr,
+        message: Option<IRExpr>,
+    },
+    /// An `exit(code)` / `quit(code)` call ending the program with the given status
+    /// (an int, or None for 0).
+    Exit(IRExpr),
     /// A break statement.
     Break,
     /// A continue statement.
     Continue,
+    /// A pass statement (does nothing).
+    Pass,
+}
+
+/// Convenience constructors for building IR by hand, e.g. when using the crate as a
+/// code generation backend without Python source. The binary builds its IR through
+/// lowering, hence the `dead_code` allowances.
+#[allow(dead_code)]
+impl IRExpr {
+    /// An integer literal.
+    pub fn int(value: i64) -> Self {
+        IRExpr::Constant(value)
+    }
+
+    /// A float literal.
+    pub fn float(value: f64) -> Self {
+        IRExpr::Float(value)
+    }
+
+    /// A boolean literal.
+    pub fn bool(value: bool) -> Self {
+        IRExpr::Bool(value)
+    }
+
+    /// A string literal.
+    pub fn string(value: impl Into<String>) -> Self {
+        IRExpr::StringLiteral(value.into())
+    }
+
+    /// A variable reference.
+    pub fn var(name: impl Into<String>) -> Self {
+        IRExpr::Variable(name.into())
+    }
+
+    /// A binary operation `left op right`.
+    pub fn binary(op: BinOp, left: IRExpr, right: IRExpr) -> Self {
+        IRExpr::BinaryOp {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    /// A comparison `left op right`.
+    pub fn compare(op: CmpOp, left: IRExpr, right: IRExpr) -> Self {
+        IRExpr::Comparison {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    /// A unary operation.
+    pub fn unary(op: UnaryOp, operand: IRExpr) -> Self {
+        IRExpr::UnaryOp {
+            op,
+            operand: Box::new(operand),
+        }
+    }
+
+    /// A call with positional arguments only.
+    pub fn call(func: impl Into<String>, args: Vec<IRExpr>) -> Self {
+        IRExpr::Call {
+            func: func.into(),
+            args,
+            keywords: Vec::new(),
+        }
+    }
+
+    /// Indexing `list[index]`.
+    pub fn index(list: IRExpr, index: IRExpr) -> Self {
+        IRExpr::Index {
+            list: Box::new(list),
+            index: Box::new(index),
+        }
+    }
+}
+
+#[allow(dead_code)]
+impl IRStmt {
+    /// A print statement with the default separator and line ending.
+    pub fn print(args: Vec<IRExpr>) -> Self {
+        IRStmt::Print {
+            args,
+            sep: None,
+            end: None,
+            stderr: false,
+        }
+    }
+
+    /// An assignment `target = value`.
+    pub fn assign(target: impl Into<String>, value: IRExpr) -> Self {
+        IRStmt::Assign {
+            target: target.into(),
+            value,
+        }
+    }
+
+    /// An if statement without an else body.
+    pub fn if_then(condition: IRExpr, then_body: Vec<IRStmt>) -> Self {
+        IRStmt::If {
+            condition,
+            then_body,
+            else_body: Vec::new(),
+        }
+    }
+
+    /// A function definition with positional parameters and no defaults.
+    pub fn function(name: impl Into<String>, params: &[&str], body: Vec<IRStmt>) -> Self {
+        IRStmt::FunctionDef {
+            name: name.into(),
+            params: params.iter().map(|param| param.to_string()).collect(),
+            defaults: vec![None; params.len()],
+            kwonly_params: Vec::new(),
+            kwonly_defaults: Vec::new(),
+            kwarg: None,
+            body,
+        }
+    }
 }
@@ -1,17 +1,24 @@
-use crate::ast::{IRExpr, IRStmt};
+use crate::ast::{BinOp, IRExpr, IRStmt, UnaryOp};
 use crate::compiler::generators::{expression, statement};
-use crate::compiler::runtime::{FormatStrings, Runtime};
-use crate::compiler::values::{ValueManager, TYPE_TAG_INT, TYPE_TAG_STRING};
-use inkwell::builder::Builder;
+use crate::compiler::runtime::{FormatStrings, Runtime, JMP_BUF_SIZE};
+use crate::compiler::values::{
+    PayloadWidth, ValueManager, TYPE_TAG_BOOL, TYPE_TAG_DICT, TYPE_TAG_INT, TYPE_TAG_LIST,
+    TYPE_TAG_SET, TYPE_TAG_STRING, TYPE_TAG_TUPLE,
+};
+use inkwell::builder::{Builder, BuilderError};
 use inkwell::context::Context;
 use inkwell::module::Module;
 use inkwell::passes::PassBuilderOptions;
 use inkwell::targets::{CodeModel, InitializationConfig, RelocMode, Target, TargetMachine};
-use inkwell::values::{FloatValue, FunctionValue, IntValue, PointerValue};
+use inkwell::values::{
+    BasicMetadataValueEnum, CallSiteValue, FloatValue, FunctionValue, IntValue, PointerValue,
+    ValueKind,
+};
 use inkwell::FloatPredicate;
 use inkwell::OptimizationLevel;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Once;
+use std::time::Instant;
 use thiserror::Error;
 
 static INIT_TARGETS: Once = Once::new();
@@ -22,6 +29,108 @@ pub enum CodeGenError {
     ModuleVerification(String),
     #[error("Undefined variable: {0}")]
     UndefinedVariable(String),
+    #[error("Invalid arguments: {0}")]
+    InvalidArguments(String),
+    #[error("Unsupported method: {0}")]
+    UnsupportedMethod(String),
+}
+
+/// The statically inferred return type of a user function.
+///
+/// Functions whose every `return` yields an integer are compiled to return the raw
+/// integer payload instead of a NaN-boxed PyObject; the call site re-tags the value
+/// with a constant INT tag, which lets LLVM fold away the tag dispatch on the result.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReturnType {
+    /// Always returns an integer (returned unboxed as a raw i64).
+    Int,
+    /// Return type unknown at compile time (returned as a NaN-boxed PyObject).
+    PyObject,
+}
+
+/// Size of a compiled LLVM module, as reported by `--stats`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct ModuleStats {
+    /// Functions with a body; external declarations such as `printf` are not counted.
+    pub functions: usize,
+    pub basic_blocks: usize,
+    pub instructions: usize,
+}
+
+/// Runs the LLVM verifier over a module, returning its diagnostic on failure.
+pub fn verify_module(module: &Module<'_>) -> Result<(), CodeGenError> {
+    module
+        .verify()
+        .map_err(|message| CodeGenError::ModuleVerification(message.to_string()))
+}
+
+/// Runs the LLVM `default<O{level}>` pipeline (new pass manager, LLVM 18+) over a
+/// module, verifying the IR after each pass. Level 0 skips the passes entirely but
+/// still runs the verifier, so invalid IR is reported the same way at every level.
+/// Levels above 3 are treated as 3.
+pub fn optimize_module(module: &Module<'_>, level: u8) -> Result<(), CodeGenError> {
+    let (pipeline, machine_level) = match level {
+        0 => return verify_module(module),
+        1 => ("default<O1>", OptimizationLevel::Less),
+        2 => ("default<O2>", OptimizationLevel::Default),
+        _ => ("default<O3>", OptimizationLevel::Aggressive),
+    };
+
+    // Initialize targets (required for run_passes)
+    Compiler::init_targets();
+
+    // Create target machine
+    let triple = TargetMachine::get_default_triple();
+    let target = Target::from_triple(&triple)
+        .map_err(|e| CodeGenError::ModuleVerification(format!("Failed to get target: {}", e)))?;
+
+    let machine = target
+        .create_target_machine(
+            &triple,
+            "generic",
+            "",
+            machine_level,
+            RelocMode::Default,
+            CodeModel::Default,
+        )
+        .ok_or_else(|| {
+            CodeGenError::ModuleVerification("Failed to create target machine".to_string())
+        })?;
+
+    // Configure pass builder options
+    let pass_options = PassBuilderOptions::create();
+    pass_options.set_verify_each(true);
+    pass_options.set_loop_vectorization(true);
+    pass_options.set_loop_slp_vectorization(true);
+    pass_options.set_loop_unrolling(true);
+    pass_options.set_merge_functions(true);
+
+    // The default pipelines include common optimizations like:
+    // - Instruction combining
+    // - Dead code elimination
+    // - GVN (global value numbering)
+    // - Memory to register promotion
+    // - Loop optimizations
+    // - Inlining
+    module
+        .run_passes(pipeline, &machine, pass_options)
+        .map_err(|e| CodeGenError::ModuleVerification(format!("Optimization passes failed: {}", e)))
+}
+
+/// Counts the defined functions, basic blocks and instructions in a module.
+pub fn module_stats(module: &Module<'_>) -> ModuleStats {
+    let mut stats = ModuleStats::default();
+    for function in module.get_functions() {
+        if function.count_basic_blocks() == 0 {
+            continue;
+        }
+        stats.functions += 1;
+        for block in function.get_basic_block_iter() {
+            stats.basic_blocks += 1;
+            stats.instructions += block.get_instructions().count();
+        }
+    }
+    stats
 }
 
 pub struct Compiler<'ctx> {
@@ -29,13 +138,51 @@ pub struct Compiler<'ctx> {
     pub(crate) builder: Builder<'ctx>,
     pub(crate) module: Module<'ctx>,
     pub(crate) variables: HashMap<String, PointerValue<'ctx>>,
+    // Text buffers (see `text_buffer_type`) of the variables in the current function
+    // that are grown in place by `s = s + ...`; the buffer owns the variable's string
+    // while its data pointer equals the string pointer the variable holds
+    pub(crate) string_buffers: HashMap<String, PointerValue<'ctx>>,
+    // Variables of the current function that `del` may unbind: their slot then holds
+    // the unbound marker, which every read checks for
+    pub(crate) deleted_variables: HashSet<String>,
     pub(crate) functions: HashMap<String, FunctionValue<'ctx>>,
     pub(crate) function_defaults: HashMap<String, Vec<Option<IRExpr>>>,
-    // Stack of (continue_target, break_target) basic blocks for nested loops
+    // Globals holding the defaults that aren't literals, keyed by function and
+    // parameter index; main evaluates them once where the function is defined
+    pub(crate) default_globals: HashMap<(String, usize), PointerValue<'ctx>>,
+    // Parameter names of user functions, used to bind keyword arguments
+    pub(crate) function_params: HashMap<String, Vec<String>>,
+    // Number of leading parameters that may be passed positionally; the rest are
+    // keyword-only
+    pub(crate) function_positional_counts: HashMap<String, usize>,
+    // Functions taking `**kwargs`; their last parameter receives the extra keywords dict
+    pub(crate) function_kwargs: HashSet<String>,
+    // Module-level variables declared `global` by some function, stored in LLVM globals
+    // (initialized to None) so functions and main share them
+    pub(crate) module_globals: HashMap<String, PointerValue<'ctx>>,
+    // Statically inferred return types of user functions (see `ReturnType`)
+    pub(crate) function_return_types: HashMap<String, ReturnType>,
+    // User functions that are only declared: their compiled bodies are linked in from
+    // a `CompilerSession` cache before the module is verified
+    pub(crate) precompiled_functions: HashSet<String>,
+    // Index in the program of the top-level statement or function definition being
+    // compiled, reported with code generation errors
+    current_statement: Option<usize>,
+    // Stack of (continue_target, break_target, try_depth) for nested loops, where
+    // try_depth is the length of `try_stack` when the loop was entered
     pub(crate) loop_stack: Vec<(
         inkwell::basic_block::BasicBlock<'ctx>,
         inkwell::basic_block::BasicBlock<'ctx>,
+        usize,
     )>,
+    // Stack of active try blocks in the current function; each entry is the slot
+    // holding the exception handler to restore when control leaves that try body
+    pub(crate) try_stack: Vec<PointerValue<'ctx>>,
+    // Whether the function being compiled contains a try block; its variables are then
+    // accessed with volatile loads/stores so values survive a longjmp into the handler
+    pub(crate) volatile_variables: bool,
+    // Whether the print being compiled writes to stderr (`file=sys.stderr`)
+    pub(crate) print_to_stderr: bool,
     // Arena for string allocations - stores pointers to allocated strings for cleanup
     // Only strings allocated in the main entry block are tracked to avoid dominance issues
     pub(crate) string_arena: Vec<PointerValue<'ctx>>,
@@ -47,6 +194,25 @@ pub struct Compiler<'ctx> {
     pub(crate) format_strings: FormatStrings<'ctx>,
     // Value manager for NaN-boxing operations
     pub(crate) values: ValueManager<'ctx>,
+    // Flush stdout after every print (for piped/interactive output)
+    pub(crate) unbuffered_output: bool,
+    // Build a printed list's text in one heap buffer and print it with a single printf
+    pub(crate) buffered_list_printing: bool,
+    // Read input() lines as strings instead of floats
+    pub(crate) string_input: bool,
+    // Compile assert statements (disabled, they are dropped like under `python -O`)
+    pub(crate) asserts_enabled: bool,
+    // LLVM optimization level (0-3); 0 skips the pass pipeline
+    pub(crate) optimization_level: u8,
+    // Trap on integer +, -, * results that leave the int payload range
+    pub(crate) checked_arithmetic: bool,
+    // Report code generation and optimization timings on stderr
+    pub(crate) time_passes: bool,
+    // Maximum depth of nested user function calls before raising RecursionError
+    pub(crate) recursion_limit: Option<u64>,
+    // Name of the function running the top-level statements; None emits no such
+    // function, leaving only the user functions
+    pub(crate) entry_point: Option<String>,
 }
 
 impl<'ctx> Compiler<'ctx> {
@@ -61,17 +227,122 @@ impl<'ctx> Compiler<'ctx> {
             builder,
             module,
             variables: HashMap::new(),
+            string_buffers: HashMap::new(),
+            deleted_variables: HashSet::new(),
             functions: HashMap::new(),
             function_defaults: HashMap::new(),
+            default_globals: HashMap::new(),
+            function_params: HashMap::new(),
+            function_positional_counts: HashMap::new(),
+            function_kwargs: HashSet::new(),
+            module_globals: HashMap::new(),
+            function_return_types: HashMap::new(),
+            precompiled_functions: HashSet::new(),
+            current_statement: None,
             loop_stack: Vec::new(),
+            try_stack: Vec::new(),
+            volatile_variables: false,
+            print_to_stderr: false,
             string_arena: Vec::new(),
             main_entry_block: None,
             runtime,
             format_strings,
             values,
+            unbuffered_output: false,
+            buffered_list_printing: false,
+            string_input: false,
+            asserts_enabled: true,
+            optimization_level: 2,
+            checked_arithmetic: false,
+            time_passes: false,
+            recursion_limit: None,
+            entry_point: Some("main".to_string()),
         }
     }
 
+    /// Sets the LLVM module name (defaults to `"main"`), e.g. to tell apart several
+    /// compiled modules that are linked together
+    pub fn with_module_name(self, name: &str) -> Self {
+        self.module.set_name(name);
+        self.module.set_source_file_name(name);
+        self
+    }
+
+    /// Enables flushing stdout after every `print`, so output written before an
+    /// `input()` prompt (or interleaved with other processes) appears immediately
+    pub fn with_unbuffered_output(mut self, enabled: bool) -> Self {
+        self.unbuffered_output = enabled;
+        self
+    }
+
+    /// Prints lists and tuples by formatting the whole `[...]` text into a growable
+    /// heap buffer first and writing it with a single printf, instead of one printf
+    /// per element and separator. Faster for large lists.
+    pub fn with_buffered_list_printing(mut self, enabled: bool) -> Self {
+        self.buffered_list_printing = enabled;
+        self
+    }
+
+    /// Makes `input()` read a whole line and return it as a string (without the
+    /// trailing newline), like CPython, instead of parsing a float
+    pub fn with_string_input(mut self, enabled: bool) -> Self {
+        self.string_input = enabled;
+        self
+    }
+
+    /// Enables `assert` statements (the default). Disabled, they are dropped without
+    /// evaluating their condition or message, like CPython's `-O`.
+    pub fn with_asserts(mut self, enabled: bool) -> Self {
+        self.asserts_enabled = enabled;
+        self
+    }
+
+    /// Sets the LLVM optimization level: 0 emits the IR as generated (still verified),
+    /// 1-3 run the `default<O1>`..`default<O3>` pipelines. Defaults to 2.
+    pub fn with_optimization_level(mut self, level: u8) -> Self {
+        self.optimization_level = level;
+        self
+    }
+
+    /// Enables overflow checking for integer `+`, `-`, `*` and `**`: results that overflow
+    /// i64 or do not fit the NaN-boxed payload call `raise_overflow` instead of
+    /// silently wrapping or losing precision
+    pub fn with_checked_arithmetic(mut self, enabled: bool) -> Self {
+        self.checked_arithmetic = enabled;
+        self
+    }
+
+    /// Sets the NaN-box payload width for ints (48 bits by default).
+    /// With `PayloadWidth::Bits32` ints wrap to 32 bits; pointers keep all 48 bits.
+    pub fn with_payload_width(mut self, width: PayloadWidth) -> Self {
+        self.values = ValueManager::with_payload_width(self.context, width);
+        self
+    }
+
+    /// Prints how long IR generation and the LLVM optimization pipeline took to
+    /// stderr; the emitted IR is unaffected
+    pub fn with_time_passes(mut self, enabled: bool) -> Self {
+        self.time_passes = enabled;
+        self
+    }
+
+    /// Limits the depth of nested user function calls: exceeding it raises a
+    /// RecursionError instead of overflowing the native stack. `None` (the default)
+    /// emits no depth tracking at all.
+    pub fn with_recursion_limit(mut self, limit: Option<u64>) -> Self {
+        self.recursion_limit = limit;
+        self
+    }
+
+    /// Names the `i32 ()` function that runs the top-level statements (`"main"` by
+    /// default), e.g. so a library can be linked into a program with its own `main`.
+    /// `None` generates no entry function: the top-level statements are dropped and
+    /// non-literal defaults stay None.
+    pub fn with_entry_point(mut self, name: Option<&str>) -> Self {
+        self.entry_point = name.map(str::to_string);
+        self
+    }
+
     /// Returns the PyObject type: i64 (NaN-boxed value)
     /// PyObjects are now single 64-bit values using NaN-boxing for 50% memory reduction
     pub(crate) fn create_pyobject_type(&self) -> inkwell::types::IntType<'ctx> {
@@ -89,6 +360,11 @@ impl<'ctx> Compiler<'ctx> {
         self.values.create_float(&self.builder, value)
     }
 
+    /// Creates the None PyObject
+    pub(crate) fn create_pyobject_none(&self) -> IntValue<'ctx> {
+        self.values.create_none()
+    }
+
     /// Creates a PyObject value from a boolean using NaN-boxing
     pub(crate) fn create_pyobject_bool(&self, value: IntValue<'ctx>) -> IntValue<'ctx> {
         self.values.create_bool(&self.builder, value)
@@ -99,6 +375,17 @@ impl<'ctx> Compiler<'ctx> {
         self.values.create_string(&self.builder, ptr)
     }
 
+    /// Records a newly allocated string in the arena freed at the end of main. Only
+    /// strings allocated in main's entry block are tracked: elsewhere the allocation
+    /// may not dominate the cleanup code.
+    pub(crate) fn track_string_in_arena(&mut self, ptr: PointerValue<'ctx>) {
+        if let Some(main_entry) = self.main_entry_block {
+            if self.builder.get_insert_block() == Some(main_entry) {
+                self.string_arena.push(ptr);
+            }
+        }
+    }
+
     /// Extracts a string pointer from a PyObject
     /// Assumes the PyObject has a STRING tag
     pub(crate) fn extract_string_ptr(&self, pyobject: IntValue<'ctx>) -> PointerValue<'ctx> {
@@ -116,8 +403,83 @@ impl<'ctx> Compiler<'ctx> {
         self.values.create_list(&self.builder, ptr, _len)
     }
 
+    /// Creates a PyObject value from a tuple pointer using NaN-boxing
+    /// Tuples share the list layout: [length: i64][element_0: i64]...[element_n: i64]
+    pub(crate) fn create_pyobject_tuple(&self, ptr: PointerValue<'ctx>) -> IntValue<'ctx> {
+        self.values.create_tuple(&self.builder, ptr)
+    }
+
+    /// Creates a PyObject value from a dict pointer using NaN-boxing
+    /// Dicts store their length, capacity and a pointer to their entries, which
+    /// alternate keys and values so they can grow in place, then a hash index of
+    /// entry numbers (-1 for a free slot) and its slot count minus one:
+    /// [length: i64][capacity: i64][entries: ptr][index: ptr][mask: i64]
+    /// -> [key_0: i64][value_0: i64]...
+    pub(crate) fn create_pyobject_dict(&self, ptr: PointerValue<'ctx>) -> IntValue<'ctx> {
+        self.values.create_dict(&self.builder, ptr)
+    }
+
+    /// Creates a PyObject value from a set pointer using NaN-boxing
+    ///
+    /// Sets use the dict layout, with None in every value slot
+    pub(crate) fn create_pyobject_set(&self, ptr: PointerValue<'ctx>) -> IntValue<'ctx> {
+        self.values.create_set(&self.builder, ptr)
+    }
+
+    /// Creates a file PyObject from a pointer to the heap cell holding its C `FILE*`
+    pub(crate) fn create_pyobject_file(&self, ptr: PointerValue<'ctx>) -> IntValue<'ctx> {
+        self.values.create_file(&self.builder, ptr)
+    }
+
+    /// Creates a PyObject value from a function descriptor pointer using NaN-boxing
+    pub(crate) fn create_pyobject_function(&self, ptr: PointerValue<'ctx>) -> IntValue<'ctx> {
+        self.values.create_function(&self.builder, ptr)
+    }
+
+    /// Extracts the pointer to the cell holding a file PyObject's `FILE*` (null once
+    /// the file is closed). The payload is a plain pointer, so it is extracted like a
+    /// string's
+    pub(crate) fn extract_file_cell(&self, pyobject: IntValue<'ctx>) -> PointerValue<'ctx> {
+        self.values.extract_string_ptr(&self.builder, pyobject)
+    }
+
+    /// Extracts the entries pointer and length from a dict PyObject
+    /// Entry `i` keeps its key in slot `2 * i` and its value in slot `2 * i + 1`
+    pub(crate) fn extract_dict_entries(
+        &self,
+        pyobject: IntValue<'ctx>,
+    ) -> (PointerValue<'ctx>, IntValue<'ctx>) {
+        let (header, len) = self
+            .values
+            .extract_list_ptr_and_len(&self.builder, pyobject);
+        let entries = self.load_dict_entries(header);
+        (entries, len)
+    }
+
+    /// Loads the entries pointer from the third word of a dict header
+    fn load_dict_entries(&self, header: PointerValue<'ctx>) -> PointerValue<'ctx> {
+        let entries_slot = unsafe {
+            self.builder
+                .build_in_bounds_gep(
+                    self.create_pyobject_type(),
+                    header,
+                    &[self.context.i64_type().const_int(2, false)],
+                    "entries_slot",
+                )
+                .unwrap()
+        };
+        self.builder
+            .build_load(
+                self.context.ptr_type(inkwell::AddressSpace::default()),
+                entries_slot,
+                "entries",
+            )
+            .unwrap()
+            .into_pointer_value()
+    }
+
     /// Extracts a list pointer and length from a PyObject
-    /// Assumes the PyObject has a LIST tag
+    /// Assumes the PyObject has a LIST (or TUPLE) tag
     /// The pointer points to: [length: i64][element_0: i64]...[element_n: i64]
     pub(crate) fn extract_list_ptr_and_len(
         &self,
@@ -160,10 +522,109 @@ impl<'ctx> Compiler<'ctx> {
         self.values.extract_payload(&self.builder, pyobject)
     }
 
+    /// Extracts the payload of an INT-tagged PyObject as an exact i64 (no f64 round-trip)
+    pub(crate) fn extract_int_payload(&self, pyobject: IntValue<'ctx>) -> IntValue<'ctx> {
+        self.values.extract_int_payload(&self.builder, pyobject)
+    }
+
     /// Converts a PyObject to a boolean (i1) for conditionals
-    /// Returns true if the value is non-zero
+    /// Empty strings, lists, tuples and dicts are false, as are None and zero numbers
+    ///
+    /// Branch-free so conditions stay a single block: the length word or first
+    /// character is loaded through a pointer that falls back to a zero global when the
+    /// value is not a container or string.
     pub(crate) fn pyobject_to_bool(&self, pyobject: IntValue<'ctx>) -> IntValue<'ctx> {
-        self.values.to_bool(&self.builder, pyobject)
+        let i64_type = self.context.i64_type();
+        let tag = self.values.extract_tag(&self.builder, pyobject);
+        let has_tag = |expected: u8, name: &str| {
+            self.builder
+                .build_int_compare(
+                    inkwell::IntPredicate::EQ,
+                    tag,
+                    i64_type.const_int(u64::from(expected), false),
+                    name,
+                )
+                .unwrap()
+        };
+        let is_list = has_tag(TYPE_TAG_LIST, "is_list");
+        let is_tuple = has_tag(TYPE_TAG_TUPLE, "is_tuple");
+        let is_dict = has_tag(TYPE_TAG_DICT, "is_dict");
+        let is_set = has_tag(TYPE_TAG_SET, "is_set");
+        let is_string = has_tag(TYPE_TAG_STRING, "is_string");
+        let is_sequence = self
+            .builder
+            .build_or(is_list, is_tuple, "is_sequence")
+            .unwrap();
+        let is_keyed = self.builder.build_or(is_dict, is_set, "is_keyed").unwrap();
+        let is_container = self
+            .builder
+            .build_or(is_sequence, is_keyed, "is_container")
+            .unwrap();
+        let has_pointer = self
+            .builder
+            .build_or(is_container, is_string, "has_pointer")
+            .unwrap();
+
+        let zero_word = self.module.get_global("truthy_zero").unwrap_or_else(|| {
+            let global = self.module.add_global(i64_type, None, "truthy_zero");
+            global.set_initializer(&i64_type.const_zero());
+            global.set_constant(true);
+            global.set_linkage(inkwell::module::Linkage::Private);
+            global
+        });
+        let payload_ptr = self.values.extract_string_ptr(&self.builder, pyobject);
+        let ptr = self
+            .builder
+            .build_select(
+                has_pointer,
+                payload_ptr,
+                zero_word.as_pointer_value(),
+                "truthy_ptr",
+            )
+            .unwrap()
+            .into_pointer_value();
+
+        // Lists, tuples and dicts store their length in the first word
+        let len = self
+            .builder
+            .build_load(i64_type, ptr, "truthy_len")
+            .unwrap()
+            .into_int_value();
+        let non_empty = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::NE,
+                len,
+                i64_type.const_zero(),
+                "non_empty",
+            )
+            .unwrap();
+        let i8_type = self.context.i8_type();
+        let first = self
+            .builder
+            .build_load(i8_type, ptr, "truthy_first")
+            .unwrap()
+            .into_int_value();
+        let has_text = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::NE,
+                first,
+                i8_type.const_zero(),
+                "has_text",
+            )
+            .unwrap();
+        let non_zero = self.values.to_bool(&self.builder, pyobject);
+
+        let pointer_truth = self
+            .builder
+            .build_select(is_container, non_empty, has_text, "pointer_truth")
+            .unwrap()
+            .into_int_value();
+        self.builder
+            .build_select(has_pointer, pointer_truth, non_zero, "to_bool")
+            .unwrap()
+            .into_int_value()
     }
 
     /// Initializes LLVM targets (only once per program execution)
@@ -173,63 +634,74 @@ impl<'ctx> Compiler<'ctx> {
         });
     }
 
-    /// Runs LLVM optimization passes using the new pass manager (LLVM 18+)
-    /// Uses a moderate optimization pipeline (O2) for good performance without excessive compile time
-    fn run_optimization_passes(&self) -> Result<(), CodeGenError> {
-        // Initialize targets (required for run_passes)
-        Self::init_targets();
+    pub fn compile_program(mut self, program: &[IRStmt]) -> Result<String, CodeGenError> {
+        let codegen_start = Instant::now();
+        self.generate_program(program)?;
+        self.finish_program(codegen_start)
+    }
+
+    /// Like `compile_program`, but an error raised while generating code comes with
+    /// the index in `program` of the statement or function definition it arose in.
+    /// Errors in the module as a whole, such as a verification failure, have none.
+    pub fn compile_program_locating_errors(
+        mut self,
+        program: &[IRStmt],
+    ) -> Result<String, (Option<usize>, CodeGenError)> {
+        let codegen_start = Instant::now();
+        if let Err(e) = self.generate_program(program) {
+            return Err((self.current_statement, e));
+        }
+        self.finish_program(codegen_start).map_err(|e| (None, e))
+    }
 
-        // Create target machine
-        let triple = TargetMachine::get_default_triple();
-        let target = Target::from_triple(&triple).map_err(|e| {
-            CodeGenError::ModuleVerification(format!("Failed to get target: {}", e))
-        })?;
+    /// Verifies and optimizes the generated module and returns its IR text; code
+    /// generation is timed from `codegen_start`
+    pub(crate) fn finish_program(self, codegen_start: Instant) -> Result<String, CodeGenError> {
+        verify_module(&self.module)?;
 
-        let machine = target
-            .create_target_machine(
-                &triple,
-                "generic",
-                "",
-                OptimizationLevel::Default,
-                RelocMode::Default,
-                CodeModel::Default,
-            )
-            .ok_or_else(|| {
-                CodeGenError::ModuleVerification("Failed to create target machine".to_string())
-            })?;
-
-        // Configure pass builder options
-        let pass_options = PassBuilderOptions::create();
-        pass_options.set_verify_each(true);
-        pass_options.set_loop_vectorization(true);
-        pass_options.set_loop_slp_vectorization(true);
-        pass_options.set_loop_unrolling(true);
-        pass_options.set_merge_functions(true);
-
-        // Run the optimization pipeline
-        // "default<O2>" runs the default optimization pipeline at O2 level
-        // This includes common optimizations like:
-        // - Instruction combining
-        // - Dead code elimination
-        // - GVN (global value numbering)
-        // - Memory to register promotion
-        // - Loop optimizations
-        // - Inlining
-        self.module
-            .run_passes("default<O2>", &machine, pass_options)
-            .map_err(|e| {
-                CodeGenError::ModuleVerification(format!("Optimization passes failed: {}", e))
-            })?;
+        let codegen_time = codegen_start.elapsed();
 
-        Ok(())
+        // Run optimization passes using the new pass manager (LLVM 18+)
+        // This optimizes all functions in the module at once
+        let optimization_start = Instant::now();
+        optimize_module(&self.module, self.optimization_level)?;
+        let optimization_time = optimization_start.elapsed();
+
+        if self.time_passes {
+            eprintln!("Pass timings:");
+            eprintln!(
+                "  IR generation:            {:>10.3} ms",
+                codegen_time.as_secs_f64() * 1000.0
+            );
+            eprintln!(
+                "  Optimization (default<O{}>): {:>8.3} ms",
+                self.optimization_level.min(3),
+                optimization_time.as_secs_f64() * 1000.0
+            );
+        }
+
+        Ok(self.module.print_to_string().to_string())
     }
 
-    pub fn compile_program(mut self, program: &[IRStmt]) -> Result<String, CodeGenError> {
+    /// Generates IR for the program and runs the LLVM verifier without optimizing.
+    /// A verification failure carries LLVM's diagnostic in
+    /// `CodeGenError::ModuleVerification`.
+    #[allow(dead_code)]
+    pub fn verify_only(mut self, program: &[IRStmt]) -> Result<(), CodeGenError> {
+        self.generate_program(program)?;
+        verify_module(&self.module)
+    }
+
+    /// Generates the (unoptimized) IR for every function and `main`
+    pub(crate) fn generate_program(&mut self, program: &[IRStmt]) -> Result<(), CodeGenError> {
         // Separate function definitions from top-level statements
         let (functions, top_level): (Vec<_>, Vec<_>) = program
             .iter()
             .partition(|stmt| matches!(stmt, IRStmt::FunctionDef { .. }));
 
+        // Infer which functions always return an integer so they can skip boxing
+        self.function_return_types = infer_return_types(&functions);
+
         // Two-pass compilation for mutual recursion support:
 
         // Pass 1: Declare all function signatures
@@ -238,36 +710,115 @@ impl<'ctx> Compiler<'ctx> {
                 name,
                 params,
                 defaults,
+                kwonly_params,
+                kwonly_defaults,
+                kwarg,
                 ..
             } = func_stmt
             {
-                self.declare_function(name, params, defaults);
+                // Keyword-only parameters are passed after the positional ones, and the
+                // `**kwargs` dict (which always has a value) last
+                let all_params: Vec<String> = params
+                    .iter()
+                    .chain(kwonly_params)
+                    .chain(kwarg)
+                    .cloned()
+                    .collect();
+                let all_defaults: Vec<Option<IRExpr>> =
+                    defaults.iter().chain(kwonly_defaults).cloned().collect();
+                self.declare_function(name, &all_params, &all_defaults);
+                for (index, default) in all_defaults.iter().enumerate() {
+                    if default.as_ref().is_some_and(|default| !is_literal(default)) {
+                        let pyobject_type = self.create_pyobject_type();
+                        let global = self.module.add_global(
+                            pyobject_type,
+                            None,
+                            &format!("default.{}.{}", name, index),
+                        );
+                        global.set_linkage(inkwell::module::Linkage::Internal);
+                        global.set_initializer(&self.create_pyobject_none());
+                        self.default_globals
+                            .insert((name.clone(), index), global.as_pointer_value());
+                    }
+                }
+                self.function_positional_counts
+                    .insert(name.clone(), params.len());
+                if kwarg.is_some() {
+                    self.function_kwargs.insert(name.clone());
+                }
             }
         }
 
+        // Variables declared `global` in any function live in LLVM globals. They start
+        // out as None so a function running before main assigns them reads a valid value
+        let mut global_names: Vec<String> = functions
+            .iter()
+            .flat_map(|stmt| match stmt {
+                IRStmt::FunctionDef { body, .. } => declared_globals(body.iter()),
+                _ => Vec::new(),
+            })
+            .collect();
+        global_names.sort();
+        global_names.dedup();
+        for name in global_names {
+            let pyobject_type = self.create_pyobject_type();
+            let global = self
+                .module
+                .add_global(pyobject_type, None, &format!("global.{}", name));
+            global.set_linkage(inkwell::module::Linkage::Internal);
+            global.set_initializer(&self.create_pyobject_none());
+            self.module_globals.insert(name, global.as_pointer_value());
+        }
+
         // Pass 2: Compile all function bodies
-        for func_stmt in &functions {
+        for (index, func_stmt) in program.iter().enumerate() {
             if let IRStmt::FunctionDef {
-                name, params, body, ..
+                name,
+                params,
+                kwonly_params,
+                kwarg,
+                body,
+                ..
             } = func_stmt
             {
-                self.compile_function_body(name, params, body)?;
+                if self.precompiled_functions.contains(name) {
+                    continue;
+                }
+                self.current_statement = Some(index);
+                let all_params: Vec<String> = params
+                    .iter()
+                    .chain(kwonly_params)
+                    .chain(kwarg)
+                    .cloned()
+                    .collect();
+                self.compile_function_body(name, &all_params, body)?;
             }
         }
 
         // Create the main function and compile top-level statements
+        self.current_statement = None;
+        let Some(entry_point) = self.entry_point.clone() else {
+            return Ok(());
+        };
         let i32_type = self.context.i32_type();
         let main_fn_type = i32_type.fn_type(&[], false);
-        let main_fn = self.module.add_function("main", main_fn_type, None);
+        let main_fn = self.module.add_function(&entry_point, main_fn_type, None);
         let entry = self.context.append_basic_block(main_fn, "entry");
         self.builder.position_at_end(entry);
 
         // Store the main entry block to track which strings can be safely freed
         self.main_entry_block = Some(entry);
+        self.volatile_variables = contains_try(top_level.iter().copied());
+        self.variables.extend(self.module_globals.clone());
+        self.declare_string_buffers(top_level.iter().copied(), main_fn);
+        self.deleted_variables = deleted_names(top_level.iter().copied());
 
-        for stmt in top_level {
+        // Definitions stay in place so their defaults are evaluated in program order
+        for (index, stmt) in program.iter().enumerate() {
+            self.current_statement = Some(index);
             self.compile_statement(stmt, main_fn)?;
         }
+        self.current_statement = None;
 
         // String cleanup: Free all allocated strings
         // Note: We accept that strings allocated in functions may leak, as we only
@@ -284,17 +835,7 @@ impl<'ctx> Compiler<'ctx> {
             .build_return(Some(&i32_type.const_int(0, false)))
             .unwrap();
 
-        if !main_fn.verify(true) {
-            return Err(CodeGenError::ModuleVerification(
-                "Main function verification failed".to_string(),
-            ));
-        }
-
-        // Run optimization passes using the new pass manager (LLVM 18+)
-        // This optimizes all functions in the module at once
-        self.run_optimization_passes()?;
-
-        Ok(self.module.print_to_string().to_string())
+        Ok(())
     }
 
     fn compile_statement(
@@ -303,26 +844,66 @@ impl<'ctx> Compiler<'ctx> {
         current_fn: FunctionValue<'ctx>,
     ) -> Result<(), CodeGenError> {
         match stmt {
-            IRStmt::Print(exprs) => statement::compile_print(self, exprs)?,
+            IRStmt::Print {
+                args,
+                sep,
+                end,
+                stderr,
+            } => {
+                self.print_to_stderr = *stderr;
+                let printed = statement::compile_print(self, args, sep.as_ref(), end.as_ref());
+                self.print_to_stderr = false;
+                printed?
+            }
             IRStmt::Assign { target, value } => {
                 statement::compile_assign(self, target, value, current_fn)?
             }
+            IRStmt::IndexAssign { list, index, value } => {
+                statement::compile_index_assign(self, list, index, value)?
+            }
+            IRStmt::UnpackAssign { targets, value } => {
+                statement::compile_unpack_assign(self, targets, value, current_fn)?
+            }
             IRStmt::ExprStmt(expr) => statement::compile_expr_stmt(self, expr)?,
-            IRStmt::Return(expr) => statement::compile_return(self, expr)?,
-            IRStmt::FunctionDef { .. } => {
-                // Function definitions are handled separately in compile_program
-                // This should not be reached during normal statement compilation
+            IRStmt::Return(expr) => statement::compile_return(self, expr, current_fn)?,
+            IRStmt::FunctionDef { name, .. } => {
+                // The body was compiled up front; executing the definition only
+                // evaluates the defaults that aren't literals (see `default_globals`)
+                let defaults = self
+                    .function_defaults
+                    .get(name)
+                    .cloned()
+                    .unwrap_or_default();
+                for (index, default) in defaults.iter().enumerate() {
+                    let (Some(default), Some(&global)) =
+                        (default, self.default_globals.get(&(name.clone(), index)))
+                    else {
+                        continue;
+                    };
+                    let value = self.compile_expression(default)?;
+                    self.builder.build_store(global, value).unwrap();
+                }
             }
             IRStmt::If {
                 condition,
                 then_body,
                 else_body,
             } => {
-                // Compile the condition expression
-                let cond_pyobj = self.compile_expression(condition)?;
+                // Peephole: `if not x` branches on x directly with the targets swapped,
+                // instead of boxing the negation into a bool and unboxing it again
+                let mut condition = condition;
+                let mut negated = false;
+                while let IRExpr::UnaryOp {
+                    op: UnaryOp::Not,
+                    operand,
+                } = condition
+                {
+                    condition = operand;
+                    negated = !negated;
+                }
 
-                // Convert PyObject to boolean for branching
-                let cond_bool = self.pyobject_to_bool(cond_pyobj);
+                // Compile the condition straight to a boolean for branching
+                let cond_bool = expression::compile_condition(self, condition)?;
 
                 // Create basic blocks for then, else, and merge
                 let then_bb = self.context.append_basic_block(current_fn, "then");
@@ -330,8 +911,13 @@ impl<'ctx> Compiler<'ctx> {
                 let merge_bb = self.context.append_basic_block(current_fn, "ifcont");
 
                 // Build conditional branch
+                let (true_bb, false_bb) = if negated {
+                    (else_bb, then_bb)
+                } else {
+                    (then_bb, else_bb)
+                };
                 self.builder
-                    .build_conditional_branch(cond_bool, then_bb, else_bb)
+                    .build_conditional_branch(cond_bool, true_bb, false_bb)
                     .unwrap();
 
                 // Compile then block
@@ -359,14 +945,20 @@ impl<'ctx> Compiler<'ctx> {
                 // Continue building in the merge block
                 self.builder.position_at_end(merge_bb);
             }
-            IRStmt::While { condition, body } => {
+            IRStmt::While {
+                condition,
+                body,
+                else_body,
+            } => {
                 // Create basic blocks for loop condition, body, and exit
                 let loop_cond_bb = self.context.append_basic_block(current_fn, "loop_cond");
                 let loop_body_bb = self.context.append_basic_block(current_fn, "loop_body");
                 let loop_exit_bb = self.context.append_basic_block(current_fn, "loop_exit");
+                let loop_done_bb = self.loop_else_block(else_body, loop_exit_bb, current_fn);
 
                 // Push loop targets onto the stack for break/continue
-                self.loop_stack.push((loop_cond_bb, loop_exit_bb));
+                self.loop_stack
+                    .push((loop_cond_bb, loop_exit_bb, self.try_stack.len()));
 
                 // Jump to the condition check
                 self.builder
@@ -375,14 +967,11 @@ impl<'ctx> Compiler<'ctx> {
 
                 // Build the condition block
                 self.builder.position_at_end(loop_cond_bb);
-                let cond_pyobj = self.compile_expression(condition)?;
-
-                // Convert PyObject to boolean for branching
-                let cond_bool = self.pyobject_to_bool(cond_pyobj);
+                let cond_bool = expression::compile_condition(self, condition)?;
 
                 // Branch based on condition
                 self.builder
-                    .build_conditional_branch(cond_bool, loop_body_bb, loop_exit_bb)
+                    .build_conditional_branch(cond_bool, loop_body_bb, loop_done_bb)
                     .unwrap();
 
                 // Build the loop body
@@ -401,34 +990,56 @@ impl<'ctx> Compiler<'ctx> {
                 // Pop loop targets from the stack
                 self.loop_stack.pop();
 
-                // Continue building after the loop
-                self.builder.position_at_end(loop_exit_bb);
+                // Run the else body on normal exit, then continue building after the loop
+                self.compile_loop_else(else_body, loop_done_bb, loop_exit_bb, current_fn)?;
             }
             IRStmt::For {
                 var,
                 start,
                 end,
+                step,
                 body,
+                else_body,
             } => {
-                // Compile for loop as: var = start; while var < end: body; var += 1
+                // Compile for loop as: i = start; while i < end: var = i; body; i += step
+                // (i > end for a negative step). The hidden counter `i` drives the loop,
+                // so reassigning var in the body doesn't change the next iteration
 
                 // Initialize loop variable
                 let start_val = self.compile_expression(start)?;
+                self.build_require_integer(start_val);
+                let step_val = self.compile_expression(step)?;
+                self.build_require_integer(step_val);
+                let step_payload = self.extract_payload(step_val);
+                let zero = self.context.f64_type().const_float(0.0);
+                let step_is_zero = self
+                    .builder
+                    .build_float_compare(FloatPredicate::OEQ, step_payload, zero, "step_is_zero")
+                    .unwrap();
+                self.build_raise_if(step_is_zero, "ValueError: range() arg 3 must not be zero");
+                let counts_up = self
+                    .builder
+                    .build_float_compare(FloatPredicate::OGT, step_payload, zero, "counts_up")
+                    .unwrap();
                 let ptr = self.variables.get(var).copied().unwrap_or_else(|| {
                     let ptr = self.create_entry_block_alloca(var, current_fn);
                     self.variables.insert(var.clone(), ptr);
                     ptr
                 });
-                self.builder.build_store(ptr, start_val).unwrap();
+                self.build_variable_store(ptr, start_val);
+                let counter_ptr = self.create_entry_block_alloca("for_counter", current_fn);
+                self.build_variable_store(counter_ptr, start_val);
 
                 // Create basic blocks for loop condition, body, and exit
                 let loop_cond_bb = self.context.append_basic_block(current_fn, "for_cond");
                 let loop_body_bb = self.context.append_basic_block(current_fn, "for_body");
                 let loop_incr_bb = self.context.append_basic_block(current_fn, "for_incr");
                 let loop_exit_bb = self.context.append_basic_block(current_fn, "for_exit");
+                let loop_done_bb = self.loop_else_block(else_body, loop_exit_bb, current_fn);
 
                 // Push loop targets onto the stack (continue goes to increment, break to exit)
-                self.loop_stack.push((loop_incr_bb, loop_exit_bb));
+                self.loop_stack
+                    .push((loop_incr_bb, loop_exit_bb, self.try_stack.len()));
 
                 // Jump to the condition check
                 self.builder
@@ -438,28 +1049,45 @@ impl<'ctx> Compiler<'ctx> {
                 // Build the condition block (var < end)
                 self.builder.position_at_end(loop_cond_bb);
                 let end_val = self.compile_expression(end)?;
-                let pyobject_type = self.create_pyobject_type();
-                let var_val = self
-                    .builder
-                    .build_load(pyobject_type, ptr, var)
-                    .unwrap()
-                    .into_int_value();
+                self.build_require_integer(end_val);
+                let counter_val = self.build_variable_load(counter_ptr, "for_counter");
 
-                // Compare var < end
-                let var_payload = self.extract_payload(var_val);
+                // Compare i < end, or i > end when counting down
+                let counter_payload = self.extract_payload(counter_val);
                 let end_payload = self.extract_payload(end_val);
-                let cond_bool = self
+                let below_end = self
+                    .builder
+                    .build_float_compare(
+                        FloatPredicate::OLT,
+                        counter_payload,
+                        end_payload,
+                        "for_cond",
+                    )
+                    .unwrap();
+                let above_end = self
                     .builder
-                    .build_float_compare(FloatPredicate::OLT, var_payload, end_payload, "for_cond")
+                    .build_float_compare(
+                        FloatPredicate::OGT,
+                        counter_payload,
+                        end_payload,
+                        "for_cond_down",
+                    )
                     .unwrap();
+                let cond_bool = self
+                    .builder
+                    .build_select(counts_up, below_end, above_end, "for_in_range")
+                    .unwrap()
+                    .into_int_value();
 
                 // Branch based on condition
                 self.builder
-                    .build_conditional_branch(cond_bool, loop_body_bb, loop_exit_bb)
+                    .build_conditional_branch(cond_bool, loop_body_bb, loop_done_bb)
                     .unwrap();
 
                 // Build the loop body
                 self.builder.position_at_end(loop_body_bb);
+                let counter_val = self.build_variable_load(counter_ptr, "for_counter");
+                self.build_variable_store(ptr, counter_val);
                 for stmt in body {
                     self.compile_statement(stmt, current_fn)?;
                 }
@@ -471,25 +1099,20 @@ impl<'ctx> Compiler<'ctx> {
                         .unwrap();
                 }
 
-                // Build the increment block (var += 1)
+                // Build the increment block (i += step); `continue` lands here too
                 self.builder.position_at_end(loop_incr_bb);
-                let var_val = self
-                    .builder
-                    .build_load(pyobject_type, ptr, var)
-                    .unwrap()
-                    .into_int_value();
-                let var_payload = self.extract_payload(var_val);
-                let one = self.context.f64_type().const_float(1.0);
+                let counter_val = self.build_variable_load(counter_ptr, "for_counter");
+                let counter_payload = self.extract_payload(counter_val);
                 let new_payload = self
                     .builder
-                    .build_float_add(var_payload, one, "for_incr")
+                    .build_float_add(counter_payload, step_payload, "for_incr")
                     .unwrap();
 
-                // Preserve the tag from the loop variable
-                let tag = self.extract_tag(var_val);
+                // Preserve the tag from the counter
+                let tag = self.extract_tag(counter_val);
                 let new_val = self.create_pyobject_from_tag_and_payload(tag, new_payload);
 
-                self.builder.build_store(ptr, new_val).unwrap();
+                self.build_variable_store(counter_ptr, new_val);
                 self.builder
                     .build_unconditional_branch(loop_cond_bb)
                     .unwrap();
@@ -497,27 +1120,55 @@ impl<'ctx> Compiler<'ctx> {
                 // Pop loop targets from the stack
                 self.loop_stack.pop();
 
-                // Continue building after the loop
-                self.builder.position_at_end(loop_exit_bb);
+                // Run the else body on normal exit, then continue building after the loop
+                self.compile_loop_else(else_body, loop_done_bb, loop_exit_bb, current_fn)?;
+            }
+            IRStmt::ForEach {
+                var,
+                iter,
+                body,
+                else_body,
+            } => self.compile_for_each(var, iter, body, else_body, current_fn)?,
+            IRStmt::Try {
+                body,
+                exceptions,
+                handler,
+                else_body,
+            } => self.compile_try(body, exceptions, handler, else_body, current_fn)?,
+            // Globals are bound when the function body starts (see `module_globals`)
+            IRStmt::Global(_) => {}
+            IRStmt::Delete(names) => statement::compile_delete(self, names)?,
+            IRStmt::Exit(code) => statement::compile_exit(self, code, current_fn)?,
+            IRStmt::Raise { exception, message } => {
+                statement::compile_raise(self, exception.as_deref(), message.as_ref(), current_fn)?
+            }
+            IRStmt::Assert { condition, message } => {
+                if self.asserts_enabled {
+                    statement::compile_assert(self, condition, message.as_ref(), current_fn)?
+                }
             }
             IRStmt::Break => {
                 // Branch to the exit block of the current loop
-                if let Some((_, break_target)) = self.loop_stack.last() {
+                if let Some(&(_, break_target, try_depth)) = self.loop_stack.last() {
+                    // Leaving try blocks opened inside the loop
+                    self.restore_exception_handler(try_depth);
                     self.builder
-                        .build_unconditional_branch(*break_target)
+                        .build_unconditional_branch(break_target)
                         .unwrap();
                 }
                 // Note: Any code after break in the same block is unreachable
             }
             IRStmt::Continue => {
                 // Branch to the continue target (loop condition or increment) of the current loop
-                if let Some((continue_target, _)) = self.loop_stack.last() {
+                if let Some(&(continue_target, _, try_depth)) = self.loop_stack.last() {
+                    self.restore_exception_handler(try_depth);
                     self.builder
-                        .build_unconditional_branch(*continue_target)
+                        .build_unconditional_branch(continue_target)
                         .unwrap();
                 }
                 // Note: Any code after continue in the same block is unreachable
             }
+            IRStmt::Pass => {}
         }
         Ok(())
     }
@@ -534,16 +1185,60 @@ impl<'ctx> Compiler<'ctx> {
             IRExpr::BinaryOp { op, left, right } => {
                 expression::compile_binary_op(self, op, left, right)
             }
-            IRExpr::Call { func, args } => expression::compile_call(self, func, args),
+            IRExpr::Call {
+                func,
+                args,
+                keywords,
+            } => expression::compile_call(self, func, args, keywords),
             IRExpr::Input => expression::compile_input(self),
             IRExpr::Len(arg) => expression::compile_len(self, arg),
             IRExpr::Comparison { op, left, right } => {
                 expression::compile_comparison(self, op, left, right)
             }
             IRExpr::StringLiteral(s) => expression::compile_string_literal(self, s),
+            IRExpr::NoneLiteral => Ok(self.create_pyobject_none()),
+            IRExpr::IfExp {
+                condition,
+                then_value,
+                else_value,
+            } => expression::compile_if_exp(self, condition, then_value, else_value),
+            IRExpr::NamedExpr { target, value } => {
+                expression::compile_named_expr(self, target, value)
+            }
             IRExpr::UnaryOp { op, operand } => expression::compile_unary_op(self, op, operand),
+            IRExpr::BoolOp { op, values } => expression::compile_bool_op(self, op, values),
             IRExpr::List(elements) => expression::compile_list(self, elements),
+            IRExpr::Tuple(elements) => expression::compile_tuple(self, elements),
+            IRExpr::Dict(entries) => expression::compile_dict(self, entries),
+            IRExpr::Set(elements) => expression::compile_set(self, elements),
             IRExpr::Index { list, index } => expression::compile_index(self, list, index),
+            IRExpr::Reversed(list) => expression::compile_reversed(self, list),
+            IRExpr::Sum(list) => expression::compile_sum(self, list),
+            IRExpr::Abs(value) => expression::compile_abs(self, value),
+            IRExpr::Int(value) => expression::compile_int(self, value),
+            IRExpr::MinMax { is_max, args } => expression::compile_min_max(self, *is_max, args),
+            IRExpr::TypeName(value) => expression::compile_type_name(self, value),
+            IRExpr::IntToBase { base, value } => {
+                expression::compile_int_to_base(self, *base, value)
+            }
+            IRExpr::Sorted { list, key } => expression::compile_sorted(self, list, key.as_deref()),
+            IRExpr::Zip(left, right) => expression::compile_zip(self, left, right),
+            IRExpr::IsClose(left, right) => expression::compile_isclose(self, left, right),
+            IRExpr::ListComp {
+                element,
+                var,
+                source,
+                conditions,
+            } => expression::compile_list_comp(self, element, var, source, conditions),
+            IRExpr::Map { func, list } => expression::compile_map(self, func, list),
+            IRExpr::FString(parts) => expression::compile_fstring(self, parts),
+            IRExpr::Filter { func, list } => expression::compile_filter(self, func, list),
+            IRExpr::Open { path, mode } => expression::compile_open(self, path, mode),
+            IRExpr::MethodCall {
+                object,
+                method,
+                args,
+            } => expression::compile_method_call(self, object, method, args),
         }
     }
 
@@ -566,10 +1261,30 @@ impl<'ctx> Compiler<'ctx> {
         self.functions.insert(name.to_string(), function);
         self.function_defaults
             .insert(name.to_string(), defaults.to_vec());
+        self.function_params
+            .insert(name.to_string(), params.to_vec());
 
         function
     }
 
+    /// Compiles the default of parameter `index` of `func`: a load of the value
+    /// stored when the function was defined, or the literal itself
+    pub(crate) fn compile_default(
+        &mut self,
+        func: &str,
+        index: usize,
+        default: &IRExpr,
+    ) -> Result<IntValue<'ctx>, CodeGenError> {
+        match self.default_globals.get(&(func.to_string(), index)) {
+            Some(&global) => Ok(self
+                .builder
+                .build_load(self.create_pyobject_type(), global, "default")
+                .unwrap()
+                .into_int_value()),
+            None => self.compile_expression(default),
+        }
+    }
+
     /// Compiles the body of a previously declared function.
     /// This is the second pass for supporting mutual recursion.
     fn compile_function_body(
@@ -590,6 +1305,11 @@ impl<'ctx> Compiler<'ctx> {
         // Save current variable scope
         let saved_variables = self.variables.clone();
         self.variables.clear();
+        self.volatile_variables = contains_try(body.iter());
+        for name in declared_globals(body.iter()) {
+            self.variables
+                .insert(name.clone(), self.module_globals[&name]);
+        }
 
         // Set up parameters as local variables
         for (i, param_name) in params.iter().enumerate() {
@@ -599,148 +1319,4977 @@ impl<'ctx> Compiler<'ctx> {
             self.variables.insert(param_name.clone(), alloca);
         }
 
+        // Count this call towards the recursion limit
+        if let Some(limit) = self.recursion_limit {
+            let depth_global = self
+                .runtime
+                .add_recursion_depth(&self.module)
+                .as_pointer_value();
+            let i64_type = self.context.i64_type();
+            let depth = self
+                .builder
+                .build_load(i64_type, depth_global, "recursion_depth")
+                .unwrap()
+                .into_int_value();
+            let depth = self
+                .builder
+                .build_int_add(depth, i64_type.const_int(1, false), "recursion_depth")
+                .unwrap();
+            self.builder.build_store(depth_global, depth).unwrap();
+            let too_deep = self
+                .builder
+                .build_int_compare(
+                    inkwell::IntPredicate::UGT,
+                    depth,
+                    i64_type.const_int(limit, false),
+                    "too_deep",
+                )
+                .unwrap();
+            self.build_raise_if(too_deep, "RecursionError: maximum recursion depth exceeded");
+        }
+
         // Compile function body
+        let saved_string_buffers = std::mem::take(&mut self.string_buffers);
+        self.declare_string_buffers(body.iter(), function);
+        let saved_deleted_variables =
+            std::mem::replace(&mut self.deleted_variables, deleted_names(body.iter()));
         for stmt in body {
             self.compile_statement(stmt, function)?;
         }
 
+        // Falling off the end of the body returns None. A function specialized to raw
+        // ints never falls through (see `infer_return_types`), so its 0 only closes a
+        // merge block after branches that all return.
+        let current_block = self.builder.get_insert_block().unwrap();
+        if current_block.get_terminator().is_none() {
+            self.build_recursion_exit();
+            let value = match self.function_return_type(name) {
+                ReturnType::Int => self.context.i64_type().const_zero(),
+                ReturnType::PyObject => self.create_pyobject_none(),
+            };
+            self.builder.build_return(Some(&value)).unwrap();
+        }
+
         // Restore variable scope
         self.variables = saved_variables;
-
-        // Verify function
-        if !function.verify(true) {
-            return Err(CodeGenError::ModuleVerification(format!(
-                "Function '{}' verification failed",
-                name
-            )));
-        }
+        self.string_buffers = saved_string_buffers;
+        self.deleted_variables = saved_deleted_variables;
 
         Ok(())
     }
 
-    pub(crate) fn create_entry_block_alloca(
+    /// Loads a variable's value from its stack slot
+    pub(crate) fn build_variable_load(
         &self,
+        ptr: PointerValue<'ctx>,
         name: &str,
-        function: FunctionValue<'ctx>,
-    ) -> PointerValue<'ctx> {
-        let builder = self.context.create_builder();
-        let entry = function.get_first_basic_block().unwrap();
-
-        match entry.get_first_instruction() {
-            Some(first_instr) => builder.position_before(&first_instr),
-            None => builder.position_at_end(entry),
+    ) -> IntValue<'ctx> {
+        let load = self
+            .builder
+            .build_load(self.create_pyobject_type(), ptr, name)
+            .unwrap()
+            .into_int_value();
+        if self.volatile_variables {
+            load.as_instruction().unwrap().set_volatile(true).unwrap();
         }
+        load
+    }
 
-        // Allocate space for PyObject struct
-        let pyobject_type = self.create_pyobject_type();
-        builder.build_alloca(pyobject_type, name).unwrap()
+    /// Raises an error if a variable `del` may have unbound holds the unbound marker:
+    /// a NameError for a module-level variable, an UnboundLocalError for a local one
+    pub(crate) fn build_bound_check(&mut self, value: IntValue<'ctx>, name: &str) {
+        if !self.deleted_variables.contains(name) {
+            return;
+        }
+        let unbound = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                value,
+                self.values.create_unbound(),
+                "is_unbound",
+            )
+            .unwrap();
+        let is_global = self
+            .variables
+            .get(name)
+            .is_some_and(|ptr| self.module_globals.get(name) == Some(ptr));
+        let message = if self.main_entry_block.is_some() || is_global {
+            format!("NameError: name '{}' is not defined", name)
+        } else {
+            format!(
+                "UnboundLocalError: cannot access local variable '{}' where it is not associated with a value",
+                name
+            )
+        };
+        self.build_raise_if(unbound, &message);
     }
 
-    pub(crate) fn build_print_value(&mut self, pyobject: IntValue<'ctx>, with_newline: bool) {
-        let printf = self.runtime.add_printf(&self.module);
+    /// Stores a value into a variable's stack slot
+    pub(crate) fn build_variable_store(&self, ptr: PointerValue<'ctx>, value: IntValue<'ctx>) {
+        let store = self.builder.build_store(ptr, value).unwrap();
+        if self.volatile_variables {
+            store.set_volatile(true).unwrap();
+        }
+    }
 
-        // Extract tag and payload
-        let tag = self.extract_tag(pyobject);
-        let payload = self.extract_payload(pyobject);
+    /// Returns the inferred return type of a user function (PyObject if unknown)
+    pub(crate) fn function_return_type(&self, name: &str) -> ReturnType {
+        self.function_return_types
+            .get(name)
+            .copied()
+            .unwrap_or(ReturnType::PyObject)
+    }
 
-        // Check the tag to determine print type
-        let int_tag = self
-            .context
-            .i64_type()
-            .const_int(TYPE_TAG_INT as u64, false);
-        let string_tag = self
-            .context
-            .i64_type()
-            .const_int(TYPE_TAG_STRING as u64, false);
+    /// Compiles `for var in iter` over a string, list, tuple, dict or set
+    ///
+    /// The iterable is evaluated once. Lists and tuples bind each element in turn, and
+    /// dicts and sets each key in insertion order. Strings bind each character as a
+    /// one-character string from a constant table, so iterating allocates nothing.
+    /// Any other value raises a TypeError.
+    fn compile_for_each(
+        &mut self,
+        var: &str,
+        iter: &IRExpr,
+        body: &[IRStmt],
+        else_body: &[IRStmt],
+        current_fn: FunctionValue<'ctx>,
+    ) -> Result<(), CodeGenError> {
+        let i64_type = self.context.i64_type();
+        let pyobject_type = self.create_pyobject_type();
 
-        let is_int = self
+        let iter_obj = self.compile_expression(iter)?;
+        let tag = self.extract_tag(iter_obj);
+        let has_tag = |compiler: &Self, type_tag: u8, name: &str| {
+            compiler
+                .builder
+                .build_int_compare(
+                    inkwell::IntPredicate::EQ,
+                    tag,
+                    i64_type.const_int(type_tag as u64, false),
+                    name,
+                )
+                .unwrap()
+        };
+        let is_string = has_tag(self, TYPE_TAG_STRING, "iter_is_string");
+        let is_list = has_tag(self, TYPE_TAG_LIST, "iter_is_list");
+        let is_tuple = has_tag(self, TYPE_TAG_TUPLE, "iter_is_tuple");
+        let is_dict = has_tag(self, TYPE_TAG_DICT, "iter_is_dict");
+        let is_set = has_tag(self, TYPE_TAG_SET, "iter_is_set");
+        let is_sequence = self
             .builder
-            .build_int_compare(inkwell::IntPredicate::EQ, tag, int_tag, "is_int")
+            .build_or(is_list, is_tuple, "iter_is_sequence")
             .unwrap();
-        let is_string = self
+        let is_mapping = self
             .builder
-            .build_int_compare(inkwell::IntPredicate::EQ, tag, string_tag, "is_string")
+            .build_or(is_dict, is_set, "iter_is_mapping")
             .unwrap();
-
-        // Get current function for creating basic blocks
-        let current_fn = self
+        let is_iterable = self
             .builder
-            .get_insert_block()
-            .unwrap()
-            .get_parent()
+            .build_or(is_string, is_sequence, "iter_is_text_or_sequence")
             .unwrap();
-
-        // Create basic blocks for type dispatch
-        let check_int_block = self.context.append_basic_block(current_fn, "check_int");
-        let int_block = self.context.append_basic_block(current_fn, "print_int");
-        let float_block = self.context.append_basic_block(current_fn, "print_float");
-        let string_block = self.context.append_basic_block(current_fn, "print_string");
-        let end_block = self.context.append_basic_block(current_fn, "print_end");
-
-        // First, check if it's a string
-        self.builder
-            .build_conditional_branch(is_string, string_block, check_int_block)
+        let is_iterable = self
+            .builder
+            .build_or(is_iterable, is_mapping, "iter_is_iterable")
             .unwrap();
-
-        // If not string, check if it's int
-        self.builder.position_at_end(check_int_block);
-        self.builder
-            .build_conditional_branch(is_int, int_block, float_block)
+        let not_iterable = self
+            .builder
+            .build_not(is_iterable, "iter_not_iterable")
             .unwrap();
+        self.build_raise_if(not_iterable, "TypeError: object is not iterable");
 
-        // Print int
-        self.builder.position_at_end(int_block);
-        let int_val = self
+        let string_len_bb = self
+            .context
+            .append_basic_block(current_fn, "foreach_str_len");
+        let not_string_bb = self
+            .context
+            .append_basic_block(current_fn, "foreach_not_str");
+        let dict_len_bb = self
+            .context
+            .append_basic_block(current_fn, "foreach_dict_len");
+        let list_len_bb = self
+            .context
+            .append_basic_block(current_fn, "foreach_list_len");
+        let init_bb = self.context.append_basic_block(current_fn, "foreach_init");
+        let loop_cond_bb = self.context.append_basic_block(current_fn, "foreach_cond");
+        let load_char_bb = self.context.append_basic_block(current_fn, "foreach_char");
+        let not_char_bb = self
+            .context
+            .append_basic_block(current_fn, "foreach_not_char");
+        let load_key_bb = self.context.append_basic_block(current_fn, "foreach_key");
+        let load_elem_bb = self.context.append_basic_block(current_fn, "foreach_elem");
+        let loop_body_bb = self.context.append_basic_block(current_fn, "foreach_body");
+        let loop_incr_bb = self.context.append_basic_block(current_fn, "foreach_incr");
+        let loop_exit_bb = self.context.append_basic_block(current_fn, "foreach_exit");
+        let loop_done_bb = self.loop_else_block(else_body, loop_exit_bb, current_fn);
+
+        // Strings, lists, tuples, dicts and sets all keep their heap pointer in the
+        // payload
+        let iter_ptr = self.extract_string_ptr(iter_obj);
+
+        // Determine the number of iterations: strlen for strings, the header for the
+        // others
+        self.builder
+            .build_conditional_branch(is_string, string_len_bb, not_string_bb)
+            .unwrap();
+
+        self.builder.position_at_end(string_len_bb);
+        let strlen = self.runtime.add_strlen(&self.module);
+        let str_len_result = self
+            .builder
+            .build_call(strlen, &[iter_ptr.into()], "str_len")
+            .unwrap();
+        let str_len = match str_len_result.try_as_basic_value() {
+            ValueKind::Basic(value) => value.into_int_value(),
+            ValueKind::Instruction(_) => {
+                return Err(CodeGenError::UndefinedVariable(
+                    "strlen did not return a value".to_string(),
+                ))
+            }
+        };
+        self.builder.build_unconditional_branch(init_bb).unwrap();
+
+        self.builder.position_at_end(not_string_bb);
+        self.builder
+            .build_conditional_branch(is_mapping, dict_len_bb, list_len_bb)
+            .unwrap();
+
+        self.builder.position_at_end(dict_len_bb);
+        let (_, dict_len) = self.extract_dict_entries(iter_obj);
+        self.builder.build_unconditional_branch(init_bb).unwrap();
+
+        self.builder.position_at_end(list_len_bb);
+        let (_, list_len) = self.extract_list_ptr_and_len(iter_obj);
+        self.builder.build_unconditional_branch(init_bb).unwrap();
+
+        self.builder.position_at_end(init_bb);
+        let len_phi = self.builder.build_phi(i64_type, "iter_len").unwrap();
+        len_phi.add_incoming(&[
+            (&str_len, string_len_bb),
+            (&dict_len, dict_len_bb),
+            (&list_len, list_len_bb),
+        ]);
+        let iter_len = len_phi.as_basic_value().into_int_value();
+
+        let var_ptr = self.variables.get(var).copied().unwrap_or_else(|| {
+            let ptr = self.create_entry_block_alloca(var, current_fn);
+            self.variables.insert(var.to_string(), ptr);
+            ptr
+        });
+        let index_ptr = self.create_entry_block_alloca(&format!("{}_index", var), current_fn);
+        self.builder
+            .build_store(index_ptr, i64_type.const_zero())
+            .unwrap();
+        self.builder
+            .build_unconditional_branch(loop_cond_bb)
+            .unwrap();
+
+        // Continue goes to increment, break to exit
+        self.loop_stack
+            .push((loop_incr_bb, loop_exit_bb, self.try_stack.len()));
+
+        // Condition: index < len
+        self.builder.position_at_end(loop_cond_bb);
+        let index = self
+            .builder
+            .build_load(i64_type, index_ptr, "foreach_index")
+            .unwrap()
+            .into_int_value();
+        let in_range = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::SLT, index, iter_len, "foreach_cond")
+            .unwrap();
+        let fetch_bb = self.context.append_basic_block(current_fn, "foreach_fetch");
+        self.builder
+            .build_conditional_branch(in_range, fetch_bb, loop_done_bb)
+            .unwrap();
+        self.builder.position_at_end(fetch_bb);
+        self.builder
+            .build_conditional_branch(is_string, load_char_bb, not_char_bb)
+            .unwrap();
+        self.builder.position_at_end(not_char_bb);
+        self.builder
+            .build_conditional_branch(is_mapping, load_key_bb, load_elem_bb)
+            .unwrap();
+
+        // String: the character at index, as a constant one-character string
+        self.builder.position_at_end(load_char_bb);
+        let char_obj = self.build_constant_char_string(iter_ptr, index);
+        self.build_variable_store(var_ptr, char_obj);
+        self.builder
+            .build_unconditional_branch(loop_body_bb)
+            .unwrap();
+
+        // Dict or set: the key of entry index. The entries are reloaded every time
+        // since the body may have grown the dict.
+        self.builder.position_at_end(load_key_bb);
+        let (entries, _) = self.extract_dict_entries(iter_obj);
+        let key_slot = self
+            .builder
+            .build_int_mul(index, i64_type.const_int(2, false), "key_slot")
+            .unwrap();
+        let key_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(pyobject_type, entries, &[key_slot], "key_ptr")
+                .unwrap()
+        };
+        let key = self
+            .builder
+            .build_load(pyobject_type, key_ptr, "key")
+            .unwrap()
+            .into_int_value();
+        self.build_variable_store(var_ptr, key);
+        self.builder
+            .build_unconditional_branch(loop_body_bb)
+            .unwrap();
+
+        // List or tuple: load the element at index (skipping the length header)
+        self.builder.position_at_end(load_elem_bb);
+        let adjusted_index = self
+            .builder
+            .build_int_add(index, i64_type.const_int(1, false), "adjusted_index")
+            .unwrap();
+        let elem_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(pyobject_type, iter_ptr, &[adjusted_index], "elem_ptr")
+                .unwrap()
+        };
+        let elem = self
+            .builder
+            .build_load(pyobject_type, elem_ptr, "elem")
+            .unwrap()
+            .into_int_value();
+        self.build_variable_store(var_ptr, elem);
+        self.builder
+            .build_unconditional_branch(loop_body_bb)
+            .unwrap();
+
+        // Loop body
+        self.builder.position_at_end(loop_body_bb);
+        for stmt in body {
+            self.compile_statement(stmt, current_fn)?;
+        }
+        let current_block = self.builder.get_insert_block().unwrap();
+        if current_block.get_terminator().is_none() {
+            self.builder
+                .build_unconditional_branch(loop_incr_bb)
+                .unwrap();
+        }
+
+        // Increment: index += 1
+        self.builder.position_at_end(loop_incr_bb);
+        let index = self
+            .builder
+            .build_load(i64_type, index_ptr, "foreach_index")
+            .unwrap()
+            .into_int_value();
+        let next_index = self
+            .builder
+            .build_int_add(index, i64_type.const_int(1, false), "next_index")
+            .unwrap();
+        self.builder.build_store(index_ptr, next_index).unwrap();
+        self.builder
+            .build_unconditional_branch(loop_cond_bb)
+            .unwrap();
+
+        self.loop_stack.pop();
+        self.compile_loop_else(else_body, loop_done_bb, loop_exit_bb, current_fn)
+    }
+
+    /// Returns the character at `index` of a string as a one-character string from a
+    /// constant table of all 256 of them, built on first use. The table is never
+    /// written to: appending to such a string copies it first, like any string a
+    /// text buffer does not own.
+    pub(crate) fn build_constant_char_string(
+        &mut self,
+        str_ptr: PointerValue<'ctx>,
+        index: IntValue<'ctx>,
+    ) -> IntValue<'ctx> {
+        let i8_type = self.context.i8_type();
+        let i64_type = self.context.i64_type();
+        // Entry `c` is the two bytes `c, 0` at offset 2 * c
+        let table = self
+            .module
+            .get_global("one_char_strings")
+            .unwrap_or_else(|| {
+                let bytes: Vec<u8> = (0..=255u8).flat_map(|byte| [byte, 0]).collect();
+                let initializer = self.context.const_string(&bytes, false);
+                let table =
+                    self.module
+                        .add_global(initializer.get_type(), None, "one_char_strings");
+                table.set_initializer(&initializer);
+                table.set_constant(true);
+                table.set_linkage(inkwell::module::Linkage::Private);
+                table
+            });
+
+        let src_char_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(i8_type, str_ptr, &[index], "src_char_ptr")
+                .unwrap()
+        };
+        let ch = self
+            .builder
+            .build_load(i8_type, src_char_ptr, "char")
+            .unwrap()
+            .into_int_value();
+        let ch = self
+            .builder
+            .build_int_z_extend(ch, i64_type, "char_index")
+            .unwrap();
+        let offset = self
+            .builder
+            .build_int_mul(ch, i64_type.const_int(2, false), "char_offset")
+            .unwrap();
+        let char_str = unsafe {
+            self.builder
+                .build_in_bounds_gep(i8_type, table.as_pointer_value(), &[offset], "char_str")
+                .unwrap()
+        };
+        self.create_pyobject_string(char_str)
+    }
+
+    /// Compiles `try: body except: handler else: else_body`
+    ///
+    /// The try block registers a stack-allocated jmp_buf as the active exception handler
+    /// and calls setjmp. A runtime error in the body (or any function it calls) makes
+    /// `raise_error` longjmp back, so setjmp returns non-zero and the handler runs.
+    /// The previous handler is restored on every way out of the body.
+    fn compile_try(
+        &mut self,
+        body: &[IRStmt],
+        exceptions: &[String],
+        handler: &[IRStmt],
+        else_body: &[IRStmt],
+        current_fn: FunctionValue<'ctx>,
+    ) -> Result<(), CodeGenError> {
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let handler_global = self
+            .runtime
+            .add_exception_handler(&self.module)
+            .as_pointer_value();
+        let setjmp = self.runtime.add_setjmp(&self.module);
+
+        // The jmp_buf and the saved handler live in the entry block, like variables
+        let entry_builder = self.context.create_builder();
+        let entry = current_fn.get_first_basic_block().unwrap();
+        match entry.get_first_instruction() {
+            Some(first_instr) => entry_builder.position_before(&first_instr),
+            None => entry_builder.position_at_end(entry),
+        }
+        let jmp_buf_type = self.context.i8_type().array_type(JMP_BUF_SIZE);
+        let jmp_buf = entry_builder.build_alloca(jmp_buf_type, "jmp_buf").unwrap();
+        jmp_buf.as_instruction().unwrap().set_alignment(16).unwrap();
+        let saved_handler = entry_builder
+            .build_alloca(ptr_type, "saved_handler")
+            .unwrap();
+
+        // A longjmp skips the depth decrements of the functions it unwinds,
+        // so the handler resets the depth to its value at the try
+        let saved_depth = if self.recursion_limit.is_some() {
+            let i64_type = self.context.i64_type();
+            let depth_global = self
+                .runtime
+                .add_recursion_depth(&self.module)
+                .as_pointer_value();
+            let slot = entry_builder.build_alloca(i64_type, "saved_depth").unwrap();
+            let depth = self
+                .builder
+                .build_load(i64_type, depth_global, "recursion_depth")
+                .unwrap();
+            self.builder.build_store(slot, depth).unwrap();
+            Some((slot, depth_global))
+        } else {
+            None
+        };
+
+        // Save the enclosing handler and install this one
+        let previous = self
+            .builder
+            .build_load(ptr_type, handler_global, "previous_handler")
+            .unwrap();
+        self.builder.build_store(saved_handler, previous).unwrap();
+        self.builder.build_store(handler_global, jmp_buf).unwrap();
+        let setjmp_result = self
+            .builder
+            .build_call(setjmp, &[jmp_buf.into()], "setjmp_result")
+            .unwrap();
+        let setjmp_result = match setjmp_result.try_as_basic_value() {
+            ValueKind::Basic(value) => value.into_int_value(),
+            ValueKind::Instruction(_) => {
+                return Err(CodeGenError::UndefinedVariable(
+                    "setjmp did not return a value".to_string(),
+                ))
+            }
+        };
+        let raised = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::NE,
+                setjmp_result,
+                self.context.i32_type().const_zero(),
+                "raised",
+            )
+            .unwrap();
+
+        let try_body_bb = self.context.append_basic_block(current_fn, "try_body");
+        let except_bb = self.context.append_basic_block(current_fn, "except");
+        let try_end_bb = self.context.append_basic_block(current_fn, "try_end");
+        self.builder
+            .build_conditional_branch(raised, except_bb, try_body_bb)
+            .unwrap();
+
+        // Body, with this handler active
+        self.builder.position_at_end(try_body_bb);
+        self.try_stack.push(saved_handler);
+        for stmt in body {
+            self.compile_statement(stmt, current_fn)?;
+        }
+        self.try_stack.pop();
+        let current_block = self.builder.get_insert_block().unwrap();
+        if current_block.get_terminator().is_none() {
+            let previous = self
+                .builder
+                .build_load(ptr_type, saved_handler, "previous_handler")
+                .unwrap();
+            self.builder.build_store(handler_global, previous).unwrap();
+            // Errors in the else body are not handled by this try
+            for stmt in else_body {
+                self.compile_statement(stmt, current_fn)?;
+            }
+            let current_block = self.builder.get_insert_block().unwrap();
+            if current_block.get_terminator().is_none() {
+                self.builder.build_unconditional_branch(try_end_bb).unwrap();
+            }
+        }
+
+        // Handler, with the enclosing handler active again
+        self.builder.position_at_end(except_bb);
+        let previous = self
+            .builder
+            .build_load(ptr_type, saved_handler, "previous_handler")
+            .unwrap();
+        self.builder.build_store(handler_global, previous).unwrap();
+        if let Some((slot, depth_global)) = saved_depth {
+            let depth = self
+                .builder
+                .build_load(self.context.i64_type(), slot, "saved_depth")
+                .unwrap();
+            self.builder.build_store(depth_global, depth).unwrap();
+        }
+        if !exceptions.is_empty() {
+            self.build_reraise_unless_caught(exceptions, current_fn)?;
+        }
+        for stmt in handler {
+            self.compile_statement(stmt, current_fn)?;
+        }
+        let current_block = self.builder.get_insert_block().unwrap();
+        if current_block.get_terminator().is_none() {
+            self.builder.build_unconditional_branch(try_end_bb).unwrap();
+        }
+
+        self.builder.position_at_end(try_end_bb);
+        Ok(())
+    }
+
+    /// Re-raises the current error (to the enclosing handler, or out of the program)
+    /// unless its type is one of `exceptions`. Errors are raised as their message,
+    /// `Type` or `Type: detail`, so the type is read from the start of the message
+    fn build_reraise_unless_caught(
+        &mut self,
+        exceptions: &[String],
+        current_fn: FunctionValue<'ctx>,
+    ) -> Result<(), CodeGenError> {
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let current_exception = self
+            .runtime
+            .add_current_exception(&self.module)
+            .as_pointer_value();
+        let message = self
+            .builder
+            .build_load(ptr_type, current_exception, "current_exception")
+            .unwrap()
+            .into_pointer_value();
+        let strcmp = self.runtime.add_strcmp(&self.module);
+        let strncmp = self.runtime.add_strncmp(&self.module);
+
+        // The message is either exactly `Type` or starts with `Type:`
+        let mut caught = self.context.bool_type().const_zero();
+        for name in exceptions {
+            let bare = self
+                .builder
+                .build_global_string_ptr(name, "exception_type")
+                .unwrap()
+                .as_pointer_value();
+            let prefix = self
+                .builder
+                .build_global_string_ptr(&format!("{}:", name), "exception_prefix")
+                .unwrap()
+                .as_pointer_value();
+            let prefix_len = self
+                .context
+                .i64_type()
+                .const_int(name.len() as u64 + 1, false);
+            let comparisons = [
+                self.builder
+                    .build_call(strcmp, &[message.into(), bare.into()], "type_compare")
+                    .unwrap(),
+                self.builder
+                    .build_call(
+                        strncmp,
+                        &[message.into(), prefix.into(), prefix_len.into()],
+                        "prefix_compare",
+                    )
+                    .unwrap(),
+            ];
+            for compared in comparisons {
+                let ValueKind::Basic(compared) = compared.try_as_basic_value() else {
+                    return Err(CodeGenError::UndefinedVariable(
+                        "string comparison did not return a value".to_string(),
+                    ));
+                };
+                let matches = self
+                    .builder
+                    .build_int_compare(
+                        inkwell::IntPredicate::EQ,
+                        compared.into_int_value(),
+                        self.context.i32_type().const_zero(),
+                        "type_matches",
+                    )
+                    .unwrap();
+                caught = self.builder.build_or(caught, matches, "caught").unwrap();
+            }
+        }
+
+        let reraise_bb = self.context.append_basic_block(current_fn, "reraise");
+        let handle_bb = self.context.append_basic_block(current_fn, "handle");
+        self.builder
+            .build_conditional_branch(caught, handle_bb, reraise_bb)
+            .unwrap();
+        self.builder.position_at_end(reraise_bb);
+        let raise_error = self.runtime.add_raise_error(&self.module);
+        self.builder
+            .build_call(raise_error, &[message.into()], "")
+            .unwrap();
+        self.builder.build_unreachable().unwrap();
+        self.builder.position_at_end(handle_bb);
+        Ok(())
+    }
+
+    /// Decrements the call depth before a user function returns (--recursion-limit)
+    pub(crate) fn build_recursion_exit(&mut self) {
+        if self.recursion_limit.is_some() {
+            let depth_global = self
+                .runtime
+                .add_recursion_depth(&self.module)
+                .as_pointer_value();
+            let i64_type = self.context.i64_type();
+            let depth = self
+                .builder
+                .build_load(i64_type, depth_global, "recursion_depth")
+                .unwrap()
+                .into_int_value();
+            let depth = self
+                .builder
+                .build_int_sub(depth, i64_type.const_int(1, false), "recursion_depth")
+                .unwrap();
+            self.builder.build_store(depth_global, depth).unwrap();
+        }
+    }
+
+    /// Restores the exception handler that was active before the try block at
+    /// `try_depth` was entered, for `break`/`continue`/`return` jumping out of try bodies
+    pub(crate) fn restore_exception_handler(&mut self, try_depth: usize) {
+        if let Some(&saved_handler) = self.try_stack.get(try_depth) {
+            let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+            let handler_global = self
+                .runtime
+                .add_exception_handler(&self.module)
+                .as_pointer_value();
+            let previous = self
+                .builder
+                .build_load(ptr_type, saved_handler, "previous_handler")
+                .unwrap();
+            self.builder.build_store(handler_global, previous).unwrap();
+        }
+    }
+
+    /// Branches to `raise_error(message)` when `condition` is true and continues
+    /// building in a fresh block otherwise
+    pub(crate) fn build_raise_if(&mut self, condition: IntValue<'ctx>, message: &str) {
+        let current_fn = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let raise_bb = self.context.append_basic_block(current_fn, "raise");
+        let continue_bb = self.context.append_basic_block(current_fn, "no_error");
+        self.builder
+            .build_conditional_branch(condition, raise_bb, continue_bb)
+            .unwrap();
+
+        self.builder.position_at_end(raise_bb);
+        let raise_error = self.runtime.add_raise_error(&self.module);
+        let message_ptr = self
+            .builder
+            .build_global_string_ptr(message, "error_message")
+            .unwrap()
+            .as_pointer_value();
+        self.builder
+            .build_call(raise_error, &[message_ptr.into()], "")
+            .unwrap();
+        self.builder.build_unreachable().unwrap();
+
+        self.builder.position_at_end(continue_bb);
+    }
+
+    /// Raises a TypeError unless `value` is an int or a bool, the only values
+    /// Python accepts where it needs an integer (range bounds, hex(), ...)
+    pub(crate) fn build_require_integer(&mut self, value: IntValue<'ctx>) {
+        let i64_type = self.context.i64_type();
+        let tag = self.extract_tag(value);
+        let is_int = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                tag,
+                i64_type.const_int(TYPE_TAG_INT as u64, false),
+                "is_int",
+            )
+            .unwrap();
+        let is_bool = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                tag,
+                i64_type.const_int(TYPE_TAG_BOOL as u64, false),
+                "is_bool",
+            )
+            .unwrap();
+        let is_integer = self
+            .builder
+            .build_or(is_int, is_bool, "is_integer")
+            .unwrap();
+        let not_integer = self.builder.build_not(is_integer, "not_integer").unwrap();
+        self.build_raise_if(
+            not_integer,
+            "TypeError: object cannot be interpreted as an integer",
+        );
+    }
+
+    /// Returns the block a loop branches to when it finishes without `break`:
+    /// a new block for the else body, or the exit block itself when there is none.
+    /// `break` always targets the exit block, so it skips the else body.
+    fn loop_else_block(
+        &self,
+        else_body: &[IRStmt],
+        loop_exit_bb: inkwell::basic_block::BasicBlock<'ctx>,
+        current_fn: FunctionValue<'ctx>,
+    ) -> inkwell::basic_block::BasicBlock<'ctx> {
+        if else_body.is_empty() {
+            loop_exit_bb
+        } else {
+            self.context.append_basic_block(current_fn, "loop_else")
+        }
+    }
+
+    /// Compiles a loop's else body into `loop_done_bb` (see `loop_else_block`) and
+    /// leaves the builder positioned at the loop exit
+    fn compile_loop_else(
+        &mut self,
+        else_body: &[IRStmt],
+        loop_done_bb: inkwell::basic_block::BasicBlock<'ctx>,
+        loop_exit_bb: inkwell::basic_block::BasicBlock<'ctx>,
+        current_fn: FunctionValue<'ctx>,
+    ) -> Result<(), CodeGenError> {
+        if loop_done_bb != loop_exit_bb {
+            self.builder.position_at_end(loop_done_bb);
+            for stmt in else_body {
+                self.compile_statement(stmt, current_fn)?;
+            }
+            let current_block = self.builder.get_insert_block().unwrap();
+            if current_block.get_terminator().is_none() {
+                self.builder
+                    .build_unconditional_branch(loop_exit_bb)
+                    .unwrap();
+            }
+        }
+        self.builder.position_at_end(loop_exit_bb);
+        Ok(())
+    }
+
+    pub(crate) fn create_entry_block_alloca(
+        &self,
+        name: &str,
+        function: FunctionValue<'ctx>,
+    ) -> PointerValue<'ctx> {
+        let builder = self.context.create_builder();
+        let entry = function.get_first_basic_block().unwrap();
+
+        match entry.get_first_instruction() {
+            Some(first_instr) => builder.position_before(&first_instr),
+            None => builder.position_at_end(entry),
+        }
+
+        // Allocate space for PyObject struct
+        let pyobject_type = self.create_pyobject_type();
+        builder.build_alloca(pyobject_type, name).unwrap()
+    }
+
+    /// Gives every local variable the body appends to with `s = s + ...` an empty
+    /// text buffer, created up front so that reads compiled before the first append
+    /// (e.g. earlier in a loop body) still release the buffer's ownership
+    fn declare_string_buffers<'a>(
+        &mut self,
+        body: impl Iterator<Item = &'a IRStmt>,
+        function: FunctionValue<'ctx>,
+    ) {
+        let buffer_type = self.text_buffer_type();
+        for name in string_append_targets(body, &self.function_return_types) {
+            if self.module_globals.contains_key(&name) {
+                continue;
+            }
+            let entry_builder = self.context.create_builder();
+            let entry = function.get_first_basic_block().unwrap();
+            match entry.get_first_instruction() {
+                Some(first_instr) => entry_builder.position_before(&first_instr),
+                None => entry_builder.position_at_end(entry),
+            }
+            let buffer = entry_builder
+                .build_alloca(buffer_type, &format!("{}.buffer", name))
+                .unwrap();
+            self.builder
+                .build_store(buffer, buffer_type.const_zero())
+                .unwrap();
+            self.string_buffers.insert(name, buffer);
+        }
+    }
+
+    /// Returns the `{ ptr function, i64 arity, char* text }` type of the descriptor a
+    /// function value points to; `text` is what printing the value shows
+    pub(crate) fn function_descriptor_type(&self) -> inkwell::types::StructType<'ctx> {
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let i64_type = self.context.i64_type();
+        self.context
+            .struct_type(&[ptr_type.into(), i64_type.into(), ptr_type.into()], false)
+    }
+
+    /// Returns a user function as a first-class value, a NaN-boxed pointer to its
+    /// descriptor (built on first use)
+    ///
+    /// Calls through the value pass positional arguments only, so functions with
+    /// keyword-only parameters or `**kwargs` cannot be used as values. A function
+    /// specialized to return raw ints is called through a wrapper that boxes the result.
+    pub(crate) fn function_value(&mut self, name: &str) -> Result<IntValue<'ctx>, CodeGenError> {
+        let function = *self
+            .functions
+            .get(name)
+            .ok_or_else(|| CodeGenError::UndefinedVariable(name.to_string()))?;
+        let descriptor_name = format!("{}.descriptor", name);
+        if let Some(descriptor) = self.module.get_global(&descriptor_name) {
+            return Ok(self.create_pyobject_function(descriptor.as_pointer_value()));
+        }
+
+        let arity = self.function_params[name].len();
+        if self.function_kwargs.contains(name) || self.function_positional_counts[name] != arity {
+            return Err(CodeGenError::InvalidArguments(format!(
+                "function '{}' has keyword-only parameters or **kwargs and cannot be used as a value",
+                name
+            )));
+        }
+        let target = match self.function_return_type(name) {
+            ReturnType::Int => self.build_boxing_wrapper(name, function),
+            ReturnType::PyObject => function,
+        };
+
+        let text = self
+            .context
+            .const_string(format!("<function {}>", name).as_bytes(), true);
+        let text_global = self
+            .module
+            .add_global(text.get_type(), None, &format!("{}.text", name));
+        text_global.set_initializer(&text);
+        text_global.set_constant(true);
+        text_global.set_linkage(inkwell::module::Linkage::Internal);
+
+        let descriptor_type = self.function_descriptor_type();
+        let descriptor = self
+            .module
+            .add_global(descriptor_type, None, &descriptor_name);
+        descriptor.set_initializer(
+            &descriptor_type.const_named_struct(&[
+                target.as_global_value().as_pointer_value().into(),
+                self.context
+                    .i64_type()
+                    .const_int(arity as u64, false)
+                    .into(),
+                text_global.as_pointer_value().into(),
+            ]),
+        );
+        descriptor.set_constant(true);
+        descriptor.set_linkage(inkwell::module::Linkage::Internal);
+        Ok(self.create_pyobject_function(descriptor.as_pointer_value()))
+    }
+
+    /// Builds `<name>.boxed`, which calls a function returning a raw int (see
+    /// `ReturnType::Int`) and returns the result as a PyObject
+    fn build_boxing_wrapper(
+        &mut self,
+        name: &str,
+        function: FunctionValue<'ctx>,
+    ) -> FunctionValue<'ctx> {
+        let wrapper = self.module.add_function(
+            &format!("{}.boxed", name),
+            function.get_type(),
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(wrapper, "entry");
+        self.builder.position_at_end(entry);
+        let args: Vec<_> = wrapper.get_param_iter().map(|param| param.into()).collect();
+        let raw = self
+            .builder
+            .build_call(function, &args, "raw_result")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        let boxed = self.create_pyobject_int(raw);
+        self.builder.build_return(Some(&boxed)).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        wrapper
+    }
+
+    /// Returns the `{ char* data, i64 len, i64 cap }` text buffer type filled by
+    /// `buffer_append`; a zeroed buffer is empty
+    pub(crate) fn text_buffer_type(&self) -> inkwell::types::StructType<'ctx> {
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let i64_type = self.context.i64_type();
+        self.context
+            .struct_type(&[ptr_type.into(), i64_type.into(), i64_type.into()], false)
+    }
+
+    /// Prints a list or tuple (no newline) by formatting it into a stack-allocated text
+    /// buffer with `format_list`, printing the text once, and freeing it
+    fn build_print_list_buffered(
+        &mut self,
+        pyobject: IntValue<'ctx>,
+        function: FunctionValue<'ctx>,
+    ) {
+        let buffer_type = self.text_buffer_type();
+        let entry_builder = self.context.create_builder();
+        let entry = function.get_first_basic_block().unwrap();
+        match entry.get_first_instruction() {
+            Some(first_instr) => entry_builder.position_before(&first_instr),
+            None => entry_builder.position_at_end(entry),
+        }
+        let buffer = entry_builder
+            .build_alloca(buffer_type, "list_text")
+            .unwrap();
+        self.builder
+            .build_store(buffer, buffer_type.const_zero())
+            .unwrap();
+
+        let format_list = self.get_or_build_format_list();
+        self.builder
+            .build_call(format_list, &[pyobject.into(), buffer.into()], "")
+            .unwrap();
+        let data_ptr = self
+            .builder
+            .build_struct_gep(buffer_type, buffer, 0, "list_text_data_ptr")
+            .unwrap();
+        let data = self
+            .builder
+            .build_load(
+                self.context.ptr_type(inkwell::AddressSpace::default()),
+                data_ptr,
+                "list_text_data",
+            )
+            .unwrap();
+        let string_format = self
+            .format_strings
+            .get_string_format_string_no_newline(&self.builder);
+        self.build_print_call(&[string_format.into(), data.into()], "printf_list")
+            .unwrap();
+        let free = self.runtime.add_free(&self.module);
+        self.builder.build_call(free, &[data.into()], "").unwrap();
+    }
+
+    /// Returns the internal `buffer_append` helper, building it on first use
+    ///
+    /// `buffer_append(buffer, text)` appends a C string to a text buffer (see
+    /// `text_buffer_type`), growing the allocation with realloc to at least double its
+    /// capacity whenever the text and terminator do not fit.
+    pub(crate) fn get_or_build_buffer_append(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("buffer_append") {
+            return function;
+        }
+
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let buffer_type = self.text_buffer_type();
+        let fn_type = self
+            .context
+            .void_type()
+            .fn_type(&[ptr_type.into(), ptr_type.into()], false);
+        let function = self.module.add_function(
+            "buffer_append",
+            fn_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let grow_block = self.context.append_basic_block(function, "grow");
+        let copy_block = self.context.append_basic_block(function, "copy");
+
+        self.builder.position_at_end(entry);
+        let buffer = function.get_nth_param(0).unwrap().into_pointer_value();
+        let text = function.get_nth_param(1).unwrap().into_pointer_value();
+        let data_ptr = self
+            .builder
+            .build_struct_gep(buffer_type, buffer, 0, "data_ptr")
+            .unwrap();
+        let len_ptr = self
+            .builder
+            .build_struct_gep(buffer_type, buffer, 1, "len_ptr")
+            .unwrap();
+        let cap_ptr = self
+            .builder
+            .build_struct_gep(buffer_type, buffer, 2, "cap_ptr")
+            .unwrap();
+        let strlen = self.runtime.add_strlen(&self.module);
+        let text_len = self
+            .builder
+            .build_call(strlen, &[text.into()], "text_len")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        let len = self
+            .builder
+            .build_load(i64_type, len_ptr, "len")
+            .unwrap()
+            .into_int_value();
+        let cap = self
+            .builder
+            .build_load(i64_type, cap_ptr, "cap")
+            .unwrap()
+            .into_int_value();
+        let new_len = self
+            .builder
+            .build_int_add(len, text_len, "new_len")
+            .unwrap();
+        let needed = self
+            .builder
+            .build_int_add(new_len, i64_type.const_int(1, false), "needed")
+            .unwrap();
+        let too_small = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::UGT, needed, cap, "too_small")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(too_small, grow_block, copy_block)
+            .unwrap();
+
+        // Grow to max(2 * cap, needed); realloc of the initial null pointer allocates
+        self.builder.position_at_end(grow_block);
+        let doubled = self
+            .builder
+            .build_int_mul(cap, i64_type.const_int(2, false), "doubled")
+            .unwrap();
+        let doubled_fits = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::UGE, doubled, needed, "doubled_fits")
+            .unwrap();
+        let new_cap = self
+            .builder
+            .build_select(doubled_fits, doubled, needed, "new_cap")
+            .unwrap();
+        let old_data = self
+            .builder
+            .build_load(ptr_type, data_ptr, "old_data")
+            .unwrap();
+        let realloc = self.runtime.add_realloc(&self.module);
+        let new_data = self
+            .builder
+            .build_call(realloc, &[old_data.into(), new_cap.into()], "new_data")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap();
+        self.builder.build_store(data_ptr, new_data).unwrap();
+        self.builder.build_store(cap_ptr, new_cap).unwrap();
+        self.builder.build_unconditional_branch(copy_block).unwrap();
+
+        // Copy the text with its terminator after the current contents
+        self.builder.position_at_end(copy_block);
+        let data = self
+            .builder
+            .build_load(ptr_type, data_ptr, "data")
+            .unwrap()
+            .into_pointer_value();
+        let dest = unsafe {
+            self.builder
+                .build_in_bounds_gep(self.context.i8_type(), data, &[len], "dest")
+                .unwrap()
+        };
+        let copy_len = self
+            .builder
+            .build_int_add(text_len, i64_type.const_int(1, false), "copy_len")
+            .unwrap();
+        let memcpy = self.runtime.add_memcpy(&self.module);
+        self.builder
+            .build_call(memcpy, &[dest.into(), text.into(), copy_len.into()], "")
+            .unwrap();
+        self.builder.build_store(len_ptr, new_len).unwrap();
+        self.builder.build_return(None).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Returns the internal `values_equal` helper, building it on first use
+    ///
+    /// `values_equal(a, b)` is how lookups compare values: it holds when
+    /// `compare_values` finds them equal, so strings match by content, numbers by
+    /// value whatever their type, lists and tuples element by element, and any other
+    /// values only when they are the same object.
+    pub(crate) fn get_or_build_values_equal(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("values_equal") {
+            return function;
+        }
+
+        let compare_values = self.get_or_build_compare_values();
+        let i64_type = self.context.i64_type();
+        let bool_type = self.context.bool_type();
+        let fn_type = bool_type.fn_type(&[i64_type.into(), i64_type.into()], false);
+        let function = self.module.add_function(
+            "values_equal",
+            fn_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        self.builder.position_at_end(entry);
+        let lhs = function.get_nth_param(0).unwrap().into_int_value();
+        let rhs = function.get_nth_param(1).unwrap().into_int_value();
+        let order = self
+            .builder
+            .build_call(compare_values, &[lhs.into(), rhs.into()], "order")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        let equal = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                order,
+                i64_type.const_zero(),
+                "equal",
+            )
+            .unwrap();
+        self.builder.build_return(Some(&equal)).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Returns the internal `compare_values` helper, building it on first use
+    ///
+    /// `compare_values(a, b)` orders two values the way tuple comparison needs: -1, 0
+    /// or 1 when `a` is less than, equal to or greater than `b`, and 2 when they are
+    /// unequal but have no order. Numbers compare by value, strings by content, and
+    /// two lists or two tuples element by element, then by length; any other values
+    /// are equal only when they are the same object.
+    pub(crate) fn get_or_build_compare_values(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("compare_values") {
+            return function;
+        }
+
+        let i64_type = self.context.i64_type();
+        let i32_type = self.context.i32_type();
+        let pyobject_type = self.create_pyobject_type();
+        let fn_type = i64_type.fn_type(&[i64_type.into(), i64_type.into()], false);
+        let function = self.module.add_function(
+            "compare_values",
+            fn_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let number_block = self.context.append_basic_block(function, "compare_numbers");
+        let not_number_block = self.context.append_basic_block(function, "not_numbers");
+        let text_block = self.context.append_basic_block(function, "compare_text");
+        let not_text_block = self.context.append_basic_block(function, "not_text");
+        let tuple_block = self
+            .context
+            .append_basic_block(function, "compare_sequences");
+        let other_block = self.context.append_basic_block(function, "compare_other");
+        let loop_block = self.context.append_basic_block(function, "loop");
+        let body_block = self.context.append_basic_block(function, "body");
+        let differ_block = self.context.append_basic_block(function, "differ");
+        let next_block = self.context.append_basic_block(function, "next");
+        let lengths_block = self.context.append_basic_block(function, "compare_lengths");
+
+        let minus_one = i64_type.const_all_ones();
+        let zero = i64_type.const_zero();
+        let one = i64_type.const_int(1, false);
+        let unordered = i64_type.const_int(2, false);
+
+        self.builder.position_at_end(entry);
+        let lhs = function.get_nth_param(0).unwrap().into_int_value();
+        let rhs = function.get_nth_param(1).unwrap().into_int_value();
+        let lhs_tag = self.values.extract_tag(&self.builder, lhs);
+        let rhs_tag = self.values.extract_tag(&self.builder, rhs);
+        let bool_tag = i64_type.const_int(TYPE_TAG_BOOL as u64, false);
+        let lhs_is_number = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::ULE,
+                lhs_tag,
+                bool_tag,
+                "lhs_is_number",
+            )
+            .unwrap();
+        let rhs_is_number = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::ULE,
+                rhs_tag,
+                bool_tag,
+                "rhs_is_number",
+            )
+            .unwrap();
+        let both_numbers = self
+            .builder
+            .build_and(lhs_is_number, rhs_is_number, "both_numbers")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(both_numbers, number_block, not_number_block)
+            .unwrap();
+
+        // Exact payloads for two ints, f64 values otherwise; NaN is unordered
+        self.builder.position_at_end(number_block);
+        let int_tag = i64_type.const_int(TYPE_TAG_INT as u64, false);
+        let lhs_is_int = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::EQ, lhs_tag, int_tag, "lhs_is_int")
+            .unwrap();
+        let rhs_is_int = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::EQ, rhs_tag, int_tag, "rhs_is_int")
+            .unwrap();
+        let both_ints = self
+            .builder
+            .build_and(lhs_is_int, rhs_is_int, "both_ints")
+            .unwrap();
+        let lhs_int = self.values.extract_int_payload(&self.builder, lhs);
+        let rhs_int = self.values.extract_int_payload(&self.builder, rhs);
+        let int_less = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::SLT, lhs_int, rhs_int, "int_less")
+            .unwrap();
+        let int_greater = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::SGT, lhs_int, rhs_int, "int_greater")
+            .unwrap();
+        let int_above = self
+            .builder
+            .build_select(int_greater, one, zero, "int_above")
+            .unwrap()
+            .into_int_value();
+        let int_order = self
+            .builder
+            .build_select(int_less, minus_one, int_above, "int_order")
+            .unwrap()
+            .into_int_value();
+        let lhs_value = self.values.extract_payload(&self.builder, lhs);
+        let rhs_value = self.values.extract_payload(&self.builder, rhs);
+        let float_less = self
+            .builder
+            .build_float_compare(
+                inkwell::FloatPredicate::OLT,
+                lhs_value,
+                rhs_value,
+                "float_less",
+            )
+            .unwrap();
+        let float_greater = self
+            .builder
+            .build_float_compare(
+                inkwell::FloatPredicate::OGT,
+                lhs_value,
+                rhs_value,
+                "float_greater",
+            )
+            .unwrap();
+        let float_equal = self
+            .builder
+            .build_float_compare(
+                inkwell::FloatPredicate::OEQ,
+                lhs_value,
+                rhs_value,
+                "float_equal",
+            )
+            .unwrap();
+        let float_same = self
+            .builder
+            .build_select(float_equal, zero, unordered, "float_same")
+            .unwrap()
+            .into_int_value();
+        let float_above = self
+            .builder
+            .build_select(float_greater, one, float_same, "float_above")
+            .unwrap()
+            .into_int_value();
+        let float_order = self
+            .builder
+            .build_select(float_less, minus_one, float_above, "float_order")
+            .unwrap()
+            .into_int_value();
+        let number_order = self
+            .builder
+            .build_select(both_ints, int_order, float_order, "number_order")
+            .unwrap();
+        self.builder.build_return(Some(&number_order)).unwrap();
+
+        self.builder.position_at_end(not_number_block);
+        let tags_match = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::EQ, lhs_tag, rhs_tag, "tags_match")
+            .unwrap();
+        let string_tag = i64_type.const_int(TYPE_TAG_STRING as u64, false);
+        let lhs_is_string = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                lhs_tag,
+                string_tag,
+                "lhs_is_string",
+            )
+            .unwrap();
+        let both_strings = self
+            .builder
+            .build_and(tags_match, lhs_is_string, "both_strings")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(both_strings, text_block, not_text_block)
+            .unwrap();
+
+        self.builder.position_at_end(text_block);
+        let lhs_text = self.values.extract_string_ptr(&self.builder, lhs);
+        let rhs_text = self.values.extract_string_ptr(&self.builder, rhs);
+        let strcmp = self.runtime.add_strcmp(&self.module);
+        let text_cmp = self
+            .builder
+            .build_call(strcmp, &[lhs_text.into(), rhs_text.into()], "text_cmp")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        let text_less = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::SLT,
+                text_cmp,
+                i32_type.const_zero(),
+                "text_less",
+            )
+            .unwrap();
+        let text_greater = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::SGT,
+                text_cmp,
+                i32_type.const_zero(),
+                "text_greater",
+            )
+            .unwrap();
+        let text_above = self
+            .builder
+            .build_select(text_greater, one, zero, "text_above")
+            .unwrap()
+            .into_int_value();
+        let text_order = self
+            .builder
+            .build_select(text_less, minus_one, text_above, "text_order")
+            .unwrap();
+        self.builder.build_return(Some(&text_order)).unwrap();
+
+        self.builder.position_at_end(not_text_block);
+        let tuple_tag = i64_type.const_int(TYPE_TAG_TUPLE as u64, false);
+        let list_tag = i64_type.const_int(TYPE_TAG_LIST as u64, false);
+        let lhs_is_tuple = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                lhs_tag,
+                tuple_tag,
+                "lhs_is_tuple",
+            )
+            .unwrap();
+        let lhs_is_list = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::EQ, lhs_tag, list_tag, "lhs_is_list")
+            .unwrap();
+        let lhs_is_sequence = self
+            .builder
+            .build_or(lhs_is_tuple, lhs_is_list, "lhs_is_sequence")
+            .unwrap();
+        let both_sequences = self
+            .builder
+            .build_and(tags_match, lhs_is_sequence, "both_sequences")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(both_sequences, tuple_block, other_block)
+            .unwrap();
+
+        self.builder.position_at_end(other_block);
+        let same_object = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::EQ, lhs, rhs, "same_object")
+            .unwrap();
+        let other_order = self
+            .builder
+            .build_select(same_object, zero, unordered, "other_order")
+            .unwrap();
+        self.builder.build_return(Some(&other_order)).unwrap();
+
+        // The first pair of elements that differ decides, then the shorter sequence is
+        // less
+        self.builder.position_at_end(tuple_block);
+        let (lhs_header, lhs_len) = self.values.extract_list_ptr_and_len(&self.builder, lhs);
+        let (rhs_header, rhs_len) = self.values.extract_list_ptr_and_len(&self.builder, rhs);
+        let lhs_shorter = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::SLT, lhs_len, rhs_len, "lhs_shorter")
+            .unwrap();
+        let common_len = self
+            .builder
+            .build_select(lhs_shorter, lhs_len, rhs_len, "common_len")
+            .unwrap()
+            .into_int_value();
+        self.builder.build_unconditional_branch(loop_block).unwrap();
+
+        self.builder.position_at_end(loop_block);
+        let index = self.builder.build_phi(i64_type, "index").unwrap();
+        index.add_incoming(&[(&zero, tuple_block)]);
+        let index_value = index.as_basic_value().into_int_value();
+        let in_bounds = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::SLT,
+                index_value,
+                common_len,
+                "in_bounds",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(in_bounds, body_block, lengths_block)
+            .unwrap();
+
+        self.builder.position_at_end(body_block);
+        let slot = self
+            .builder
+            .build_int_add(index_value, one, "slot")
+            .unwrap();
+        let lhs_element_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(pyobject_type, lhs_header, &[slot], "lhs_element_ptr")
+                .unwrap()
+        };
+        let rhs_element_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(pyobject_type, rhs_header, &[slot], "rhs_element_ptr")
+                .unwrap()
+        };
+        let lhs_element = self
+            .builder
+            .build_load(pyobject_type, lhs_element_ptr, "lhs_element")
+            .unwrap();
+        let rhs_element = self
+            .builder
+            .build_load(pyobject_type, rhs_element_ptr, "rhs_element")
+            .unwrap();
+        let element_order = self
+            .builder
+            .build_call(
+                function,
+                &[lhs_element.into(), rhs_element.into()],
+                "element_order",
+            )
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        let elements_differ = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::NE,
+                element_order,
+                zero,
+                "elements_differ",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(elements_differ, differ_block, next_block)
+            .unwrap();
+
+        self.builder.position_at_end(differ_block);
+        self.builder.build_return(Some(&element_order)).unwrap();
+
+        self.builder.position_at_end(next_block);
+        index.add_incoming(&[(&slot, next_block)]);
+        self.builder.build_unconditional_branch(loop_block).unwrap();
+
+        self.builder.position_at_end(lengths_block);
+        let lhs_longer = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::SGT, lhs_len, rhs_len, "lhs_longer")
+            .unwrap();
+        let longer_order = self
+            .builder
+            .build_select(lhs_longer, one, zero, "longer_order")
+            .unwrap()
+            .into_int_value();
+        let length_order = self
+            .builder
+            .build_select(lhs_shorter, minus_one, longer_order, "length_order")
+            .unwrap();
+        self.builder.build_return(Some(&length_order)).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Returns the internal `find_item` helper, building it on first use
+    ///
+    /// `find_item(item, container)` looks `item` up in a string (substring search), a
+    /// list or tuple (its elements) or a dict or set (its keys), so `item in container`
+    /// holds when it is not -1. It returns the index of the first matching element,
+    /// entry or substring, and -1 when there is no match.
+    /// Dict and set keys are found with `dict_find`; list and tuple elements are
+    /// compared in turn with `values_equal`. The caller checks the operand types.
+    pub(crate) fn get_or_build_find_item(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("find_item") {
+            return function;
+        }
+
+        let values_equal = self.get_or_build_values_equal();
+        let i64_type = self.context.i64_type();
+        let pyobject_type = self.create_pyobject_type();
+        let fn_type = i64_type.fn_type(&[i64_type.into(), i64_type.into()], false);
+        let function = self.module.add_function(
+            "find_item",
+            fn_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let substring_block = self.context.append_basic_block(function, "substring");
+        let scan_block = self.context.append_basic_block(function, "scan");
+        let loop_block = self.context.append_basic_block(function, "loop");
+        let body_block = self.context.append_basic_block(function, "body");
+        let next_block = self.context.append_basic_block(function, "next");
+        let found_block = self.context.append_basic_block(function, "found");
+        let missing_block = self.context.append_basic_block(function, "missing");
+
+        self.builder.position_at_end(entry);
+        let item = function.get_nth_param(0).unwrap().into_int_value();
+        let container = function.get_nth_param(1).unwrap().into_int_value();
+        let string_tag = i64_type.const_int(TYPE_TAG_STRING as u64, false);
+        let container_tag = self.values.extract_tag(&self.builder, container);
+        let is_string = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                container_tag,
+                string_tag,
+                "is_string",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(is_string, substring_block, scan_block)
+            .unwrap();
+
+        self.builder.position_at_end(substring_block);
+        let haystack = self.values.extract_string_ptr(&self.builder, container);
+        let needle = self.values.extract_string_ptr(&self.builder, item);
+        let strstr = self.runtime.add_strstr(&self.module);
+        let position = self
+            .builder
+            .build_call(strstr, &[haystack.into(), needle.into()], "position")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_pointer_value();
+        let is_substring = self
+            .builder
+            .build_is_not_null(position, "is_substring")
+            .unwrap();
+        let position_addr = self
+            .builder
+            .build_ptr_to_int(position, i64_type, "position_addr")
+            .unwrap();
+        let haystack_addr = self
+            .builder
+            .build_ptr_to_int(haystack, i64_type, "haystack_addr")
+            .unwrap();
+        let offset = self
+            .builder
+            .build_int_sub(position_addr, haystack_addr, "offset")
+            .unwrap();
+        let substring_index = self
+            .builder
+            .build_select(
+                is_substring,
+                offset,
+                i64_type.const_all_ones(),
+                "substring_index",
+            )
+            .unwrap();
+        self.builder.build_return(Some(&substring_index)).unwrap();
+
+        // Dicts and sets look their keys up in the hash index
+        self.builder.position_at_end(scan_block);
+        let dict_tag = i64_type.const_int(TYPE_TAG_DICT as u64, false);
+        let set_tag = i64_type.const_int(TYPE_TAG_SET as u64, false);
+        let is_dict_tag = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                container_tag,
+                dict_tag,
+                "is_dict_tag",
+            )
+            .unwrap();
+        let is_set = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::EQ, container_tag, set_tag, "is_set")
+            .unwrap();
+        let is_dict = self
+            .builder
+            .build_or(is_dict_tag, is_set, "is_dict")
+            .unwrap();
+        let dict_block = self.context.append_basic_block(function, "dict_lookup");
+        let sequence_block = self
+            .context
+            .append_basic_block(function, "sequence_elements");
+        self.builder
+            .build_conditional_branch(is_dict, dict_block, sequence_block)
+            .unwrap();
+
+        self.builder.position_at_end(dict_block);
+        let dict_find = self.get_or_build_dict_find();
+        let entry_index = self
+            .builder
+            .build_call(dict_find, &[container.into(), item.into()], "entry_index")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap();
+        self.builder.build_return(Some(&entry_index)).unwrap();
+
+        // List and tuple elements follow the length
+        self.builder.position_at_end(sequence_block);
+        let (header, len) = self
+            .values
+            .extract_list_ptr_and_len(&self.builder, container);
+        let elements = unsafe {
+            self.builder
+                .build_in_bounds_gep(
+                    pyobject_type,
+                    header,
+                    &[i64_type.const_int(1, false)],
+                    "first_element",
+                )
+                .unwrap()
+        };
+        self.builder.build_unconditional_branch(loop_block).unwrap();
+
+        self.builder.position_at_end(loop_block);
+        let index = self.builder.build_phi(i64_type, "index").unwrap();
+        index.add_incoming(&[(&i64_type.const_zero(), sequence_block)]);
+        let index_value = index.as_basic_value().into_int_value();
+        let in_bounds = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::SLT, index_value, len, "in_bounds")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(in_bounds, body_block, missing_block)
+            .unwrap();
+
+        self.builder.position_at_end(body_block);
+        let element_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(pyobject_type, elements, &[index_value], "element_ptr")
+                .unwrap()
+        };
+        let element = self
+            .builder
+            .build_load(pyobject_type, element_ptr, "element")
+            .unwrap()
+            .into_int_value();
+        let same_value = self
+            .builder
+            .build_call(values_equal, &[item.into(), element.into()], "same_value")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        self.builder
+            .build_conditional_branch(same_value, found_block, next_block)
+            .unwrap();
+
+        self.builder.position_at_end(next_block);
+        let next_index = self
+            .builder
+            .build_int_add(index_value, i64_type.const_int(1, false), "next_index")
+            .unwrap();
+        index.add_incoming(&[(&next_index, next_block)]);
+        self.builder.build_unconditional_branch(loop_block).unwrap();
+
+        self.builder.position_at_end(found_block);
+        self.builder.build_return(Some(&index_value)).unwrap();
+
+        self.builder.position_at_end(missing_block);
+        self.builder
+            .build_return(Some(&i64_type.const_all_ones()))
+            .unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Returns the internal `count_item` helper, building it on first use
+    ///
+    /// `count_item(item, container)` counts the non-overlapping occurrences of the
+    /// string `item` in a string container, or the list or tuple elements for which
+    /// `values_equal` holds. The caller checks the operand types.
+    pub(crate) fn get_or_build_count_item(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("count_item") {
+            return function;
+        }
+
+        let values_equal = self.get_or_build_values_equal();
+        let i64_type = self.context.i64_type();
+        let i8_type = self.context.i8_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let pyobject_type = self.create_pyobject_type();
+        let fn_type = i64_type.fn_type(&[i64_type.into(), i64_type.into()], false);
+        let function = self.module.add_function(
+            "count_item",
+            fn_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let substring_block = self.context.append_basic_block(function, "substring");
+        let empty_block = self.context.append_basic_block(function, "empty_substring");
+        let search_block = self.context.append_basic_block(function, "search");
+        let match_block = self.context.append_basic_block(function, "match");
+        let search_done = self.context.append_basic_block(function, "search_done");
+        let scan_block = self.context.append_basic_block(function, "scan");
+        let loop_block = self.context.append_basic_block(function, "loop");
+        let body_block = self.context.append_basic_block(function, "body");
+        let done_block = self.context.append_basic_block(function, "done");
+
+        self.builder.position_at_end(entry);
+        let item = function.get_nth_param(0).unwrap().into_int_value();
+        let container = function.get_nth_param(1).unwrap().into_int_value();
+        let string_tag = i64_type.const_int(TYPE_TAG_STRING as u64, false);
+        let container_tag = self.values.extract_tag(&self.builder, container);
+        let is_string = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                container_tag,
+                string_tag,
+                "is_string",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(is_string, substring_block, scan_block)
+            .unwrap();
+
+        // Python counts an empty substring once per gap between characters
+        self.builder.position_at_end(substring_block);
+        let haystack = self.values.extract_string_ptr(&self.builder, container);
+        let needle = self.values.extract_string_ptr(&self.builder, item);
+        let strlen = self.runtime.add_strlen(&self.module);
+        let needle_len = self
+            .builder
+            .build_call(strlen, &[needle.into()], "needle_len")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        let is_empty = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                needle_len,
+                i64_type.const_zero(),
+                "is_empty",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(is_empty, empty_block, search_block)
+            .unwrap();
+
+        self.builder.position_at_end(empty_block);
+        let haystack_len = self
+            .builder
+            .build_call(strlen, &[haystack.into()], "haystack_len")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        let gaps = self
+            .builder
+            .build_int_add(haystack_len, i64_type.const_int(1, false), "gaps")
+            .unwrap();
+        self.builder.build_return(Some(&gaps)).unwrap();
+
+        self.builder.position_at_end(search_block);
+        let cursor = self.builder.build_phi(ptr_type, "cursor").unwrap();
+        cursor.add_incoming(&[(&haystack, substring_block)]);
+        let found = self.builder.build_phi(i64_type, "found").unwrap();
+        found.add_incoming(&[(&i64_type.const_zero(), substring_block)]);
+        let found_value = found.as_basic_value().into_int_value();
+        let strstr = self.runtime.add_strstr(&self.module);
+        let position = self
+            .builder
+            .build_call(
+                strstr,
+                &[cursor.as_basic_value().into(), needle.into()],
+                "position",
+            )
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_pointer_value();
+        let is_match = self
+            .builder
+            .build_is_not_null(position, "is_match")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(is_match, match_block, search_done)
+            .unwrap();
+
+        // Resume after the match so occurrences never overlap
+        self.builder.position_at_end(match_block);
+        let next_cursor = unsafe {
+            self.builder
+                .build_in_bounds_gep(i8_type, position, &[needle_len], "next_cursor")
+                .unwrap()
+        };
+        let next_found = self
+            .builder
+            .build_int_add(found_value, i64_type.const_int(1, false), "next_found")
+            .unwrap();
+        cursor.add_incoming(&[(&next_cursor, match_block)]);
+        found.add_incoming(&[(&next_found, match_block)]);
+        self.builder
+            .build_unconditional_branch(search_block)
+            .unwrap();
+
+        self.builder.position_at_end(search_done);
+        self.builder.build_return(Some(&found_value)).unwrap();
+
+        self.builder.position_at_end(scan_block);
+        let (header, len) = self
+            .values
+            .extract_list_ptr_and_len(&self.builder, container);
+        let elements = unsafe {
+            self.builder
+                .build_in_bounds_gep(
+                    pyobject_type,
+                    header,
+                    &[i64_type.const_int(1, false)],
+                    "elements",
+                )
+                .unwrap()
+        };
+        self.builder.build_unconditional_branch(loop_block).unwrap();
+
+        self.builder.position_at_end(loop_block);
+        let index = self.builder.build_phi(i64_type, "index").unwrap();
+        index.add_incoming(&[(&i64_type.const_zero(), scan_block)]);
+        let index_value = index.as_basic_value().into_int_value();
+        let total = self.builder.build_phi(i64_type, "total").unwrap();
+        total.add_incoming(&[(&i64_type.const_zero(), scan_block)]);
+        let total_value = total.as_basic_value().into_int_value();
+        let in_bounds = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::SLT, index_value, len, "in_bounds")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(in_bounds, body_block, done_block)
+            .unwrap();
+
+        self.builder.position_at_end(body_block);
+        let element_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(pyobject_type, elements, &[index_value], "element_ptr")
+                .unwrap()
+        };
+        let element = self
+            .builder
+            .build_load(pyobject_type, element_ptr, "element")
+            .unwrap()
+            .into_int_value();
+        let same_value = self
+            .builder
+            .build_call(values_equal, &[item.into(), element.into()], "same_value")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        let matches = self
+            .builder
+            .build_int_z_extend(same_value, i64_type, "matches")
+            .unwrap();
+        let next_total = self
+            .builder
+            .build_int_add(total_value, matches, "next_total")
+            .unwrap();
+        let next_index = self
+            .builder
+            .build_int_add(index_value, i64_type.const_int(1, false), "next_index")
+            .unwrap();
+        index.add_incoming(&[(&next_index, body_block)]);
+        total.add_incoming(&[(&next_total, body_block)]);
+        self.builder.build_unconditional_branch(loop_block).unwrap();
+
+        self.builder.position_at_end(done_block);
+        self.builder.build_return(Some(&total_value)).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Loads the hash index pointer and its mask (the slot count minus one) from the
+    /// fourth and fifth words of a dict header
+    fn load_dict_index(&self, header: PointerValue<'ctx>) -> (PointerValue<'ctx>, IntValue<'ctx>) {
+        let i64_type = self.context.i64_type();
+        let pyobject_type = self.create_pyobject_type();
+        let index_slot = unsafe {
+            self.builder
+                .build_in_bounds_gep(
+                    pyobject_type,
+                    header,
+                    &[i64_type.const_int(3, false)],
+                    "index_slot",
+                )
+                .unwrap()
+        };
+        let mask_slot = unsafe {
+            self.builder
+                .build_in_bounds_gep(
+                    pyobject_type,
+                    header,
+                    &[i64_type.const_int(4, false)],
+                    "mask_slot",
+                )
+                .unwrap()
+        };
+        let index = self
+            .builder
+            .build_load(
+                self.context.ptr_type(inkwell::AddressSpace::default()),
+                index_slot,
+                "index",
+            )
+            .unwrap()
+            .into_pointer_value();
+        let mask = self
+            .builder
+            .build_load(i64_type, mask_slot, "mask")
+            .unwrap()
+            .into_int_value();
+        (index, mask)
+    }
+
+    /// Scrambles the bits of `value` (the splitmix64 finalizer), so that hashes
+    /// differing only in their high bits land in different index slots
+    fn build_mix_hash(&self, value: IntValue<'ctx>) -> IntValue<'ctx> {
+        let i64_type = self.context.i64_type();
+        let mut hash = value;
+        for (shift, multiplier) in [(30, 0xBF58_476D_1CE4_E5B9u64), (27, 0x94D0_49BB_1331_11EB)] {
+            let shifted = self
+                .builder
+                .build_right_shift(hash, i64_type.const_int(shift, false), false, "shifted")
+                .unwrap();
+            let folded = self.builder.build_xor(hash, shifted, "folded").unwrap();
+            hash = self
+                .builder
+                .build_int_mul(folded, i64_type.const_int(multiplier, false), "mixed")
+                .unwrap();
+        }
+        let shifted = self
+            .builder
+            .build_right_shift(hash, i64_type.const_int(31, false), false, "shifted")
+            .unwrap();
+        self.builder.build_xor(hash, shifted, "hash").unwrap()
+    }
+
+    /// Returns the internal `hash_value` helper, building it on first use
+    ///
+    /// `hash_value(value)` hashes a dict key so that values equal under `values_equal`
+    /// hash alike: numbers by their f64 value (so `1`, `1.0` and `True` agree),
+    /// strings by content (FNV-1a), lists and tuples by their elements' hashes, and
+    /// anything else by its bits, since it is only equal to itself.
+    pub(crate) fn get_or_build_hash_value(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("hash_value") {
+            return function;
+        }
+
+        let i64_type = self.context.i64_type();
+        let i8_type = self.context.i8_type();
+        let pyobject_type = self.create_pyobject_type();
+        let fn_type = i64_type.fn_type(&[i64_type.into()], false);
+        let function = self.module.add_function(
+            "hash_value",
+            fn_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let number_block = self.context.append_basic_block(function, "hash_number");
+        let not_number_block = self.context.append_basic_block(function, "not_number");
+        let text_block = self.context.append_basic_block(function, "hash_text");
+        let text_loop_block = self.context.append_basic_block(function, "text_loop");
+        let text_body_block = self.context.append_basic_block(function, "text_body");
+        let text_done_block = self.context.append_basic_block(function, "text_done");
+        let not_text_block = self.context.append_basic_block(function, "not_text");
+        let sequence_block = self.context.append_basic_block(function, "hash_sequence");
+        let sequence_loop_block = self.context.append_basic_block(function, "sequence_loop");
+        let sequence_body_block = self.context.append_basic_block(function, "sequence_body");
+        let sequence_done_block = self.context.append_basic_block(function, "sequence_done");
+        let other_block = self.context.append_basic_block(function, "hash_other");
+
+        let fnv_offset = i64_type.const_int(0xCBF2_9CE4_8422_2325, false);
+        let fnv_prime = i64_type.const_int(0x0000_0100_0000_01B3, false);
+        let has_tag = |compiler: &Self, tag: IntValue<'ctx>, type_tag: u8, name: &str| {
+            compiler
+                .builder
+                .build_int_compare(
+                    inkwell::IntPredicate::EQ,
+                    tag,
+                    i64_type.const_int(type_tag as u64, false),
+                    name,
+                )
+                .unwrap()
+        };
+
+        self.builder.position_at_end(entry);
+        let value = function.get_nth_param(0).unwrap().into_int_value();
+        let tag = self.values.extract_tag(&self.builder, value);
+        let is_number = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::ULE,
+                tag,
+                i64_type.const_int(TYPE_TAG_BOOL as u64, false),
+                "is_number",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(is_number, number_block, not_number_block)
+            .unwrap();
+
+        // Adding 0.0 turns -0.0 into 0.0, which it equals
+        self.builder.position_at_end(number_block);
+        let number = self.values.extract_payload(&self.builder, value);
+        let number = self
+            .builder
+            .build_float_add(number, self.context.f64_type().const_zero(), "number")
+            .unwrap();
+        let bits = self
+            .builder
+            .build_bit_cast(number, i64_type, "number_bits")
+            .unwrap()
+            .into_int_value();
+        let number_hash = self.build_mix_hash(bits);
+        self.builder.build_return(Some(&number_hash)).unwrap();
+
+        self.builder.position_at_end(not_number_block);
+        let is_string = has_tag(self, tag, TYPE_TAG_STRING, "is_string");
+        self.builder
+            .build_conditional_branch(is_string, text_block, not_text_block)
+            .unwrap();
+
+        self.builder.position_at_end(text_block);
+        let text = self.values.extract_string_ptr(&self.builder, value);
+        self.builder
+            .build_unconditional_branch(text_loop_block)
+            .unwrap();
+
+        self.builder.position_at_end(text_loop_block);
+        let position = self.builder.build_phi(i64_type, "position").unwrap();
+        let text_hash = self.builder.build_phi(i64_type, "text_hash").unwrap();
+        position.add_incoming(&[(&i64_type.const_zero(), text_block)]);
+        text_hash.add_incoming(&[(&fnv_offset, text_block)]);
+        let position_value = position.as_basic_value().into_int_value();
+        let text_hash_value = text_hash.as_basic_value().into_int_value();
+        let byte_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(i8_type, text, &[position_value], "byte_ptr")
+                .unwrap()
+        };
+        let byte = self
+            .builder
+            .build_load(i8_type, byte_ptr, "byte")
+            .unwrap()
+            .into_int_value();
+        let at_end = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                byte,
+                i8_type.const_zero(),
+                "at_end",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(at_end, text_done_block, text_body_block)
+            .unwrap();
+
+        self.builder.position_at_end(text_body_block);
+        let byte = self
+            .builder
+            .build_int_z_extend(byte, i64_type, "byte_value")
+            .unwrap();
+        let folded = self
+            .builder
+            .build_xor(text_hash_value, byte, "folded")
+            .unwrap();
+        let next_text_hash = self
+            .builder
+            .build_int_mul(folded, fnv_prime, "next_text_hash")
+            .unwrap();
+        let next_position = self
+            .builder
+            .build_int_add(
+                position_value,
+                i64_type.const_int(1, false),
+                "next_position",
+            )
+            .unwrap();
+        position.add_incoming(&[(&next_position, text_body_block)]);
+        text_hash.add_incoming(&[(&next_text_hash, text_body_block)]);
+        self.builder
+            .build_unconditional_branch(text_loop_block)
+            .unwrap();
+
+        self.builder.position_at_end(text_done_block);
+        let text_hash_value = self.build_mix_hash(text_hash_value);
+        self.builder.build_return(Some(&text_hash_value)).unwrap();
+
+        // Lists and tuples only ever equal their own kind, so both hash the same way
+        self.builder.position_at_end(not_text_block);
+        let is_list = has_tag(self, tag, TYPE_TAG_LIST, "is_list");
+        let is_tuple = has_tag(self, tag, TYPE_TAG_TUPLE, "is_tuple");
+        let is_sequence = self
+            .builder
+            .build_or(is_list, is_tuple, "is_sequence")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(is_sequence, sequence_block, other_block)
+            .unwrap();
+
+        self.builder.position_at_end(sequence_block);
+        let (elements, len) = self.values.extract_list_ptr_and_len(&self.builder, value);
+        self.builder
+            .build_unconditional_branch(sequence_loop_block)
+            .unwrap();
+
+        self.builder.position_at_end(sequence_loop_block);
+        let index = self.builder.build_phi(i64_type, "index").unwrap();
+        let sequence_hash = self.builder.build_phi(i64_type, "sequence_hash").unwrap();
+        index.add_incoming(&[(&i64_type.const_zero(), sequence_block)]);
+        sequence_hash.add_incoming(&[(&fnv_offset, sequence_block)]);
+        let index_value = index.as_basic_value().into_int_value();
+        let sequence_hash_value = sequence_hash.as_basic_value().into_int_value();
+        let in_bounds = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::SLT, index_value, len, "in_bounds")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(in_bounds, sequence_body_block, sequence_done_block)
+            .unwrap();
+
+        // Elements follow the length
+        self.builder.position_at_end(sequence_body_block);
+        let slot = self
+            .builder
+            .build_int_add(index_value, i64_type.const_int(1, false), "slot")
+            .unwrap();
+        let element_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(pyobject_type, elements, &[slot], "element_ptr")
+                .unwrap()
+        };
+        let element = self
+            .builder
+            .build_load(pyobject_type, element_ptr, "element")
+            .unwrap()
+            .into_int_value();
+        let element_hash = self
+            .builder
+            .build_call(function, &[element.into()], "element_hash")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        let folded = self
+            .builder
+            .build_xor(sequence_hash_value, element_hash, "folded")
+            .unwrap();
+        let next_sequence_hash = self
+            .builder
+            .build_int_mul(folded, fnv_prime, "next_sequence_hash")
+            .unwrap();
+        let next_index = self
+            .builder
+            .build_int_add(index_value, i64_type.const_int(1, false), "next_index")
+            .unwrap();
+        index.add_incoming(&[(&next_index, sequence_body_block)]);
+        sequence_hash.add_incoming(&[(&next_sequence_hash, sequence_body_block)]);
+        self.builder
+            .build_unconditional_branch(sequence_loop_block)
+            .unwrap();
+
+        self.builder.position_at_end(sequence_done_block);
+        self.builder
+            .build_return(Some(&sequence_hash_value))
+            .unwrap();
+
+        self.builder.position_at_end(other_block);
+        let other_hash = self.build_mix_hash(value);
+        self.builder.build_return(Some(&other_hash)).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Returns the internal `dict_find` helper, building it on first use
+    ///
+    /// `dict_find(dict, key)` returns the index of the entry whose key equals `key`,
+    /// or -1 when there is none. It probes the hash index linearly from the key's
+    /// hash until it reaches the entry or an empty (-1) slot; the index is kept at
+    /// most two thirds full, so a free slot always ends the probe.
+    pub(crate) fn get_or_build_dict_find(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("dict_find") {
+            return function;
+        }
+
+        let hash_value = self.get_or_build_hash_value();
+        let values_equal = self.get_or_build_values_equal();
+        let i64_type = self.context.i64_type();
+        let pyobject_type = self.create_pyobject_type();
+        let fn_type = i64_type.fn_type(&[i64_type.into(), i64_type.into()], false);
+        let function = self.module.add_function(
+            "dict_find",
+            fn_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let probe_block = self.context.append_basic_block(function, "probe");
+        let occupied_block = self.context.append_basic_block(function, "occupied");
+        let next_block = self.context.append_basic_block(function, "next");
+        let found_block = self.context.append_basic_block(function, "found");
+        let missing_block = self.context.append_basic_block(function, "missing");
+
+        self.builder.position_at_end(entry);
+        let dict = function.get_nth_param(0).unwrap().into_int_value();
+        let key = function.get_nth_param(1).unwrap().into_int_value();
+        let (header, _) = self.values.extract_list_ptr_and_len(&self.builder, dict);
+        let entries = self.load_dict_entries(header);
+        let (index, mask) = self.load_dict_index(header);
+        let hash = self
+            .builder
+            .build_call(hash_value, &[key.into()], "hash")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        let start = self.builder.build_and(hash, mask, "start").unwrap();
+        self.builder
+            .build_unconditional_branch(probe_block)
+            .unwrap();
+
+        self.builder.position_at_end(probe_block);
+        let position = self.builder.build_phi(i64_type, "position").unwrap();
+        position.add_incoming(&[(&start, entry)]);
+        let position_value = position.as_basic_value().into_int_value();
+        let slot_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(i64_type, index, &[position_value], "slot_ptr")
+                .unwrap()
+        };
+        let entry_index = self
+            .builder
+            .build_load(i64_type, slot_ptr, "entry_index")
+            .unwrap()
+            .into_int_value();
+        let is_free = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                entry_index,
+                i64_type.const_all_ones(),
+                "is_free",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(is_free, missing_block, occupied_block)
+            .unwrap();
+
+        self.builder.position_at_end(occupied_block);
+        let key_slot = self
+            .builder
+            .build_int_mul(entry_index, i64_type.const_int(2, false), "key_slot")
+            .unwrap();
+        let key_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(pyobject_type, entries, &[key_slot], "key_ptr")
+                .unwrap()
+        };
+        let entry_key = self
+            .builder
+            .build_load(pyobject_type, key_ptr, "entry_key")
+            .unwrap()
+            .into_int_value();
+        let same_key = self
+            .builder
+            .build_call(values_equal, &[key.into(), entry_key.into()], "same_key")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        self.builder
+            .build_conditional_branch(same_key, found_block, next_block)
+            .unwrap();
+
+        self.builder.position_at_end(next_block);
+        let next_position = self
+            .builder
+            .build_int_add(
+                position_value,
+                i64_type.const_int(1, false),
+                "next_position",
+            )
+            .unwrap();
+        let next_position = self
+            .builder
+            .build_and(next_position, mask, "wrapped_position")
+            .unwrap();
+        position.add_incoming(&[(&next_position, next_block)]);
+        self.builder
+            .build_unconditional_branch(probe_block)
+            .unwrap();
+
+        self.builder.position_at_end(found_block);
+        self.builder.build_return(Some(&entry_index)).unwrap();
+
+        self.builder.position_at_end(missing_block);
+        self.builder
+            .build_return(Some(&i64_type.const_all_ones()))
+            .unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Returns the internal `dict_new_index` helper, building it on first use
+    ///
+    /// `dict_new_index(slots)` allocates a hash index of `slots` words (a power of
+    /// two), every one of them -1 for a free slot.
+    pub(crate) fn get_or_build_dict_new_index(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("dict_new_index") {
+            return function;
+        }
+
+        let malloc = self.runtime.add_malloc(&self.module);
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fn_type = ptr_type.fn_type(&[i64_type.into()], false);
+        let function = self.module.add_function(
+            "dict_new_index",
+            fn_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let loop_block = self.context.append_basic_block(function, "loop");
+        let body_block = self.context.append_basic_block(function, "body");
+        let done_block = self.context.append_basic_block(function, "done");
+
+        self.builder.position_at_end(entry);
+        let slots = function.get_nth_param(0).unwrap().into_int_value();
+        let size = self
+            .builder
+            .build_int_mul(slots, i64_type.size_of(), "index_size")
+            .unwrap();
+        let index = self
+            .builder
+            .build_call(malloc, &[size.into()], "index")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_pointer_value();
+        self.builder.build_unconditional_branch(loop_block).unwrap();
+
+        self.builder.position_at_end(loop_block);
+        let position = self.builder.build_phi(i64_type, "position").unwrap();
+        position.add_incoming(&[(&i64_type.const_zero(), entry)]);
+        let position_value = position.as_basic_value().into_int_value();
+        let in_bounds = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::ULT,
+                position_value,
+                slots,
+                "in_bounds",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(in_bounds, body_block, done_block)
+            .unwrap();
+
+        self.builder.position_at_end(body_block);
+        let slot_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(i64_type, index, &[position_value], "slot_ptr")
+                .unwrap()
+        };
+        self.builder
+            .build_store(slot_ptr, i64_type.const_all_ones())
+            .unwrap();
+        let next_position = self
+            .builder
+            .build_int_add(
+                position_value,
+                i64_type.const_int(1, false),
+                "next_position",
+            )
+            .unwrap();
+        position.add_incoming(&[(&next_position, body_block)]);
+        self.builder.build_unconditional_branch(loop_block).unwrap();
+
+        self.builder.position_at_end(done_block);
+        self.builder.build_return(Some(&index)).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Returns the internal `dict_index_insert` helper, building it on first use
+    ///
+    /// `dict_index_insert(index, mask, hash, entry_index)` records `entry_index` in
+    /// the first free slot of the hash index probing from `hash`, the way
+    /// `dict_find` will look for it.
+    pub(crate) fn get_or_build_dict_index_insert(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("dict_index_insert") {
+            return function;
+        }
+
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fn_type = self.context.void_type().fn_type(
+            &[
+                ptr_type.into(),
+                i64_type.into(),
+                i64_type.into(),
+                i64_type.into(),
+            ],
+            false,
+        );
+        let function = self.module.add_function(
+            "dict_index_insert",
+            fn_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let probe_block = self.context.append_basic_block(function, "probe");
+        let next_block = self.context.append_basic_block(function, "next");
+        let store_block = self.context.append_basic_block(function, "store");
+
+        self.builder.position_at_end(entry);
+        let index = function.get_nth_param(0).unwrap().into_pointer_value();
+        let mask = function.get_nth_param(1).unwrap().into_int_value();
+        let hash = function.get_nth_param(2).unwrap().into_int_value();
+        let entry_index = function.get_nth_param(3).unwrap().into_int_value();
+        let start = self.builder.build_and(hash, mask, "start").unwrap();
+        self.builder
+            .build_unconditional_branch(probe_block)
+            .unwrap();
+
+        self.builder.position_at_end(probe_block);
+        let position = self.builder.build_phi(i64_type, "position").unwrap();
+        position.add_incoming(&[(&start, entry)]);
+        let position_value = position.as_basic_value().into_int_value();
+        let slot_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(i64_type, index, &[position_value], "slot_ptr")
+                .unwrap()
+        };
+        let slot = self
+            .builder
+            .build_load(i64_type, slot_ptr, "slot")
+            .unwrap()
+            .into_int_value();
+        let is_free = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                slot,
+                i64_type.const_all_ones(),
+                "is_free",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(is_free, store_block, next_block)
+            .unwrap();
+
+        self.builder.position_at_end(next_block);
+        let next_position = self
+            .builder
+            .build_int_add(
+                position_value,
+                i64_type.const_int(1, false),
+                "next_position",
+            )
+            .unwrap();
+        let next_position = self
+            .builder
+            .build_and(next_position, mask, "wrapped_position")
+            .unwrap();
+        position.add_incoming(&[(&next_position, next_block)]);
+        self.builder
+            .build_unconditional_branch(probe_block)
+            .unwrap();
+
+        self.builder.position_at_end(store_block);
+        self.builder.build_store(slot_ptr, entry_index).unwrap();
+        self.builder.build_return(None).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Returns the internal `dict_set` helper, building it on first use
+    ///
+    /// `dict_set(dict, key, value)` is `dict[key] = value`: it replaces the value of
+    /// the entry `dict_find` finds for the key or appends a new entry, doubling the
+    /// capacity of the entries block when it is full. Before an insert would leave
+    /// the hash index more than two thirds full, the index doubles and every key is
+    /// hashed into it again. The header stays where it is, so every reference to the
+    /// dict sees the change.
+    pub(crate) fn get_or_build_dict_set(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("dict_set") {
+            return function;
+        }
+
+        let dict_find = self.get_or_build_dict_find();
+        let hash_value = self.get_or_build_hash_value();
+        let dict_new_index = self.get_or_build_dict_new_index();
+        let dict_index_insert = self.get_or_build_dict_index_insert();
+        let realloc = self.runtime.add_realloc(&self.module);
+        let free = self.runtime.add_free(&self.module);
+        let i64_type = self.context.i64_type();
+        let pyobject_type = self.create_pyobject_type();
+        let fn_type = self
+            .context
+            .void_type()
+            .fn_type(&[i64_type.into(), i64_type.into(), i64_type.into()], false);
+        let function = self.module.add_function(
+            "dict_set",
+            fn_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let update_block = self.context.append_basic_block(function, "update");
+        let insert_block = self.context.append_basic_block(function, "insert");
+        let rehash_block = self.context.append_basic_block(function, "rehash");
+        let rehash_loop_block = self.context.append_basic_block(function, "rehash_loop");
+        let rehash_body_block = self.context.append_basic_block(function, "rehash_body");
+        let rehash_done_block = self.context.append_basic_block(function, "rehash_done");
+        let check_capacity_block = self.context.append_basic_block(function, "check_capacity");
+        let grow_block = self.context.append_basic_block(function, "grow");
+        let append_block = self.context.append_basic_block(function, "append");
+
+        self.builder.position_at_end(entry);
+        let dict = function.get_nth_param(0).unwrap().into_int_value();
+        let key = function.get_nth_param(1).unwrap().into_int_value();
+        let value = function.get_nth_param(2).unwrap().into_int_value();
+        let store_slot =
+            |entries: PointerValue<'ctx>, slot: IntValue<'ctx>, item: IntValue<'ctx>| {
+                let slot_ptr = unsafe {
+                    self.builder
+                        .build_in_bounds_gep(pyobject_type, entries, &[slot], "slot_ptr")
+                        .unwrap()
+                };
+                self.builder.build_store(slot_ptr, item).unwrap();
+            };
+        let position = self
+            .builder
+            .build_call(dict_find, &[dict.into(), key.into()], "position")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        let found = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::SGE,
+                position,
+                i64_type.const_zero(),
+                "found",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(found, update_block, insert_block)
+            .unwrap();
+
+        self.builder.position_at_end(update_block);
+        let (entries, _) = self.extract_dict_entries(dict);
+        let key_slot = self
+            .builder
+            .build_int_mul(position, i64_type.const_int(2, false), "key_slot")
+            .unwrap();
+        let value_slot = self
+            .builder
+            .build_int_add(key_slot, i64_type.const_int(1, false), "value_slot")
+            .unwrap();
+        store_slot(entries, value_slot, value);
+        self.builder.build_return(None).unwrap();
+
+        // Keep at most two thirds of the index slots in use: rehash when
+        // 3 * (len + 1) > 2 * slots
+        self.builder.position_at_end(insert_block);
+        let (header, len) = self.values.extract_list_ptr_and_len(&self.builder, dict);
+        let header_slot = |slot: u64, name: &str| unsafe {
+            self.builder
+                .build_in_bounds_gep(
+                    pyobject_type,
+                    header,
+                    &[i64_type.const_int(slot, false)],
+                    name,
+                )
+                .unwrap()
+        };
+        let (old_index, old_mask) = self.load_dict_index(header);
+        let slots = self
+            .builder
+            .build_int_add(old_mask, i64_type.const_int(1, false), "slots")
+            .unwrap();
+        let new_len = self
+            .builder
+            .build_int_add(len, i64_type.const_int(1, false), "new_len")
+            .unwrap();
+        let used = self
+            .builder
+            .build_int_mul(new_len, i64_type.const_int(3, false), "used")
+            .unwrap();
+        let room = self
+            .builder
+            .build_int_mul(slots, i64_type.const_int(2, false), "room")
+            .unwrap();
+        let too_full = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::UGT, used, room, "too_full")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(too_full, rehash_block, check_capacity_block)
+            .unwrap();
+
+        self.builder.position_at_end(rehash_block);
+        let new_slots = self
+            .builder
+            .build_int_mul(slots, i64_type.const_int(2, false), "new_slots")
+            .unwrap();
+        let new_mask = self
+            .builder
+            .build_int_sub(new_slots, i64_type.const_int(1, false), "new_mask")
+            .unwrap();
+        let new_index = self
+            .builder
+            .build_call(dict_new_index, &[new_slots.into()], "new_index")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_pointer_value();
+        let entries = self.load_dict_entries(header);
+        self.builder
+            .build_unconditional_branch(rehash_loop_block)
+            .unwrap();
+
+        self.builder.position_at_end(rehash_loop_block);
+        let rehashed = self.builder.build_phi(i64_type, "rehashed").unwrap();
+        rehashed.add_incoming(&[(&i64_type.const_zero(), rehash_block)]);
+        let rehashed_value = rehashed.as_basic_value().into_int_value();
+        let more = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::SLT, rehashed_value, len, "more")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(more, rehash_body_block, rehash_done_block)
+            .unwrap();
+
+        self.builder.position_at_end(rehash_body_block);
+        let key_slot = self
+            .builder
+            .build_int_mul(rehashed_value, i64_type.const_int(2, false), "key_slot")
+            .unwrap();
+        let key_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(pyobject_type, entries, &[key_slot], "key_ptr")
+                .unwrap()
+        };
+        let entry_key = self
+            .builder
+            .build_load(pyobject_type, key_ptr, "entry_key")
+            .unwrap();
+        let entry_hash = self
+            .builder
+            .build_call(hash_value, &[entry_key.into()], "entry_hash")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap();
+        self.builder
+            .build_call(
+                dict_index_insert,
+                &[
+                    new_index.into(),
+                    new_mask.into(),
+                    entry_hash.into(),
+                    rehashed_value.into(),
+                ],
+                "",
+            )
+            .unwrap();
+        let next_rehashed = self
+            .builder
+            .build_int_add(
+                rehashed_value,
+                i64_type.const_int(1, false),
+                "next_rehashed",
+            )
+            .unwrap();
+        rehashed.add_incoming(&[(&next_rehashed, rehash_body_block)]);
+        self.builder
+            .build_unconditional_branch(rehash_loop_block)
+            .unwrap();
+
+        self.builder.position_at_end(rehash_done_block);
+        self.builder
+            .build_call(free, &[old_index.into()], "")
+            .unwrap();
+        self.builder
+            .build_store(header_slot(3, "index_slot"), new_index)
+            .unwrap();
+        self.builder
+            .build_store(header_slot(4, "mask_slot"), new_mask)
+            .unwrap();
+        self.builder
+            .build_unconditional_branch(check_capacity_block)
+            .unwrap();
+
+        // The capacity counts entries, each taking two words
+        self.builder.position_at_end(check_capacity_block);
+        let capacity_ptr = header_slot(1, "capacity_ptr");
+        let capacity = self
+            .builder
+            .build_load(i64_type, capacity_ptr, "capacity")
+            .unwrap()
+            .into_int_value();
+        let is_full = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::UGE, len, capacity, "is_full")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(is_full, grow_block, append_block)
+            .unwrap();
+
+        self.builder.position_at_end(grow_block);
+        let is_empty = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                capacity,
+                i64_type.const_zero(),
+                "is_empty",
+            )
+            .unwrap();
+        let doubled = self
+            .builder
+            .build_int_mul(capacity, i64_type.const_int(2, false), "doubled")
+            .unwrap();
+        let new_capacity = self
+            .builder
+            .build_select(
+                is_empty,
+                i64_type.const_int(4, false),
+                doubled,
+                "new_capacity",
+            )
+            .unwrap()
+            .into_int_value();
+        let entry_size = self
+            .builder
+            .build_int_mul(
+                pyobject_type.size_of(),
+                i64_type.const_int(2, false),
+                "entry_size",
+            )
+            .unwrap();
+        let new_size = self
+            .builder
+            .build_int_mul(new_capacity, entry_size, "new_size")
+            .unwrap();
+        let old_entries = self.load_dict_entries(header);
+        let new_entries = self
+            .builder
+            .build_call(
+                realloc,
+                &[old_entries.into(), new_size.into()],
+                "new_entries",
+            )
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_pointer_value();
+        self.builder
+            .build_store(capacity_ptr, new_capacity)
+            .unwrap();
+        self.builder
+            .build_store(header_slot(2, "entries_slot"), new_entries)
+            .unwrap();
+        self.builder
+            .build_unconditional_branch(append_block)
+            .unwrap();
+
+        self.builder.position_at_end(append_block);
+        let entries = self.load_dict_entries(header);
+        let key_slot = self
+            .builder
+            .build_int_mul(len, i64_type.const_int(2, false), "new_key_slot")
+            .unwrap();
+        let value_slot = self
+            .builder
+            .build_int_add(key_slot, i64_type.const_int(1, false), "new_value_slot")
+            .unwrap();
+        store_slot(entries, key_slot, key);
+        store_slot(entries, value_slot, value);
+        let (index, mask) = self.load_dict_index(header);
+        let hash = self
+            .builder
+            .build_call(hash_value, &[key.into()], "hash")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap();
+        self.builder
+            .build_call(
+                dict_index_insert,
+                &[index.into(), mask.into(), hash.into(), len.into()],
+                "",
+            )
+            .unwrap();
+        self.builder.build_store(header, new_len).unwrap();
+        self.builder.build_return(None).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Returns the internal `strip` helper, building it on first use
+    ///
+    /// `strip(text, left, right)` returns a new heap string holding `text` without
+    /// its leading (if `left`) and trailing (if `right`) ASCII whitespace: spaces and
+    /// `\t`, `\n`, `\v`, `\f`, `\r`.
+    pub(crate) fn get_or_build_strip(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("strip") {
+            return function;
+        }
+
+        let i64_type = self.context.i64_type();
+        let i8_type = self.context.i8_type();
+        let bool_type = self.context.bool_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fn_type = ptr_type.fn_type(
+            &[ptr_type.into(), bool_type.into(), bool_type.into()],
+            false,
+        );
+        let function =
+            self.module
+                .add_function("strip", fn_type, Some(inkwell::module::Linkage::Internal));
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let front_block = self.context.append_basic_block(function, "front");
+        let front_check = self.context.append_basic_block(function, "front_check");
+        let front_next = self.context.append_basic_block(function, "front_next");
+        let back_start = self.context.append_basic_block(function, "back_start");
+        let back_block = self.context.append_basic_block(function, "back");
+        let back_check = self.context.append_basic_block(function, "back_check");
+        let back_next = self.context.append_basic_block(function, "back_next");
+        let done_block = self.context.append_basic_block(function, "done");
+
+        self.builder.position_at_end(entry);
+        let text = function.get_nth_param(0).unwrap().into_pointer_value();
+        let left = function.get_nth_param(1).unwrap().into_int_value();
+        let right = function.get_nth_param(2).unwrap().into_int_value();
+        let strlen = self.runtime.add_strlen(&self.module);
+        let length = self
+            .builder
+            .build_call(strlen, &[text.into()], "length")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        self.builder
+            .build_unconditional_branch(front_block)
+            .unwrap();
+
+        let one = i64_type.const_int(1, false);
+        // Whether the byte at `index` is whitespace and `enabled` says to drop it
+        let strippable = |compiler: &Self, index: IntValue<'ctx>, enabled: IntValue<'ctx>| {
+            let byte_ptr = unsafe {
+                compiler
+                    .builder
+                    .build_gep(i8_type, text, &[index], "byte_ptr")
+                    .unwrap()
+            };
+            let byte = compiler
+                .builder
+                .build_load(i8_type, byte_ptr, "byte")
+                .unwrap()
+                .into_int_value();
+            let is_space = compiler
+                .builder
+                .build_int_compare(
+                    inkwell::IntPredicate::EQ,
+                    byte,
+                    i8_type.const_int(b' ' as u64, false),
+                    "is_space",
+                )
+                .unwrap();
+            // \t..\r are the consecutive bytes 9 to 13
+            let control_offset = compiler
+                .builder
+                .build_int_sub(byte, i8_type.const_int(9, false), "control_offset")
+                .unwrap();
+            let is_control = compiler
+                .builder
+                .build_int_compare(
+                    inkwell::IntPredicate::ULT,
+                    control_offset,
+                    i8_type.const_int(5, false),
+                    "is_control_space",
+                )
+                .unwrap();
+            let is_whitespace = compiler
+                .builder
+                .build_or(is_space, is_control, "is_whitespace")
+                .unwrap();
+            compiler
+                .builder
+                .build_and(is_whitespace, enabled, "strippable")
+                .unwrap()
+        };
+
+        // Advance `start` past leading whitespace
+        self.builder.position_at_end(front_block);
+        let start = self.builder.build_phi(i64_type, "start").unwrap();
+        start.add_incoming(&[(&i64_type.const_zero(), entry)]);
+        let start_value = start.as_basic_value().into_int_value();
+        let in_text = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::ULT, start_value, length, "in_text")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(in_text, front_check, back_start)
+            .unwrap();
+
+        self.builder.position_at_end(front_check);
+        let drop_front = strippable(self, start_value, left);
+        self.builder
+            .build_conditional_branch(drop_front, front_next, back_start)
+            .unwrap();
+
+        self.builder.position_at_end(front_next);
+        let next_start = self
+            .builder
+            .build_int_add(start_value, one, "next_start")
+            .unwrap();
+        start.add_incoming(&[(&next_start, front_next)]);
+        self.builder
+            .build_unconditional_branch(front_block)
+            .unwrap();
+
+        // Pull `end` back over trailing whitespace, never past `start`
+        self.builder.position_at_end(back_start);
+        self.builder.build_unconditional_branch(back_block).unwrap();
+
+        self.builder.position_at_end(back_block);
+        let end = self.builder.build_phi(i64_type, "end").unwrap();
+        end.add_incoming(&[(&length, back_start)]);
+        let end_value = end.as_basic_value().into_int_value();
+        let has_more = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::UGT,
+                end_value,
+                start_value,
+                "has_more",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(has_more, back_check, done_block)
+            .unwrap();
+
+        self.builder.position_at_end(back_check);
+        let last = self.builder.build_int_sub(end_value, one, "last").unwrap();
+        let drop_back = strippable(self, last, right);
+        self.builder
+            .build_conditional_branch(drop_back, back_next, done_block)
+            .unwrap();
+
+        self.builder.position_at_end(back_next);
+        end.add_incoming(&[(&last, back_next)]);
+        self.builder.build_unconditional_branch(back_block).unwrap();
+
+        // Copy text[start..end] into a new string
+        self.builder.position_at_end(done_block);
+        let size = self
+            .builder
+            .build_int_sub(end_value, start_value, "size")
+            .unwrap();
+        let alloc_size = self.builder.build_int_add(size, one, "alloc_size").unwrap();
+        let malloc = self.runtime.add_malloc(&self.module);
+        let result = self
+            .builder
+            .build_call(malloc, &[alloc_size.into()], "result")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_pointer_value();
+        let source = unsafe {
+            self.builder
+                .build_gep(i8_type, text, &[start_value], "source")
+                .unwrap()
+        };
+        let memcpy = self.runtime.add_memcpy(&self.module);
+        self.builder
+            .build_call(memcpy, &[result.into(), source.into(), size.into()], "")
+            .unwrap();
+        let terminator = unsafe {
+            self.builder
+                .build_gep(i8_type, result, &[size], "terminator")
+                .unwrap()
+        };
+        self.builder
+            .build_store(terminator, i8_type.const_zero())
+            .unwrap();
+        self.builder.build_return(Some(&result)).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Returns the internal `replace` helper, building it on first use
+    ///
+    /// `replace(text, old, new)` returns a new heap string with every non-overlapping
+    /// occurrence of `old` replaced by `new`, found left to right with strstr. A first
+    /// pass counts the occurrences to size the allocation. An empty `old` returns a
+    /// plain copy.
+    pub(crate) fn get_or_build_replace(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("replace") {
+            return function;
+        }
+
+        let i64_type = self.context.i64_type();
+        let i8_type = self.context.i8_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fn_type = ptr_type.fn_type(&[ptr_type.into(), ptr_type.into(), ptr_type.into()], false);
+        let function =
+            self.module
+                .add_function("replace", fn_type, Some(inkwell::module::Linkage::Internal));
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let count_block = self.context.append_basic_block(function, "count");
+        let count_next = self.context.append_basic_block(function, "count_next");
+        let allocate_block = self.context.append_basic_block(function, "allocate");
+        let copy_block = self.context.append_basic_block(function, "copy");
+        let copy_match = self.context.append_basic_block(function, "copy_match");
+        let tail_block = self.context.append_basic_block(function, "tail");
+
+        let strlen = self.runtime.add_strlen(&self.module);
+        let strstr = self.runtime.add_strstr(&self.module);
+        let malloc = self.runtime.add_malloc(&self.module);
+        let memcpy = self.runtime.add_memcpy(&self.module);
+        let call = |compiler: &Self,
+                    function: FunctionValue<'ctx>,
+                    args: &[BasicMetadataValueEnum<'ctx>],
+                    name: &str| {
+            compiler
+                .builder
+                .build_call(function, args, name)
+                .unwrap()
+                .try_as_basic_value()
+                .basic()
+                .unwrap()
+        };
+        let advance = |compiler: &Self, ptr: PointerValue<'ctx>, by: IntValue<'ctx>, name: &str| unsafe {
+            compiler
+                .builder
+                .build_gep(i8_type, ptr, &[by], name)
+                .unwrap()
+        };
+
+        self.builder.position_at_end(entry);
+        let text = function.get_nth_param(0).unwrap().into_pointer_value();
+        let old = function.get_nth_param(1).unwrap().into_pointer_value();
+        let new = function.get_nth_param(2).unwrap().into_pointer_value();
+        let text_len = call(self, strlen, &[text.into()], "text_len").into_int_value();
+        let old_len = call(self, strlen, &[old.into()], "old_len").into_int_value();
+        let new_len = call(self, strlen, &[new.into()], "new_len").into_int_value();
+        // An empty `old` would match everywhere without advancing; skip both passes
+        let old_is_empty = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                old_len,
+                i64_type.const_zero(),
+                "old_is_empty",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(old_is_empty, allocate_block, count_block)
+            .unwrap();
+
+        // Count the occurrences
+        self.builder.position_at_end(count_block);
+        let count_cursor = self.builder.build_phi(ptr_type, "count_cursor").unwrap();
+        let count = self.builder.build_phi(i64_type, "count").unwrap();
+        count_cursor.add_incoming(&[(&text, entry)]);
+        count.add_incoming(&[(&i64_type.const_zero(), entry)]);
+        let count_cursor_value = count_cursor.as_basic_value().into_pointer_value();
+        let count_value = count.as_basic_value().into_int_value();
+        let found = call(
+            self,
+            strstr,
+            &[count_cursor_value.into(), old.into()],
+            "found",
+        )
+        .into_pointer_value();
+        let found_none = self.builder.build_is_null(found, "found_none").unwrap();
+        self.builder
+            .build_conditional_branch(found_none, allocate_block, count_next)
+            .unwrap();
+
+        self.builder.position_at_end(count_next);
+        let next_count = self
+            .builder
+            .build_int_add(count_value, i64_type.const_int(1, false), "next_count")
+            .unwrap();
+        let after_match = advance(self, found, old_len, "after_match");
+        count_cursor.add_incoming(&[(&after_match, count_next)]);
+        count.add_incoming(&[(&next_count, count_next)]);
+        self.builder
+            .build_unconditional_branch(count_block)
+            .unwrap();
+
+        // Size: text_len + count * (new_len - old_len) + 1
+        self.builder.position_at_end(allocate_block);
+        let total = self.builder.build_phi(i64_type, "total").unwrap();
+        total.add_incoming(&[(&i64_type.const_zero(), entry), (&count_value, count_block)]);
+        let total_value = total.as_basic_value().into_int_value();
+        let growth = self
+            .builder
+            .build_int_sub(new_len, old_len, "growth")
+            .unwrap();
+        let extra = self
+            .builder
+            .build_int_mul(total_value, growth, "extra")
+            .unwrap();
+        let result_len = self
+            .builder
+            .build_int_add(text_len, extra, "result_len")
+            .unwrap();
+        let size = self
+            .builder
+            .build_int_add(result_len, i64_type.const_int(1, false), "size")
+            .unwrap();
+        let result = call(self, malloc, &[size.into()], "result").into_pointer_value();
+        self.builder
+            .build_conditional_branch(old_is_empty, tail_block, copy_block)
+            .unwrap();
+
+        // Copy the text before each match, then `new` in place of the match
+        self.builder.position_at_end(copy_block);
+        let source = self.builder.build_phi(ptr_type, "source").unwrap();
+        let dest = self.builder.build_phi(ptr_type, "dest").unwrap();
+        source.add_incoming(&[(&text, allocate_block)]);
+        dest.add_incoming(&[(&result, allocate_block)]);
+        let source_value = source.as_basic_value().into_pointer_value();
+        let dest_value = dest.as_basic_value().into_pointer_value();
+        let next_match = call(
+            self,
+            strstr,
+            &[source_value.into(), old.into()],
+            "next_match",
+        )
+        .into_pointer_value();
+        let no_match = self.builder.build_is_null(next_match, "no_match").unwrap();
+        self.builder
+            .build_conditional_branch(no_match, tail_block, copy_match)
+            .unwrap();
+
+        self.builder.position_at_end(copy_match);
+        let match_addr = self
+            .builder
+            .build_ptr_to_int(next_match, i64_type, "match_addr")
+            .unwrap();
+        let source_addr = self
+            .builder
+            .build_ptr_to_int(source_value, i64_type, "source_addr")
+            .unwrap();
+        let before_len = self
+            .builder
+            .build_int_sub(match_addr, source_addr, "before_len")
+            .unwrap();
+        self.builder
+            .build_call(
+                memcpy,
+                &[dest_value.into(), source_value.into(), before_len.into()],
+                "",
+            )
+            .unwrap();
+        let replacement_dest = advance(self, dest_value, before_len, "replacement_dest");
+        self.builder
+            .build_call(
+                memcpy,
+                &[replacement_dest.into(), new.into(), new_len.into()],
+                "",
+            )
+            .unwrap();
+        let next_dest = advance(self, replacement_dest, new_len, "next_dest");
+        let next_source = advance(self, next_match, old_len, "next_source");
+        source.add_incoming(&[(&next_source, copy_match)]);
+        dest.add_incoming(&[(&next_dest, copy_match)]);
+        self.builder.build_unconditional_branch(copy_block).unwrap();
+
+        // Copy what follows the last match, with the terminator
+        self.builder.position_at_end(tail_block);
+        let tail_source = self.builder.build_phi(ptr_type, "tail_source").unwrap();
+        let tail_dest = self.builder.build_phi(ptr_type, "tail_dest").unwrap();
+        tail_source.add_incoming(&[(&text, allocate_block), (&source_value, copy_block)]);
+        tail_dest.add_incoming(&[(&result, allocate_block), (&dest_value, copy_block)]);
+        let tail_source_value = tail_source.as_basic_value().into_pointer_value();
+        let tail_len = call(self, strlen, &[tail_source_value.into()], "tail_len").into_int_value();
+        let tail_size = self
+            .builder
+            .build_int_add(tail_len, i64_type.const_int(1, false), "tail_size")
+            .unwrap();
+        self.builder
+            .build_call(
+                memcpy,
+                &[
+                    tail_dest.as_basic_value().into(),
+                    tail_source_value.into(),
+                    tail_size.into(),
+                ],
+                "",
+            )
+            .unwrap();
+        self.builder.build_return(Some(&result)).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Returns the internal `split` helper, building it on first use
+    ///
+    /// `split(text, sep)` returns a new list of the pieces of `text` between the
+    /// non-overlapping occurrences of `sep`, found left to right with strstr, each
+    /// copied into a new heap string. A first pass counts the occurrences to size the
+    /// list. `sep` must not be empty (the caller raises a ValueError first).
+    pub(crate) fn get_or_build_split(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("split") {
+            return function;
+        }
+
+        let i64_type = self.context.i64_type();
+        let i8_type = self.context.i8_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let pyobject_type = self.create_pyobject_type();
+        let fn_type = pyobject_type.fn_type(&[ptr_type.into(), ptr_type.into()], false);
+        let function =
+            self.module
+                .add_function("split", fn_type, Some(inkwell::module::Linkage::Internal));
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let count_block = self.context.append_basic_block(function, "count");
+        let count_next = self.context.append_basic_block(function, "count_next");
+        let allocate_block = self.context.append_basic_block(function, "allocate");
+        let piece_block = self.context.append_basic_block(function, "piece");
+        let piece_match = self.context.append_basic_block(function, "piece_match");
+        let tail_block = self.context.append_basic_block(function, "tail");
+
+        let strlen = self.runtime.add_strlen(&self.module);
+        let strstr = self.runtime.add_strstr(&self.module);
+        let malloc = self.runtime.add_malloc(&self.module);
+        let memcpy = self.runtime.add_memcpy(&self.module);
+        let call = |compiler: &Self,
+                    function: FunctionValue<'ctx>,
+                    args: &[BasicMetadataValueEnum<'ctx>],
+                    name: &str| {
+            compiler
+                .builder
+                .build_call(function, args, name)
+                .unwrap()
+                .try_as_basic_value()
+                .basic()
+                .unwrap()
+        };
+        let advance = |compiler: &Self, ptr: PointerValue<'ctx>, by: IntValue<'ctx>, name: &str| unsafe {
+            compiler
+                .builder
+                .build_gep(i8_type, ptr, &[by], name)
+                .unwrap()
+        };
+        // Copies `len` bytes from `start` into a new terminated string and stores it
+        // in list slot `slot`
+        let store_piece = |compiler: &Self,
+                           list: PointerValue<'ctx>,
+                           slot: IntValue<'ctx>,
+                           start: PointerValue<'ctx>,
+                           len: IntValue<'ctx>| {
+            let size = compiler
+                .builder
+                .build_int_add(len, i64_type.const_int(1, false), "piece_size")
+                .unwrap();
+            let piece = call(compiler, malloc, &[size.into()], "piece").into_pointer_value();
+            compiler
+                .builder
+                .build_call(memcpy, &[piece.into(), start.into(), len.into()], "")
+                .unwrap();
+            let terminator = advance(compiler, piece, len, "piece_end");
+            compiler
+                .builder
+                .build_store(terminator, i8_type.const_zero())
+                .unwrap();
+            let slot_ptr = unsafe {
+                compiler
+                    .builder
+                    .build_in_bounds_gep(pyobject_type, list, &[slot], "slot_ptr")
+                    .unwrap()
+            };
+            compiler
+                .builder
+                .build_store(slot_ptr, compiler.create_pyobject_string(piece))
+                .unwrap();
+        };
+
+        self.builder.position_at_end(entry);
+        let text = function.get_nth_param(0).unwrap().into_pointer_value();
+        let sep = function.get_nth_param(1).unwrap().into_pointer_value();
+        let sep_len = call(self, strlen, &[sep.into()], "sep_len").into_int_value();
+        self.builder
+            .build_unconditional_branch(count_block)
+            .unwrap();
+
+        // Count the separators
+        self.builder.position_at_end(count_block);
+        let count_cursor = self.builder.build_phi(ptr_type, "count_cursor").unwrap();
+        let count = self.builder.build_phi(i64_type, "count").unwrap();
+        count_cursor.add_incoming(&[(&text, entry)]);
+        count.add_incoming(&[(&i64_type.const_zero(), entry)]);
+        let count_cursor_value = count_cursor.as_basic_value().into_pointer_value();
+        let count_value = count.as_basic_value().into_int_value();
+        let found = call(
+            self,
+            strstr,
+            &[count_cursor_value.into(), sep.into()],
+            "found",
+        )
+        .into_pointer_value();
+        let found_none = self.builder.build_is_null(found, "found_none").unwrap();
+        self.builder
+            .build_conditional_branch(found_none, allocate_block, count_next)
+            .unwrap();
+
+        self.builder.position_at_end(count_next);
+        let next_count = self
+            .builder
+            .build_int_add(count_value, i64_type.const_int(1, false), "next_count")
+            .unwrap();
+        let after_match = advance(self, found, sep_len, "after_match");
+        count_cursor.add_incoming(&[(&after_match, count_next)]);
+        count.add_incoming(&[(&next_count, count_next)]);
+        self.builder
+            .build_unconditional_branch(count_block)
+            .unwrap();
+
+        // One more piece than separators: [len][piece_0]...[piece_n]
+        self.builder.position_at_end(allocate_block);
+        let pieces = self
+            .builder
+            .build_int_add(count_value, i64_type.const_int(1, false), "pieces")
+            .unwrap();
+        let slots = self
+            .builder
+            .build_int_add(pieces, i64_type.const_int(1, false), "slots")
+            .unwrap();
+        let list_size = self
+            .builder
+            .build_int_mul(pyobject_type.size_of(), slots, "list_size")
+            .unwrap();
+        let list = call(self, malloc, &[list_size.into()], "list").into_pointer_value();
+        self.builder.build_store(list, pieces).unwrap();
+        self.builder
+            .build_unconditional_branch(piece_block)
+            .unwrap();
+
+        // Copy the text before each separator into the next slot
+        self.builder.position_at_end(piece_block);
+        let source = self.builder.build_phi(ptr_type, "source").unwrap();
+        let slot = self.builder.build_phi(i64_type, "slot").unwrap();
+        source.add_incoming(&[(&text, allocate_block)]);
+        slot.add_incoming(&[(&i64_type.const_int(1, false), allocate_block)]);
+        let source_value = source.as_basic_value().into_pointer_value();
+        let slot_value = slot.as_basic_value().into_int_value();
+        let next_match = call(
+            self,
+            strstr,
+            &[source_value.into(), sep.into()],
+            "next_match",
+        )
+        .into_pointer_value();
+        let no_match = self.builder.build_is_null(next_match, "no_match").unwrap();
+        self.builder
+            .build_conditional_branch(no_match, tail_block, piece_match)
+            .unwrap();
+
+        self.builder.position_at_end(piece_match);
+        let match_addr = self
+            .builder
+            .build_ptr_to_int(next_match, i64_type, "match_addr")
+            .unwrap();
+        let source_addr = self
+            .builder
+            .build_ptr_to_int(source_value, i64_type, "source_addr")
+            .unwrap();
+        let piece_len = self
+            .builder
+            .build_int_sub(match_addr, source_addr, "piece_len")
+            .unwrap();
+        store_piece(self, list, slot_value, source_value, piece_len);
+        let next_source = advance(self, next_match, sep_len, "next_source");
+        let next_slot = self
+            .builder
+            .build_int_add(slot_value, i64_type.const_int(1, false), "next_slot")
+            .unwrap();
+        source.add_incoming(&[(&next_source, piece_match)]);
+        slot.add_incoming(&[(&next_slot, piece_match)]);
+        self.builder
+            .build_unconditional_branch(piece_block)
+            .unwrap();
+
+        // The last piece is what follows the last separator
+        self.builder.position_at_end(tail_block);
+        let tail_len = call(self, strlen, &[source_value.into()], "tail_len").into_int_value();
+        store_piece(self, list, slot_value, source_value, tail_len);
+        let result = self.create_pyobject_list(list, 0);
+        self.builder.build_return(Some(&result)).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Returns the internal `format_int_base` helper, building it on first use
+    ///
+    /// `format_int_base(n, base, letter)` returns a new heap string with `n` written
+    /// in `base` (2 to 16) after a `0` and `letter` prefix, e.g. `-0xff`. The digits
+    /// are written backwards into a stack buffer big enough for 64 binary digits,
+    /// then copied out. The magnitude is taken as unsigned so `i64::MIN` works too.
+    pub(crate) fn get_or_build_format_int_base(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("format_int_base") {
+            return function;
+        }
+
+        // A sign, the two prefix characters, 64 digits and the terminator
+        const BUFFER_SIZE: u64 = 68;
+        let i64_type = self.context.i64_type();
+        let i8_type = self.context.i8_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fn_type = ptr_type.fn_type(&[i64_type.into(), i64_type.into(), i8_type.into()], false);
+        let function = self.module.add_function(
+            "format_int_base",
+            fn_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let digit_block = self.context.append_basic_block(function, "digit");
+        let prefix_block = self.context.append_basic_block(function, "prefix");
+
+        self.builder.position_at_end(entry);
+        let number = function.get_nth_param(0).unwrap().into_int_value();
+        let base = function.get_nth_param(1).unwrap().into_int_value();
+        let letter = function.get_nth_param(2).unwrap().into_int_value();
+        let scratch = self
+            .builder
+            .build_array_alloca(i8_type, i64_type.const_int(BUFFER_SIZE, false), "scratch")
+            .unwrap();
+        let is_negative = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::SLT,
+                number,
+                i64_type.const_zero(),
+                "is_negative",
+            )
+            .unwrap();
+        let negated = self.builder.build_int_neg(number, "negated").unwrap();
+        let magnitude = self
+            .builder
+            .build_select(is_negative, negated, number, "magnitude")
+            .unwrap()
+            .into_int_value();
+        let last = i64_type.const_int(BUFFER_SIZE - 1, false);
+        let terminator = unsafe {
+            self.builder
+                .build_gep(i8_type, scratch, &[last], "terminator")
+                .unwrap()
+        };
+        self.builder
+            .build_store(terminator, i8_type.const_zero())
+            .unwrap();
+        let digits = self
+            .builder
+            .build_global_string_ptr("0123456789abcdef", "base_digits")
+            .unwrap()
+            .as_pointer_value();
+        self.builder
+            .build_unconditional_branch(digit_block)
+            .unwrap();
+
+        // Emit the lowest digit until nothing is left; zero still gets one digit
+        self.builder.position_at_end(digit_block);
+        let position = self.builder.build_phi(i64_type, "position").unwrap();
+        let remaining = self.builder.build_phi(i64_type, "remaining").unwrap();
+        position.add_incoming(&[(&last, entry)]);
+        remaining.add_incoming(&[(&magnitude, entry)]);
+        let position_value = position.as_basic_value().into_int_value();
+        let remaining_value = remaining.as_basic_value().into_int_value();
+        let one = i64_type.const_int(1, false);
+        let digit_position = self
+            .builder
+            .build_int_sub(position_value, one, "digit_position")
+            .unwrap();
+        let digit = self
+            .builder
+            .build_int_unsigned_rem(remaining_value, base, "digit")
+            .unwrap();
+        let digit_char_ptr = unsafe {
+            self.builder
+                .build_gep(i8_type, digits, &[digit], "digit_char_ptr")
+                .unwrap()
+        };
+        let digit_char = self
+            .builder
+            .build_load(i8_type, digit_char_ptr, "digit_char")
+            .unwrap();
+        let digit_slot = unsafe {
+            self.builder
+                .build_gep(i8_type, scratch, &[digit_position], "digit_slot")
+                .unwrap()
+        };
+        self.builder.build_store(digit_slot, digit_char).unwrap();
+        let rest = self
+            .builder
+            .build_int_unsigned_div(remaining_value, base, "rest")
+            .unwrap();
+        position.add_incoming(&[(&digit_position, digit_block)]);
+        remaining.add_incoming(&[(&rest, digit_block)]);
+        let more = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::NE,
+                rest,
+                i64_type.const_zero(),
+                "more",
+            )
+            .unwrap();
+        self.builder
+            .build_conditional_branch(more, digit_block, prefix_block)
+            .unwrap();
+
+        // Prepend the prefix, and the sign (always written, but only kept when negative)
+        self.builder.position_at_end(prefix_block);
+        let mut start = digit_position;
+        for (character, name) in [
+            (letter, "letter_slot"),
+            (i8_type.const_int(b'0' as u64, false), "zero_slot"),
+            (i8_type.const_int(b'-' as u64, false), "sign_slot"),
+        ] {
+            start = self.builder.build_int_sub(start, one, name).unwrap();
+            let slot = unsafe {
+                self.builder
+                    .build_gep(i8_type, scratch, &[start], name)
+                    .unwrap()
+            };
+            self.builder.build_store(slot, character).unwrap();
+        }
+        let unsigned_start = self
+            .builder
+            .build_int_add(start, one, "unsigned_start")
+            .unwrap();
+        let start = self
+            .builder
+            .build_select(is_negative, start, unsigned_start, "start")
+            .unwrap()
+            .into_int_value();
+        let text = unsafe {
+            self.builder
+                .build_gep(i8_type, scratch, &[start], "text")
+                .unwrap()
+        };
+        let size = self
+            .builder
+            .build_int_sub(i64_type.const_int(BUFFER_SIZE, false), start, "size")
+            .unwrap();
+        let malloc = self.runtime.add_malloc(&self.module);
+        let result = self
+            .builder
+            .build_call(malloc, &[size.into()], "result")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_pointer_value();
+        let memcpy = self.runtime.add_memcpy(&self.module);
+        self.builder
+            .build_call(memcpy, &[result.into(), text.into(), size.into()], "")
+            .unwrap();
+        self.builder.build_return(Some(&result)).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Returns the internal `format_list` helper, building it on first use
+    ///
+    /// `format_list(list, buffer)` appends the text `print_list` would print to a text
+    /// buffer (see `buffer_append`). Nested lists and tuples recurse; other elements
+    /// are formatted like `str()`, with strings quoted. Dicts are handed to
+    /// `format_dict`, so callers can pass any container.
+    pub(crate) fn get_or_build_format_list(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("format_list") {
+            return function;
+        }
+
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let pyobject_type = self.create_pyobject_type();
+        let fn_type = self
+            .context
+            .void_type()
+            .fn_type(&[pyobject_type.into(), ptr_type.into()], false);
+        let function = self.module.add_function(
+            "format_list",
+            fn_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let append = self.get_or_build_buffer_append();
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let cond_block = self.context.append_basic_block(function, "elem_cond");
+        let body_block = self.context.append_basic_block(function, "elem_body");
+        let done_block = self.context.append_basic_block(function, "elem_done");
+
+        self.builder.position_at_end(entry);
+        let list_obj = function.get_nth_param(0).unwrap().into_int_value();
+        let buffer = function.get_nth_param(1).unwrap().into_pointer_value();
+        let is_dict = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                self.extract_tag(list_obj),
+                i64_type.const_int(TYPE_TAG_DICT as u64, false),
+                "is_dict",
+            )
+            .unwrap();
+        let dict_block = self.context.append_basic_block(function, "format_dict");
+        let sequence_block = self.context.append_basic_block(function, "format_sequence");
+        self.builder
+            .build_conditional_branch(is_dict, dict_block, sequence_block)
+            .unwrap();
+
+        self.builder.position_at_end(dict_block);
+        let format_dict = self.get_or_build_format_dict();
+        self.builder
+            .build_call(format_dict, &[list_obj.into(), buffer.into()], "")
+            .unwrap();
+        self.builder.build_return(None).unwrap();
+
+        self.builder.position_at_end(sequence_block);
+        let is_tuple = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                self.extract_tag(list_obj),
+                i64_type.const_int(TYPE_TAG_TUPLE as u64, false),
+                "is_tuple",
+            )
+            .unwrap();
+        let list_open = self
+            .builder
+            .build_global_string_ptr("[", "list_open")
+            .unwrap()
+            .as_pointer_value();
+        let tuple_open = self
+            .builder
+            .build_global_string_ptr("(", "tuple_open")
+            .unwrap()
+            .as_pointer_value();
+        let open_bracket = self
+            .builder
+            .build_select(is_tuple, tuple_open, list_open, "open_bracket")
+            .unwrap();
+        self.builder
+            .build_call(append, &[buffer.into(), open_bracket.into()], "")
+            .unwrap();
+        let (list_ptr, list_len) = self.extract_list_ptr_and_len(list_obj);
+        let index_ptr = self.create_entry_block_alloca("index", function);
+        self.builder
+            .build_store(index_ptr, i64_type.const_zero())
+            .unwrap();
+        self.builder.build_unconditional_branch(cond_block).unwrap();
+
+        // Loop over the elements: index < len
+        self.builder.position_at_end(cond_block);
+        let index = self
+            .builder
+            .build_load(i64_type, index_ptr, "index")
+            .unwrap()
+            .into_int_value();
+        let in_range = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::SLT, index, list_len, "in_range")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(in_range, body_block, done_block)
+            .unwrap();
+
+        // Append ", " before every element except the first, then the element
+        self.builder.position_at_end(body_block);
+        let is_first = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                index,
+                i64_type.const_zero(),
+                "is_first",
+            )
+            .unwrap();
+        let no_separator = self
+            .builder
+            .build_global_string_ptr("", "list_no_separator")
+            .unwrap()
+            .as_pointer_value();
+        let separator = self
+            .builder
+            .build_global_string_ptr(", ", "list_separator")
+            .unwrap()
+            .as_pointer_value();
+        let separator = self
+            .builder
+            .build_select(is_first, no_separator, separator, "separator")
+            .unwrap();
+        self.builder
+            .build_call(append, &[buffer.into(), separator.into()], "")
+            .unwrap();
+        let adjusted_index = self
+            .builder
+            .build_int_add(index, i64_type.const_int(1, false), "adjusted_index")
+            .unwrap();
+        let elem_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(pyobject_type, list_ptr, &[adjusted_index], "elem_ptr")
+                .unwrap()
+        };
+        let elem = self
+            .builder
+            .build_load(pyobject_type, elem_ptr, "elem")
+            .unwrap()
+            .into_int_value();
+
+        let elem_tag = self.extract_tag(elem);
+        let elem_is_string = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                elem_tag,
+                i64_type.const_int(TYPE_TAG_STRING as u64, false),
+                "elem_is_string",
+            )
+            .unwrap();
+        let elem_is_list = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                elem_tag,
+                i64_type.const_int(TYPE_TAG_LIST as u64, false),
+                "elem_is_list",
+            )
+            .unwrap();
+        let elem_is_tuple = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                elem_tag,
+                i64_type.const_int(TYPE_TAG_TUPLE as u64, false),
+                "elem_is_tuple",
+            )
+            .unwrap();
+        let elem_is_sequence = self
+            .builder
+            .build_or(elem_is_list, elem_is_tuple, "elem_is_sequence")
+            .unwrap();
+        let repr_string_block = self
+            .context
+            .append_basic_block(function, "elem_repr_string");
+        let check_sequence_block = self
+            .context
+            .append_basic_block(function, "elem_check_sequence");
+        let nested_block = self.context.append_basic_block(function, "elem_nested");
+        let str_block = self.context.append_basic_block(function, "elem_str");
+        let next_block = self.context.append_basic_block(function, "elem_next");
+        self.builder
+            .build_conditional_branch(elem_is_string, repr_string_block, check_sequence_block)
+            .unwrap();
+
+        self.builder.position_at_end(repr_string_block);
+        let quote = self
+            .builder
+            .build_global_string_ptr("'", "repr_quote")
+            .unwrap()
+            .as_pointer_value();
+        let elem_str = self.extract_string_ptr(elem);
+        for text in [quote, elem_str, quote] {
+            self.builder
+                .build_call(append, &[buffer.into(), text.into()], "")
+                .unwrap();
+        }
+        self.builder.build_unconditional_branch(next_block).unwrap();
+
+        self.builder.position_at_end(check_sequence_block);
+        self.builder
+            .build_conditional_branch(elem_is_sequence, nested_block, str_block)
+            .unwrap();
+
+        self.builder.position_at_end(nested_block);
+        self.builder
+            .build_call(function, &[elem.into(), buffer.into()], "")
+            .unwrap();
+        self.builder.build_unconditional_branch(next_block).unwrap();
+
+        self.builder.position_at_end(str_block);
+        let text = expression::build_str_ptr(self, elem)
+            .expect("formatting a list element should not fail");
+        self.builder
+            .build_call(append, &[buffer.into(), text.into()], "")
+            .unwrap();
+        self.builder.build_unconditional_branch(next_block).unwrap();
+
+        self.builder.position_at_end(next_block);
+        let next_index = self
+            .builder
+            .build_int_add(index, i64_type.const_int(1, false), "next_index")
+            .unwrap();
+        self.builder.build_store(index_ptr, next_index).unwrap();
+        self.builder.build_unconditional_branch(cond_block).unwrap();
+
+        self.builder.position_at_end(done_block);
+        let is_single = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                list_len,
+                i64_type.const_int(1, false),
+                "is_single",
+            )
+            .unwrap();
+        let is_single_tuple = self
+            .builder
+            .build_and(is_tuple, is_single, "is_single_tuple")
+            .unwrap();
+        let list_close = self
+            .builder
+            .build_global_string_ptr("]", "list_close")
+            .unwrap()
+            .as_pointer_value();
+        let tuple_close = self
+            .builder
+            .build_global_string_ptr(")", "tuple_close")
+            .unwrap()
+            .as_pointer_value();
+        let single_tuple_close = self
+            .builder
+            .build_global_string_ptr(",)", "single_tuple_close")
+            .unwrap()
+            .as_pointer_value();
+        let close_bracket = self
+            .builder
+            .build_select(is_tuple, tuple_close, list_close, "close_bracket")
+            .unwrap();
+        let close_bracket = self
+            .builder
+            .build_select(
+                is_single_tuple,
+                single_tuple_close,
+                close_bracket.into_pointer_value(),
+                "close_bracket",
+            )
+            .unwrap();
+        self.builder
+            .build_call(append, &[buffer.into(), close_bracket.into()], "")
+            .unwrap();
+        self.builder.build_return(None).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Returns the internal `format_dict` helper, building it on first use
+    ///
+    /// `format_dict(dict, buffer)` appends a dict's text to a text buffer, in
+    /// insertion order: `{1: 'a', 2: 'b'}`, or `{}` when it is empty. Keys and values
+    /// are formatted like list elements, so strings are quoted and containers recurse.
+    pub(crate) fn get_or_build_format_dict(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("format_dict") {
+            return function;
+        }
+
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let pyobject_type = self.create_pyobject_type();
+        let fn_type = self
+            .context
+            .void_type()
+            .fn_type(&[pyobject_type.into(), ptr_type.into()], false);
+        let function = self.module.add_function(
+            "format_dict",
+            fn_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let append = self.get_or_build_buffer_append();
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let cond_block = self.context.append_basic_block(function, "entry_cond");
+        let body_block = self.context.append_basic_block(function, "entry_body");
+        let done_block = self.context.append_basic_block(function, "entry_done");
+
+        self.builder.position_at_end(entry);
+        let dict = function.get_nth_param(0).unwrap().into_int_value();
+        let buffer = function.get_nth_param(1).unwrap().into_pointer_value();
+        let open_brace = self
+            .builder
+            .build_global_string_ptr("{", "dict_open")
+            .unwrap()
+            .as_pointer_value();
+        self.builder
+            .build_call(append, &[buffer.into(), open_brace.into()], "")
+            .unwrap();
+        let (entries, len) = self.extract_dict_entries(dict);
+        let index_ptr = self.create_entry_block_alloca("index", function);
+        self.builder
+            .build_store(index_ptr, i64_type.const_zero())
+            .unwrap();
+        self.builder.build_unconditional_branch(cond_block).unwrap();
+
+        // Loop over the entries: index < len
+        self.builder.position_at_end(cond_block);
+        let index = self
+            .builder
+            .build_load(i64_type, index_ptr, "index")
+            .unwrap()
+            .into_int_value();
+        let in_range = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::SLT, index, len, "in_range")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(in_range, body_block, done_block)
+            .unwrap();
+
+        // Append ", " before every entry except the first, then `key: value`
+        self.builder.position_at_end(body_block);
+        let is_first = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                index,
+                i64_type.const_zero(),
+                "is_first",
+            )
+            .unwrap();
+        let no_separator = self
+            .builder
+            .build_global_string_ptr("", "dict_no_separator")
+            .unwrap()
+            .as_pointer_value();
+        let separator = self
+            .builder
+            .build_global_string_ptr(", ", "dict_separator")
+            .unwrap()
+            .as_pointer_value();
+        let separator = self
+            .builder
+            .build_select(is_first, no_separator, separator, "separator")
+            .unwrap();
+        self.builder
+            .build_call(append, &[buffer.into(), separator.into()], "")
+            .unwrap();
+        let key_slot = self
+            .builder
+            .build_int_mul(index, i64_type.const_int(2, false), "key_slot")
+            .unwrap();
+        let value_slot = self
+            .builder
+            .build_int_add(key_slot, i64_type.const_int(1, false), "value_slot")
+            .unwrap();
+        let load_slot = |slot: IntValue<'ctx>, name: &str| {
+            let slot_ptr = unsafe {
+                self.builder
+                    .build_in_bounds_gep(pyobject_type, entries, &[slot], "slot_ptr")
+                    .unwrap()
+            };
+            self.builder
+                .build_load(pyobject_type, slot_ptr, name)
+                .unwrap()
+                .into_int_value()
+        };
+        let key = load_slot(key_slot, "key");
+        let value = load_slot(value_slot, "value");
+        self.build_append_repr(key, buffer, function);
+        let colon = self
+            .builder
+            .build_global_string_ptr(": ", "dict_colon")
+            .unwrap()
+            .as_pointer_value();
+        self.builder
+            .build_call(append, &[buffer.into(), colon.into()], "")
+            .unwrap();
+        self.build_append_repr(value, buffer, function);
+        let next_index = self
+            .builder
+            .build_int_add(index, i64_type.const_int(1, false), "next_index")
+            .unwrap();
+        self.builder.build_store(index_ptr, next_index).unwrap();
+        self.builder.build_unconditional_branch(cond_block).unwrap();
+
+        self.builder.position_at_end(done_block);
+        let close_brace = self
+            .builder
+            .build_global_string_ptr("}", "dict_close")
+            .unwrap()
+            .as_pointer_value();
+        self.builder
+            .build_call(append, &[buffer.into(), close_brace.into()], "")
+            .unwrap();
+        self.builder.build_return(None).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Appends the repr of `value` to a text buffer: strings are quoted, everything
+    /// else gets its `str()` text (which formats containers recursively)
+    fn build_append_repr(
+        &mut self,
+        value: IntValue<'ctx>,
+        buffer: PointerValue<'ctx>,
+        function: FunctionValue<'ctx>,
+    ) {
+        let append = self.get_or_build_buffer_append();
+        let is_string = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                self.extract_tag(value),
+                self.context
+                    .i64_type()
+                    .const_int(TYPE_TAG_STRING as u64, false),
+                "is_string",
+            )
+            .unwrap();
+        let quoted_block = self.context.append_basic_block(function, "repr_quoted");
+        let str_block = self.context.append_basic_block(function, "repr_str");
+        let done_block = self.context.append_basic_block(function, "repr_done");
+        self.builder
+            .build_conditional_branch(is_string, quoted_block, str_block)
+            .unwrap();
+
+        self.builder.position_at_end(quoted_block);
+        let quote = self
+            .builder
+            .build_global_string_ptr("'", "repr_quote")
+            .unwrap()
+            .as_pointer_value();
+        let text = self.extract_string_ptr(value);
+        for text in [quote, text, quote] {
+            self.builder
+                .build_call(append, &[buffer.into(), text.into()], "")
+                .unwrap();
+        }
+        self.builder.build_unconditional_branch(done_block).unwrap();
+
+        self.builder.position_at_end(str_block);
+        let text = expression::build_str_ptr(self, value)
+            .expect("formatting a dict entry should not fail");
+        self.builder
+            .build_call(append, &[buffer.into(), text.into()], "")
+            .unwrap();
+        self.builder.build_unconditional_branch(done_block).unwrap();
+
+        self.builder.position_at_end(done_block);
+    }
+
+    /// Returns the internal `print_list` helper, building it on first use
+    ///
+    /// `print_list(list)` prints a list as `[a, b, c]` without a trailing newline, or a
+    /// tuple as `(a, b)` (`(a,)` for a single element) since both share the layout.
+    /// String elements are quoted like Python's repr (`['a', 'b']`); other elements are
+    /// printed with `build_print_value`, so nested lists recurse at runtime.
+    fn get_or_build_print_list(&mut self) -> FunctionValue<'ctx> {
+        if let Some(function) = self.module.get_function("print_list") {
+            return function;
+        }
+
+        let i64_type = self.context.i64_type();
+        let pyobject_type = self.create_pyobject_type();
+        let fn_type = self
+            .context
+            .void_type()
+            .fn_type(&[pyobject_type.into()], false);
+        let function = self.module.add_function(
+            "print_list",
+            fn_type,
+            Some(inkwell::module::Linkage::Internal),
+        );
+        let saved_block = self.builder.get_insert_block();
+
+        let entry = self.context.append_basic_block(function, "entry");
+        let cond_block = self.context.append_basic_block(function, "elem_cond");
+        let body_block = self.context.append_basic_block(function, "elem_body");
+        let done_block = self.context.append_basic_block(function, "elem_done");
+
+        self.builder.position_at_end(entry);
+        let printf = self.runtime.add_printf(&self.module);
+        let list_obj = function.get_first_param().unwrap().into_int_value();
+        let list_tag = self.extract_tag(list_obj);
+        let is_tuple = self
             .builder
-            .build_float_to_signed_int(payload, self.context.i64_type(), "to_int")
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                list_tag,
+                i64_type.const_int(TYPE_TAG_TUPLE as u64, false),
+                "is_tuple",
+            )
+            .unwrap();
+        let list_open = self
+            .builder
+            .build_global_string_ptr("[", "list_open")
+            .unwrap()
+            .as_pointer_value();
+        let tuple_open = self
+            .builder
+            .build_global_string_ptr("(", "tuple_open")
+            .unwrap()
+            .as_pointer_value();
+        let open_bracket = self
+            .builder
+            .build_select(is_tuple, tuple_open, list_open, "open_bracket")
             .unwrap();
-        let int_format = if with_newline {
-            self.format_strings.get_int_format_string(&self.builder)
-        } else {
-            self.format_strings
-                .get_int_format_string_no_newline(&self.builder)
-        };
         self.builder
-            .build_call(printf, &[int_format.into(), int_val.into()], "printf_int")
+            .build_call(printf, &[open_bracket.into()], "printf_open")
             .unwrap();
-        self.builder.build_unconditional_branch(end_block).unwrap();
+        let (list_ptr, list_len) = self.extract_list_ptr_and_len(list_obj);
+        let index_ptr = self.create_entry_block_alloca("index", function);
+        self.builder
+            .build_store(index_ptr, i64_type.const_zero())
+            .unwrap();
+        self.builder.build_unconditional_branch(cond_block).unwrap();
 
-        // Float block
-        self.builder.position_at_end(float_block);
-        let float_format = if with_newline {
-            self.format_strings.get_float_format_string(&self.builder)
-        } else {
-            self.format_strings
-                .get_float_format_string_no_newline(&self.builder)
+        // Loop over the elements: index < len
+        self.builder.position_at_end(cond_block);
+        let index = self
+            .builder
+            .build_load(i64_type, index_ptr, "index")
+            .unwrap()
+            .into_int_value();
+        let in_range = self
+            .builder
+            .build_int_compare(inkwell::IntPredicate::SLT, index, list_len, "in_range")
+            .unwrap();
+        self.builder
+            .build_conditional_branch(in_range, body_block, done_block)
+            .unwrap();
+
+        // Print ", " before every element except the first, then the element
+        self.builder.position_at_end(body_block);
+        let is_first = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                index,
+                i64_type.const_zero(),
+                "is_first",
+            )
+            .unwrap();
+        let no_separator = self
+            .builder
+            .build_global_string_ptr("", "list_no_separator")
+            .unwrap()
+            .as_pointer_value();
+        let separator = self
+            .builder
+            .build_global_string_ptr(", ", "list_separator")
+            .unwrap()
+            .as_pointer_value();
+        let separator = self
+            .builder
+            .build_select(is_first, no_separator, separator, "separator")
+            .unwrap();
+        self.builder
+            .build_call(printf, &[separator.into()], "printf_separator")
+            .unwrap();
+        let adjusted_index = self
+            .builder
+            .build_int_add(index, i64_type.const_int(1, false), "adjusted_index")
+            .unwrap();
+        let elem_ptr = unsafe {
+            self.builder
+                .build_in_bounds_gep(pyobject_type, list_ptr, &[adjusted_index], "elem_ptr")
+                .unwrap()
         };
+        let elem = self
+            .builder
+            .build_load(pyobject_type, elem_ptr, "elem")
+            .unwrap()
+            .into_int_value();
+
+        // Elements are printed as their repr: strings are quoted, everything else
+        // prints as it would at the top level
+        let elem_tag = self.extract_tag(elem);
+        let elem_is_string = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                elem_tag,
+                i64_type.const_int(TYPE_TAG_STRING as u64, false),
+                "elem_is_string",
+            )
+            .unwrap();
+        let repr_string_block = self
+            .context
+            .append_basic_block(function, "elem_repr_string");
+        let print_elem_block = self.context.append_basic_block(function, "elem_print");
+        let next_block = self.context.append_basic_block(function, "elem_next");
+        self.builder
+            .build_conditional_branch(elem_is_string, repr_string_block, print_elem_block)
+            .unwrap();
+
+        self.builder.position_at_end(repr_string_block);
+        let quoted_format = self
+            .builder
+            .build_global_string_ptr("'%s'", "repr_string_format")
+            .unwrap()
+            .as_pointer_value();
+        let elem_str = self.extract_string_ptr(elem);
         self.builder
             .build_call(
                 printf,
-                &[float_format.into(), payload.into()],
-                "printf_float",
+                &[quoted_format.into(), elem_str.into()],
+                "printf_repr",
             )
             .unwrap();
-        self.builder.build_unconditional_branch(end_block).unwrap();
+        self.builder.build_unconditional_branch(next_block).unwrap();
+
+        self.builder.position_at_end(print_elem_block);
+        self.build_print_value(elem, false);
+        self.builder.build_unconditional_branch(next_block).unwrap();
+
+        self.builder.position_at_end(next_block);
+        let next_index = self
+            .builder
+            .build_int_add(index, i64_type.const_int(1, false), "next_index")
+            .unwrap();
+        self.builder.build_store(index_ptr, next_index).unwrap();
+        self.builder.build_unconditional_branch(cond_block).unwrap();
+
+        self.builder.position_at_end(done_block);
+        let is_single = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                list_len,
+                i64_type.const_int(1, false),
+                "is_single",
+            )
+            .unwrap();
+        let is_single_tuple = self
+            .builder
+            .build_and(is_tuple, is_single, "is_single_tuple")
+            .unwrap();
+        let list_close = self
+            .builder
+            .build_global_string_ptr("]", "list_close")
+            .unwrap()
+            .as_pointer_value();
+        let tuple_close = self
+            .builder
+            .build_global_string_ptr(")", "tuple_close")
+            .unwrap()
+            .as_pointer_value();
+        let single_tuple_close = self
+            .builder
+            .build_global_string_ptr(",)", "single_tuple_close")
+            .unwrap()
+            .as_pointer_value();
+        let close_bracket = self
+            .builder
+            .build_select(is_tuple, tuple_close, list_close, "close_bracket")
+            .unwrap();
+        let close_bracket = self
+            .builder
+            .build_select(
+                is_single_tuple,
+                single_tuple_close,
+                close_bracket.into_pointer_value(),
+                "close_bracket",
+            )
+            .unwrap();
+        self.builder
+            .build_call(printf, &[close_bracket.into()], "printf_close")
+            .unwrap();
+        self.builder.build_return(None).unwrap();
+
+        if let Some(block) = saved_block {
+            self.builder.position_at_end(block);
+        }
+        function
+    }
+
+    /// Emits a printf of `args` (a format string and its values), or an fprintf of them
+    /// to stderr while compiling a `print(..., file=sys.stderr)`
+    pub(crate) fn build_print_call(
+        &self,
+        args: &[BasicMetadataValueEnum<'ctx>],
+        name: &str,
+    ) -> Result<CallSiteValue<'ctx>, BuilderError> {
+        if !self.print_to_stderr {
+            let printf = self.runtime.add_printf(&self.module);
+            return self.builder.build_call(printf, args, name);
+        }
+        let fprintf = self.runtime.add_fprintf(&self.module);
+        let stderr_global = self.runtime.add_stderr(&self.module);
+        let stderr = self.builder.build_load(
+            self.context.ptr_type(inkwell::AddressSpace::default()),
+            stderr_global.as_pointer_value(),
+            "stderr",
+        )?;
+        let mut fprintf_args = vec![stderr.into()];
+        fprintf_args.extend_from_slice(args);
+        self.builder.build_call(fprintf, &fprintf_args, name)
+    }
 
-        // String block
-        self.builder.position_at_end(string_block);
-        let str_ptr = self.extract_string_ptr(pyobject);
-        let string_format = if with_newline {
-            self.format_strings.get_string_format_string(&self.builder)
+    /// Writes `len` bytes from `ptr` to the print stream with `fwrite`, so bytes a
+    /// `%s` format would stop at (an embedded NUL) are written too
+    pub(crate) fn build_print_bytes(&self, ptr: PointerValue<'ctx>, len: IntValue<'ctx>) {
+        let stream_global = if self.print_to_stderr {
+            self.runtime.add_stderr(&self.module)
         } else {
-            self.format_strings
-                .get_string_format_string_no_newline(&self.builder)
+            self.runtime.add_stdout(&self.module)
         };
+        let stream = self
+            .builder
+            .build_load(
+                self.context.ptr_type(inkwell::AddressSpace::default()),
+                stream_global.as_pointer_value(),
+                "stream",
+            )
+            .unwrap();
+        let fwrite = self.runtime.add_fwrite(&self.module);
+        let i64_type = self.context.i64_type();
         self.builder
             .build_call(
-                printf,
-                &[string_format.into(), str_ptr.into()],
-                "printf_string",
+                fwrite,
+                &[
+                    ptr.into(),
+                    i64_type.const_int(1, false).into(),
+                    len.into(),
+                    stream.into(),
+                ],
+                "fwrite",
+            )
+            .unwrap();
+    }
+
+    /// Prints a value as `str()` would show it: lists and tuples go through
+    /// `print_list` (or a `format_list` buffer), everything else through `build_str_ptr`,
+    /// the conversion f-strings and `str()` use too. Ints skip the intermediate buffer
+    /// but use the same format.
+    pub(crate) fn build_print_value(&mut self, pyobject: IntValue<'ctx>, with_newline: bool) {
+        let tag = self.extract_tag(pyobject);
+        let i64_type = self.context.i64_type();
+        let is_int = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                tag,
+                i64_type.const_int(TYPE_TAG_INT as u64, false),
+                "is_int",
+            )
+            .unwrap();
+        let is_list = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                tag,
+                i64_type.const_int(TYPE_TAG_LIST as u64, false),
+                "is_list",
             )
             .unwrap();
+        let is_tuple = self
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                tag,
+                i64_type.const_int(TYPE_TAG_TUPLE as u64, false),
+                "is_tuple",
+            )
+            .unwrap();
+        let is_list = self
+            .builder
+            .build_or(is_list, is_tuple, "is_list_or_tuple")
+            .unwrap();
+
+        let current_fn = self
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let int_block = self.context.append_basic_block(current_fn, "print_int");
+        let check_list_block = self.context.append_basic_block(current_fn, "check_list");
+        let list_block = self.context.append_basic_block(current_fn, "print_list");
+        let text_block = self.context.append_basic_block(current_fn, "print_text");
+        let end_block = self.context.append_basic_block(current_fn, "print_end");
+        self.builder
+            .build_conditional_branch(is_int, int_block, check_list_block)
+            .unwrap();
+
+        self.builder.position_at_end(int_block);
+        let int_value = self.extract_int_payload(pyobject);
+        let int_format = if with_newline {
+            self.format_strings.get_int_format_string(&self.builder)
+        } else {
+            self.format_strings
+                .get_int_format_string_no_newline(&self.builder)
+        };
+        self.build_print_call(&[int_format.into(), int_value.into()], "printf_int")
+            .unwrap();
+        self.builder.build_unconditional_branch(end_block).unwrap();
+
+        self.builder.position_at_end(check_list_block);
+        self.builder
+            .build_conditional_branch(is_list, list_block, text_block)
+            .unwrap();
+
+        // List block: print_list handles the brackets and elements (recursively), or
+        // format_list builds the same text in a buffer that is printed in one call
+        self.builder.position_at_end(list_block);
+        // print_list writes to stdout, so stderr output always goes through a buffer
+        if self.buffered_list_printing || self.print_to_stderr {
+            self.build_print_list_buffered(pyobject, current_fn);
+        } else {
+            let print_list = self.get_or_build_print_list();
+            self.builder
+                .build_call(print_list, &[pyobject.into()], "")
+                .unwrap();
+        }
+        if with_newline {
+            let newline_format = self.format_strings.get_newline_format_string(&self.builder);
+            self.build_print_call(&[newline_format.into()], "printf_newline")
+                .unwrap();
+        }
+        self.builder.build_unconditional_branch(end_block).unwrap();
+
+        self.builder.position_at_end(text_block);
+        let text = expression::build_str_ptr(self, pyobject)
+            .expect("converting a value to text should not fail");
+        let strlen = self.runtime.add_strlen(&self.module);
+        let text_len = self
+            .builder
+            .build_call(strlen, &[text.into()], "text_len")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        self.build_print_bytes(text, text_len);
+        if with_newline {
+            let newline_format = self.format_strings.get_newline_format_string(&self.builder);
+            self.build_print_call(&[newline_format.into()], "printf_newline")
+                .unwrap();
+        }
         self.builder.build_unconditional_branch(end_block).unwrap();
 
-        // Continue at end block
         self.builder.position_at_end(end_block);
     }
 }
+
+/// Infers the return type of every function definition.
+///
+/// Starts optimistically from `ReturnType::Int` for all functions and demotes any function
+/// with a `return` that is not statically an integer, or whose body can fall off the end
+/// (returning None), iterating until a fixpoint is reached so that (mutually) recursive
+/// calls to integer-returning functions are handled.
+pub(crate) fn infer_return_types(functions: &[&IRStmt]) -> HashMap<String, ReturnType> {
+    let mut types: HashMap<String, ReturnType> = functions
+        .iter()
+        .filter_map(|stmt| match stmt {
+            IRStmt::FunctionDef { name, .. } => Some((name.clone(), ReturnType::Int)),
+            _ => None,
+        })
+        .collect();
+
+    loop {
+        let mut changed = false;
+        for stmt in functions {
+            if let IRStmt::FunctionDef {
+                name,
+                params,
+                kwonly_params,
+                kwarg,
+                body,
+                ..
+            } = stmt
+            {
+                if types[name] != ReturnType::Int {
+                    continue;
+                }
+                // Calls to a local named like a user function (e.g. a parameter) go
+                // through the local, whose return type is unknown
+                let mut locals: HashSet<String> = params
+                    .iter()
+                    .chain(kwonly_params)
+                    .chain(kwarg)
+                    .cloned()
+                    .collect();
+                collect_assigned_names(body, &mut locals);
+                let visible: HashMap<String, ReturnType> = types
+                    .iter()
+                    .filter(|(function, _)| !locals.contains(*function))
+                    .map(|(function, return_type)| (function.clone(), *return_type))
+                    .collect();
+                let mut returns = Vec::new();
+                collect_returns(body, &mut returns);
+                let all_int = always_returns(body)
+                    && returns.iter().all(|expr| is_static_int(expr, &visible));
+                if !all_int {
+                    types.insert(name.clone(), ReturnType::PyObject);
+                    changed = true;
+                }
+            }
+        }
+        if !changed {
+            return types;
+        }
+    }
+}
+
+/// Collects the variables assigned in a function body, including nested blocks
+fn collect_assigned_names(body: &[IRStmt], names: &mut HashSet<String>) {
+    for stmt in body {
+        match stmt {
+            IRStmt::Assign { target, .. } => {
+                names.insert(target.clone());
+            }
+            IRStmt::UnpackAssign { targets, .. } => names.extend(targets.iter().cloned()),
+            IRStmt::If {
+                then_body: body,
+                else_body,
+                ..
+            }
+            | IRStmt::While {
+                body, else_body, ..
+            } => {
+                collect_assigned_names(body, names);
+                collect_assigned_names(else_body, names);
+            }
+            IRStmt::For {
+                var,
+                body,
+                else_body,
+                ..
+            }
+            | IRStmt::ForEach {
+                var,
+                body,
+                else_body,
+                ..
+            } => {
+                names.insert(var.clone());
+                collect_assigned_names(body, names);
+                collect_assigned_names(else_body, names);
+            }
+            IRStmt::Try {
+                body,
+                handler,
+                else_body,
+                ..
+            } => {
+                collect_assigned_names(body, names);
+                collect_assigned_names(handler, names);
+                collect_assigned_names(else_body, names);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns true if a block never falls off its end: every path through it returns,
+/// raises or exits. Loops are assumed to finish, since their condition is not known.
+fn always_returns(body: &[IRStmt]) -> bool {
+    body.iter().any(|stmt| match stmt {
+        IRStmt::Return(_) | IRStmt::Raise { .. } | IRStmt::Exit(_) => true,
+        IRStmt::If {
+            then_body,
+            else_body,
+            ..
+        } => always_returns(then_body) && always_returns(else_body),
+        IRStmt::Try {
+            body,
+            handler,
+            else_body,
+            ..
+        } => (always_returns(body) || always_returns(else_body)) && always_returns(handler),
+        _ => false,
+    })
+}
+
+/// Collects the expressions of all `return` statements in a function body
+fn collect_returns<'a>(body: &'a [IRStmt], returns: &mut Vec<&'a IRExpr>) {
+    for stmt in body {
+        match stmt {
+            IRStmt::Return(expr) => returns.push(expr),
+            IRStmt::If {
+                then_body: body,
+                else_body,
+                ..
+            }
+            | IRStmt::While {
+                body, else_body, ..
+            }
+            | IRStmt::For {
+                body, else_body, ..
+            }
+            | IRStmt::ForEach {
+                body, else_body, ..
+            } => {
+                collect_returns(body, returns);
+                collect_returns(else_body, returns);
+            }
+            IRStmt::Try {
+                body,
+                handler,
+                else_body,
+                ..
+            } => {
+                collect_returns(body, returns);
+                collect_returns(handler, returns);
+                collect_returns(else_body, returns);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns true if any statement (at any nesting depth) is a try block
+fn contains_try<'a>(body: impl Iterator<Item = &'a IRStmt>) -> bool {
+    body.into_iter().any(|stmt| match stmt {
+        IRStmt::Try { .. } => true,
+        IRStmt::If {
+            then_body: body,
+            else_body,
+            ..
+        }
+        | IRStmt::While {
+            body, else_body, ..
+        }
+        | IRStmt::For {
+            body, else_body, ..
+        }
+        | IRStmt::ForEach {
+            body, else_body, ..
+        } => contains_try(body.iter()) || contains_try(else_body.iter()),
+        _ => false,
+    })
+}
+
+/// Returns the names a `del` anywhere in a function body may unbind
+fn deleted_names<'a>(body: impl Iterator<Item = &'a IRStmt>) -> HashSet<String> {
+    body.into_iter()
+        .flat_map(|stmt| match stmt {
+            IRStmt::Delete(names) => names.iter().cloned().collect(),
+            IRStmt::If {
+                then_body: body,
+                else_body,
+                ..
+            }
+            | IRStmt::While {
+                body, else_body, ..
+            }
+            | IRStmt::For {
+                body, else_body, ..
+            }
+            | IRStmt::ForEach {
+                body, else_body, ..
+            } => deleted_names(body.iter().chain(else_body)),
+            IRStmt::Try {
+                body,
+                handler,
+                else_body,
+                ..
+            } => deleted_names(body.iter().chain(handler).chain(else_body)),
+            _ => HashSet::new(),
+        })
+        .collect()
+}
+
+/// Returns the names declared `global` anywhere in a function body
+fn declared_globals<'a>(body: impl Iterator<Item = &'a IRStmt>) -> Vec<String> {
+    body.into_iter()
+        .flat_map(|stmt| match stmt {
+            IRStmt::Global(names) => names.clone(),
+            IRStmt::If {
+                then_body: body,
+                else_body,
+                ..
+            }
+            | IRStmt::While {
+                body, else_body, ..
+            }
+            | IRStmt::For {
+                body, else_body, ..
+            }
+            | IRStmt::ForEach {
+                body, else_body, ..
+            } => declared_globals(body.iter().chain(else_body)),
+            IRStmt::Try {
+                body,
+                handler,
+                else_body,
+                ..
+            } => declared_globals(body.iter().chain(handler).chain(else_body)),
+            _ => Vec::new(),
+        })
+        .collect()
+}
+
+/// Returns the addend of `target = target + addend` when it may be a string, so the
+/// assignment can append to the target's text buffer in place. Addends known to be
+/// numbers keep the plain arithmetic path.
+pub(crate) fn string_append_operand<'a>(
+    target: &str,
+    value: &'a IRExpr,
+    types: &HashMap<String, ReturnType>,
+) -> Option<&'a IRExpr> {
+    match value {
+        IRExpr::BinaryOp {
+            op: BinOp::Add,
+            left,
+            right,
+        } if matches!(left.as_ref(), IRExpr::Variable(name) if name == target)
+            && !matches!(right.as_ref(), IRExpr::Float(_) | IRExpr::Bool(_))
+            && !is_static_int(right, types) =>
+        {
+            Some(right)
+        }
+        _ => None,
+    }
+}
+
+/// Collects the variables a function body (including nested blocks) appends to with
+/// `s = s + ...` (see `string_append_operand`)
+fn string_append_targets<'a>(
+    body: impl Iterator<Item = &'a IRStmt>,
+    types: &HashMap<String, ReturnType>,
+) -> Vec<String> {
+    let mut targets: Vec<String> = body
+        .flat_map(|stmt| match stmt {
+            IRStmt::Assign { target, value } => string_append_operand(target, value, types)
+                .map(|_| target.clone())
+                .into_iter()
+                .collect(),
+            IRStmt::If {
+                then_body: body,
+                else_body,
+                ..
+            }
+            | IRStmt::While {
+                body, else_body, ..
+            }
+            | IRStmt::For {
+                body, else_body, ..
+            }
+            | IRStmt::ForEach {
+                body, else_body, ..
+            } => string_append_targets(body.iter().chain(else_body), types),
+            IRStmt::Try {
+                body,
+                handler,
+                else_body,
+                ..
+            } => string_append_targets(body.iter().chain(handler).chain(else_body), types),
+            _ => Vec::new(),
+        })
+        .collect();
+    targets.sort();
+    targets.dedup();
+    targets
+}
+
+/// Returns true if an expression is a literal, whose value is the same wherever it is
+/// evaluated
+fn is_literal(expr: &IRExpr) -> bool {
+    matches!(
+        expr,
+        IRExpr::Constant(_)
+            | IRExpr::Float(_)
+            | IRExpr::Bool(_)
+            | IRExpr::StringLiteral(_)
+            | IRExpr::NoneLiteral
+    )
+}
+
+/// Returns true if an expression always evaluates to an integer, whatever its inputs
+fn is_static_int(expr: &IRExpr, types: &HashMap<String, ReturnType>) -> bool {
+    match expr {
+        IRExpr::Constant(_) | IRExpr::Len(_) => true,
+        IRExpr::Call { func, .. } => types.get(func) == Some(&ReturnType::Int),
+        IRExpr::BinaryOp { op, left, right } => match op {
+            // Shifts always produce an integer
+            BinOp::LShift | BinOp::RShift => true,
+            // &, | and ^ keep two bools a bool, so one side must be known to be an integer
+            BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor => {
+                is_static_int(left, types) || is_static_int(right, types)
+            }
+            BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::FloorDiv | BinOp::Mod => {
+                is_static_int(left, types) && is_static_int(right, types)
+            }
+            // A negative exponent makes an integer power a float
+            BinOp::Div | BinOp::Pow => false,
+        },
+        IRExpr::IfExp {
+            then_value,
+            else_value,
+            ..
+        } => is_static_int(then_value, types) && is_static_int(else_value, types),
+        IRExpr::NamedExpr { value, .. } => is_static_int(value, types),
+        IRExpr::BoolOp { values, .. } => values.iter().all(|value| is_static_int(value, types)),
+        IRExpr::UnaryOp { op, operand } => match op {
+            UnaryOp::Invert => true,
+            UnaryOp::UAdd | UnaryOp::USub => is_static_int(operand, types),
+            UnaryOp::Not => false,
+        },
+        _ => false,
+    }
+}
@@ -15,11 +15,18 @@
 //! These functions are called from `Compiler::compile_expression()` to handle specific
 //! expression types while keeping the main compilation logic clean and maintainable.
 
-use crate::ast::{BinOp, CmpOp, IRExpr, UnaryOp};
-use crate::codegen::{CodeGenError, Compiler};
-use crate::compiler::values::{TYPE_TAG_FLOAT, TYPE_TAG_INT, TYPE_TAG_LIST, TYPE_TAG_STRING};
-use inkwell::values::IntValue;
-use inkwell::FloatPredicate;
+use crate::ast::{BinOp, BoolOp, CmpOp, ComprehensionSource, FStringPart, IRExpr, UnaryOp};
+use crate::codegen::{CodeGenError, Compiler, ReturnType};
+use crate::compiler::runtime::{FLOAT_REPR_SIZE, INPUT_LINE_SIZE};
+use crate::compiler::values::{
+    TYPE_TAG_BOOL, TYPE_TAG_DICT, TYPE_TAG_FILE, TYPE_TAG_FLOAT, TYPE_TAG_FUNC, TYPE_TAG_INT,
+    TYPE_TAG_LIST, TYPE_TAG_NONE, TYPE_TAG_SET, TYPE_TAG_STRING, TYPE_TAG_TUPLE,
+};
+use inkwell::intrinsics::Intrinsic;
+use inkwell::values::{
+    BasicMetadataValueEnum, BasicValueEnum, FloatValue, FunctionValue, IntValue, PointerValue,
+};
+use inkwell::{FloatPredicate, IntPredicate};
 
 // ============================================================================
 // Simple Expression Helpers
@@ -52,23 +59,61 @@ pub fn compile_bool<'ctx>(
     Ok(compiler.create_pyobject_bool(bool_val))
 }
 
-/// Compiles a variable access expression
+/// Compiles a variable access expression; a name that is not a variable but a user
+/// function evaluates to the function as a value
 pub fn compile_variable<'ctx>(
-    compiler: &Compiler<'ctx>,
+    compiler: &mut Compiler<'ctx>,
     name: &str,
 ) -> Result<IntValue<'ctx>, CodeGenError> {
-    let ptr = compiler
-        .variables
-        .get(name)
-        .ok_or_else(|| CodeGenError::UndefinedVariable(name.to_string()))?;
+    let Some(&ptr) = compiler.variables.get(name) else {
+        return compiler.function_value(name);
+    };
 
-    let pyobject_type = compiler.create_pyobject_type();
-    let loaded = compiler
-        .builder
-        .build_load(pyobject_type, *ptr, name)
-        .unwrap();
+    // The string read here may now be shared, so the next append must copy it
+    if let Some(buffer) = compiler.string_buffers.get(name) {
+        let data_ptr = compiler
+            .builder
+            .build_struct_gep(compiler.text_buffer_type(), *buffer, 0, "buffer_data_ptr")
+            .unwrap();
+        let null = compiler
+            .context
+            .ptr_type(inkwell::AddressSpace::default())
+            .const_null();
+        let store = compiler.builder.build_store(data_ptr, null).unwrap();
+        if compiler.volatile_variables {
+            store.set_volatile(true).unwrap();
+        }
+    }
+
+    let value = compiler.build_variable_load(ptr, name);
+    compiler.build_bound_check(value, name);
+    Ok(value)
+}
 
-    Ok(loaded.into_int_value())
+/// Compiles an assignment expression `target := value`: stores the value in the
+/// variable (declaring it on first use) and yields it
+pub fn compile_named_expr<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    target: &str,
+    value: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let value = compiler.compile_expression(value)?;
+    let ptr = match compiler.variables.get(target) {
+        Some(ptr) => *ptr,
+        None => {
+            let current_fn = compiler
+                .builder
+                .get_insert_block()
+                .unwrap()
+                .get_parent()
+                .unwrap();
+            let ptr = compiler.create_entry_block_alloca(target, current_fn);
+            compiler.variables.insert(target.to_string(), ptr);
+            ptr
+        }
+    };
+    compiler.build_variable_store(ptr, value);
+    Ok(value)
 }
 
 /// Compiles a string literal expression
@@ -119,850 +164,5966 @@ pub fn compile_string_literal<'ctx>(
         )
         .unwrap();
 
-    // Track the allocated string in the arena for cleanup only if in main entry block
-    if let Some(main_entry) = compiler.main_entry_block {
-        if compiler.builder.get_insert_block() == Some(main_entry) {
-            compiler.string_arena.push(str_ptr);
-        }
-    }
+    compiler.track_string_in_arena(str_ptr);
 
     // Wrap the string pointer in a PyObject
     Ok(compiler.create_pyobject_string(str_ptr))
 }
 
 // ============================================================================
-// Comparison Operations
-// ============================================================================
-
-/// Compiles a comparison expression (==, !=, <, >, <=, >=)
-pub fn compile_comparison<'ctx>(
-    compiler: &mut Compiler<'ctx>,
-    op: &CmpOp,
-    left: &IRExpr,
-    right: &IRExpr,
-) -> Result<IntValue<'ctx>, CodeGenError> {
-    let lhs_obj = compiler.compile_expression(left)?;
-    let rhs_obj = compiler.compile_expression(right)?;
-
-    // Extract payloads (values are already stored as f64)
-    let lhs_payload = compiler.extract_payload(lhs_obj);
-    let rhs_payload = compiler.extract_payload(rhs_obj);
-
-    // Perform the comparison
-    let predicate = match op {
-        CmpOp::Eq => FloatPredicate::OEQ,    // Ordered and equal
-        CmpOp::NotEq => FloatPredicate::ONE, // Ordered and not equal
-        CmpOp::Lt => FloatPredicate::OLT,    // Ordered and less than
-        CmpOp::Gt => FloatPredicate::OGT,    // Ordered and greater than
-        CmpOp::LtE => FloatPredicate::OLE,   // Ordered and less than or equal
-        CmpOp::GtE => FloatPredicate::OGE,   // Ordered and greater than or equal
-    };
-
-    let cmp_result = compiler
-        .builder
-        .build_float_compare(predicate, lhs_payload, rhs_payload, "cmptmp")
-        .unwrap();
-
-    // Return as PyObject with bool tag
-    Ok(compiler.create_pyobject_bool(cmp_result))
-}
-
-// ============================================================================
-// Unary Operations
+// F-strings
 // ============================================================================
 
-/// Compiles a unary operation expression (-, +, ~, not)
-pub fn compile_unary_op<'ctx>(
+/// Compiles an f-string into a new string
+///
+/// The parts become a single printf-style format (literal text with '%' escaped, one
+/// directive per replacement field), which snprintf runs twice: once to measure the
+/// result and once to fill the allocation.
+pub fn compile_fstring<'ctx>(
     compiler: &mut Compiler<'ctx>,
-    op: &UnaryOp,
-    operand: &IRExpr,
+    parts: &[FStringPart],
 ) -> Result<IntValue<'ctx>, CodeGenError> {
-    let operand_obj = compiler.compile_expression(operand)?;
+    let i32_type = compiler.context.i32_type();
+    let i64_type = compiler.context.i64_type();
 
-    match op {
-        UnaryOp::Invert => {
-            // Bitwise NOT (~x)
-            let payload = compiler.extract_payload(operand_obj);
-            let operand_int = compiler
-                .builder
-                .build_float_to_signed_int(payload, compiler.context.i64_type(), "to_int")
-                .unwrap();
-            let result = compiler.builder.build_not(operand_int, "not").unwrap();
-            Ok(compiler.create_pyobject_int(result))
-        }
-        UnaryOp::USub => {
-            // Unary minus (-x)
-            let payload = compiler.extract_payload(operand_obj);
-            let zero = compiler.context.f64_type().const_float(0.0);
-            let result = compiler
-                .builder
-                .build_float_sub(zero, payload, "neg")
-                .unwrap();
-
-            // Preserve the type tag from the operand
-            let tag = compiler.extract_tag(operand_obj);
-            let result_obj = compiler.create_pyobject_from_tag_and_payload(tag, result);
-
-            Ok(result_obj)
-        }
-        UnaryOp::UAdd => {
-            // Unary plus (+x) - just return the operand unchanged
-            Ok(operand_obj)
-        }
-        UnaryOp::Not => {
-            // Logical NOT (not x)
-            let payload = compiler.extract_payload(operand_obj);
-            let zero = compiler.context.f64_type().const_float(0.0);
+    let mut format = String::new();
+    let mut args: Vec<BasicMetadataValueEnum<'ctx>> = Vec::new();
+    for part in parts {
+        let (value, spec) = match part {
+            FStringPart::Literal(text) => {
+                format.push_str(&text.replace('%', "%%"));
+                continue;
+            }
+            FStringPart::Value { value, spec } => (
+                compiler.compile_expression(value)?,
+                spec.clone().unwrap_or_default(),
+            ),
+        };
 
-            // Check if operand is zero
-            let is_zero = compiler
-                .builder
-                .build_float_compare(FloatPredicate::OEQ, payload, zero, "is_zero")
-                .unwrap();
+        let mut directive = String::from("%");
+        match spec.kind {
+            Some(kind @ ('d' | 'f' | 'e' | 'g')) => {
+                // Numbers align right unless told otherwise
+                let is_int = build_tag_check(compiler, value, TYPE_TAG_INT, "is_int");
+                let is_bool = build_tag_check(compiler, value, TYPE_TAG_BOOL, "is_bool");
+                let is_integral = compiler
+                    .builder
+                    .build_or(is_int, is_bool, "is_integral")
+                    .unwrap();
+                let accepted = if kind == 'd' {
+                    is_integral
+                } else {
+                    let is_float = build_tag_check(compiler, value, TYPE_TAG_FLOAT, "is_float");
+                    compiler
+                        .builder
+                        .build_or(is_integral, is_float, "is_number")
+                        .unwrap()
+                };
+                let rejected = compiler.builder.build_not(accepted, "bad_format").unwrap();
+                let message = if kind == 'd' {
+                    "ValueError: format code 'd' requires an int".to_string()
+                } else {
+                    format!("ValueError: format code '{}' requires a number", kind)
+                };
+                compiler.build_raise_if(rejected, &message);
 
-            // Return True if operand is zero, False otherwise
-            Ok(compiler.create_pyobject_bool(is_zero))
+                if spec.align == Some('<') {
+                    directive.push('-');
+                }
+                if spec.zero_pad {
+                    directive.push('0');
+                }
+                if let Some(width) = spec.width {
+                    directive.push_str(&width.to_string());
+                }
+                if kind == 'd' {
+                    directive.push_str("lld");
+                    args.push(compiler.extract_int_payload(value).into());
+                } else {
+                    if let Some(precision) = spec.precision {
+                        directive.push_str(&format!(".{}", precision));
+                    }
+                    directive.push(kind);
+                    args.push(compiler.extract_payload(value).into());
+                }
+            }
+            _ => {
+                // Converted like str(); with `s` the text aligns left like a string, and
+                // without a type strings align left and other values right, which a
+                // negative `*` width expresses at runtime
+                let text = build_str_ptr(compiler, value)?;
+                match (spec.align, spec.width) {
+                    (None, Some(width)) if spec.kind.is_none() => {
+                        let is_string =
+                            build_tag_check(compiler, value, TYPE_TAG_STRING, "is_string");
+                        let right = i32_type.const_int(width as u64, false);
+                        let left = i32_type.const_int((width as i64).wrapping_neg() as u64, true);
+                        let width = compiler
+                            .builder
+                            .build_select(is_string, left, right, "field_width")
+                            .unwrap();
+                        directive.push('*');
+                        args.push(width.into());
+                    }
+                    (align, Some(width)) => {
+                        if align != Some('>') {
+                            directive.push('-');
+                        }
+                        directive.push_str(&width.to_string());
+                    }
+                    (_, None) => {}
+                }
+                if let Some(precision) = spec.precision {
+                    directive.push_str(&format!(".{}", precision));
+                }
+                directive.push('s');
+                args.push(text.into());
+            }
         }
-    }
-}
-
-// ============================================================================
-// List Operations
-// ============================================================================
-
-/// Compiles a list literal expression `[a, b, c]`
-pub fn compile_list<'ctx>(
-    compiler: &mut Compiler<'ctx>,
-    elements: &[IRExpr],
-) -> Result<IntValue<'ctx>, CodeGenError> {
-    // Compile all element expressions
-    let mut compiled_elements = Vec::new();
-    for elem in elements {
-        let elem_pyobj = compiler.compile_expression(elem)?;
-        compiled_elements.push(elem_pyobj);
+        format.push_str(&directive);
     }
 
-    let list_len = elements.len();
-    let pyobject_type = compiler.create_pyobject_type();
+    let snprintf = compiler.runtime.add_snprintf(&compiler.module);
+    let format_ptr = compiler
+        .builder
+        .build_global_string_ptr(&format, "fstring_format")
+        .unwrap()
+        .as_pointer_value();
 
-    // Allocate memory for: [length: i64][element_0: i64]...[element_n: i64]
-    // Total size = (1 + list_len) * sizeof(i64)
-    let pyobject_size = pyobject_type.size_of();
-    let element_count = compiler
+    // snprintf(NULL, 0, ...) returns the length the result needs
+    let null = compiler
         .context
-        .i64_type()
-        .const_int((list_len + 1) as u64, false); // +1 for length header
-    let total_size = compiler
+        .ptr_type(inkwell::AddressSpace::default())
+        .const_null();
+    let mut measure_args = vec![null.into(), i64_type.const_zero().into(), format_ptr.into()];
+    measure_args.extend_from_slice(&args);
+    let length = build_libc_call(compiler, snprintf, &measure_args, "fstring_len")?;
+    let length = compiler
         .builder
-        .build_int_mul(pyobject_size, element_count, "list_size")
+        .build_int_z_extend(length.into_int_value(), i64_type, "fstring_len64")
         .unwrap();
-
-    // Allocate the list
-    let malloc_fn = compiler.runtime.add_malloc(&compiler.module);
-    let list_ptr_result = compiler
+    let size = compiler
         .builder
-        .build_call(malloc_fn, &[total_size.into()], "malloc_list")
+        .build_int_add(length, i64_type.const_int(1, false), "fstring_size")
         .unwrap();
-    let list_ptr = match list_ptr_result.try_as_basic_value() {
-        inkwell::values::ValueKind::Basic(value) => value.into_pointer_value(),
-        _ => {
-            return Err(CodeGenError::UndefinedVariable(
-                "malloc did not return a value".to_string(),
-            ))
-        }
-    };
-
-    // Store the length at offset 0
-    let len_value = compiler
-        .context
-        .i64_type()
-        .const_int(list_len as u64, false);
-    let len_ptr = unsafe {
-        compiler
-            .builder
-            .build_in_bounds_gep(
-                pyobject_type,
-                list_ptr,
-                &[compiler.context.i64_type().const_int(0, false)],
-                "len_ptr",
-            )
-            .unwrap()
-    };
-    compiler.builder.build_store(len_ptr, len_value).unwrap();
 
-    // Store each element in the array (starting at offset 1)
-    for (i, elem_pyobj) in compiled_elements.iter().enumerate() {
-        let index = compiler.context.i64_type().const_int((i + 1) as u64, false); // +1 to skip length header
-        let elem_ptr = unsafe {
-            compiler
-                .builder
-                .build_in_bounds_gep(
-                    pyobject_type,
-                    list_ptr,
-                    &[index],
-                    &format!("elem_ptr_{}", i),
-                )
-                .unwrap()
-        };
-        compiler.builder.build_store(elem_ptr, *elem_pyobj).unwrap();
-    }
+    let malloc_fn = compiler.runtime.add_malloc(&compiler.module);
+    let buffer = build_libc_call(compiler, malloc_fn, &[size.into()], "malloc_fstring")?
+        .into_pointer_value();
+    let mut write_args = vec![buffer.into(), size.into(), format_ptr.into()];
+    write_args.extend_from_slice(&args);
+    build_libc_call(compiler, snprintf, &write_args, "fstring_write")?;
+    compiler.track_string_in_arena(buffer);
 
-    // Create a PyObject with LIST tag and the pointer as payload
-    Ok(compiler.create_pyobject_list(list_ptr, list_len))
+    Ok(compiler.create_pyobject_string(buffer))
 }
 
-/// Compiles a list indexing expression `list[index]`
-pub fn compile_index<'ctx>(
+/// Returns a C string with the `str()` text of a value, raising a TypeError for
+/// sets. Numbers are written to an entry-block buffer, so their text is only valid
+/// until the enclosing function returns; lists, tuples and dicts are formatted into
+/// a new heap allocation by `format_list`.
+pub fn build_str_ptr<'ctx>(
     compiler: &mut Compiler<'ctx>,
-    list: &IRExpr,
-    index: &IRExpr,
-) -> Result<IntValue<'ctx>, CodeGenError> {
-    let list_obj = compiler.compile_expression(list)?;
-    let index_obj = compiler.compile_expression(index)?;
-
-    // Extract the list pointer and length from the PyObject
-    let (list_ptr, _list_len) = compiler.extract_list_ptr_and_len(list_obj);
-
-    // Extract the index value
-    let index_payload = compiler.extract_payload(index_obj);
-    let index_int = compiler
+    value: IntValue<'ctx>,
+) -> Result<PointerValue<'ctx>, CodeGenError> {
+    let is_set = build_tag_check(compiler, value, TYPE_TAG_SET, "is_set");
+    compiler.build_raise_if(is_set, "TypeError: sets cannot be converted to str");
+    let is_string = build_tag_check(compiler, value, TYPE_TAG_STRING, "is_string");
+    let is_int = build_tag_check(compiler, value, TYPE_TAG_INT, "is_int");
+    let is_float = build_tag_check(compiler, value, TYPE_TAG_FLOAT, "is_float");
+    let is_none = build_tag_check(compiler, value, TYPE_TAG_NONE, "is_none");
+    let is_file = build_tag_check(compiler, value, TYPE_TAG_FILE, "is_file");
+    let is_func = build_tag_check(compiler, value, TYPE_TAG_FUNC, "is_func");
+    let is_list = build_tag_check(compiler, value, TYPE_TAG_LIST, "is_list");
+    let is_tuple = build_tag_check(compiler, value, TYPE_TAG_TUPLE, "is_tuple");
+    let is_dict = build_tag_check(compiler, value, TYPE_TAG_DICT, "is_dict");
+    let is_sequence = compiler
         .builder
-        .build_float_to_signed_int(index_payload, compiler.context.i64_type(), "index_int")
+        .build_or(is_list, is_tuple, "is_sequence")
         .unwrap();
-
-    // Add 1 to the index to skip the length header
-    // List layout: [length: i64][element_0: i64]...[element_n: i64]
-    let adjusted_index = compiler
+    let is_sequence = compiler
         .builder
-        .build_int_add(
-            index_int,
-            compiler.context.i64_type().const_int(1, false),
-            "adjusted_index",
-        )
+        .build_or(is_sequence, is_dict, "is_container")
         .unwrap();
 
-    // Get the element at the adjusted index
-    let pyobject_type = compiler.create_pyobject_type();
-    let elem_ptr = unsafe {
-        compiler
-            .builder
-            .build_in_bounds_gep(pyobject_type, list_ptr, &[adjusted_index], "elem_ptr")
-            .unwrap()
-    };
-
-    // Load and return the element
-    let elem = compiler
+    let current_fn = compiler
         .builder
-        .build_load(pyobject_type, elem_ptr, "elem")
+        .get_insert_block()
         .unwrap()
-        .into_int_value();
-
-    Ok(elem)
-}
-
-/// Compiles a len() expression for strings and lists
-pub fn compile_len<'ctx>(
-    compiler: &mut Compiler<'ctx>,
-    arg: &IRExpr,
-) -> Result<IntValue<'ctx>, CodeGenError> {
-    let arg_obj = compiler.compile_expression(arg)?;
-    let arg_tag = compiler.extract_tag(arg_obj);
-
-    // Check if the argument is a string or list
-    let string_tag_const = compiler
+        .get_parent()
+        .unwrap();
+    let string_block = compiler
         .context
-        .i64_type()
-        .const_int(TYPE_TAG_STRING as u64, false);
-    let list_tag_const = compiler
+        .append_basic_block(current_fn, "str_string");
+    let check_int_block = compiler
         .context
-        .i64_type()
-        .const_int(TYPE_TAG_LIST as u64, false);
+        .append_basic_block(current_fn, "str_check_int");
+    let int_block = compiler.context.append_basic_block(current_fn, "str_int");
+    let check_float_block = compiler
+        .context
+        .append_basic_block(current_fn, "str_check_float");
+    let float_block = compiler.context.append_basic_block(current_fn, "str_float");
+    let check_sequence_block = compiler
+        .context
+        .append_basic_block(current_fn, "str_check_sequence");
+    let sequence_block = compiler
+        .context
+        .append_basic_block(current_fn, "str_sequence");
+    let check_func_block = compiler
+        .context
+        .append_basic_block(current_fn, "str_check_func");
+    let func_block = compiler.context.append_basic_block(current_fn, "str_func");
+    let bool_block = compiler.context.append_basic_block(current_fn, "str_bool");
+    let merge_block = compiler.context.append_basic_block(current_fn, "str_merge");
 
-    let is_string = compiler
-        .builder
-        .build_int_compare(
-            inkwell::IntPredicate::EQ,
-            arg_tag,
-            string_tag_const,
-            "is_string",
+    // Numbers share one buffer in the entry block, as do list texts
+    let entry_builder = compiler.context.create_builder();
+    let entry = current_fn.get_first_basic_block().unwrap();
+    match entry.get_first_instruction() {
+        Some(first_instr) => entry_builder.position_before(&first_instr),
+        None => entry_builder.position_at_end(entry),
+    }
+    let buffer = entry_builder
+        .build_alloca(
+            compiler.context.i8_type().array_type(FLOAT_REPR_SIZE),
+            "str_buffer",
         )
         .unwrap();
-    let is_list = compiler
-        .builder
-        .build_int_compare(
-            inkwell::IntPredicate::EQ,
-            arg_tag,
-            list_tag_const,
-            "is_list",
-        )
+    let text_buffer_type = compiler.text_buffer_type();
+    let text_buffer = entry_builder
+        .build_alloca(text_buffer_type, "str_list_text")
         .unwrap();
 
-    // Get current function for creating basic blocks
-    let current_fn = compiler
+    compiler
         .builder
-        .get_insert_block()
-        .unwrap()
-        .get_parent()
+        .build_conditional_branch(is_string, string_block, check_int_block)
         .unwrap();
 
-    let string_len_block = compiler
-        .context
-        .append_basic_block(current_fn, "string_len");
-    let list_len_block = compiler.context.append_basic_block(current_fn, "list_len");
-    let other_len_block = compiler.context.append_basic_block(current_fn, "other_len");
-    let merge_block = compiler.context.append_basic_block(current_fn, "len_merge");
-
-    // Branch: is_string ? string_len : check_list
-    let check_list_block = compiler
-        .context
-        .append_basic_block(current_fn, "check_list");
+    compiler.builder.position_at_end(string_block);
+    let string_ptr = compiler.extract_string_ptr(value);
     compiler
         .builder
-        .build_conditional_branch(is_string, string_len_block, check_list_block)
+        .build_unconditional_branch(merge_block)
         .unwrap();
 
-    // Check if it's a list
-    compiler.builder.position_at_end(check_list_block);
+    compiler.builder.position_at_end(check_int_block);
     compiler
         .builder
-        .build_conditional_branch(is_list, list_len_block, other_len_block)
+        .build_conditional_branch(is_int, int_block, check_float_block)
         .unwrap();
 
-    // String length block
-    compiler.builder.position_at_end(string_len_block);
-    let str_ptr = compiler.extract_string_ptr(arg_obj);
-    let strlen_fn = compiler.runtime.add_strlen(&compiler.module);
-    let len_result = compiler
+    compiler.builder.position_at_end(int_block);
+    let snprintf = compiler.runtime.add_snprintf(&compiler.module);
+    let int_format = compiler
+        .format_strings
+        .get_int_format_string_no_newline(&compiler.builder);
+    let payload = compiler.extract_int_payload(value);
+    compiler
         .builder
-        .build_call(strlen_fn, &[str_ptr.into()], "strlen")
+        .build_call(
+            snprintf,
+            &[
+                buffer.into(),
+                compiler
+                    .context
+                    .i64_type()
+                    .const_int(FLOAT_REPR_SIZE as u64, false)
+                    .into(),
+                int_format.into(),
+                payload.into(),
+            ],
+            "",
+        )
         .unwrap();
-    let len_int = match len_result.try_as_basic_value() {
-        inkwell::values::ValueKind::Basic(value) => value.into_int_value(),
-        _ => {
-            return Err(CodeGenError::UndefinedVariable(
-                "strlen did not return a value".to_string(),
-            ))
-        }
-    };
-    let string_len_result = compiler.create_pyobject_int(len_int);
     compiler
         .builder
         .build_unconditional_branch(merge_block)
         .unwrap();
 
-    // List length block
-    compiler.builder.position_at_end(list_len_block);
-    let (_list_ptr, list_len) = compiler.extract_list_ptr_and_len(arg_obj);
-    let list_len_result = compiler.create_pyobject_int(list_len);
+    compiler.builder.position_at_end(check_float_block);
     compiler
         .builder
-        .build_unconditional_branch(merge_block)
+        .build_conditional_branch(is_float, float_block, check_sequence_block)
         .unwrap();
 
-    // Other types - return 0 for now
-    compiler.builder.position_at_end(other_len_block);
-    let zero_int = compiler.context.i64_type().const_int(0, false);
-    let other_len_result = compiler.create_pyobject_int(zero_int);
+    compiler.builder.position_at_end(float_block);
+    let format_float = compiler.runtime.add_format_float(&compiler.module);
+    let payload = compiler.extract_payload(value);
+    compiler
+        .builder
+        .build_call(format_float, &[payload.into(), buffer.into()], "")
+        .unwrap();
     compiler
         .builder
         .build_unconditional_branch(merge_block)
         .unwrap();
 
-    // Merge block
-    compiler.builder.position_at_end(merge_block);
-    let pyobject_type = compiler.create_pyobject_type();
-    let phi = compiler
+    compiler.builder.position_at_end(check_sequence_block);
+    compiler
         .builder
-        .build_phi(pyobject_type, "len_result")
+        .build_conditional_branch(is_sequence, sequence_block, check_func_block)
         .unwrap();
-    phi.add_incoming(&[
-        (&string_len_result, string_len_block),
-        (&list_len_result, list_len_block),
-        (&other_len_result, other_len_block),
-    ]);
-    Ok(phi.as_basic_value().into_int_value())
-}
-
-// ============================================================================
-// Input/Output Operations
-// ============================================================================
 
-/// Compiles an input() expression for reading user input
-pub fn compile_input<'ctx>(compiler: &mut Compiler<'ctx>) -> Result<IntValue<'ctx>, CodeGenError> {
-    let scanf = compiler.runtime.add_scanf(&compiler.module);
-    let format_string = compiler
-        .format_strings
-        .get_scanf_float_format_string(&compiler.builder);
-
-    // Allocate space for the input value
-    let input_alloca = compiler
+    compiler.builder.position_at_end(sequence_block);
+    compiler
+        .builder
+        .build_store(text_buffer, text_buffer_type.const_zero())
+        .unwrap();
+    let format_list = compiler.get_or_build_format_list();
+    compiler
+        .builder
+        .build_call(format_list, &[value.into(), text_buffer.into()], "")
+        .unwrap();
+    let data_ptr = compiler
+        .builder
+        .build_struct_gep(text_buffer_type, text_buffer, 0, "list_text_data_ptr")
+        .unwrap();
+    let list_text = compiler
+        .builder
+        .build_load(
+            compiler.context.ptr_type(inkwell::AddressSpace::default()),
+            data_ptr,
+            "list_text",
+        )
+        .unwrap()
+        .into_pointer_value();
+    compiler
         .builder
-        .build_alloca(compiler.context.f64_type(), "input_tmp")
+        .build_unconditional_branch(merge_block)
         .unwrap();
 
-    // Call scanf
+    compiler.builder.position_at_end(check_func_block);
     compiler
         .builder
-        .build_call(
-            scanf,
-            &[format_string.into(), input_alloca.into()],
-            "scanf_call",
+        .build_conditional_branch(is_func, func_block, bool_block)
+        .unwrap();
+
+    // Function block: the descriptor's `<function name>` text
+    compiler.builder.position_at_end(func_block);
+    let descriptor = compiler.extract_string_ptr(value);
+    let func_text_ptr = compiler
+        .builder
+        .build_struct_gep(
+            compiler.function_descriptor_type(),
+            descriptor,
+            2,
+            "func_text_ptr",
+        )
+        .unwrap();
+    let func_text = compiler
+        .builder
+        .build_load(
+            compiler.context.ptr_type(inkwell::AddressSpace::default()),
+            func_text_ptr,
+            "func_text",
         )
+        .unwrap()
+        .into_pointer_value();
+    compiler
+        .builder
+        .build_unconditional_branch(merge_block)
         .unwrap();
 
-    // Load the value from the alloca
-    let value = compiler
+    // Bool block: "True" / "False", "None", or "<file>"
+    compiler.builder.position_at_end(bool_block);
+    let truthy = compiler.pyobject_to_bool(value);
+    let true_str = compiler
         .builder
-        .build_load(compiler.context.f64_type(), input_alloca, "input_value")
+        .build_global_string_ptr("True", "true_str")
         .unwrap()
-        .into_float_value();
+        .as_pointer_value();
+    let false_str = compiler
+        .builder
+        .build_global_string_ptr("False", "false_str")
+        .unwrap()
+        .as_pointer_value();
+    let none_str = compiler
+        .builder
+        .build_global_string_ptr("None", "none_str")
+        .unwrap()
+        .as_pointer_value();
+    let file_str = compiler
+        .builder
+        .build_global_string_ptr("<file>", "file_str")
+        .unwrap()
+        .as_pointer_value();
+    let bool_str = compiler
+        .builder
+        .build_select(truthy, true_str, false_str, "bool_str")
+        .unwrap();
+    let bool_str = compiler
+        .builder
+        .build_select(is_none, none_str, bool_str.into_pointer_value(), "bool_str")
+        .unwrap();
+    let bool_str = compiler
+        .builder
+        .build_select(is_file, file_str, bool_str.into_pointer_value(), "bool_str")
+        .unwrap()
+        .into_pointer_value();
+    compiler
+        .builder
+        .build_unconditional_branch(merge_block)
+        .unwrap();
 
-    // Wrap in PyObject (as float since input() reads floats)
-    Ok(compiler.create_pyobject_float(value))
+    compiler.builder.position_at_end(merge_block);
+    let phi = compiler
+        .builder
+        .build_phi(
+            compiler.context.ptr_type(inkwell::AddressSpace::default()),
+            "str_text",
+        )
+        .unwrap();
+    phi.add_incoming(&[
+        (&string_ptr, string_block),
+        (&buffer, int_block),
+        (&buffer, float_block),
+        (&list_text, sequence_block),
+        (&func_text, func_block),
+        (&bool_str, bool_block),
+    ]);
+    Ok(phi.as_basic_value().into_pointer_value())
 }
 
 // ============================================================================
-// Function Call Operations
+// Comparison Operations
 // ============================================================================
 
-/// Compiles a function call expression func(arg1, arg2, ...)
-pub fn compile_call<'ctx>(
+/// Compiles a comparison expression (==, !=, <, >, <=, >=)
+pub fn compile_comparison<'ctx>(
     compiler: &mut Compiler<'ctx>,
-    func: &str,
-    args: &[IRExpr],
+    op: &CmpOp,
+    left: &IRExpr,
+    right: &IRExpr,
 ) -> Result<IntValue<'ctx>, CodeGenError> {
-    // Clone the function value to avoid borrow checker issues
-    let function = *compiler
-        .functions
-        .get(func)
-        .ok_or_else(|| CodeGenError::UndefinedVariable(format!("function '{}'", func)))?;
+    if matches!(op, CmpOp::Is | CmpOp::IsNot) {
+        return compile_identity(compiler, op, left, right);
+    }
+    if matches!(op, CmpOp::In | CmpOp::NotIn) {
+        return compile_membership(compiler, op, left, right);
+    }
 
-    // Get defaults for this function
-    let defaults = compiler
-        .function_defaults
-        .get(func)
-        .cloned()
-        .unwrap_or_default();
-    let num_provided_args = args.len();
+    let lhs_obj = compiler.compile_expression(left)?;
+    let rhs_obj = compiler.compile_expression(right)?;
 
-    // Compile provided arguments
-    let mut compiled_args = Vec::new();
-    for arg in args.iter() {
-        let arg_pyobj = compiler.compile_expression(arg)?;
-        compiled_args.push(arg_pyobj.into());
-    }
+    // Extract payloads (values are already stored as f64)
+    let lhs_payload = compiler.extract_payload(lhs_obj);
+    let rhs_payload = compiler.extract_payload(rhs_obj);
 
-    // Add default arguments for missing parameters
-    if num_provided_args < defaults.len() {
-        for (i, default_opt) in defaults.iter().enumerate().skip(num_provided_args) {
-            if let Some(default_expr) = default_opt {
-                let default_pyobj = compiler.compile_expression(default_expr)?;
-                compiled_args.push(default_pyobj.into());
-            } else {
-                return Err(CodeGenError::UndefinedVariable(format!(
-                    "Missing required argument {} for function '{}'",
-                    i, func
-                )));
-            }
-        }
-    }
+    // Perform the comparison
+    let predicate = match op {
+        CmpOp::Eq => FloatPredicate::OEQ,    // Ordered and equal
+        CmpOp::NotEq => FloatPredicate::UNE, // Unordered or not equal (NaN != NaN)
+        CmpOp::Lt => FloatPredicate::OLT,    // Ordered and less than
+        CmpOp::Gt => FloatPredicate::OGT,    // Ordered and greater than
+        CmpOp::LtE => FloatPredicate::OLE,   // Ordered and less than or equal
+        CmpOp::GtE => FloatPredicate::OGE,   // Ordered and greater than or equal
+        CmpOp::Is | CmpOp::IsNot | CmpOp::In | CmpOp::NotIn => unreachable!(),
+    };
 
-    let call_result = compiler
+    let float_cmp = compiler
         .builder
-        .build_call(function, &compiled_args, "calltmp")
+        .build_float_compare(predicate, lhs_payload, rhs_payload, "cmptmp")
         .unwrap();
 
-    // Extract the return value from the call (should be a PyObject)
-    use inkwell::values::ValueKind;
-    match call_result.try_as_basic_value() {
-        ValueKind::Basic(value) => Ok(value.into_int_value()),
-        ValueKind::Instruction(_) => Err(CodeGenError::UndefinedVariable(
-            "Function call did not return a value".to_string(),
-        )),
-    }
-}
+    // When both operands are ints, compare the exact 48-bit payloads instead of
+    // their f64 conversions
+    let int_predicate = match op {
+        CmpOp::Eq => IntPredicate::EQ,
+        CmpOp::NotEq => IntPredicate::NE,
+        CmpOp::Lt => IntPredicate::SLT,
+        CmpOp::Gt => IntPredicate::SGT,
+        CmpOp::LtE => IntPredicate::SLE,
+        CmpOp::GtE => IntPredicate::SGE,
+        CmpOp::Is | CmpOp::IsNot | CmpOp::In | CmpOp::NotIn => unreachable!(),
+    };
+    let lhs_int = compiler.extract_int_payload(lhs_obj);
+    let rhs_int = compiler.extract_int_payload(rhs_obj);
+    let int_cmp = compiler
+        .builder
+        .build_int_compare(int_predicate, lhs_int, rhs_int, "int_cmptmp")
+        .unwrap();
 
-// ============================================================================
-// Binary Operations
-// ============================================================================
+    let int_tag = compiler
+        .context
+        .i64_type()
+        .const_int(TYPE_TAG_INT as u64, false);
+    let lhs_tag = compiler.extract_tag(lhs_obj);
+    let rhs_tag = compiler.extract_tag(rhs_obj);
+    let lhs_is_int = compiler
+        .builder
+        .build_int_compare(IntPredicate::EQ, lhs_tag, int_tag, "lhs_is_int")
+        .unwrap();
+    let rhs_is_int = compiler
+        .builder
+        .build_int_compare(IntPredicate::EQ, rhs_tag, int_tag, "rhs_is_int")
+        .unwrap();
+    let both_int = compiler
+        .builder
+        .build_and(lhs_is_int, rhs_is_int, "both_int")
+        .unwrap();
 
-/// Compiles a binary operation expression (arithmetic, bitwise, string concatenation)
-pub fn compile_binary_op<'ctx>(
+    let cmp_result = compiler
+        .builder
+        .build_select(both_int, int_cmp, float_cmp, "cmp_result")
+        .unwrap()
+        .into_int_value();
+
+    // Numbers (int, float, bool) compare with each other; any other pairing is
+    // only comparable when both operands share the same tag
+    let bool_tag = compiler
+        .context
+        .i64_type()
+        .const_int(TYPE_TAG_BOOL as u64, false);
+    let lhs_is_number = compiler
+        .builder
+        .build_int_compare(IntPredicate::ULE, lhs_tag, bool_tag, "lhs_is_number")
+        .unwrap();
+    let rhs_is_number = compiler
+        .builder
+        .build_int_compare(IntPredicate::ULE, rhs_tag, bool_tag, "rhs_is_number")
+        .unwrap();
+    let number_mismatch = compiler
+        .builder
+        .build_xor(lhs_is_number, rhs_is_number, "number_mismatch")
+        .unwrap();
+    let tags_differ = compiler
+        .builder
+        .build_int_compare(IntPredicate::NE, lhs_tag, rhs_tag, "tags_differ")
+        .unwrap();
+    let lhs_not_number = compiler
+        .builder
+        .build_not(lhs_is_number, "lhs_not_number")
+        .unwrap();
+    let other_mismatch = compiler
+        .builder
+        .build_and(lhs_not_number, tags_differ, "other_mismatch")
+        .unwrap();
+    let incompatible = compiler
+        .builder
+        .build_or(number_mismatch, other_mismatch, "incompatible")
+        .unwrap();
+
+    let cmp_result = match op {
+        // Values of incompatible types are never equal
+        CmpOp::Eq => {
+            let compatible = compiler
+                .builder
+                .build_not(incompatible, "compatible")
+                .unwrap();
+            compiler
+                .builder
+                .build_and(compatible, cmp_result, "eq_result")
+                .unwrap()
+        }
+        CmpOp::NotEq => compiler
+            .builder
+            .build_or(incompatible, cmp_result, "ne_result")
+            .unwrap(),
+        CmpOp::Lt | CmpOp::Gt | CmpOp::LtE | CmpOp::GtE => {
+            let symbol = match op {
+                CmpOp::Lt => "<",
+                CmpOp::Gt => ">",
+                CmpOp::LtE => "<=",
+                _ => ">=",
+            };
+            compiler.build_raise_if(
+                incompatible,
+                &format!(
+                    "TypeError: '{}' not supported between instances of different types",
+                    symbol
+                ),
+            );
+            cmp_result
+        }
+        CmpOp::Is | CmpOp::IsNot | CmpOp::In | CmpOp::NotIn => unreachable!(),
+    };
+    let cmp_result = build_sequence_comparison(compiler, op, lhs_obj, rhs_obj, cmp_result)?;
+
+    // Return as PyObject with bool tag
+    Ok(compiler.create_pyobject_bool(cmp_result))
+}
+
+/// Replaces `scalar_result` with a comparison by content when both operands are
+/// strings, both lists or both tuples, as `compare_values` orders them: strings
+/// character by character, sequences by the first pair of elements that differ, then
+/// by length
+fn build_sequence_comparison<'ctx>(
     compiler: &mut Compiler<'ctx>,
-    op: &BinOp,
-    left: &IRExpr,
-    right: &IRExpr,
+    op: &CmpOp,
+    lhs_obj: IntValue<'ctx>,
+    rhs_obj: IntValue<'ctx>,
+    scalar_result: IntValue<'ctx>,
 ) -> Result<IntValue<'ctx>, CodeGenError> {
-    let lhs_obj = compiler.compile_expression(left)?;
-    let rhs_obj = compiler.compile_expression(right)?;
-
-    // Extract tags to check types
+    let i64_type = compiler.context.i64_type();
     let lhs_tag = compiler.extract_tag(lhs_obj);
     let rhs_tag = compiler.extract_tag(rhs_obj);
-    let string_tag_const = compiler
+    let tags_match = compiler
+        .builder
+        .build_int_compare(IntPredicate::EQ, lhs_tag, rhs_tag, "tags_match")
+        .unwrap();
+    let lhs_is_string = build_tag_check(compiler, lhs_obj, TYPE_TAG_STRING, "lhs_is_string");
+    let lhs_is_list = build_tag_check(compiler, lhs_obj, TYPE_TAG_LIST, "lhs_is_list");
+    let lhs_is_tuple = build_tag_check(compiler, lhs_obj, TYPE_TAG_TUPLE, "lhs_is_tuple");
+    let lhs_is_sequence = compiler
+        .builder
+        .build_or(lhs_is_string, lhs_is_list, "lhs_is_text_or_list")
+        .unwrap();
+    let lhs_is_sequence = compiler
+        .builder
+        .build_or(lhs_is_sequence, lhs_is_tuple, "lhs_is_sequence")
+        .unwrap();
+    let both_sequences = compiler
+        .builder
+        .build_and(tags_match, lhs_is_sequence, "both_sequences")
+        .unwrap();
+
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let scalar_block = compiler.builder.get_insert_block().unwrap();
+    let sequence_block = compiler
         .context
-        .i64_type()
-        .const_int(TYPE_TAG_STRING as u64, false);
+        .append_basic_block(current_fn, "sequence_compare");
+    let merge_block = compiler
+        .context
+        .append_basic_block(current_fn, "sequence_compare_done");
+    compiler
+        .builder
+        .build_conditional_branch(both_sequences, sequence_block, merge_block)
+        .unwrap();
 
-    // Handle string concatenation for Add operator
-    if matches!(op, BinOp::Add) {
-        let lhs_is_string = compiler
+    compiler.builder.position_at_end(sequence_block);
+    let compare_values = compiler.get_or_build_compare_values();
+    let order = build_libc_call(
+        compiler,
+        compare_values,
+        &[lhs_obj.into(), rhs_obj.into()],
+        "sequence_order",
+    )?
+    .into_int_value();
+    let unordered = i64_type.const_int(2, false);
+    let sequence_result = match op {
+        CmpOp::Eq => compiler
             .builder
             .build_int_compare(
-                inkwell::IntPredicate::EQ,
-                lhs_tag,
-                string_tag_const,
-                "lhs_is_string",
+                IntPredicate::EQ,
+                order,
+                i64_type.const_zero(),
+                "sequence_eq",
             )
-            .unwrap();
-        let rhs_is_string = compiler
+            .unwrap(),
+        CmpOp::NotEq => compiler
             .builder
             .build_int_compare(
-                inkwell::IntPredicate::EQ,
-                rhs_tag,
-                string_tag_const,
-                "rhs_is_string",
+                IntPredicate::NE,
+                order,
+                i64_type.const_zero(),
+                "sequence_ne",
             )
-            .unwrap();
-        let both_strings = compiler
-            .builder
-            .build_and(lhs_is_string, rhs_is_string, "both_strings")
-            .unwrap();
-
-        // Get current function for creating basic blocks
-        let current_fn = compiler
-            .builder
-            .get_insert_block()
-            .unwrap()
-            .get_parent()
-            .unwrap();
+            .unwrap(),
+        CmpOp::Lt | CmpOp::Gt | CmpOp::LtE | CmpOp::GtE => {
+            let (symbol, predicate) = match op {
+                CmpOp::Lt => ("<", IntPredicate::SLT),
+                CmpOp::Gt => (">", IntPredicate::SGT),
+                CmpOp::LtE => ("<=", IntPredicate::SLE),
+                _ => (">=", IntPredicate::SGE),
+            };
+            let is_unordered = compiler
+                .builder
+                .build_int_compare(IntPredicate::EQ, order, unordered, "unordered")
+                .unwrap();
+            compiler.build_raise_if(
+                is_unordered,
+                &format!(
+                    "TypeError: '{}' not supported between instances of different types",
+                    symbol
+                ),
+            );
+            compiler
+                .builder
+                .build_int_compare(
+                    predicate,
+                    order,
+                    i64_type.const_zero(),
+                    "sequence_order_cmp",
+                )
+                .unwrap()
+        }
+        CmpOp::Is | CmpOp::IsNot | CmpOp::In | CmpOp::NotIn => unreachable!(),
+    };
+    let sequence_end_block = compiler.builder.get_insert_block().unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(merge_block)
+        .unwrap();
 
-        let concat_block = compiler
-            .context
-            .append_basic_block(current_fn, "str_concat");
-        let arithmetic_block = compiler
-            .context
-            .append_basic_block(current_fn, "arithmetic");
-        let merge_block = compiler.context.append_basic_block(current_fn, "add_merge");
+    compiler.builder.position_at_end(merge_block);
+    let result = compiler
+        .builder
+        .build_phi(compiler.context.bool_type(), "sequence_cmp_result")
+        .unwrap();
+    result.add_incoming(&[
+        (&scalar_result, scalar_block),
+        (&sequence_result, sequence_end_block),
+    ]);
+    Ok(result.as_basic_value().into_int_value())
+}
 
-        let pyobject_type = compiler.create_pyobject_type();
+/// Compiles `is` / `is not`. Against a None literal this is a tag check; otherwise
+/// values have no identity of their own, so the NaN-boxed bits are compared
+fn compile_identity<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    op: &CmpOp,
+    left: &IRExpr,
+    right: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let lhs_obj = compiler.compile_expression(left)?;
+    let rhs_obj = compiler.compile_expression(right)?;
 
-        // Branch based on whether both are strings
-        compiler
+    let none_operand = match (left, right) {
+        (_, IRExpr::NoneLiteral) => Some(lhs_obj),
+        (IRExpr::NoneLiteral, _) => Some(rhs_obj),
+        _ => None,
+    };
+    let same = match none_operand {
+        Some(value) => {
+            let tag = compiler.extract_tag(value);
+            let none_tag = compiler
+                .context
+                .i64_type()
+                .const_int(TYPE_TAG_NONE as u64, false);
+            compiler
+                .builder
+                .build_int_compare(IntPredicate::EQ, tag, none_tag, "is_none")
+                .unwrap()
+        }
+        None => compiler
             .builder
-            .build_conditional_branch(both_strings, concat_block, arithmetic_block)
-            .unwrap();
+            .build_int_compare(IntPredicate::EQ, lhs_obj, rhs_obj, "is_same")
+            .unwrap(),
+    };
 
-        // String concatenation block
-        compiler.builder.position_at_end(concat_block);
-        let lhs_str_ptr = compiler.extract_string_ptr(lhs_obj);
-        let rhs_str_ptr = compiler.extract_string_ptr(rhs_obj);
+    let result = match op {
+        CmpOp::IsNot => compiler.builder.build_not(same, "is_not").unwrap(),
+        _ => same,
+    };
+    Ok(compiler.create_pyobject_bool(result))
+}
 
-        // Get lengths of both strings using strlen
-        let strlen_fn = compiler.runtime.add_strlen(&compiler.module);
-        let lhs_len_result = compiler
-            .builder
-            .build_call(strlen_fn, &[lhs_str_ptr.into()], "lhs_len")
-            .unwrap();
-        let lhs_len = match lhs_len_result.try_as_basic_value() {
-            inkwell::values::ValueKind::Basic(value) => value.into_int_value(),
-            _ => {
-                return Err(CodeGenError::UndefinedVariable(
-                    "strlen did not return a value".to_string(),
-                ))
-            }
-        };
-        let rhs_len_result = compiler
-            .builder
-            .build_call(strlen_fn, &[rhs_str_ptr.into()], "rhs_len")
-            .unwrap();
-        let rhs_len = match rhs_len_result.try_as_basic_value() {
-            inkwell::values::ValueKind::Basic(value) => value.into_int_value(),
-            _ => {
-                return Err(CodeGenError::UndefinedVariable(
-                    "strlen did not return a value".to_string(),
-                ))
-            }
-        };
+/// Compiles `in` / `not in` through the `find_item` helper, after checking that the
+/// right operand is a container and that only a string is looked up in a string
+fn compile_membership<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    op: &CmpOp,
+    left: &IRExpr,
+    right: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let item_obj = compiler.compile_expression(left)?;
+    let container_obj = compiler.compile_expression(right)?;
 
-        // Calculate total size (lhs_len + rhs_len + 1 for null terminator)
-        let total_len = compiler
-            .builder
-            .build_int_add(lhs_len, rhs_len, "total_len")
-            .unwrap();
-        let total_size = compiler
+    let i64_type = compiler.context.i64_type();
+    let container_tag = compiler.extract_tag(container_obj);
+    let has_tag = |compiler: &Compiler<'ctx>, tag: IntValue<'ctx>, expected: u8, name: &str| {
+        compiler
             .builder
-            .build_int_add(
-                total_len,
-                compiler.context.i64_type().const_int(1, false),
-                "total_size",
+            .build_int_compare(
+                IntPredicate::EQ,
+                tag,
+                i64_type.const_int(expected as u64, false),
+                name,
             )
-            .unwrap();
+            .unwrap()
+    };
+    let is_string = has_tag(compiler, container_tag, TYPE_TAG_STRING, "is_string");
+    let is_list = has_tag(compiler, container_tag, TYPE_TAG_LIST, "is_list");
+    let is_tuple = has_tag(compiler, container_tag, TYPE_TAG_TUPLE, "is_tuple");
+    let is_dict = has_tag(compiler, container_tag, TYPE_TAG_DICT, "is_dict");
+    let is_set = has_tag(compiler, container_tag, TYPE_TAG_SET, "is_set");
+    let is_sequence = compiler
+        .builder
+        .build_or(is_list, is_tuple, "is_sequence")
+        .unwrap();
+    let is_keyed = compiler
+        .builder
+        .build_or(is_dict, is_set, "is_keyed")
+        .unwrap();
+    let is_collection = compiler
+        .builder
+        .build_or(is_sequence, is_keyed, "is_collection")
+        .unwrap();
+    let is_container = compiler
+        .builder
+        .build_or(is_collection, is_string, "is_container")
+        .unwrap();
+    let not_container = compiler
+        .builder
+        .build_not(is_container, "not_container")
+        .unwrap();
+    compiler.build_raise_if(not_container, "TypeError: argument is not iterable");
 
-        // Allocate memory for concatenated string
-        let malloc_fn = compiler.runtime.add_malloc(&compiler.module);
-        let concat_ptr_result = compiler
-            .builder
-            .build_call(malloc_fn, &[total_size.into()], "malloc_concat")
-            .unwrap();
-        let concat_ptr = match concat_ptr_result.try_as_basic_value() {
-            inkwell::values::ValueKind::Basic(value) => value.into_pointer_value(),
-            _ => {
-                return Err(CodeGenError::UndefinedVariable(
-                    "malloc did not return a value".to_string(),
-                ))
-            }
-        };
+    let item_tag = compiler.extract_tag(item_obj);
+    let item_is_string = has_tag(compiler, item_tag, TYPE_TAG_STRING, "item_is_string");
+    let item_not_string = compiler
+        .builder
+        .build_not(item_is_string, "item_not_string")
+        .unwrap();
+    let bad_substring = compiler
+        .builder
+        .build_and(is_string, item_not_string, "bad_substring")
+        .unwrap();
+    compiler.build_raise_if(
+        bad_substring,
+        "TypeError: 'in <string>' requires string as left operand",
+    );
 
-        // Copy first string
-        let memcpy_fn = compiler.runtime.add_memcpy(&compiler.module);
-        compiler
-            .builder
-            .build_call(
-                memcpy_fn,
-                &[concat_ptr.into(), lhs_str_ptr.into(), lhs_len.into()],
-                "memcpy_lhs",
-            )
-            .unwrap();
+    let find_item = compiler.get_or_build_find_item();
+    let position = compiler
+        .builder
+        .build_call(
+            find_item,
+            &[item_obj.into(), container_obj.into()],
+            "position",
+        )
+        .unwrap()
+        .try_as_basic_value()
+        .basic()
+        .unwrap()
+        .into_int_value();
+    let found = compiler
+        .builder
+        .build_int_compare(IntPredicate::SGE, position, i64_type.const_zero(), "found")
+        .unwrap();
+    let result = match op {
+        CmpOp::NotIn => compiler.builder.build_not(found, "not_in").unwrap(),
+        _ => found,
+    };
+    Ok(compiler.create_pyobject_bool(result))
+}
 
-        // Copy second string (offset by lhs_len)
-        let rhs_dest = unsafe {
-            compiler
+// ============================================================================
+// Unary Operations
+// ============================================================================
+
+/// Compiles a unary operation expression (-, +, ~, not)
+pub fn compile_unary_op<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    op: &UnaryOp,
+    operand: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let operand_obj = compiler.compile_expression(operand)?;
+
+    match op {
+        UnaryOp::Invert => {
+            // Bitwise NOT (~x)
+            let payload = compiler.extract_payload(operand_obj);
+            let operand_int = compiler
                 .builder
-                .build_gep(
-                    compiler.context.i8_type(),
-                    concat_ptr,
-                    &[lhs_len],
-                    "rhs_dest",
-                )
-                .unwrap()
-        };
-        // Copy rhs_len + 1 to include null terminator
-        let rhs_copy_len = compiler
-            .builder
-            .build_int_add(
-                rhs_len,
-                compiler.context.i64_type().const_int(1, false),
-                "rhs_copy_len",
-            )
-            .unwrap();
-        compiler
-            .builder
-            .build_call(
-                memcpy_fn,
-                &[rhs_dest.into(), rhs_str_ptr.into(), rhs_copy_len.into()],
-                "memcpy_rhs",
-            )
-            .unwrap();
+                .build_float_to_signed_int(payload, compiler.context.i64_type(), "to_int")
+                .unwrap();
+            let result = compiler.builder.build_not(operand_int, "not").unwrap();
+            Ok(compiler.create_pyobject_int(result))
+        }
+        UnaryOp::USub => {
+            // Unary minus (-x); a negation rather than 0 - x so that -0.0 keeps its sign
+            let payload = compiler.extract_payload(operand_obj);
+            let result = compiler.builder.build_float_neg(payload, "neg").unwrap();
 
-        // Track the allocated string in the arena only if in main entry block
-        if let Some(main_entry) = compiler.main_entry_block {
-            if compiler.builder.get_insert_block() == Some(main_entry) {
-                compiler.string_arena.push(concat_ptr);
-            }
+            // Preserve the type tag from the operand
+            let tag = compiler.extract_tag(operand_obj);
+            let result_obj = compiler.create_pyobject_from_tag_and_payload(tag, result);
+
+            Ok(result_obj)
+        }
+        UnaryOp::UAdd => {
+            // Unary plus (+x) - just return the operand unchanged
+            Ok(operand_obj)
+        }
+        UnaryOp::Not => {
+            // Logical NOT (not x): negate the operand's truthiness
+            let truthy = compiler.pyobject_to_bool(operand_obj);
+            let is_falsy = compiler.builder.build_not(truthy, "not").unwrap();
+            Ok(compiler.create_pyobject_bool(is_falsy))
         }
+    }
+}
 
-        // Create PyObject for concatenated string
-        let concat_result = compiler.create_pyobject_string(concat_ptr);
-        compiler
-            .builder
-            .build_unconditional_branch(merge_block)
-            .unwrap();
+// ============================================================================
+// Conditional Expressions
+// ============================================================================
 
-        // Arithmetic block (for non-string addition)
-        compiler.builder.position_at_end(arithmetic_block);
-        let lhs_payload = compiler.extract_payload(lhs_obj);
-        let rhs_payload = compiler.extract_payload(rhs_obj);
+/// Compiles a conditional expression `a if cond else b`
+///
+/// Only the selected arm is evaluated. Each arm may itself contain control flow
+/// (e.g. a nested conditional), so the phi takes its incoming edges from the block
+/// each arm *ends* in rather than the block it started in.
+///
+/// When both arms are scalar literals or variables, evaluating the unselected one is
+/// harmless, so both are computed and a `select` picks the result without branching.
+pub fn compile_if_exp<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    condition: &IRExpr,
+    then_value: &IRExpr,
+    else_value: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let cond_bool = compile_condition(compiler, condition)?;
 
-        // Check if either operand is a float (tag == TYPE_TAG_FLOAT)
-        let float_tag_const = compiler
-            .context
-            .i64_type()
-            .const_int(TYPE_TAG_FLOAT as u64, false);
-        let lhs_is_float = compiler
+    let is_scalar = |expr: &IRExpr| {
+        matches!(
+            expr,
+            IRExpr::Constant(_)
+                | IRExpr::Float(_)
+                | IRExpr::Bool(_)
+                | IRExpr::NoneLiteral
+                | IRExpr::Variable(_)
+        )
+    };
+    if is_scalar(then_value) && is_scalar(else_value) {
+        let then_obj = compiler.compile_expression(then_value)?;
+        let else_obj = compiler.compile_expression(else_value)?;
+        return Ok(compiler
             .builder
-            .build_int_compare(
-                inkwell::IntPredicate::EQ,
-                lhs_tag,
-                float_tag_const,
-                "lhs_is_float",
-            )
-            .unwrap();
-        let rhs_is_float = compiler
-            .builder
-            .build_int_compare(
-                inkwell::IntPredicate::EQ,
-                rhs_tag,
-                float_tag_const,
-                "rhs_is_float",
-            )
-            .unwrap();
+            .build_select(cond_bool, then_obj, else_obj, "ifexp_result")
+            .unwrap()
+            .into_int_value());
+    }
 
-        // If either is float, result should be float
-        let result_is_float = compiler
-            .builder
-            .build_or(lhs_is_float, rhs_is_float, "result_is_float")
-            .unwrap();
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let then_bb = compiler
+        .context
+        .append_basic_block(current_fn, "ifexp_then");
+    let else_bb = compiler
+        .context
+        .append_basic_block(current_fn, "ifexp_else");
+    let merge_bb = compiler
+        .context
+        .append_basic_block(current_fn, "ifexp_merge");
+    compiler
+        .builder
+        .build_conditional_branch(cond_bool, then_bb, else_bb)
+        .unwrap();
 
-        let result_payload = compiler
-            .builder
-            .build_float_add(lhs_payload, rhs_payload, "addtmp")
-            .unwrap();
+    compiler.builder.position_at_end(then_bb);
+    let then_obj = compiler.compile_expression(then_value)?;
+    let then_end = compiler.builder.get_insert_block().unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(merge_bb)
+        .unwrap();
 
-        // Select the result tag based on whether either operand is float
-        let int_tag = compiler
-            .context
-            .i64_type()
-            .const_int(TYPE_TAG_INT as u64, false);
-        let float_tag = compiler
-            .context
-            .i64_type()
-            .const_int(TYPE_TAG_FLOAT as u64, false);
-        let result_tag = compiler
-            .builder
-            .build_select(result_is_float, float_tag, int_tag, "result_tag")
-            .unwrap()
-            .into_int_value();
+    compiler.builder.position_at_end(else_bb);
+    let else_obj = compiler.compile_expression(else_value)?;
+    let else_end = compiler.builder.get_insert_block().unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(merge_bb)
+        .unwrap();
 
-        // Create result PyObject
-        let arithmetic_result =
-            compiler.create_pyobject_from_tag_and_payload(result_tag, result_payload);
+    compiler.builder.position_at_end(merge_bb);
+    let phi = compiler
+        .builder
+        .build_phi(compiler.context.i64_type(), "ifexp_result")
+        .unwrap();
+    phi.add_incoming(&[(&then_obj, then_end), (&else_obj, else_end)]);
+    Ok(phi.as_basic_value().into_int_value())
+}
+
+// ============================================================================
+// Boolean Operations
+// ============================================================================
+
+/// Compiles a short-circuiting `and` / `or` expression
+///
+/// Each operand is only evaluated when the ones before it did not decide the result;
+/// the result is the deciding operand itself (`0 or "x"` is `"x"`), so the phi merges
+/// PyObjects from the block each operand ends in.
+pub fn compile_bool_op<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    op: &BoolOp,
+    values: &[IRExpr],
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let (last, rest) = values
+        .split_last()
+        .expect("and/or has at least two operands");
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let merge_bb = compiler
+        .context
+        .append_basic_block(current_fn, "boolop_merge");
+
+    let mut incoming = Vec::new();
+    for value in rest {
+        let value_obj = compiler.compile_expression(value)?;
+        let truthy = compiler.pyobject_to_bool(value_obj);
+        let next_bb = compiler
+            .context
+            .append_basic_block(current_fn, "boolop_next");
+        // `and` stops at the first falsy operand, `or` at the first truthy one
+        let (truthy_bb, falsy_bb) = match op {
+            BoolOp::And => (next_bb, merge_bb),
+            BoolOp::Or => (merge_bb, next_bb),
+        };
         compiler
             .builder
-            .build_unconditional_branch(merge_block)
+            .build_conditional_branch(truthy, truthy_bb, falsy_bb)
             .unwrap();
+        incoming.push((value_obj, compiler.builder.get_insert_block().unwrap()));
+        compiler.builder.position_at_end(next_bb);
+    }
+    let last_obj = compiler.compile_expression(last)?;
+    incoming.push((last_obj, compiler.builder.get_insert_block().unwrap()));
+    compiler
+        .builder
+        .build_unconditional_branch(merge_bb)
+        .unwrap();
 
-        // Merge block - phi node to select result
-        compiler.builder.position_at_end(merge_block);
-        let phi = compiler
+    compiler.builder.position_at_end(merge_bb);
+    let phi = compiler
+        .builder
+        .build_phi(compiler.context.i64_type(), "boolop_result")
+        .unwrap();
+    for (value, block) in &incoming {
+        phi.add_incoming(&[(value, *block)]);
+    }
+    Ok(phi.as_basic_value().into_int_value())
+}
+
+/// Compiles an expression used as a branch condition straight to an i1
+///
+/// `and` / `or` become a chain of branches merging an i1, with each operand itself
+/// compiled as a condition, so `if a < b and c:` never boxes the intermediate
+/// results into bool PyObjects only to unbox them again. Anything else is compiled
+/// as a value and tested for truthiness.
+pub fn compile_condition<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    condition: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let IRExpr::BoolOp { op, values } = condition else {
+        let cond_obj = compiler.compile_expression(condition)?;
+        return Ok(compiler.pyobject_to_bool(cond_obj));
+    };
+
+    let (last, rest) = values
+        .split_last()
+        .expect("and/or has at least two operands");
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let merge_bb = compiler
+        .context
+        .append_basic_block(current_fn, "cond_merge");
+    // The value an operand short-circuits with: false for `and`, true for `or`
+    let short_circuit = compiler
+        .context
+        .bool_type()
+        .const_int(u64::from(matches!(op, BoolOp::Or)), false);
+
+    let mut incoming = Vec::new();
+    for value in rest {
+        let truthy = compile_condition(compiler, value)?;
+        let next_bb = compiler.context.append_basic_block(current_fn, "cond_next");
+        let (truthy_bb, falsy_bb) = match op {
+            BoolOp::And => (next_bb, merge_bb),
+            BoolOp::Or => (merge_bb, next_bb),
+        };
+        compiler
             .builder
-            .build_phi(pyobject_type, "add_result")
+            .build_conditional_branch(truthy, truthy_bb, falsy_bb)
             .unwrap();
-        phi.add_incoming(&[
-            (&concat_result, concat_block),
-            (&arithmetic_result, arithmetic_block),
-        ]);
-        return Ok(phi.as_basic_value().into_int_value());
+        incoming.push((short_circuit, compiler.builder.get_insert_block().unwrap()));
+        compiler.builder.position_at_end(next_bb);
     }
+    let last_bool = compile_condition(compiler, last)?;
+    incoming.push((last_bool, compiler.builder.get_insert_block().unwrap()));
+    compiler
+        .builder
+        .build_unconditional_branch(merge_bb)
+        .unwrap();
 
-    // Handle bitwise operations separately (they require integer operands)
-    match op {
-        BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::LShift | BinOp::RShift => {
-            // Convert payloads to integers
-            let lhs_payload = compiler.extract_payload(lhs_obj);
-            let rhs_payload = compiler.extract_payload(rhs_obj);
+    compiler.builder.position_at_end(merge_bb);
+    let phi = compiler
+        .builder
+        .build_phi(compiler.context.bool_type(), "cond_result")
+        .unwrap();
+    for (value, block) in &incoming {
+        phi.add_incoming(&[(value, *block)]);
+    }
+    Ok(phi.as_basic_value().into_int_value())
+}
 
-            let lhs_int = compiler
-                .builder
-                .build_float_to_signed_int(lhs_payload, compiler.context.i64_type(), "lhs_to_int")
-                .unwrap();
-            let rhs_int = compiler
-                .builder
-                .build_float_to_signed_int(rhs_payload, compiler.context.i64_type(), "rhs_to_int")
-                .unwrap();
+// ============================================================================
+// List Operations
+// ============================================================================
 
-            // Perform bitwise operation
-            let result_int = match op {
-                BinOp::BitAnd => compiler.builder.build_and(lhs_int, rhs_int, "and").unwrap(),
-                BinOp::BitOr => compiler.builder.build_or(lhs_int, rhs_int, "or").unwrap(),
-                BinOp::BitXor => compiler.builder.build_xor(lhs_int, rhs_int, "xor").unwrap(),
-                BinOp::LShift => compiler
-                    .builder
-                    .build_left_shift(lhs_int, rhs_int, "shl")
-                    .unwrap(),
-                BinOp::RShift => compiler
-                    .builder
-                    .build_right_shift(lhs_int, rhs_int, true, "shr")
-                    .unwrap(),
-                _ => unreachable!(),
-            };
+/// Compiles a list literal expression `[a, b, c]`
+pub fn compile_list<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    elements: &[IRExpr],
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let list_ptr = build_sequence(compiler, elements)?;
 
-            // Convert result back to PyObject (always returns integer type)
-            Ok(compiler.create_pyobject_int(result_int))
-        }
-        // Arithmetic operations (Add, Sub, Mul, Div, Mod)
-        _ => {
-            // Extract tags and payloads
-            let lhs_tag = compiler.extract_tag(lhs_obj);
-            let rhs_tag = compiler.extract_tag(rhs_obj);
-            let lhs_payload = compiler.extract_payload(lhs_obj);
-            let rhs_payload = compiler.extract_payload(rhs_obj);
+    // Create a PyObject with LIST tag and the pointer as payload
+    Ok(compiler.create_pyobject_list(list_ptr, elements.len()))
+}
 
-            // Check if either operand is a float (tag == TYPE_TAG_FLOAT)
-            let float_tag_const = compiler
-                .context
-                .i64_type()
-                .const_int(TYPE_TAG_FLOAT as u64, false);
-            let lhs_is_float = compiler
-                .builder
-                .build_int_compare(
-                    inkwell::IntPredicate::EQ,
-                    lhs_tag,
-                    float_tag_const,
-                    "lhs_is_float",
-                )
-                .unwrap();
-            let rhs_is_float = compiler
-                .builder
-                .build_int_compare(
-                    inkwell::IntPredicate::EQ,
-                    rhs_tag,
-                    float_tag_const,
-                    "rhs_is_float",
-                )
-                .unwrap();
+/// Compiles a tuple literal: same allocation as a list, boxed with the TUPLE tag
+pub fn compile_tuple<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    elements: &[IRExpr],
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let tuple_ptr = build_sequence(compiler, elements)?;
+    Ok(compiler.create_pyobject_tuple(tuple_ptr))
+}
 
-            // If either is float, result should be float
-            let result_is_float = compiler
-                .builder
-                .build_or(lhs_is_float, rhs_is_float, "result_is_float")
-                .unwrap();
+/// Compiles a dict literal `{k: v, ...}`; keys and values are evaluated in order
+pub fn compile_dict<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    entries: &[(IRExpr, IRExpr)],
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let mut compiled_entries = Vec::new();
+    for (key, value) in entries {
+        let key_pyobj = compiler.compile_expression(key)?;
+        let value_pyobj = compiler.compile_expression(value)?;
+        compiled_entries.push((key_pyobj, value_pyobj));
+    }
+    build_dict(compiler, &compiled_entries)
+}
 
-            // Perform the operation on payloads
-            let result_payload = match op {
-                BinOp::Add => compiler
-                    .builder
-                    .build_float_add(lhs_payload, rhs_payload, "addtmp")
-                    .unwrap(),
-                BinOp::Sub => compiler
-                    .builder
-                    .build_float_sub(lhs_payload, rhs_payload, "subtmp")
-                    .unwrap(),
-                BinOp::Mul => compiler
-                    .builder
-                    .build_float_mul(lhs_payload, rhs_payload, "multmp")
-                    .unwrap(),
-                BinOp::Div => compiler
-                    .builder
-                    .build_float_div(lhs_payload, rhs_payload, "divtmp")
-                    .unwrap(),
-                BinOp::Mod => compiler
-                    .builder
-                    .build_float_rem(lhs_payload, rhs_payload, "modtmp")
-                    .unwrap(),
-                _ => unreachable!(),
-            };
+/// Compiles a set literal: an empty set that each element is added to in turn
+/// with `dict_set`, so repeated elements are kept once
+pub fn compile_set<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    elements: &[IRExpr],
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let mut compiled_elements = Vec::new();
+    for element in elements {
+        compiled_elements.push(compiler.compile_expression(element)?);
+    }
+    let header_ptr = build_dict_header(compiler, compiled_elements.len())?;
+    let set_obj = compiler.create_pyobject_set(header_ptr);
+    let dict_set = compiler.get_or_build_dict_set();
+    let none = compiler.create_pyobject_none();
+    for element in compiled_elements {
+        compiler
+            .builder
+            .build_call(dict_set, &[set_obj.into(), element.into(), none.into()], "")
+            .unwrap();
+    }
+    Ok(set_obj)
+}
 
-            // Select the result tag based on whether either operand is float
-            let int_tag = compiler
-                .context
-                .i64_type()
-                .const_int(TYPE_TAG_INT as u64, false);
-            let float_tag = compiler
-                .context
-                .i64_type()
-                .const_int(TYPE_TAG_FLOAT as u64, false);
-            let result_tag = compiler
+/// Boxes a new dict holding already compiled entries, added in turn with
+/// `dict_set` so a repeated key keeps its first position and its last value
+fn build_dict<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    entries: &[(IntValue<'ctx>, IntValue<'ctx>)],
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let header_ptr = build_dict_header(compiler, entries.len())?;
+    let dict_obj = compiler.create_pyobject_dict(header_ptr);
+    let dict_set = compiler.get_or_build_dict_set();
+    for (key, value) in entries {
+        compiler
+            .builder
+            .build_call(
+                dict_set,
+                &[dict_obj.into(), (*key).into(), (*value).into()],
+                "",
+            )
+            .unwrap();
+    }
+    Ok(dict_obj)
+}
+
+/// Allocates the header of an empty dict with room for `capacity` entries:
+/// `[length][capacity][entries][index][mask]`. The entries block holds
+/// `[key_0][value_0]...` in insertion order, and the hash index is a power-of-two
+/// number of slots (`mask + 1`) holding entry numbers, or -1 when free, sized so
+/// `capacity` entries fill at most two thirds of it
+fn build_dict_header<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    capacity: usize,
+) -> Result<PointerValue<'ctx>, CodeGenError> {
+    let i64_type = compiler.context.i64_type();
+    let pyobject_type = compiler.create_pyobject_type();
+    let malloc_fn = compiler.runtime.add_malloc(&compiler.module);
+    let dict_new_index = compiler.get_or_build_dict_new_index();
+    let entries_size = compiler
+        .builder
+        .build_int_mul(
+            pyobject_type.size_of(),
+            i64_type.const_int(2 * capacity as u64, false),
+            "entries_size",
+        )
+        .unwrap();
+    let entries_ptr = build_libc_call(
+        compiler,
+        malloc_fn,
+        &[entries_size.into()],
+        "malloc_entries",
+    )?
+    .into_pointer_value();
+    let mut slots = 8;
+    while 3 * capacity > 2 * slots {
+        slots *= 2;
+    }
+    let index_ptr = build_libc_call(
+        compiler,
+        dict_new_index,
+        &[i64_type.const_int(slots as u64, false).into()],
+        "dict_index",
+    )?
+    .into_pointer_value();
+
+    let header_size = compiler
+        .builder
+        .build_int_mul(
+            pyobject_type.size_of(),
+            i64_type.const_int(5, false),
+            "dict_header_size",
+        )
+        .unwrap();
+    let header_ptr = build_libc_call(compiler, malloc_fn, &[header_size.into()], "malloc_dict")?
+        .into_pointer_value();
+    let header_values: [BasicValueEnum<'ctx>; 5] = [
+        i64_type.const_zero().into(),
+        i64_type.const_int(capacity as u64, false).into(),
+        entries_ptr.into(),
+        index_ptr.into(),
+        i64_type.const_int(slots as u64 - 1, false).into(),
+    ];
+    for (i, header_value) in header_values.into_iter().enumerate() {
+        let field_ptr = unsafe {
+            compiler
                 .builder
-                .build_select(result_is_float, float_tag, int_tag, "result_tag")
+                .build_in_bounds_gep(
+                    pyobject_type,
+                    header_ptr,
+                    &[i64_type.const_int(i as u64, false)],
+                    "dict_field_ptr",
+                )
                 .unwrap()
-                .into_int_value();
+        };
+        compiler
+            .builder
+            .build_store(field_ptr, header_value)
+            .unwrap();
+    }
+    Ok(header_ptr)
+}
 
-            // Create result PyObject
-            let result_obj =
-                compiler.create_pyobject_from_tag_and_payload(result_tag, result_payload);
+/// Allocates `[length][element_0]...[element_n]` and stores the compiled elements
+fn build_sequence<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    elements: &[IRExpr],
+) -> Result<inkwell::values::PointerValue<'ctx>, CodeGenError> {
+    // Compile all element expressions
+    let mut compiled_elements = Vec::new();
+    for elem in elements {
+        let elem_pyobj = compiler.compile_expression(elem)?;
+        compiled_elements.push(elem_pyobj);
+    }
+    store_sequence(compiler, elements.len(), &compiled_elements)
+}
 
-            Ok(result_obj)
+/// Allocates a `[length][slot_0]...[slot_n]` block with `list_len` in the header
+fn store_sequence<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    list_len: usize,
+    compiled_elements: &[IntValue<'ctx>],
+) -> Result<inkwell::values::PointerValue<'ctx>, CodeGenError> {
+    let pyobject_type = compiler.create_pyobject_type();
+
+    // Allocate memory for: [length: i64][slot_0: i64]...[slot_n: i64]
+    // Total size = (1 + slot count) * sizeof(i64)
+    let pyobject_size = pyobject_type.size_of();
+    let element_count = compiler
+        .context
+        .i64_type()
+        .const_int((compiled_elements.len() + 1) as u64, false); // +1 for length header
+    let total_size = compiler
+        .builder
+        .build_int_mul(pyobject_size, element_count, "list_size")
+        .unwrap();
+
+    // Allocate the list
+    let malloc_fn = compiler.runtime.add_malloc(&compiler.module);
+    let list_ptr_result = compiler
+        .builder
+        .build_call(malloc_fn, &[total_size.into()], "malloc_list")
+        .unwrap();
+    let list_ptr = match list_ptr_result.try_as_basic_value() {
+        inkwell::values::ValueKind::Basic(value) => value.into_pointer_value(),
+        _ => {
+            return Err(CodeGenError::UndefinedVariable(
+                "malloc did not return a value".to_string(),
+            ))
         }
+    };
+
+    // Store the length at offset 0
+    let len_value = compiler
+        .context
+        .i64_type()
+        .const_int(list_len as u64, false);
+    let len_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(
+                pyobject_type,
+                list_ptr,
+                &[compiler.context.i64_type().const_int(0, false)],
+                "len_ptr",
+            )
+            .unwrap()
+    };
+    compiler.builder.build_store(len_ptr, len_value).unwrap();
+
+    // Store each element in the array (starting at offset 1)
+    for (i, elem_pyobj) in compiled_elements.iter().enumerate() {
+        let index = compiler.context.i64_type().const_int((i + 1) as u64, false); // +1 to skip length header
+        let elem_ptr = unsafe {
+            compiler
+                .builder
+                .build_in_bounds_gep(
+                    pyobject_type,
+                    list_ptr,
+                    &[index],
+                    &format!("elem_ptr_{}", i),
+                )
+                .unwrap()
+        };
+        compiler.builder.build_store(elem_ptr, *elem_pyobj).unwrap();
+    }
+
+    Ok(list_ptr)
+}
+
+/// Compiles a list indexing expression `list[index]`
+pub fn compile_index<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    list: &IRExpr,
+    index: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let list_obj = compiler.compile_expression(list)?;
+    let index_obj = compiler.compile_expression(index)?;
+    let pyobject_type = compiler.create_pyobject_type();
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let dict_block = compiler
+        .context
+        .append_basic_block(current_fn, "dict_lookup");
+    let sequence_block = compiler
+        .context
+        .append_basic_block(current_fn, "sequence_index");
+    let merge_block = compiler
+        .context
+        .append_basic_block(current_fn, "index_merge");
+    let is_dict = build_tag_check(compiler, list_obj, TYPE_TAG_DICT, "is_dict");
+    compiler
+        .builder
+        .build_conditional_branch(is_dict, dict_block, sequence_block)
+        .unwrap();
+
+    // Dicts look the key up among their keys
+    compiler.builder.position_at_end(dict_block);
+    let value_ptr = build_dict_value_ptr(compiler, list_obj, index_obj)?;
+    let dict_value = compiler
+        .builder
+        .build_load(pyobject_type, value_ptr, "dict_value")
+        .unwrap();
+    let dict_end_block = compiler.builder.get_insert_block().unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(merge_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(sequence_block);
+    let elem_ptr = build_element_ptr(
+        compiler,
+        list_obj,
+        index_obj,
+        "IndexError: list index out of range",
+    );
+    let elem = compiler
+        .builder
+        .build_load(pyobject_type, elem_ptr, "elem")
+        .unwrap();
+    let sequence_end_block = compiler.builder.get_insert_block().unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(merge_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(merge_block);
+    let phi = compiler
+        .builder
+        .build_phi(pyobject_type, "index_result")
+        .unwrap();
+    phi.add_incoming(&[(&dict_value, dict_end_block), (&elem, sequence_end_block)]);
+    Ok(phi.as_basic_value().into_int_value())
+}
+
+/// Returns a pointer to the value stored under `key` in a dict, raising a
+/// `KeyError` when no key matches
+fn build_dict_value_ptr<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    dict_obj: IntValue<'ctx>,
+    key_obj: IntValue<'ctx>,
+) -> Result<PointerValue<'ctx>, CodeGenError> {
+    let i64_type = compiler.context.i64_type();
+    let find_item = compiler.get_or_build_find_item();
+    let position = build_libc_call(
+        compiler,
+        find_item,
+        &[key_obj.into(), dict_obj.into()],
+        "key_position",
+    )?
+    .into_int_value();
+    let missing = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::SLT,
+            position,
+            i64_type.const_zero(),
+            "key_missing",
+        )
+        .unwrap();
+    compiler.build_raise_if(missing, "KeyError: key not found");
+    Ok(build_entry_value_ptr(compiler, dict_obj, position))
+}
+
+/// Returns a pointer to the value of dict entry `position`
+fn build_entry_value_ptr<'ctx>(
+    compiler: &Compiler<'ctx>,
+    dict_obj: IntValue<'ctx>,
+    position: IntValue<'ctx>,
+) -> PointerValue<'ctx> {
+    let i64_type = compiler.context.i64_type();
+    let (entries_ptr, _) = compiler.extract_dict_entries(dict_obj);
+    let key_slot = compiler
+        .builder
+        .build_int_mul(position, i64_type.const_int(2, false), "key_slot")
+        .unwrap();
+    let value_slot = compiler
+        .builder
+        .build_int_add(key_slot, i64_type.const_int(1, false), "value_slot")
+        .unwrap();
+    let pyobject_type = compiler.create_pyobject_type();
+    unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, entries_ptr, &[value_slot], "value_ptr")
+            .unwrap()
+    }
+}
+
+/// Returns a pointer to the slot of element `index` of a list or tuple. A negative
+/// index counts from the end; one still out of range raises `error`.
+pub(crate) fn build_element_ptr<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    list_obj: IntValue<'ctx>,
+    index_obj: IntValue<'ctx>,
+    error: &str,
+) -> PointerValue<'ctx> {
+    let (list_ptr, list_len) = compiler.extract_list_ptr_and_len(list_obj);
+    let index_payload = compiler.extract_payload(index_obj);
+    let i64_type = compiler.context.i64_type();
+    let index_int = compiler
+        .builder
+        .build_float_to_signed_int(index_payload, i64_type, "index_int")
+        .unwrap();
+
+    let is_negative = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::SLT,
+            index_int,
+            i64_type.const_zero(),
+            "index_is_negative",
+        )
+        .unwrap();
+    let from_end = compiler
+        .builder
+        .build_int_add(index_int, list_len, "index_from_end")
+        .unwrap();
+    let index_int = compiler
+        .builder
+        .build_select(is_negative, from_end, index_int, "normalized_index")
+        .unwrap()
+        .into_int_value();
+    // Unsigned, an index still negative is out of range too
+    let out_of_range = compiler
+        .builder
+        .build_int_compare(IntPredicate::UGE, index_int, list_len, "index_out_of_range")
+        .unwrap();
+    compiler.build_raise_if(out_of_range, error);
+
+    // Add 1 to the index to skip the length header
+    // List layout: [length: i64][element_0: i64]...[element_n: i64]
+    let adjusted_index = compiler
+        .builder
+        .build_int_add(index_int, i64_type.const_int(1, false), "adjusted_index")
+        .unwrap();
+    let pyobject_type = compiler.create_pyobject_type();
+    unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, list_ptr, &[adjusted_index], "elem_ptr")
+            .unwrap()
+    }
+}
+
+/// Compiles a reversed() call: copies the list into a new allocation in reverse order
+/// The original list is left unchanged
+pub fn compile_reversed<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    list: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let list_obj = compiler.compile_expression(list)?;
+    let (src_ptr, list_len) = compiler.extract_list_ptr_and_len(list_obj);
+    let dst_ptr = build_list_alloc(compiler, list_len)?;
+
+    let i64_type = compiler.context.i64_type();
+    let pyobject_type = compiler.create_pyobject_type();
+    let one = i64_type.const_int(1, false);
+
+    // Copy loop: dst[i] = src[len - 1 - i] (both offset by 1 for the length header)
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let entry_block = compiler.builder.get_insert_block().unwrap();
+    let cond_block = compiler
+        .context
+        .append_basic_block(current_fn, "reversed_cond");
+    let body_block = compiler
+        .context
+        .append_basic_block(current_fn, "reversed_body");
+    let done_block = compiler
+        .context
+        .append_basic_block(current_fn, "reversed_done");
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(cond_block);
+    let counter = compiler.builder.build_phi(i64_type, "reversed_i").unwrap();
+    let i = counter.as_basic_value().into_int_value();
+    let in_range = compiler
+        .builder
+        .build_int_compare(IntPredicate::SLT, i, list_len, "reversed_in_range")
+        .unwrap();
+    compiler
+        .builder
+        .build_conditional_branch(in_range, body_block, done_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(body_block);
+    // Source slot: (len - 1 - i) + 1 == len - i
+    let src_index = compiler
+        .builder
+        .build_int_sub(list_len, i, "src_index")
+        .unwrap();
+    let dst_index = compiler.builder.build_int_add(i, one, "dst_index").unwrap();
+    let src_elem_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, src_ptr, &[src_index], "src_elem_ptr")
+            .unwrap()
+    };
+    let dst_elem_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, dst_ptr, &[dst_index], "dst_elem_ptr")
+            .unwrap()
+    };
+    let elem = compiler
+        .builder
+        .build_load(pyobject_type, src_elem_ptr, "elem")
+        .unwrap();
+    compiler.builder.build_store(dst_elem_ptr, elem).unwrap();
+    let next_i = compiler
+        .builder
+        .build_int_add(i, one, "reversed_next_i")
+        .unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+    counter.add_incoming(&[(&i64_type.const_zero(), entry_block), (&next_i, body_block)]);
+
+    compiler.builder.position_at_end(done_block);
+    Ok(compiler.create_pyobject_list(dst_ptr, 0))
+}
+
+/// Python's names for the types, ordered by type tag
+const TYPE_NAMES: [&str; 11] = [
+    "int",
+    "float",
+    "bool",
+    "str",
+    "list",
+    "NoneType",
+    "tuple",
+    "dict",
+    "_io.TextIOWrapper",
+    "function",
+    "set",
+];
+
+/// Compiles a type() call: the name is selected by the tag and copied to the heap
+/// like a string literal
+pub fn compile_type_name<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    value: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let value_obj = compiler.compile_expression(value)?;
+    let tag = compiler.extract_tag(value_obj);
+
+    let i64_type = compiler.context.i64_type();
+    let mut name = None;
+    for (tag_value, type_name) in TYPE_NAMES.iter().enumerate() {
+        let text = compiler
+            .builder
+            .build_global_string_ptr(&format!("<class '{}'>", type_name), "type_name")
+            .unwrap()
+            .as_pointer_value();
+        name = Some(match name {
+            None => text,
+            Some(previous) => {
+                let matches = compiler
+                    .builder
+                    .build_int_compare(
+                        IntPredicate::EQ,
+                        tag,
+                        i64_type.const_int(tag_value as u64, false),
+                        "is_type",
+                    )
+                    .unwrap();
+                compiler
+                    .builder
+                    .build_select(matches, text, previous, "type_name")
+                    .unwrap()
+                    .into_pointer_value()
+            }
+        });
     }
+    let name = name.unwrap();
+
+    let strlen_fn = compiler.runtime.add_strlen(&compiler.module);
+    let len =
+        build_libc_call(compiler, strlen_fn, &[name.into()], "type_name_len")?.into_int_value();
+    let size = compiler
+        .builder
+        .build_int_add(len, i64_type.const_int(1, false), "type_name_size")
+        .unwrap();
+    let malloc_fn = compiler.runtime.add_malloc(&compiler.module);
+    let copy = build_libc_call(compiler, malloc_fn, &[size.into()], "malloc_type_name")?
+        .into_pointer_value();
+    let memcpy_fn = compiler.runtime.add_memcpy(&compiler.module);
+    compiler
+        .builder
+        .build_call(
+            memcpy_fn,
+            &[copy.into(), name.into(), size.into()],
+            "memcpy_type_name",
+        )
+        .unwrap();
+    Ok(compiler.create_pyobject_string(copy))
+}
+
+/// Compiles a sum() call over a list or tuple. The payloads are added as floats and
+/// the total is tagged as a float if any element is one, like mixed `+` chains.
+pub fn compile_sum<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    list: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let list_obj = compiler.compile_expression(list)?;
+    let is_list = build_tag_check(compiler, list_obj, TYPE_TAG_LIST, "is_list");
+    let is_tuple = build_tag_check(compiler, list_obj, TYPE_TAG_TUPLE, "is_tuple");
+    let is_sequence = compiler
+        .builder
+        .build_or(is_list, is_tuple, "is_sequence")
+        .unwrap();
+    let not_sequence = compiler
+        .builder
+        .build_not(is_sequence, "not_sequence")
+        .unwrap();
+    compiler.build_raise_if(not_sequence, "TypeError: sum() argument is not iterable");
+    let (list_ptr, list_len) = compiler.extract_list_ptr_and_len(list_obj);
+
+    let i64_type = compiler.context.i64_type();
+    let f64_type = compiler.context.f64_type();
+    let bool_type = compiler.context.bool_type();
+    let pyobject_type = compiler.create_pyobject_type();
+    let one = i64_type.const_int(1, false);
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let entry_block = compiler.builder.get_insert_block().unwrap();
+    let cond_block = compiler.context.append_basic_block(current_fn, "sum_cond");
+    let body_block = compiler.context.append_basic_block(current_fn, "sum_body");
+    let done_block = compiler.context.append_basic_block(current_fn, "sum_done");
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(cond_block);
+    let counter = compiler.builder.build_phi(i64_type, "sum_i").unwrap();
+    let total = compiler.builder.build_phi(f64_type, "sum_total").unwrap();
+    let any_float = compiler
+        .builder
+        .build_phi(bool_type, "sum_any_float")
+        .unwrap();
+    let i = counter.as_basic_value().into_int_value();
+    let total_value = total.as_basic_value().into_float_value();
+    let any_float_value = any_float.as_basic_value().into_int_value();
+    let in_range = compiler
+        .builder
+        .build_int_compare(IntPredicate::SLT, i, list_len, "sum_in_range")
+        .unwrap();
+    compiler
+        .builder
+        .build_conditional_branch(in_range, body_block, done_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(body_block);
+    // Skip the length header
+    let slot = compiler.builder.build_int_add(i, one, "sum_slot").unwrap();
+    let elem_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, list_ptr, &[slot], "sum_elem_ptr")
+            .unwrap()
+    };
+    let elem = compiler
+        .builder
+        .build_load(pyobject_type, elem_ptr, "sum_elem")
+        .unwrap()
+        .into_int_value();
+    let is_int = build_tag_check(compiler, elem, TYPE_TAG_INT, "elem_is_int");
+    let is_bool = build_tag_check(compiler, elem, TYPE_TAG_BOOL, "elem_is_bool");
+    let is_float = build_tag_check(compiler, elem, TYPE_TAG_FLOAT, "elem_is_float");
+    let is_integer = compiler
+        .builder
+        .build_or(is_int, is_bool, "elem_is_integer")
+        .unwrap();
+    let is_number = compiler
+        .builder
+        .build_or(is_integer, is_float, "elem_is_number")
+        .unwrap();
+    let not_number = compiler
+        .builder
+        .build_not(is_number, "elem_not_number")
+        .unwrap();
+    compiler.build_raise_if(not_number, "TypeError: sum() can only add numbers");
+    let payload = compiler.extract_payload(elem);
+    let next_total = compiler
+        .builder
+        .build_float_add(total_value, payload, "sum_next_total")
+        .unwrap();
+    let next_any_float = compiler
+        .builder
+        .build_or(any_float_value, is_float, "sum_next_any_float")
+        .unwrap();
+    let next_i = compiler
+        .builder
+        .build_int_add(i, one, "sum_next_i")
+        .unwrap();
+    // The type check split the body; the back edge leaves from its last block
+    let latch_block = compiler.builder.get_insert_block().unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+    counter.add_incoming(&[
+        (&i64_type.const_zero(), entry_block),
+        (&next_i, latch_block),
+    ]);
+    total.add_incoming(&[
+        (&f64_type.const_zero(), entry_block),
+        (&next_total, latch_block),
+    ]);
+    any_float.add_incoming(&[
+        (&bool_type.const_zero(), entry_block),
+        (&next_any_float, latch_block),
+    ]);
+
+    compiler.builder.position_at_end(done_block);
+    let tag = compiler
+        .builder
+        .build_select(
+            any_float_value,
+            i64_type.const_int(TYPE_TAG_FLOAT as u64, false),
+            i64_type.const_int(TYPE_TAG_INT as u64, false),
+            "sum_tag",
+        )
+        .unwrap()
+        .into_int_value();
+    Ok(compiler.create_pyobject_from_tag_and_payload(tag, total_value))
+}
+
+/// Compiles an abs() call: ints and bools give an int, floats a float, and any other
+/// value raises a TypeError
+pub fn compile_abs<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    value: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let value_obj = compiler.compile_expression(value)?;
+    let is_int = build_tag_check(compiler, value_obj, TYPE_TAG_INT, "is_int");
+    let is_bool = build_tag_check(compiler, value_obj, TYPE_TAG_BOOL, "is_bool");
+    let is_float = build_tag_check(compiler, value_obj, TYPE_TAG_FLOAT, "is_float");
+    let is_integer = compiler
+        .builder
+        .build_or(is_int, is_bool, "is_integer")
+        .unwrap();
+    let is_number = compiler
+        .builder
+        .build_or(is_integer, is_float, "is_number")
+        .unwrap();
+    let not_number = compiler.builder.build_not(is_number, "not_number").unwrap();
+    compiler.build_raise_if(not_number, "TypeError: bad operand type for abs()");
+
+    let i64_type = compiler.context.i64_type();
+    let int_value = compiler.extract_int_payload(value_obj);
+    let is_negative = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::SLT,
+            int_value,
+            i64_type.const_zero(),
+            "is_negative",
+        )
+        .unwrap();
+    let negated = compiler
+        .builder
+        .build_int_neg(int_value, "negated")
+        .unwrap();
+    let int_magnitude = compiler
+        .builder
+        .build_select(is_negative, negated, int_value, "int_magnitude")
+        .unwrap()
+        .into_int_value();
+    let int_result = compiler.create_pyobject_int(int_magnitude);
+
+    // fabs also clears the sign of -0.0, which a compare-and-negate would keep
+    let fabs = Intrinsic::find("llvm.fabs")
+        .and_then(|intrinsic| {
+            intrinsic.get_declaration(&compiler.module, &[compiler.context.f64_type().into()])
+        })
+        .expect("llvm.fabs intrinsic should be available");
+    let float_value = compiler.extract_payload(value_obj);
+    let float_magnitude =
+        build_libc_call(compiler, fabs, &[float_value.into()], "float_magnitude")?
+            .into_float_value();
+    let float_result = compiler.create_pyobject_float(float_magnitude);
+
+    Ok(compiler
+        .builder
+        .build_select(is_float, float_result, int_result, "abs_result")
+        .unwrap()
+        .into_int_value())
+}
+
+/// Compiles an int() call: ints are kept, bools become 0 or 1 and floats are
+/// truncated toward zero, so `int(-2.9)` is -2 where `-2.9 // 1` is -3.0. Strings
+/// are not parsed yet: anything but a number raises a TypeError
+pub fn compile_int<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    value: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let value_obj = compiler.compile_expression(value)?;
+    let is_int = build_tag_check(compiler, value_obj, TYPE_TAG_INT, "is_int");
+    let is_bool = build_tag_check(compiler, value_obj, TYPE_TAG_BOOL, "is_bool");
+    let is_float = build_tag_check(compiler, value_obj, TYPE_TAG_FLOAT, "is_float");
+    let is_integer = compiler
+        .builder
+        .build_or(is_int, is_bool, "is_integer")
+        .unwrap();
+    let is_number = compiler
+        .builder
+        .build_or(is_integer, is_float, "is_number")
+        .unwrap();
+    let not_number = compiler.builder.build_not(is_number, "not_number").unwrap();
+    compiler.build_raise_if(not_number, "TypeError: int() argument must be a number");
+
+    // An infinite or NaN float has no int value
+    let float_value = compiler.extract_payload(value_obj);
+    let is_nan = compiler
+        .builder
+        .build_float_compare(FloatPredicate::UNO, float_value, float_value, "is_nan")
+        .unwrap();
+    let float_nan = compiler
+        .builder
+        .build_and(is_float, is_nan, "float_nan")
+        .unwrap();
+    compiler.build_raise_if(float_nan, "ValueError: cannot convert float NaN to integer");
+    let fabs = Intrinsic::find("llvm.fabs")
+        .and_then(|intrinsic| {
+            intrinsic.get_declaration(&compiler.module, &[compiler.context.f64_type().into()])
+        })
+        .expect("llvm.fabs intrinsic should be available");
+    let magnitude =
+        build_libc_call(compiler, fabs, &[float_value.into()], "magnitude")?.into_float_value();
+    let is_infinite = compiler
+        .builder
+        .build_float_compare(
+            FloatPredicate::OEQ,
+            magnitude,
+            compiler.context.f64_type().const_float(f64::INFINITY),
+            "is_infinite",
+        )
+        .unwrap();
+    let float_infinite = compiler
+        .builder
+        .build_and(is_float, is_infinite, "float_infinite")
+        .unwrap();
+    compiler.build_raise_if(
+        float_infinite,
+        "OverflowError: cannot convert float infinity to integer",
+    );
+
+    // fptosi rounds toward zero; ints and bools keep their exact payload
+    let truncated = compiler
+        .builder
+        .build_float_to_signed_int(float_value, compiler.context.i64_type(), "truncated")
+        .unwrap();
+    let int_value = compiler.extract_int_payload(value_obj);
+    let result = compiler
+        .builder
+        .build_select(is_float, truncated, int_value, "int_value")
+        .unwrap()
+        .into_int_value();
+    Ok(compiler.create_pyobject_int(result))
+}
+
+/// Compiles a min() or max() call. Several arguments are compared directly; a
+/// single argument must be a non-empty list or tuple, whose elements are compared.
+/// Values are ordered as tuple comparison orders them (`compare_values`), and the
+/// first smallest (or largest) one is returned unchanged, so `max(1, 2.0)` is the
+/// float and `max(1, 1.0)` the int.
+pub fn compile_min_max<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    is_max: bool,
+    args: &[IRExpr],
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let name = if is_max { "max" } else { "min" };
+    if args.len() > 1 {
+        let mut best = compiler.compile_expression(&args[0])?;
+        for arg in &args[1..] {
+            let candidate = compiler.compile_expression(arg)?;
+            best = build_min_max_step(compiler, is_max, best, candidate)?;
+        }
+        return Ok(best);
+    }
+
+    let list_obj = compiler.compile_expression(&args[0])?;
+    let is_list = build_tag_check(compiler, list_obj, TYPE_TAG_LIST, "is_list");
+    let is_tuple = build_tag_check(compiler, list_obj, TYPE_TAG_TUPLE, "is_tuple");
+    let is_sequence = compiler
+        .builder
+        .build_or(is_list, is_tuple, "is_sequence")
+        .unwrap();
+    let not_sequence = compiler
+        .builder
+        .build_not(is_sequence, "not_sequence")
+        .unwrap();
+    compiler.build_raise_if(
+        not_sequence,
+        &format!("TypeError: {}() argument is not iterable", name),
+    );
+    let (list_ptr, list_len) = compiler.extract_list_ptr_and_len(list_obj);
+    let i64_type = compiler.context.i64_type();
+    let is_empty = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::EQ,
+            list_len,
+            i64_type.const_zero(),
+            "is_empty",
+        )
+        .unwrap();
+    compiler.build_raise_if(
+        is_empty,
+        &format!("ValueError: {}() iterable argument is empty", name),
+    );
+
+    let pyobject_type = compiler.create_pyobject_type();
+    let one = i64_type.const_int(1, false);
+    let load_element = |compiler: &Compiler<'ctx>, index: IntValue<'ctx>| {
+        // Skip the length header
+        let slot = compiler
+            .builder
+            .build_int_add(index, one, "min_max_slot")
+            .unwrap();
+        let elem_ptr = unsafe {
+            compiler
+                .builder
+                .build_in_bounds_gep(pyobject_type, list_ptr, &[slot], "min_max_elem_ptr")
+                .unwrap()
+        };
+        compiler
+            .builder
+            .build_load(pyobject_type, elem_ptr, "min_max_elem")
+            .unwrap()
+            .into_int_value()
+    };
+    let first = load_element(compiler, i64_type.const_zero());
+
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let entry_block = compiler.builder.get_insert_block().unwrap();
+    let cond_block = compiler
+        .context
+        .append_basic_block(current_fn, "min_max_cond");
+    let body_block = compiler
+        .context
+        .append_basic_block(current_fn, "min_max_body");
+    let done_block = compiler
+        .context
+        .append_basic_block(current_fn, "min_max_done");
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(cond_block);
+    let counter = compiler.builder.build_phi(i64_type, "min_max_i").unwrap();
+    let best = compiler
+        .builder
+        .build_phi(pyobject_type, "min_max_best")
+        .unwrap();
+    let i = counter.as_basic_value().into_int_value();
+    let best_value = best.as_basic_value().into_int_value();
+    let in_range = compiler
+        .builder
+        .build_int_compare(IntPredicate::SLT, i, list_len, "min_max_in_range")
+        .unwrap();
+    compiler
+        .builder
+        .build_conditional_branch(in_range, body_block, done_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(body_block);
+    let candidate = load_element(compiler, i);
+    let next_best = build_min_max_step(compiler, is_max, best_value, candidate)?;
+    let next_i = compiler
+        .builder
+        .build_int_add(i, one, "min_max_next_i")
+        .unwrap();
+    // The type check split the body; the back edge leaves from its last block
+    let latch_block = compiler.builder.get_insert_block().unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+    counter.add_incoming(&[(&one, entry_block), (&next_i, latch_block)]);
+    best.add_incoming(&[(&first, entry_block), (&next_best, latch_block)]);
+
+    compiler.builder.position_at_end(done_block);
+    Ok(best_value)
+}
+
+/// Returns `candidate` when it is strictly smaller (or larger) than `best`, and
+/// `best` otherwise. Values that cannot be ordered raise a TypeError, except
+/// numbers: a NaN never replaces or is replaced, as in Python.
+fn build_min_max_step<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    is_max: bool,
+    best: IntValue<'ctx>,
+    candidate: IntValue<'ctx>,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let i64_type = compiler.context.i64_type();
+    let compare_values = compiler.get_or_build_compare_values();
+    let order = build_libc_call(
+        compiler,
+        compare_values,
+        &[candidate.into(), best.into()],
+        "min_max_order",
+    )?
+    .into_int_value();
+
+    let bool_tag = i64_type.const_int(TYPE_TAG_BOOL as u64, false);
+    let best_tag = compiler.extract_tag(best);
+    let candidate_tag = compiler.extract_tag(candidate);
+    let best_is_number = compiler
+        .builder
+        .build_int_compare(IntPredicate::ULE, best_tag, bool_tag, "best_is_number")
+        .unwrap();
+    let candidate_is_number = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::ULE,
+            candidate_tag,
+            bool_tag,
+            "candidate_is_number",
+        )
+        .unwrap();
+    let both_numbers = compiler
+        .builder
+        .build_and(best_is_number, candidate_is_number, "both_numbers")
+        .unwrap();
+    let not_numbers = compiler
+        .builder
+        .build_not(both_numbers, "not_numbers")
+        .unwrap();
+    let unordered = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::EQ,
+            order,
+            i64_type.const_int(2, false),
+            "unordered",
+        )
+        .unwrap();
+    let incomparable = compiler
+        .builder
+        .build_and(unordered, not_numbers, "incomparable")
+        .unwrap();
+    compiler.build_raise_if(
+        incomparable,
+        &format!(
+            "TypeError: '{}' not supported between instances of different types",
+            if is_max { ">" } else { "<" }
+        ),
+    );
+
+    let wanted = if is_max {
+        i64_type.const_int(1, false)
+    } else {
+        i64_type.const_all_ones()
+    };
+    let replaces = compiler
+        .builder
+        .build_int_compare(IntPredicate::EQ, order, wanted, "replaces")
+        .unwrap();
+    Ok(compiler
+        .builder
+        .build_select(replaces, candidate, best, "min_max_result")
+        .unwrap()
+        .into_int_value())
+}
+
+/// Compiles a hex(), oct() or bin() call. Only ints and bools have digits; other
+/// values raise a TypeError like Python's.
+pub fn compile_int_to_base<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    base: u32,
+    value: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let value_obj = compiler.compile_expression(value)?;
+    compiler.build_require_integer(value_obj);
+
+    let i64_type = compiler.context.i64_type();
+    let number = compiler.extract_int_payload(value_obj);
+    let prefix = match base {
+        16 => b'x',
+        8 => b'o',
+        _ => b'b',
+    };
+    let format_int_base = compiler.get_or_build_format_int_base();
+    let text = compiler
+        .builder
+        .build_call(
+            format_int_base,
+            &[
+                number.into(),
+                i64_type.const_int(base as u64, false).into(),
+                compiler
+                    .context
+                    .i8_type()
+                    .const_int(prefix as u64, false)
+                    .into(),
+            ],
+            "int_to_base",
+        )
+        .unwrap();
+    let text = match text.try_as_basic_value() {
+        inkwell::values::ValueKind::Basic(value) => value.into_pointer_value(),
+        _ => unreachable!("format_int_base returns a string"),
+    };
+
+    compiler.track_string_in_arena(text);
+    Ok(compiler.create_pyobject_string(text))
+}
+
+/// Compiles an isclose() call: `abs(a - b) <= 1e-9 * max(abs(a), abs(b))`, which
+/// holds for equal values (including infinities) and never for NaN
+pub fn compile_isclose<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    left: &IRExpr,
+    right: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let left_obj = compiler.compile_expression(left)?;
+    let right_obj = compiler.compile_expression(right)?;
+    let a = compiler.extract_payload(left_obj);
+    let b = compiler.extract_payload(right_obj);
+    let fabs = Intrinsic::find("llvm.fabs")
+        .and_then(|intrinsic| {
+            intrinsic.get_declaration(&compiler.module, &[compiler.context.f64_type().into()])
+        })
+        .expect("llvm.fabs intrinsic should be available");
+    let abs_of = |compiler: &Compiler<'ctx>, value: FloatValue<'ctx>, name: &str| match compiler
+        .builder
+        .build_call(fabs, &[value.into()], name)
+        .unwrap()
+        .try_as_basic_value()
+    {
+        inkwell::values::ValueKind::Basic(value) => value.into_float_value(),
+        _ => unreachable!("llvm.fabs returns a float"),
+    };
+
+    let equal = compiler
+        .builder
+        .build_float_compare(FloatPredicate::OEQ, a, b, "exactly_equal")
+        .unwrap();
+    let difference = compiler
+        .builder
+        .build_float_sub(a, b, "difference")
+        .unwrap();
+    let distance = abs_of(compiler, difference, "distance");
+    let abs_a = abs_of(compiler, a, "abs_a");
+    let abs_b = abs_of(compiler, b, "abs_b");
+    let a_larger = compiler
+        .builder
+        .build_float_compare(FloatPredicate::OGT, abs_a, abs_b, "a_larger")
+        .unwrap();
+    let larger = compiler
+        .builder
+        .build_select(a_larger, abs_a, abs_b, "larger")
+        .unwrap()
+        .into_float_value();
+    let tolerance = compiler
+        .builder
+        .build_float_mul(
+            larger,
+            compiler.context.f64_type().const_float(1e-9),
+            "tolerance",
+        )
+        .unwrap();
+    let within = compiler
+        .builder
+        .build_float_compare(FloatPredicate::OLE, distance, tolerance, "within")
+        .unwrap();
+    let close = compiler.builder.build_or(equal, within, "isclose").unwrap();
+    Ok(compiler.create_pyobject_bool(close))
+}
+
+/// Compiles a zip() call: builds a new list of `(left[i], right[i])` tuples, stopping
+/// at the end of the shorter list
+pub fn compile_zip<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    left: &IRExpr,
+    right: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let left_obj = compiler.compile_expression(left)?;
+    let right_obj = compiler.compile_expression(right)?;
+    let (left_ptr, left_len) = compiler.extract_list_ptr_and_len(left_obj);
+    let (right_ptr, right_len) = compiler.extract_list_ptr_and_len(right_obj);
+    let left_is_shorter = compiler
+        .builder
+        .build_int_compare(IntPredicate::SLT, left_len, right_len, "left_is_shorter")
+        .unwrap();
+    let list_len = compiler
+        .builder
+        .build_select(left_is_shorter, left_len, right_len, "zip_len")
+        .unwrap()
+        .into_int_value();
+    let dst_ptr = build_list_alloc(compiler, list_len)?;
+
+    let i64_type = compiler.context.i64_type();
+    let pyobject_type = compiler.create_pyobject_type();
+    let one = i64_type.const_int(1, false);
+    let two = i64_type.const_int(2, false);
+
+    // Pairing loop: dst[i] = (left[i], right[i]) (all offset by 1 for the length header)
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let entry_block = compiler.builder.get_insert_block().unwrap();
+    let cond_block = compiler.context.append_basic_block(current_fn, "zip_cond");
+    let body_block = compiler.context.append_basic_block(current_fn, "zip_body");
+    let done_block = compiler.context.append_basic_block(current_fn, "zip_done");
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(cond_block);
+    let counter = compiler.builder.build_phi(i64_type, "zip_i").unwrap();
+    let i = counter.as_basic_value().into_int_value();
+    let in_range = compiler
+        .builder
+        .build_int_compare(IntPredicate::SLT, i, list_len, "zip_in_range")
+        .unwrap();
+    compiler
+        .builder
+        .build_conditional_branch(in_range, body_block, done_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(body_block);
+    let slot = compiler.builder.build_int_add(i, one, "zip_slot").unwrap();
+    let pair_ptr = build_list_alloc(compiler, two)?;
+    for (offset, src_ptr, name) in [(1, left_ptr, "left"), (2, right_ptr, "right")] {
+        let src_elem_ptr = unsafe {
+            compiler
+                .builder
+                .build_in_bounds_gep(pyobject_type, src_ptr, &[slot], &format!("{name}_elem_ptr"))
+                .unwrap()
+        };
+        let elem = compiler
+            .builder
+            .build_load(pyobject_type, src_elem_ptr, &format!("{name}_elem"))
+            .unwrap();
+        let pair_elem_ptr = unsafe {
+            compiler
+                .builder
+                .build_in_bounds_gep(
+                    pyobject_type,
+                    pair_ptr,
+                    &[i64_type.const_int(offset, false)],
+                    &format!("pair_{name}_ptr"),
+                )
+                .unwrap()
+        };
+        compiler.builder.build_store(pair_elem_ptr, elem).unwrap();
+    }
+    let pair = compiler.create_pyobject_tuple(pair_ptr);
+    let dst_elem_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, dst_ptr, &[slot], "dst_elem_ptr")
+            .unwrap()
+    };
+    compiler.builder.build_store(dst_elem_ptr, pair).unwrap();
+    let next_i = compiler
+        .builder
+        .build_int_add(i, one, "zip_next_i")
+        .unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+    counter.add_incoming(&[(&i64_type.const_zero(), entry_block), (&next_i, body_block)]);
+
+    compiler.builder.position_at_end(done_block);
+    Ok(compiler.create_pyobject_list(dst_ptr, 0))
+}
+
+/// Compiles a list comprehension
+///
+/// The result is allocated with room for every iteration up front; each iteration
+/// binds the variable, checks the conditions and, if they all hold, evaluates the
+/// element into the next slot. The length header is set to the number kept. The
+/// variable gets its own slot for the duration of the comprehension, so it does not
+/// leak into (or clobber) a variable of the same name.
+pub fn compile_list_comp<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    element: &IRExpr,
+    var: &str,
+    source: &ComprehensionSource,
+    conditions: &[IRExpr],
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let i64_type = compiler.context.i64_type();
+    let pyobject_type = compiler.create_pyobject_type();
+    let one = i64_type.const_int(1, false);
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+
+    // Number of iterations: the size of the range, the list length or the string length
+    let (count, range_start, iter_obj) = match source {
+        ComprehensionSource::Range { start, end } => {
+            let start_obj = compiler.compile_expression(start)?;
+            compiler.build_require_integer(start_obj);
+            let end_obj = compiler.compile_expression(end)?;
+            compiler.build_require_integer(end_obj);
+            let start = compiler.extract_int_payload(start_obj);
+            let end = compiler.extract_int_payload(end_obj);
+            let span = compiler
+                .builder
+                .build_int_sub(end, start, "range_span")
+                .unwrap();
+            let is_empty = compiler
+                .builder
+                .build_int_compare(
+                    IntPredicate::SLT,
+                    span,
+                    i64_type.const_zero(),
+                    "range_empty",
+                )
+                .unwrap();
+            let count = compiler
+                .builder
+                .build_select(is_empty, i64_type.const_zero(), span, "comp_count")
+                .unwrap()
+                .into_int_value();
+            (count, Some(start), None)
+        }
+        ComprehensionSource::Iterable(iter) => {
+            let iter_obj = compiler.compile_expression(iter)?;
+            let is_string = build_tag_check(compiler, iter_obj, TYPE_TAG_STRING, "comp_is_string");
+            let str_len_block = compiler
+                .context
+                .append_basic_block(current_fn, "comp_str_len");
+            let list_len_block = compiler
+                .context
+                .append_basic_block(current_fn, "comp_list_len");
+            let count_block = compiler
+                .context
+                .append_basic_block(current_fn, "comp_count");
+            compiler
+                .builder
+                .build_conditional_branch(is_string, str_len_block, list_len_block)
+                .unwrap();
+
+            compiler.builder.position_at_end(str_len_block);
+            let str_ptr = compiler.extract_string_ptr(iter_obj);
+            let strlen_fn = compiler.runtime.add_strlen(&compiler.module);
+            let str_len = build_libc_call(compiler, strlen_fn, &[str_ptr.into()], "str_len")?
+                .into_int_value();
+            compiler
+                .builder
+                .build_unconditional_branch(count_block)
+                .unwrap();
+
+            compiler.builder.position_at_end(list_len_block);
+            let (_, list_len) = compiler.extract_list_ptr_and_len(iter_obj);
+            compiler
+                .builder
+                .build_unconditional_branch(count_block)
+                .unwrap();
+
+            compiler.builder.position_at_end(count_block);
+            let count = compiler.builder.build_phi(i64_type, "comp_count").unwrap();
+            count.add_incoming(&[(&str_len, str_len_block), (&list_len, list_len_block)]);
+            (
+                count.as_basic_value().into_int_value(),
+                None,
+                Some((iter_obj, is_string)),
+            )
+        }
+    };
+    let dst_ptr = build_list_alloc(compiler, count)?;
+
+    let var_ptr = compiler.create_entry_block_alloca(var, current_fn);
+    let shadowed = compiler.variables.insert(var.to_string(), var_ptr);
+    let i_ptr = compiler.create_entry_block_alloca(&format!("{}_comp_i", var), current_fn);
+    let kept_ptr = compiler.create_entry_block_alloca(&format!("{}_comp_kept", var), current_fn);
+    compiler
+        .builder
+        .build_store(i_ptr, i64_type.const_zero())
+        .unwrap();
+    compiler
+        .builder
+        .build_store(kept_ptr, i64_type.const_zero())
+        .unwrap();
+
+    let cond_block = compiler.context.append_basic_block(current_fn, "comp_cond");
+    let body_block = compiler.context.append_basic_block(current_fn, "comp_body");
+    let incr_block = compiler.context.append_basic_block(current_fn, "comp_incr");
+    let done_block = compiler.context.append_basic_block(current_fn, "comp_done");
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(cond_block);
+    let i = compiler
+        .builder
+        .build_load(i64_type, i_ptr, "comp_i")
+        .unwrap()
+        .into_int_value();
+    let in_range = compiler
+        .builder
+        .build_int_compare(IntPredicate::SLT, i, count, "comp_in_range")
+        .unwrap();
+    compiler
+        .builder
+        .build_conditional_branch(in_range, body_block, done_block)
+        .unwrap();
+
+    // Bind the variable to the current value
+    compiler.builder.position_at_end(body_block);
+    let value = match (range_start, iter_obj) {
+        (Some(start), _) => {
+            let n = compiler.builder.build_int_add(start, i, "comp_n").unwrap();
+            compiler.create_pyobject_int(n)
+        }
+        (None, Some((iter_obj, is_string))) => {
+            let char_block = compiler.context.append_basic_block(current_fn, "comp_char");
+            let elem_block = compiler.context.append_basic_block(current_fn, "comp_elem");
+            let bound_block = compiler
+                .context
+                .append_basic_block(current_fn, "comp_bound");
+            compiler
+                .builder
+                .build_conditional_branch(is_string, char_block, elem_block)
+                .unwrap();
+
+            compiler.builder.position_at_end(char_block);
+            let str_ptr = compiler.extract_string_ptr(iter_obj);
+            let char_obj = compiler.build_constant_char_string(str_ptr, i);
+            compiler
+                .builder
+                .build_unconditional_branch(bound_block)
+                .unwrap();
+
+            // Elements are offset by 1 for the length header
+            compiler.builder.position_at_end(elem_block);
+            let (src_ptr, _) = compiler.extract_list_ptr_and_len(iter_obj);
+            let src_index = compiler.builder.build_int_add(i, one, "src_index").unwrap();
+            let src_elem_ptr = unsafe {
+                compiler
+                    .builder
+                    .build_in_bounds_gep(pyobject_type, src_ptr, &[src_index], "src_elem_ptr")
+                    .unwrap()
+            };
+            let elem = compiler
+                .builder
+                .build_load(pyobject_type, src_elem_ptr, "elem")
+                .unwrap()
+                .into_int_value();
+            compiler
+                .builder
+                .build_unconditional_branch(bound_block)
+                .unwrap();
+
+            compiler.builder.position_at_end(bound_block);
+            let phi = compiler
+                .builder
+                .build_phi(pyobject_type, "comp_value")
+                .unwrap();
+            phi.add_incoming(&[(&char_obj, char_block), (&elem, elem_block)]);
+            phi.as_basic_value().into_int_value()
+        }
+        (None, None) => unreachable!("a comprehension source is a range or an iterable"),
+    };
+    compiler.build_variable_store(var_ptr, value);
+
+    // Skip to the next iteration as soon as a condition fails
+    for condition in conditions {
+        let holds = compile_condition(compiler, condition)?;
+        let next_block = compiler
+            .context
+            .append_basic_block(current_fn, "comp_condition_holds");
+        compiler
+            .builder
+            .build_conditional_branch(holds, next_block, incr_block)
+            .unwrap();
+        compiler.builder.position_at_end(next_block);
+    }
+
+    let result = compiler.compile_expression(element)?;
+    let kept = compiler
+        .builder
+        .build_load(i64_type, kept_ptr, "comp_kept")
+        .unwrap()
+        .into_int_value();
+    let dst_index = compiler
+        .builder
+        .build_int_add(kept, one, "dst_index")
+        .unwrap();
+    let dst_elem_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, dst_ptr, &[dst_index], "dst_elem_ptr")
+            .unwrap()
+    };
+    compiler.builder.build_store(dst_elem_ptr, result).unwrap();
+    let next_kept = compiler
+        .builder
+        .build_int_add(kept, one, "comp_next_kept")
+        .unwrap();
+    compiler.builder.build_store(kept_ptr, next_kept).unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(incr_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(incr_block);
+    let i = compiler
+        .builder
+        .build_load(i64_type, i_ptr, "comp_i")
+        .unwrap()
+        .into_int_value();
+    let next_i = compiler
+        .builder
+        .build_int_add(i, one, "comp_next_i")
+        .unwrap();
+    compiler.builder.build_store(i_ptr, next_i).unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(done_block);
+    let kept = compiler
+        .builder
+        .build_load(i64_type, kept_ptr, "comp_kept")
+        .unwrap();
+    compiler.builder.build_store(dst_ptr, kept).unwrap();
+    match shadowed {
+        Some(ptr) => compiler.variables.insert(var.to_string(), ptr),
+        None => compiler.variables.remove(var),
+    };
+    Ok(compiler.create_pyobject_list(dst_ptr, 0))
+}
+
+/// Compiles a sorted() call over a list of numbers, or ordered by a key function
+///
+/// The list is copied into a new allocation which is then insertion-sorted in place by
+/// comparing the f64 payloads (ints, floats and bools). With a key, the keys are
+/// computed once into a temporary list (like map()) that is sorted alongside the
+/// elements and compared instead. The sort is stable and the original list is left
+/// unchanged.
+pub fn compile_sorted<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    list: &IRExpr,
+    key: Option<&str>,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let list_obj = compiler.compile_expression(list)?;
+    let keys_obj = match key {
+        Some(key) => Some(build_list_transform(compiler, key, list_obj, false)?),
+        None => None,
+    };
+    let (src_ptr, list_len) = compiler.extract_list_ptr_and_len(list_obj);
+    let dst_ptr = build_list_alloc(compiler, list_len)?;
+
+    let i64_type = compiler.context.i64_type();
+    let pyobject_type = compiler.create_pyobject_type();
+    let one = i64_type.const_int(1, false);
+
+    // Copy the elements (the length header is already stored)
+    let byte_count = compiler
+        .builder
+        .build_int_mul(pyobject_type.size_of(), list_len, "sorted_bytes")
+        .unwrap();
+    let src_elems = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, src_ptr, &[one], "src_elems")
+            .unwrap()
+    };
+    let dst_elems = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, dst_ptr, &[one], "dst_elems")
+            .unwrap()
+    };
+    let memcpy_fn = compiler.runtime.add_memcpy(&compiler.module);
+    compiler
+        .builder
+        .build_call(
+            memcpy_fn,
+            &[dst_elems.into(), src_elems.into(), byte_count.into()],
+            "memcpy_sorted",
+        )
+        .unwrap();
+    // Without a key function each element is its own key
+    let key_elems = match keys_obj {
+        Some(keys_obj) => {
+            let (keys_ptr, _) = compiler.extract_list_ptr_and_len(keys_obj);
+            unsafe {
+                compiler
+                    .builder
+                    .build_in_bounds_gep(pyobject_type, keys_ptr, &[one], "key_elems")
+                    .unwrap()
+            }
+        }
+        None => dst_elems,
+    };
+
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let i_ptr = compiler.create_entry_block_alloca("sort_i", current_fn);
+    let j_ptr = compiler.create_entry_block_alloca("sort_j", current_fn);
+    let outer_cond = compiler
+        .context
+        .append_basic_block(current_fn, "sort_outer_cond");
+    let outer_body = compiler
+        .context
+        .append_basic_block(current_fn, "sort_outer_body");
+    let inner_cond = compiler
+        .context
+        .append_basic_block(current_fn, "sort_inner_cond");
+    let inner_compare = compiler
+        .context
+        .append_basic_block(current_fn, "sort_inner_compare");
+    let inner_shift = compiler
+        .context
+        .append_basic_block(current_fn, "sort_inner_shift");
+    let insert_block = compiler
+        .context
+        .append_basic_block(current_fn, "sort_insert");
+    let done_block = compiler.context.append_basic_block(current_fn, "sort_done");
+
+    // for i in 1..len
+    compiler.builder.build_store(i_ptr, one).unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(outer_cond)
+        .unwrap();
+
+    compiler.builder.position_at_end(outer_cond);
+    let i = compiler
+        .builder
+        .build_load(i64_type, i_ptr, "i")
+        .unwrap()
+        .into_int_value();
+    let outer_in_range = compiler
+        .builder
+        .build_int_compare(IntPredicate::SLT, i, list_len, "outer_in_range")
+        .unwrap();
+    compiler
+        .builder
+        .build_conditional_branch(outer_in_range, outer_body, done_block)
+        .unwrap();
+
+    // key = elems[i]; j = i - 1
+    compiler.builder.position_at_end(outer_body);
+    let key_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, dst_elems, &[i], "key_ptr")
+            .unwrap()
+    };
+    let key = compiler
+        .builder
+        .build_load(pyobject_type, key_ptr, "key")
+        .unwrap()
+        .into_int_value();
+    let key_rank_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, key_elems, &[i], "key_rank_ptr")
+            .unwrap()
+    };
+    let key_rank = compiler
+        .builder
+        .build_load(pyobject_type, key_rank_ptr, "key_rank")
+        .unwrap()
+        .into_int_value();
+    let key_payload = compiler.extract_payload(key_rank);
+    let i_minus_one = compiler
+        .builder
+        .build_int_sub(i, one, "i_minus_one")
+        .unwrap();
+    compiler.builder.build_store(j_ptr, i_minus_one).unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(inner_cond)
+        .unwrap();
+
+    // while j >= 0 and rank(elems[j]) > rank(key)
+    compiler.builder.position_at_end(inner_cond);
+    let j = compiler
+        .builder
+        .build_load(i64_type, j_ptr, "j")
+        .unwrap()
+        .into_int_value();
+    let j_in_range = compiler
+        .builder
+        .build_int_compare(IntPredicate::SGE, j, i64_type.const_zero(), "j_in_range")
+        .unwrap();
+    compiler
+        .builder
+        .build_conditional_branch(j_in_range, inner_compare, insert_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(inner_compare);
+    let j_elem_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, dst_elems, &[j], "j_elem_ptr")
+            .unwrap()
+    };
+    let j_elem = compiler
+        .builder
+        .build_load(pyobject_type, j_elem_ptr, "j_elem")
+        .unwrap()
+        .into_int_value();
+    let j_rank_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, key_elems, &[j], "j_rank_ptr")
+            .unwrap()
+    };
+    let j_rank = compiler
+        .builder
+        .build_load(pyobject_type, j_rank_ptr, "j_rank")
+        .unwrap()
+        .into_int_value();
+    let j_payload = compiler.extract_payload(j_rank);
+    let greater = compiler
+        .builder
+        .build_float_compare(FloatPredicate::OGT, j_payload, key_payload, "greater")
+        .unwrap();
+    compiler
+        .builder
+        .build_conditional_branch(greater, inner_shift, insert_block)
+        .unwrap();
+
+    // elems[j + 1] = elems[j]; j -= 1
+    compiler.builder.position_at_end(inner_shift);
+    let j_plus_one = compiler
+        .builder
+        .build_int_add(j, one, "j_plus_one")
+        .unwrap();
+    let shift_dst_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, dst_elems, &[j_plus_one], "shift_dst_ptr")
+            .unwrap()
+    };
+    compiler.builder.build_store(shift_dst_ptr, j_elem).unwrap();
+    if keys_obj.is_some() {
+        let shift_rank_ptr = unsafe {
+            compiler
+                .builder
+                .build_in_bounds_gep(pyobject_type, key_elems, &[j_plus_one], "shift_rank_ptr")
+                .unwrap()
+        };
+        compiler
+            .builder
+            .build_store(shift_rank_ptr, j_rank)
+            .unwrap();
+    }
+    let j_minus_one = compiler
+        .builder
+        .build_int_sub(j, one, "j_minus_one")
+        .unwrap();
+    compiler.builder.build_store(j_ptr, j_minus_one).unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(inner_cond)
+        .unwrap();
+
+    // elems[j + 1] = key; i += 1
+    compiler.builder.position_at_end(insert_block);
+    let j = compiler
+        .builder
+        .build_load(i64_type, j_ptr, "j")
+        .unwrap()
+        .into_int_value();
+    let insert_index = compiler
+        .builder
+        .build_int_add(j, one, "insert_index")
+        .unwrap();
+    let insert_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, dst_elems, &[insert_index], "insert_ptr")
+            .unwrap()
+    };
+    compiler.builder.build_store(insert_ptr, key).unwrap();
+    if keys_obj.is_some() {
+        let insert_rank_ptr = unsafe {
+            compiler
+                .builder
+                .build_in_bounds_gep(pyobject_type, key_elems, &[insert_index], "insert_rank_ptr")
+                .unwrap()
+        };
+        compiler
+            .builder
+            .build_store(insert_rank_ptr, key_rank)
+            .unwrap();
+    }
+    let next_i = compiler.builder.build_int_add(i, one, "next_i").unwrap();
+    compiler.builder.build_store(i_ptr, next_i).unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(outer_cond)
+        .unwrap();
+
+    compiler.builder.position_at_end(done_block);
+    if let Some(keys_obj) = keys_obj {
+        let (keys_ptr, _) = compiler.extract_list_ptr_and_len(keys_obj);
+        let free_fn = compiler.runtime.add_free(&compiler.module);
+        compiler
+            .builder
+            .build_call(free_fn, &[keys_ptr.into()], "free_keys")
+            .unwrap();
+    }
+    Ok(compiler.create_pyobject_list(dst_ptr, 0))
+}
+
+/// Compiles a map() call: applies the named function to each element of the list
+/// and collects the results into a new list
+pub fn compile_map<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    func: &str,
+    list: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let list_obj = compiler.compile_expression(list)?;
+    build_list_transform(compiler, func, list_obj, false)
+}
+
+/// Compiles a filter() call: keeps the elements of the list for which the named
+/// function returns a truthy value, in a new list
+pub fn compile_filter<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    func: &str,
+    list: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let list_obj = compiler.compile_expression(list)?;
+    build_list_transform(compiler, func, list_obj, true)
+}
+
+/// Walks the list calling `func` on each element. When `filter` is false the results
+/// are stored; otherwise the elements with a truthy result are kept and the length
+/// header is set to the number kept.
+fn build_list_transform<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    func: &str,
+    list_obj: IntValue<'ctx>,
+    filter: bool,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let (src_ptr, list_len) = compiler.extract_list_ptr_and_len(list_obj);
+    let dst_ptr = build_list_alloc(compiler, list_len)?;
+
+    let i64_type = compiler.context.i64_type();
+    let pyobject_type = compiler.create_pyobject_type();
+    let one = i64_type.const_int(1, false);
+    let name = if filter { "filter" } else { "map" };
+
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let i_ptr = compiler.create_entry_block_alloca(&format!("{}_i", name), current_fn);
+    let kept_ptr = compiler.create_entry_block_alloca(&format!("{}_kept", name), current_fn);
+    let cond_block = compiler
+        .context
+        .append_basic_block(current_fn, &format!("{}_cond", name));
+    let body_block = compiler
+        .context
+        .append_basic_block(current_fn, &format!("{}_body", name));
+    let done_block = compiler
+        .context
+        .append_basic_block(current_fn, &format!("{}_done", name));
+    compiler
+        .builder
+        .build_store(i_ptr, i64_type.const_zero())
+        .unwrap();
+    compiler
+        .builder
+        .build_store(kept_ptr, i64_type.const_zero())
+        .unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(cond_block);
+    let i = compiler
+        .builder
+        .build_load(i64_type, i_ptr, "i")
+        .unwrap()
+        .into_int_value();
+    let in_range = compiler
+        .builder
+        .build_int_compare(IntPredicate::SLT, i, list_len, "in_range")
+        .unwrap();
+    compiler
+        .builder
+        .build_conditional_branch(in_range, body_block, done_block)
+        .unwrap();
+
+    // Elements are offset by 1 for the length header
+    compiler.builder.position_at_end(body_block);
+    let src_index = compiler.builder.build_int_add(i, one, "src_index").unwrap();
+    let src_elem_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, src_ptr, &[src_index], "src_elem_ptr")
+            .unwrap()
+    };
+    let elem = compiler
+        .builder
+        .build_load(pyobject_type, src_elem_ptr, "elem")
+        .unwrap()
+        .into_int_value();
+    let result = build_apply(compiler, func, elem)?;
+
+    let kept = compiler
+        .builder
+        .build_load(i64_type, kept_ptr, "kept")
+        .unwrap()
+        .into_int_value();
+    let dst_index = compiler
+        .builder
+        .build_int_add(kept, one, "dst_index")
+        .unwrap();
+    let dst_elem_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, dst_ptr, &[dst_index], "dst_elem_ptr")
+            .unwrap()
+    };
+    let (stored, keep) = if filter {
+        (elem, compiler.pyobject_to_bool(result))
+    } else {
+        (result, compiler.context.bool_type().const_int(1, false))
+    };
+    // Always write the slot; it is only counted when the element is kept
+    compiler.builder.build_store(dst_elem_ptr, stored).unwrap();
+    let keep_count = compiler
+        .builder
+        .build_int_z_extend(keep, i64_type, "keep_count")
+        .unwrap();
+    let next_kept = compiler
+        .builder
+        .build_int_add(kept, keep_count, "next_kept")
+        .unwrap();
+    compiler.builder.build_store(kept_ptr, next_kept).unwrap();
+    let next_i = compiler.builder.build_int_add(i, one, "next_i").unwrap();
+    compiler.builder.build_store(i_ptr, next_i).unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(done_block);
+    if filter {
+        let kept = compiler
+            .builder
+            .build_load(i64_type, kept_ptr, "kept")
+            .unwrap();
+        compiler.builder.build_store(dst_ptr, kept).unwrap();
+    }
+    Ok(compiler.create_pyobject_list(dst_ptr, 0))
+}
+
+/// Applies the named function to one compiled argument, as map(), filter() and
+/// sorted(key=...) do
+///
+/// `abs` and `bool` are handled inline; any other name must be a user function that
+/// can be called with a single positional argument (its other parameters need
+/// defaults), or a variable holding a function value.
+fn build_apply<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    func: &str,
+    arg: IntValue<'ctx>,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    match func {
+        "bool" => {
+            let truthy = compiler.pyobject_to_bool(arg);
+            return Ok(compiler.create_pyobject_bool(truthy));
+        }
+        "abs" => {
+            let payload = compiler.extract_payload(arg);
+            let zero = compiler.context.f64_type().const_float(0.0);
+            let is_negative = compiler
+                .builder
+                .build_float_compare(FloatPredicate::OLT, payload, zero, "is_negative")
+                .unwrap();
+            let negated = compiler
+                .builder
+                .build_float_neg(payload, "negated")
+                .unwrap();
+            let magnitude = compiler
+                .builder
+                .build_select(is_negative, negated, payload, "abs")
+                .unwrap()
+                .into_float_value();
+            // abs() of a bool is an int; other numbers keep their tag
+            let i64_type = compiler.context.i64_type();
+            let tag = compiler.extract_tag(arg);
+            let is_bool = compiler
+                .builder
+                .build_int_compare(
+                    IntPredicate::EQ,
+                    tag,
+                    i64_type.const_int(TYPE_TAG_BOOL as u64, false),
+                    "is_bool",
+                )
+                .unwrap();
+            let tag = compiler
+                .builder
+                .build_select(
+                    is_bool,
+                    i64_type.const_int(TYPE_TAG_INT as u64, false),
+                    tag,
+                    "abs_tag",
+                )
+                .unwrap()
+                .into_int_value();
+            return Ok(compiler.create_pyobject_from_tag_and_payload(tag, magnitude));
+        }
+        _ => {}
+    }
+    if compiler.variables.contains_key(func) {
+        let callee = compile_variable(compiler, func)?;
+        return Ok(build_indirect_call(compiler, callee, &[arg.into()], func));
+    }
+
+    let function = *compiler
+        .functions
+        .get(func)
+        .ok_or_else(|| CodeGenError::UndefinedVariable(format!("function '{}'", func)))?;
+    let params = compiler
+        .function_params
+        .get(func)
+        .cloned()
+        .unwrap_or_default();
+    let defaults = compiler
+        .function_defaults
+        .get(func)
+        .cloned()
+        .unwrap_or_default();
+    let positional_count = compiler
+        .function_positional_counts
+        .get(func)
+        .copied()
+        .unwrap_or(params.len());
+    let has_kwargs = compiler.function_kwargs.contains(func);
+    let named_count = params.len() - usize::from(has_kwargs);
+    if positional_count == 0 {
+        return Err(CodeGenError::InvalidArguments(format!(
+            "function '{}' takes 0 positional arguments but 1 was given",
+            func
+        )));
+    }
+
+    let mut compiled_args: Vec<BasicMetadataValueEnum> = vec![arg.into()];
+    for (i, param) in params.iter().enumerate().take(named_count).skip(1) {
+        let Some(default_expr) = defaults.get(i).cloned().flatten() else {
+            return Err(CodeGenError::InvalidArguments(format!(
+                "function '{}' missing required argument '{}'",
+                func, param
+            )));
+        };
+        compiled_args.push(compiler.compile_default(func, i, &default_expr)?.into());
+    }
+    if has_kwargs {
+        compiled_args.push(build_dict(compiler, &[])?.into());
+    }
+
+    let call_result = compiler
+        .builder
+        .build_call(function, &compiled_args, &format!("{}_result", func))
+        .unwrap();
+    let value = match call_result.try_as_basic_value() {
+        inkwell::values::ValueKind::Basic(value) => value.into_int_value(),
+        inkwell::values::ValueKind::Instruction(_) => {
+            return Err(CodeGenError::UndefinedVariable(
+                "Function call did not return a value".to_string(),
+            ))
+        }
+    };
+    match compiler.function_return_type(func) {
+        ReturnType::Int => Ok(compiler.create_pyobject_int(value)),
+        ReturnType::PyObject => Ok(value),
+    }
+}
+
+/// Allocates an uninitialized list with room for `len` elements and stores its length header
+/// Layout: [length: i64][element_0: i64]...[element_n: i64]
+fn build_list_alloc<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    len: IntValue<'ctx>,
+) -> Result<inkwell::values::PointerValue<'ctx>, CodeGenError> {
+    let pyobject_type = compiler.create_pyobject_type();
+    let slot_count = compiler
+        .builder
+        .build_int_add(
+            len,
+            compiler.context.i64_type().const_int(1, false),
+            "slot_count",
+        )
+        .unwrap();
+    let total_size = compiler
+        .builder
+        .build_int_mul(pyobject_type.size_of(), slot_count, "list_size")
+        .unwrap();
+
+    let malloc_fn = compiler.runtime.add_malloc(&compiler.module);
+    let list_ptr_result = compiler
+        .builder
+        .build_call(malloc_fn, &[total_size.into()], "malloc_list")
+        .unwrap();
+    let list_ptr = match list_ptr_result.try_as_basic_value() {
+        inkwell::values::ValueKind::Basic(value) => value.into_pointer_value(),
+        _ => {
+            return Err(CodeGenError::UndefinedVariable(
+                "malloc did not return a value".to_string(),
+            ))
+        }
+    };
+    compiler.builder.build_store(list_ptr, len).unwrap();
+    Ok(list_ptr)
+}
+
+/// Concatenates two lists into a new allocation whose length header is the sum of
+/// both lengths. The operands are left unchanged.
+fn build_list_concat<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    lhs_obj: IntValue<'ctx>,
+    rhs_obj: IntValue<'ctx>,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let (lhs_ptr, lhs_len) = compiler.extract_list_ptr_and_len(lhs_obj);
+    let (rhs_ptr, rhs_len) = compiler.extract_list_ptr_and_len(rhs_obj);
+    let total_len = compiler
+        .builder
+        .build_int_add(lhs_len, rhs_len, "concat_len")
+        .unwrap();
+    let dst_ptr = build_list_alloc(compiler, total_len)?;
+
+    let i64_type = compiler.context.i64_type();
+    let pyobject_type = compiler.create_pyobject_type();
+    let one = i64_type.const_int(1, false);
+    let memcpy_fn = compiler.runtime.add_memcpy(&compiler.module);
+
+    // Elements start after the length header: lhs goes to slot 1, rhs to slot lhs_len + 1
+    let rhs_start = compiler
+        .builder
+        .build_int_add(lhs_len, one, "rhs_start")
+        .unwrap();
+    for (src_ptr, len, dst_start, name) in [
+        (lhs_ptr, lhs_len, one, "lhs"),
+        (rhs_ptr, rhs_len, rhs_start, "rhs"),
+    ] {
+        let src_elems = unsafe {
+            compiler
+                .builder
+                .build_in_bounds_gep(pyobject_type, src_ptr, &[one], &format!("{name}_elems"))
+                .unwrap()
+        };
+        let dst_elems = unsafe {
+            compiler
+                .builder
+                .build_in_bounds_gep(
+                    pyobject_type,
+                    dst_ptr,
+                    &[dst_start],
+                    &format!("{name}_dest"),
+                )
+                .unwrap()
+        };
+        let byte_count = compiler
+            .builder
+            .build_int_mul(pyobject_type.size_of(), len, &format!("{name}_bytes"))
+            .unwrap();
+        compiler
+            .builder
+            .build_call(
+                memcpy_fn,
+                &[dst_elems.into(), src_elems.into(), byte_count.into()],
+                &format!("memcpy_{name}_elems"),
+            )
+            .unwrap();
+    }
+
+    Ok(compiler.create_pyobject_list(dst_ptr, 0))
+}
+
+/// Compiles a len() expression for strings and lists
+pub fn compile_len<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    arg: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let arg_obj = compiler.compile_expression(arg)?;
+    let arg_tag = compiler.extract_tag(arg_obj);
+
+    // Check if the argument is a string or list
+    let string_tag_const = compiler
+        .context
+        .i64_type()
+        .const_int(TYPE_TAG_STRING as u64, false);
+    let list_tag_const = compiler
+        .context
+        .i64_type()
+        .const_int(TYPE_TAG_LIST as u64, false);
+
+    let is_string = compiler
+        .builder
+        .build_int_compare(
+            inkwell::IntPredicate::EQ,
+            arg_tag,
+            string_tag_const,
+            "is_string",
+        )
+        .unwrap();
+    let is_list = compiler
+        .builder
+        .build_int_compare(
+            inkwell::IntPredicate::EQ,
+            arg_tag,
+            list_tag_const,
+            "is_list",
+        )
+        .unwrap();
+    // Tuples share the list layout, so they take the list path
+    let tuple_tag_const = compiler
+        .context
+        .i64_type()
+        .const_int(TYPE_TAG_TUPLE as u64, false);
+    let is_tuple = compiler
+        .builder
+        .build_int_compare(
+            inkwell::IntPredicate::EQ,
+            arg_tag,
+            tuple_tag_const,
+            "is_tuple",
+        )
+        .unwrap();
+    let is_list = compiler
+        .builder
+        .build_or(is_list, is_tuple, "is_list_or_tuple")
+        .unwrap();
+    // Dicts also keep their length in the header slot
+    let dict_tag_const = compiler
+        .context
+        .i64_type()
+        .const_int(TYPE_TAG_DICT as u64, false);
+    let is_dict = compiler
+        .builder
+        .build_int_compare(
+            inkwell::IntPredicate::EQ,
+            arg_tag,
+            dict_tag_const,
+            "is_dict",
+        )
+        .unwrap();
+    let is_list = compiler
+        .builder
+        .build_or(is_list, is_dict, "is_sized_container")
+        .unwrap();
+    // Sets share the dict layout
+    let set_tag_const = compiler
+        .context
+        .i64_type()
+        .const_int(TYPE_TAG_SET as u64, false);
+    let is_set = compiler
+        .builder
+        .build_int_compare(inkwell::IntPredicate::EQ, arg_tag, set_tag_const, "is_set")
+        .unwrap();
+    let is_list = compiler
+        .builder
+        .build_or(is_list, is_set, "is_sized_container")
+        .unwrap();
+
+    // Get current function for creating basic blocks
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+
+    let string_len_block = compiler
+        .context
+        .append_basic_block(current_fn, "string_len");
+    let list_len_block = compiler.context.append_basic_block(current_fn, "list_len");
+    let other_len_block = compiler.context.append_basic_block(current_fn, "other_len");
+    let merge_block = compiler.context.append_basic_block(current_fn, "len_merge");
+
+    // Branch: is_string ? string_len : check_list
+    let check_list_block = compiler
+        .context
+        .append_basic_block(current_fn, "check_list");
+    compiler
+        .builder
+        .build_conditional_branch(is_string, string_len_block, check_list_block)
+        .unwrap();
+
+    // Check if it's a list
+    compiler.builder.position_at_end(check_list_block);
+    compiler
+        .builder
+        .build_conditional_branch(is_list, list_len_block, other_len_block)
+        .unwrap();
+
+    // String length block
+    compiler.builder.position_at_end(string_len_block);
+    let str_ptr = compiler.extract_string_ptr(arg_obj);
+    let strlen_fn = compiler.runtime.add_strlen(&compiler.module);
+    let len_result = compiler
+        .builder
+        .build_call(strlen_fn, &[str_ptr.into()], "strlen")
+        .unwrap();
+    let len_int = match len_result.try_as_basic_value() {
+        inkwell::values::ValueKind::Basic(value) => value.into_int_value(),
+        _ => {
+            return Err(CodeGenError::UndefinedVariable(
+                "strlen did not return a value".to_string(),
+            ))
+        }
+    };
+    let string_len_result = compiler.create_pyobject_int(len_int);
+    compiler
+        .builder
+        .build_unconditional_branch(merge_block)
+        .unwrap();
+
+    // List length block
+    compiler.builder.position_at_end(list_len_block);
+    let (_list_ptr, list_len) = compiler.extract_list_ptr_and_len(arg_obj);
+    let list_len_result = compiler.create_pyobject_int(list_len);
+    compiler
+        .builder
+        .build_unconditional_branch(merge_block)
+        .unwrap();
+
+    // Other types - return 0 for now
+    compiler.builder.position_at_end(other_len_block);
+    let zero_int = compiler.context.i64_type().const_int(0, false);
+    let other_len_result = compiler.create_pyobject_int(zero_int);
+    compiler
+        .builder
+        .build_unconditional_branch(merge_block)
+        .unwrap();
+
+    // Merge block
+    compiler.builder.position_at_end(merge_block);
+    let pyobject_type = compiler.create_pyobject_type();
+    let phi = compiler
+        .builder
+        .build_phi(pyobject_type, "len_result")
+        .unwrap();
+    phi.add_incoming(&[
+        (&string_len_result, string_len_block),
+        (&list_len_result, list_len_block),
+        (&other_len_result, other_len_block),
+    ]);
+    Ok(phi.as_basic_value().into_int_value())
+}
+
+// ============================================================================
+// Input/Output Operations
+// ============================================================================
+
+/// Compiles an input() expression for reading user input
+///
+/// Reads a line and parses it as a number: an int when it is written as one (`5`,
+/// `-3`), a float otherwise (`5.5`, `1e3`, `inf`). A line that does not start with a
+/// number raises a ValueError, and reading past the end of input an EOFError. With
+/// string input enabled the line itself is returned.
+pub fn compile_input<'ctx>(compiler: &mut Compiler<'ctx>) -> Result<IntValue<'ctx>, CodeGenError> {
+    if compiler.string_input {
+        return compile_input_line(compiler);
+    }
+    let (line_ptr, at_eof) = build_read_line(compiler)?;
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let end_slot = compiler.create_entry_block_alloca("input_end", current_fn);
+    let ptr_type = compiler.context.ptr_type(inkwell::AddressSpace::default());
+
+    // Both parsers stop where their number ends; the int reading is only taken when
+    // it covers as much text as the float one, i.e. there is no fraction or exponent
+    let strtod = compiler.runtime.add_strtod(&compiler.module);
+    let float_value = build_libc_call(
+        compiler,
+        strtod,
+        &[line_ptr.into(), end_slot.into()],
+        "input_float",
+    )?
+    .into_float_value();
+    let float_end = compiler
+        .builder
+        .build_load(ptr_type, end_slot, "input_float_end")
+        .unwrap()
+        .into_pointer_value();
+    let strtoll = compiler.runtime.add_strtoll(&compiler.module);
+    let base = compiler.context.i32_type().const_int(10, false);
+    let int_value = build_libc_call(
+        compiler,
+        strtoll,
+        &[line_ptr.into(), end_slot.into(), base.into()],
+        "input_int",
+    )?
+    .into_int_value();
+    let int_end = compiler
+        .builder
+        .build_load(ptr_type, end_slot, "input_int_end")
+        .unwrap()
+        .into_pointer_value();
+
+    let no_number = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::EQ,
+            compiler
+                .builder
+                .build_ptr_to_int(float_end, compiler.context.i64_type(), "float_end")
+                .unwrap(),
+            compiler
+                .builder
+                .build_ptr_to_int(line_ptr, compiler.context.i64_type(), "line_start")
+                .unwrap(),
+            "no_number",
+        )
+        .unwrap();
+    let free = compiler.runtime.add_free(&compiler.module);
+    let is_int = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::EQ,
+            compiler
+                .builder
+                .build_ptr_to_int(int_end, compiler.context.i64_type(), "int_end")
+                .unwrap(),
+            compiler
+                .builder
+                .build_ptr_to_int(float_end, compiler.context.i64_type(), "float_end")
+                .unwrap(),
+            "input_is_int",
+        )
+        .unwrap();
+    compiler
+        .builder
+        .build_call(free, &[line_ptr.into()], "")
+        .unwrap();
+    compiler.build_raise_if(at_eof, "EOFError: EOF when reading a line");
+    compiler.build_raise_if(no_number, "ValueError: input is not a number");
+
+    let int_obj = compiler.create_pyobject_int(int_value);
+    let float_obj = compiler.create_pyobject_float(float_value);
+    Ok(compiler
+        .builder
+        .build_select(is_int, int_obj, float_obj, "input_value")
+        .unwrap()
+        .into_int_value())
+}
+
+/// Reads one line of stdin into a new heap string, dropping the newline. An empty
+/// line yields the empty string; end of input raises an EOFError.
+fn compile_input_line<'ctx>(compiler: &mut Compiler<'ctx>) -> Result<IntValue<'ctx>, CodeGenError> {
+    let (line_ptr, at_eof) = build_read_line(compiler)?;
+    compiler.track_string_in_arena(line_ptr);
+    compiler.build_raise_if(at_eof, "EOFError: EOF when reading a line");
+    Ok(compiler.create_pyobject_string(line_ptr))
+}
+
+/// Reads one line of stdin, without its newline, into a new `INPUT_LINE_SIZE` heap
+/// buffer owned by the caller. Also returns whether stdin was already at its end.
+fn build_read_line<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+) -> Result<(PointerValue<'ctx>, IntValue<'ctx>), CodeGenError> {
+    let scanf = compiler.runtime.add_scanf(&compiler.module);
+    let malloc_fn = compiler.runtime.add_malloc(&compiler.module);
+    let buffer_size = compiler
+        .context
+        .i64_type()
+        .const_int(INPUT_LINE_SIZE, false);
+    let line_ptr = build_libc_call(compiler, malloc_fn, &[buffer_size.into()], "input_line")?
+        .into_pointer_value();
+    // scanf leaves the buffer untouched when the line is empty
+    compiler
+        .builder
+        .build_store(line_ptr, compiler.context.i8_type().const_zero())
+        .unwrap();
+
+    let line_format = compiler
+        .format_strings
+        .get_scanf_line_format_string(&compiler.builder);
+    let matched = compiler
+        .builder
+        .build_call(scanf, &[line_format.into(), line_ptr.into()], "scanf_line")
+        .unwrap()
+        .try_as_basic_value()
+        .basic()
+        .unwrap()
+        .into_int_value();
+    // scanf returns EOF (-1) when nothing is left to read, 0 for an empty line
+    let at_eof = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::SLT,
+            matched,
+            compiler.context.i32_type().const_zero(),
+            "input_at_eof",
+        )
+        .unwrap();
+    // Consume the newline so the next input() starts on the following line
+    let skip_format = compiler
+        .format_strings
+        .get_scanf_skip_char_format_string(&compiler.builder);
+    compiler
+        .builder
+        .build_call(scanf, &[skip_format.into()], "scanf_newline")
+        .unwrap();
+    Ok((line_ptr, at_eof))
+}
+
+// ============================================================================
+// File Operations
+// ============================================================================
+
+/// Compiles an open() call: opens the file with C `fopen` and wraps the `FILE*`
+/// in a file object. The object points to a heap cell holding the `FILE*`, so that
+/// `close()` can null it for every copy of the object. Raises an OSError if the file
+/// cannot be opened.
+pub fn compile_open<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    path: &IRExpr,
+    mode: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let path_obj = compiler.compile_expression(path)?;
+    let mode_obj = compiler.compile_expression(mode)?;
+    let path_is_string = build_tag_check(compiler, path_obj, TYPE_TAG_STRING, "path_is_string");
+    let mode_is_string = build_tag_check(compiler, mode_obj, TYPE_TAG_STRING, "mode_is_string");
+    let both_strings = compiler
+        .builder
+        .build_and(path_is_string, mode_is_string, "open_args_are_strings")
+        .unwrap();
+    let not_strings = compiler
+        .builder
+        .build_not(both_strings, "open_args_not_strings")
+        .unwrap();
+    compiler.build_raise_if(
+        not_strings,
+        "TypeError: open() path and mode must be strings",
+    );
+
+    let path_ptr = compiler.extract_string_ptr(path_obj);
+    let mode_ptr = compiler.extract_string_ptr(mode_obj);
+    let fopen_fn = compiler.runtime.add_fopen(&compiler.module);
+    let file_ptr = build_libc_call(
+        compiler,
+        fopen_fn,
+        &[path_ptr.into(), mode_ptr.into()],
+        "fopen",
+    )?
+    .into_pointer_value();
+    let open_failed = compiler
+        .builder
+        .build_is_null(file_ptr, "open_failed")
+        .unwrap();
+    compiler.build_raise_if(open_failed, "OSError: could not open file");
+
+    let malloc_fn = compiler.runtime.add_malloc(&compiler.module);
+    let cell_size = compiler.context.i64_type().const_int(8, false);
+    let cell = build_libc_call(compiler, malloc_fn, &[cell_size.into()], "file_cell")?
+        .into_pointer_value();
+    compiler.builder.build_store(cell, file_ptr).unwrap();
+    Ok(compiler.create_pyobject_file(cell))
+}
+
+/// Compiles a method call `object.method(args...)`.
+/// Supported methods:
+/// - `file.read()`: reads the rest of the file into a string
+/// - `file.write(s)`: writes a string, returning the number of characters written
+/// - `file.close()`: closes the file, returning None; closing it again does nothing
+///
+/// Reading or writing a closed file raises a ValueError.
+pub fn compile_method_call<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    object: &IRExpr,
+    method: &str,
+    args: &[IRExpr],
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    if matches!(
+        method,
+        "strip" | "lstrip" | "rstrip" | "replace" | "find" | "startswith" | "endswith" | "split"
+    ) {
+        return compile_string_method(compiler, object, method, args);
+    }
+    if matches!(method, "keys" | "values" | "items" | "get") {
+        return compile_dict_method(compiler, object, method, args);
+    }
+    if matches!(method, "count" | "index") {
+        return compile_search_method(compiler, object, method, args);
+    }
+
+    let expected_args = match method {
+        "read" | "close" => 0,
+        "write" => 1,
+        _ => return Err(CodeGenError::UnsupportedMethod(method.to_string())),
+    };
+    if args.len() != expected_args {
+        return Err(CodeGenError::InvalidArguments(format!(
+            "{}() takes {} arguments but {} were given",
+            method,
+            expected_args,
+            args.len()
+        )));
+    }
+
+    let object_obj = compiler.compile_expression(object)?;
+    let is_file = build_tag_check(compiler, object_obj, TYPE_TAG_FILE, "is_file");
+    let not_file = compiler.builder.build_not(is_file, "not_file").unwrap();
+    compiler.build_raise_if(
+        not_file,
+        &format!("AttributeError: object has no attribute '{}'", method),
+    );
+    let file_cell = compiler.extract_file_cell(object_obj);
+    let ptr_type = compiler.context.ptr_type(inkwell::AddressSpace::default());
+    let file_ptr = compiler
+        .builder
+        .build_load(ptr_type, file_cell, "file_ptr")
+        .unwrap()
+        .into_pointer_value();
+    let is_closed = compiler
+        .builder
+        .build_is_null(file_ptr, "file_is_closed")
+        .unwrap();
+    if method != "close" {
+        compiler.build_raise_if(is_closed, "ValueError: I/O operation on closed file");
+    }
+
+    match method {
+        "read" => build_file_read(compiler, file_ptr),
+        "write" => {
+            let text_obj = compiler.compile_expression(&args[0])?;
+            let is_string = build_tag_check(compiler, text_obj, TYPE_TAG_STRING, "is_string");
+            let not_string = compiler.builder.build_not(is_string, "not_string").unwrap();
+            compiler.build_raise_if(not_string, "TypeError: write() argument must be str");
+
+            let text_ptr = compiler.extract_string_ptr(text_obj);
+            let fputs_fn = compiler.runtime.add_fputs(&compiler.module);
+            build_libc_call(
+                compiler,
+                fputs_fn,
+                &[text_ptr.into(), file_ptr.into()],
+                "fputs",
+            )?;
+            let strlen_fn = compiler.runtime.add_strlen(&compiler.module);
+            let written = build_libc_call(compiler, strlen_fn, &[text_ptr.into()], "written")?
+                .into_int_value();
+            Ok(compiler.create_pyobject_int(written))
+        }
+        _ => {
+            let current_fn = compiler
+                .builder
+                .get_insert_block()
+                .unwrap()
+                .get_parent()
+                .unwrap();
+            let close_block = compiler
+                .context
+                .append_basic_block(current_fn, "close_file");
+            let done_block = compiler
+                .context
+                .append_basic_block(current_fn, "file_closed");
+            compiler
+                .builder
+                .build_conditional_branch(is_closed, done_block, close_block)
+                .unwrap();
+
+            compiler.builder.position_at_end(close_block);
+            let fclose_fn = compiler.runtime.add_fclose(&compiler.module);
+            build_libc_call(compiler, fclose_fn, &[file_ptr.into()], "fclose")?;
+            compiler
+                .builder
+                .build_store(file_cell, ptr_type.const_null())
+                .unwrap();
+            compiler
+                .builder
+                .build_unconditional_branch(done_block)
+                .unwrap();
+
+            compiler.builder.position_at_end(done_block);
+            Ok(compiler.create_pyobject_none())
+        }
+    }
+}
+
+/// Compiles a dict method:
+/// - `d.keys()`, `d.values()` and `d.items()` are new lists of the keys, the values
+///   or `(key, value)` tuples, in insertion order
+/// - `d.get(key)` and `d.get(key, default)` are the value for `key`, or the default
+///   (None if not given) when no key matches
+fn compile_dict_method<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    object: &IRExpr,
+    method: &str,
+    args: &[IRExpr],
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let (min_args, max_args) = if method == "get" { (1, 2) } else { (0, 0) };
+    if args.len() < min_args || args.len() > max_args {
+        let expected = if min_args == max_args {
+            min_args.to_string()
+        } else {
+            format!("{} or {}", min_args, max_args)
+        };
+        return Err(CodeGenError::InvalidArguments(format!(
+            "{}() takes {} arguments but {} were given",
+            method,
+            expected,
+            args.len()
+        )));
+    }
+
+    let object_obj = compiler.compile_expression(object)?;
+    let is_dict = build_tag_check(compiler, object_obj, TYPE_TAG_DICT, "is_dict");
+    let not_dict = compiler.builder.build_not(is_dict, "not_dict").unwrap();
+    compiler.build_raise_if(
+        not_dict,
+        &format!("AttributeError: object has no attribute '{}'", method),
+    );
+    if method == "get" {
+        let key_obj = compiler.compile_expression(&args[0])?;
+        let default_obj = match args.get(1) {
+            Some(default) => compiler.compile_expression(default)?,
+            None => compiler.create_pyobject_none(),
+        };
+        return build_dict_get(compiler, object_obj, key_obj, default_obj);
+    }
+    let (entries_ptr, dict_len) = compiler.extract_dict_entries(object_obj);
+    let dst_ptr = build_list_alloc(compiler, dict_len)?;
+
+    let i64_type = compiler.context.i64_type();
+    let pyobject_type = compiler.create_pyobject_type();
+    let one = i64_type.const_int(1, false);
+    let two = i64_type.const_int(2, false);
+
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let entry_block = compiler.builder.get_insert_block().unwrap();
+    let cond_block = compiler
+        .context
+        .append_basic_block(current_fn, "dict_view_cond");
+    let body_block = compiler
+        .context
+        .append_basic_block(current_fn, "dict_view_body");
+    let done_block = compiler
+        .context
+        .append_basic_block(current_fn, "dict_view_done");
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(cond_block);
+    let counter = compiler.builder.build_phi(i64_type, "dict_view_i").unwrap();
+    let i = counter.as_basic_value().into_int_value();
+    let in_range = compiler
+        .builder
+        .build_int_compare(IntPredicate::SLT, i, dict_len, "dict_view_in_range")
+        .unwrap();
+    compiler
+        .builder
+        .build_conditional_branch(in_range, body_block, done_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(body_block);
+    let key_slot = compiler.builder.build_int_mul(i, two, "key_slot").unwrap();
+    let value_slot = compiler
+        .builder
+        .build_int_add(key_slot, one, "value_slot")
+        .unwrap();
+    let load_slot = |slot: IntValue<'ctx>, name: &str| {
+        let slot_ptr = unsafe {
+            compiler
+                .builder
+                .build_in_bounds_gep(pyobject_type, entries_ptr, &[slot], &format!("{name}_ptr"))
+                .unwrap()
+        };
+        compiler
+            .builder
+            .build_load(pyobject_type, slot_ptr, name)
+            .unwrap()
+            .into_int_value()
+    };
+    let key = load_slot(key_slot, "key");
+    let value = load_slot(value_slot, "value");
+    let elem = match method {
+        "keys" => key,
+        "values" => value,
+        _ => {
+            let pair_ptr = build_list_alloc(compiler, two)?;
+            for (offset, item, name) in [(1, key, "pair_key_ptr"), (2, value, "pair_value_ptr")] {
+                let pair_elem_ptr = unsafe {
+                    compiler
+                        .builder
+                        .build_in_bounds_gep(
+                            pyobject_type,
+                            pair_ptr,
+                            &[i64_type.const_int(offset, false)],
+                            name,
+                        )
+                        .unwrap()
+                };
+                compiler.builder.build_store(pair_elem_ptr, item).unwrap();
+            }
+            compiler.create_pyobject_tuple(pair_ptr)
+        }
+    };
+    let dst_slot = compiler.builder.build_int_add(i, one, "dst_slot").unwrap();
+    let dst_elem_ptr = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, dst_ptr, &[dst_slot], "dst_elem_ptr")
+            .unwrap()
+    };
+    compiler.builder.build_store(dst_elem_ptr, elem).unwrap();
+    let next_i = compiler
+        .builder
+        .build_int_add(i, one, "dict_view_next_i")
+        .unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+    counter.add_incoming(&[(&i64_type.const_zero(), entry_block), (&next_i, body_block)]);
+
+    compiler.builder.position_at_end(done_block);
+    Ok(compiler.create_pyobject_list(dst_ptr, 0))
+}
+
+/// Compiles a method that searches a string, list or tuple for `item`:
+/// - `x.count(item)` is the number of non-overlapping occurrences of a substring in a
+///   string, or of elements equal to `item` in a list or tuple
+/// - `x.index(item)` is the index of the first occurrence, raising a `ValueError`
+///   when there is none
+fn compile_search_method<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    object: &IRExpr,
+    method: &str,
+    args: &[IRExpr],
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    if args.len() != 1 {
+        return Err(CodeGenError::InvalidArguments(format!(
+            "{}() takes 1 arguments but {} were given",
+            method,
+            args.len()
+        )));
+    }
+
+    let object_obj = compiler.compile_expression(object)?;
+    let item_obj = compiler.compile_expression(&args[0])?;
+    let is_string = build_tag_check(compiler, object_obj, TYPE_TAG_STRING, "is_string");
+    let is_list = build_tag_check(compiler, object_obj, TYPE_TAG_LIST, "is_list");
+    let is_tuple = build_tag_check(compiler, object_obj, TYPE_TAG_TUPLE, "is_tuple");
+    let is_sequence = compiler
+        .builder
+        .build_or(is_list, is_tuple, "is_sequence")
+        .unwrap();
+    let countable = compiler
+        .builder
+        .build_or(is_sequence, is_string, "countable")
+        .unwrap();
+    let not_countable = compiler
+        .builder
+        .build_not(countable, "not_countable")
+        .unwrap();
+    compiler.build_raise_if(
+        not_countable,
+        &format!("AttributeError: object has no attribute '{}'", method),
+    );
+
+    let item_is_string = build_tag_check(compiler, item_obj, TYPE_TAG_STRING, "item_is_string");
+    let item_not_string = compiler
+        .builder
+        .build_not(item_is_string, "item_not_string")
+        .unwrap();
+    let bad_substring = compiler
+        .builder
+        .build_and(is_string, item_not_string, "bad_substring")
+        .unwrap();
+    compiler.build_raise_if(
+        bad_substring,
+        &format!("TypeError: {}() argument must be str", method),
+    );
+
+    if method == "index" {
+        let i64_type = compiler.context.i64_type();
+        let find_item = compiler.get_or_build_find_item();
+        let position = build_libc_call(
+            compiler,
+            find_item,
+            &[item_obj.into(), object_obj.into()],
+            "position",
+        )?
+        .into_int_value();
+        let missing = compiler
+            .builder
+            .build_int_compare(
+                IntPredicate::SLT,
+                position,
+                i64_type.const_zero(),
+                "missing",
+            )
+            .unwrap();
+        let missing_substring = compiler
+            .builder
+            .build_and(missing, is_string, "missing_substring")
+            .unwrap();
+        compiler.build_raise_if(missing_substring, "ValueError: substring not found");
+        let missing_element = compiler
+            .builder
+            .build_and(missing, is_tuple, "missing_element")
+            .unwrap();
+        compiler.build_raise_if(
+            missing_element,
+            "ValueError: tuple.index(x): x not in tuple",
+        );
+        compiler.build_raise_if(missing, "ValueError: list.index(x): x not in list");
+        return Ok(compiler.create_pyobject_int(position));
+    }
+
+    let count_item = compiler.get_or_build_count_item();
+    let count = build_libc_call(
+        compiler,
+        count_item,
+        &[item_obj.into(), object_obj.into()],
+        "count",
+    )?
+    .into_int_value();
+    Ok(compiler.create_pyobject_int(count))
+}
+
+/// Looks `key` up in a dict, branching to `default` instead of loading a value when
+/// no key matches
+fn build_dict_get<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    dict_obj: IntValue<'ctx>,
+    key_obj: IntValue<'ctx>,
+    default_obj: IntValue<'ctx>,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let i64_type = compiler.context.i64_type();
+    let pyobject_type = compiler.create_pyobject_type();
+    let find_item = compiler.get_or_build_find_item();
+    let position = build_libc_call(
+        compiler,
+        find_item,
+        &[key_obj.into(), dict_obj.into()],
+        "key_position",
+    )?
+    .into_int_value();
+    let found = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::SGE,
+            position,
+            i64_type.const_zero(),
+            "key_found",
+        )
+        .unwrap();
+
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let lookup_block = compiler.builder.get_insert_block().unwrap();
+    let found_block = compiler.context.append_basic_block(current_fn, "get_found");
+    let merge_block = compiler.context.append_basic_block(current_fn, "get_merge");
+    compiler
+        .builder
+        .build_conditional_branch(found, found_block, merge_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(found_block);
+    let value_ptr = build_entry_value_ptr(compiler, dict_obj, position);
+    let value = compiler
+        .builder
+        .build_load(pyobject_type, value_ptr, "value")
+        .unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(merge_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(merge_block);
+    let phi = compiler
+        .builder
+        .build_phi(pyobject_type, "get_result")
+        .unwrap();
+    phi.add_incoming(&[(&value, found_block), (&default_obj, lookup_block)]);
+    Ok(phi.as_basic_value().into_int_value())
+}
+
+/// Compiles a string method:
+/// - `text.strip()`, `.lstrip()` and `.rstrip()` drop leading and/or trailing ASCII
+///   whitespace
+/// - `text.replace(old, new)` replaces every non-overlapping `old`; an empty `old`
+///   leaves the text unchanged
+/// - `text.find(sub)` is the byte index of the first `sub`, or -1
+/// - `text.startswith(prefix)` and `text.endswith(suffix)` are bools
+/// - `text.split(sep)` is a new list of the pieces between each `sep`; an empty
+///   `sep` raises a ValueError
+fn compile_string_method<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    object: &IRExpr,
+    method: &str,
+    args: &[IRExpr],
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let expected_args = match method {
+        "replace" => 2,
+        "find" | "startswith" | "endswith" | "split" => 1,
+        _ => 0,
+    };
+    if args.len() != expected_args {
+        return Err(CodeGenError::InvalidArguments(format!(
+            "{}() takes {} arguments but {} were given",
+            method,
+            expected_args,
+            args.len()
+        )));
+    }
+
+    let object_obj = compiler.compile_expression(object)?;
+    let is_string = build_tag_check(compiler, object_obj, TYPE_TAG_STRING, "is_string");
+    let not_string = compiler.builder.build_not(is_string, "not_string").unwrap();
+    compiler.build_raise_if(
+        not_string,
+        &format!("AttributeError: object has no attribute '{}'", method),
+    );
+    let text_ptr = compiler.extract_string_ptr(object_obj);
+    let mut arg_ptrs = Vec::new();
+    for arg in args {
+        let arg_obj = compiler.compile_expression(arg)?;
+        let is_string = build_tag_check(compiler, arg_obj, TYPE_TAG_STRING, "arg_is_string");
+        let not_string = compiler
+            .builder
+            .build_not(is_string, "arg_not_string")
+            .unwrap();
+        let plural = if args.len() > 1 { "s" } else { "" };
+        compiler.build_raise_if(
+            not_string,
+            &format!("TypeError: {}() argument{} must be str", method, plural),
+        );
+        arg_ptrs.push(compiler.extract_string_ptr(arg_obj));
+    }
+
+    let i64_type = compiler.context.i64_type();
+    match method {
+        "find" => {
+            let strstr = compiler.runtime.add_strstr(&compiler.module);
+            let found = build_libc_call(
+                compiler,
+                strstr,
+                &[text_ptr.into(), arg_ptrs[0].into()],
+                "found",
+            )?
+            .into_pointer_value();
+            let missing = compiler.builder.build_is_null(found, "missing").unwrap();
+            let found_addr = compiler
+                .builder
+                .build_ptr_to_int(found, i64_type, "found_addr")
+                .unwrap();
+            let text_addr = compiler
+                .builder
+                .build_ptr_to_int(text_ptr, i64_type, "text_addr")
+                .unwrap();
+            let offset = compiler
+                .builder
+                .build_int_sub(found_addr, text_addr, "offset")
+                .unwrap();
+            let index = compiler
+                .builder
+                .build_select(missing, i64_type.const_all_ones(), offset, "find_index")
+                .unwrap()
+                .into_int_value();
+            return Ok(compiler.create_pyobject_int(index));
+        }
+        "startswith" | "endswith" => {
+            let strlen = compiler.runtime.add_strlen(&compiler.module);
+            let affix_len = build_libc_call(compiler, strlen, &[arg_ptrs[0].into()], "affix_len")?
+                .into_int_value();
+            let order = if method == "startswith" {
+                let strncmp = compiler.runtime.add_strncmp(&compiler.module);
+                build_libc_call(
+                    compiler,
+                    strncmp,
+                    &[text_ptr.into(), arg_ptrs[0].into(), affix_len.into()],
+                    "prefix_order",
+                )?
+            } else {
+                // Compare the last affix_len bytes; a suffix longer than the text is
+                // compared from the start instead, which can't match
+                let text_len = build_libc_call(compiler, strlen, &[text_ptr.into()], "text_len")?
+                    .into_int_value();
+                let too_long = compiler
+                    .builder
+                    .build_int_compare(IntPredicate::UGT, affix_len, text_len, "too_long")
+                    .unwrap();
+                let tail_start = compiler
+                    .builder
+                    .build_int_sub(text_len, affix_len, "tail_start")
+                    .unwrap();
+                let tail_start = compiler
+                    .builder
+                    .build_select(too_long, i64_type.const_zero(), tail_start, "tail_start")
+                    .unwrap()
+                    .into_int_value();
+                let tail = unsafe {
+                    compiler
+                        .builder
+                        .build_gep(compiler.context.i8_type(), text_ptr, &[tail_start], "tail")
+                        .unwrap()
+                };
+                let strcmp = compiler.runtime.add_strcmp(&compiler.module);
+                build_libc_call(
+                    compiler,
+                    strcmp,
+                    &[tail.into(), arg_ptrs[0].into()],
+                    "suffix_order",
+                )?
+            }
+            .into_int_value();
+            let matches = compiler
+                .builder
+                .build_int_compare(
+                    IntPredicate::EQ,
+                    order,
+                    order.get_type().const_zero(),
+                    "affix_matches",
+                )
+                .unwrap();
+            return Ok(compiler.create_pyobject_bool(matches));
+        }
+        "split" => {
+            let first_byte = compiler
+                .builder
+                .build_load(compiler.context.i8_type(), arg_ptrs[0], "sep_first_byte")
+                .unwrap()
+                .into_int_value();
+            let sep_is_empty = compiler
+                .builder
+                .build_int_compare(
+                    IntPredicate::EQ,
+                    first_byte,
+                    first_byte.get_type().const_zero(),
+                    "sep_is_empty",
+                )
+                .unwrap();
+            compiler.build_raise_if(sep_is_empty, "ValueError: empty separator");
+            let split = compiler.get_or_build_split();
+            return Ok(build_libc_call(
+                compiler,
+                split,
+                &[text_ptr.into(), arg_ptrs[0].into()],
+                "pieces",
+            )?
+            .into_int_value());
+        }
+        _ => {}
+    }
+
+    let result = if method == "replace" {
+        let replace = compiler.get_or_build_replace();
+        build_libc_call(
+            compiler,
+            replace,
+            &[text_ptr.into(), arg_ptrs[0].into(), arg_ptrs[1].into()],
+            "replaced",
+        )?
+    } else {
+        let bool_type = compiler.context.bool_type();
+        let left = bool_type.const_int(u64::from(method != "rstrip"), false);
+        let right = bool_type.const_int(u64::from(method != "lstrip"), false);
+        let strip = compiler.get_or_build_strip();
+        build_libc_call(
+            compiler,
+            strip,
+            &[text_ptr.into(), left.into(), right.into()],
+            "stripped",
+        )?
+    }
+    .into_pointer_value();
+
+    compiler.track_string_in_arena(result);
+    Ok(compiler.create_pyobject_string(result))
+}
+
+/// Reads from the current position to the end of a file into a new string.
+/// The remaining size is measured with ftell/fseek before a single fread.
+fn build_file_read<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    file_ptr: inkwell::values::PointerValue<'ctx>,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let i32_type = compiler.context.i32_type();
+    let i64_type = compiler.context.i64_type();
+    let ftell_fn = compiler.runtime.add_ftell(&compiler.module);
+    let fseek_fn = compiler.runtime.add_fseek(&compiler.module);
+
+    // SEEK_SET = 0, SEEK_END = 2
+    let start =
+        build_libc_call(compiler, ftell_fn, &[file_ptr.into()], "read_start")?.into_int_value();
+    build_libc_call(
+        compiler,
+        fseek_fn,
+        &[
+            file_ptr.into(),
+            i64_type.const_zero().into(),
+            i32_type.const_int(2, false).into(),
+        ],
+        "seek_end",
+    )?;
+    let end = build_libc_call(compiler, ftell_fn, &[file_ptr.into()], "read_end")?.into_int_value();
+    build_libc_call(
+        compiler,
+        fseek_fn,
+        &[file_ptr.into(), start.into(), i32_type.const_zero().into()],
+        "seek_back",
+    )?;
+
+    let size = compiler
+        .builder
+        .build_int_sub(end, start, "read_size")
+        .unwrap();
+    let alloc_size = compiler
+        .builder
+        .build_int_add(size, i64_type.const_int(1, false), "read_alloc_size")
+        .unwrap();
+    let malloc_fn = compiler.runtime.add_malloc(&compiler.module);
+    let buffer = build_libc_call(compiler, malloc_fn, &[alloc_size.into()], "read_buffer")?
+        .into_pointer_value();
+    let fread_fn = compiler.runtime.add_fread(&compiler.module);
+    let bytes_read = build_libc_call(
+        compiler,
+        fread_fn,
+        &[
+            buffer.into(),
+            i64_type.const_int(1, false).into(),
+            size.into(),
+            file_ptr.into(),
+        ],
+        "bytes_read",
+    )?
+    .into_int_value();
+
+    // Null-terminate after the bytes actually read
+    let terminator = unsafe {
+        compiler
+            .builder
+            .build_gep(
+                compiler.context.i8_type(),
+                buffer,
+                &[bytes_read],
+                "read_end_ptr",
+            )
+            .unwrap()
+    };
+    compiler
+        .builder
+        .build_store(terminator, compiler.context.i8_type().const_zero())
+        .unwrap();
+
+    compiler.track_string_in_arena(buffer);
+
+    Ok(compiler.create_pyobject_string(buffer))
+}
+
+/// Returns an i1 that is true when the PyObject has the given type tag
+fn build_tag_check<'ctx>(
+    compiler: &Compiler<'ctx>,
+    pyobject: IntValue<'ctx>,
+    type_tag: u8,
+    name: &str,
+) -> IntValue<'ctx> {
+    let tag = compiler.extract_tag(pyobject);
+    let expected = compiler
+        .context
+        .i64_type()
+        .const_int(type_tag as u64, false);
+    compiler
+        .builder
+        .build_int_compare(IntPredicate::EQ, tag, expected, name)
+        .unwrap()
+}
+
+/// Calls a C library function and returns its result
+fn build_libc_call<'ctx>(
+    compiler: &Compiler<'ctx>,
+    function: FunctionValue<'ctx>,
+    args: &[BasicMetadataValueEnum<'ctx>],
+    name: &str,
+) -> Result<BasicValueEnum<'ctx>, CodeGenError> {
+    let result = compiler.builder.build_call(function, args, name).unwrap();
+    match result.try_as_basic_value() {
+        inkwell::values::ValueKind::Basic(value) => Ok(value),
+        _ => Err(CodeGenError::UndefinedVariable(format!(
+            "{} did not return a value",
+            name
+        ))),
+    }
+}
+
+// ============================================================================
+// Function Call Operations
+// ============================================================================
+
+/// Compiles a function call expression func(arg1, arg2, ...)
+pub fn compile_call<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    func: &str,
+    args: &[IRExpr],
+    keywords: &[(String, IRExpr)],
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    // A variable (e.g. a parameter) shadows a user function of the same name
+    if compiler.variables.contains_key(func) {
+        return compile_indirect_call(compiler, func, args, keywords);
+    }
+
+    // Clone the function value to avoid borrow checker issues
+    let function = *compiler
+        .functions
+        .get(func)
+        .ok_or_else(|| CodeGenError::UndefinedVariable(format!("function '{}'", func)))?;
+
+    // Get parameters and defaults for this function
+    let params = compiler
+        .function_params
+        .get(func)
+        .cloned()
+        .unwrap_or_default();
+    let defaults = compiler
+        .function_defaults
+        .get(func)
+        .cloned()
+        .unwrap_or_default();
+    let positional_count = compiler
+        .function_positional_counts
+        .get(func)
+        .copied()
+        .unwrap_or(params.len());
+
+    // A `**kwargs` parameter comes last and is never bound by name
+    let has_kwargs = compiler.function_kwargs.contains(func);
+    let named_count = params.len() - usize::from(has_kwargs);
+
+    // Keyword-only parameters can't be filled positionally
+    if args.len() > positional_count {
+        return Err(CodeGenError::InvalidArguments(format!(
+            "function '{}' takes {} positional arguments but {} were given",
+            func,
+            positional_count,
+            args.len()
+        )));
+    }
+
+    // Bind positional arguments, then keyword arguments by name, to parameter slots;
+    // unmatched keywords are collected for `**kwargs`
+    let mut slots: Vec<Option<IntValue<'ctx>>> = vec![None; named_count];
+    for (slot, arg) in slots.iter_mut().zip(args) {
+        *slot = Some(compiler.compile_expression(arg)?);
+    }
+    let mut extra_keywords = Vec::new();
+    for (name, value) in keywords {
+        let Some(index) = params[..named_count].iter().position(|param| param == name) else {
+            if !has_kwargs {
+                return Err(CodeGenError::InvalidArguments(format!(
+                    "function '{}' got an unexpected keyword argument '{}'",
+                    func, name
+                )));
+            }
+            let key = compile_string_literal(compiler, name)?;
+            extra_keywords.push((key, compiler.compile_expression(value)?));
+            continue;
+        };
+        if slots[index].is_some() {
+            return Err(CodeGenError::InvalidArguments(format!(
+                "function '{}' got multiple values for argument '{}'",
+                func, name
+            )));
+        }
+        slots[index] = Some(compiler.compile_expression(value)?);
+    }
+
+    // Fill the remaining parameters from their defaults
+    let mut compiled_args = Vec::new();
+    for (i, slot) in slots.into_iter().enumerate() {
+        let arg_pyobj = match (slot, defaults.get(i).cloned().flatten()) {
+            (Some(value), _) => value,
+            (None, Some(default_expr)) => compiler.compile_default(func, i, &default_expr)?,
+            (None, None) => {
+                return Err(CodeGenError::InvalidArguments(format!(
+                    "function '{}' missing required argument '{}'",
+                    func, params[i]
+                )))
+            }
+        };
+        compiled_args.push(arg_pyobj.into());
+    }
+    if has_kwargs {
+        compiled_args.push(build_dict(compiler, &extra_keywords)?.into());
+    }
+
+    let call_result = compiler
+        .builder
+        .build_call(function, &compiled_args, &format!("{}_result", func))
+        .unwrap();
+
+    // Extract the return value from the call (should be a PyObject)
+    use inkwell::values::ValueKind;
+    let value = match call_result.try_as_basic_value() {
+        ValueKind::Basic(value) => value.into_int_value(),
+        ValueKind::Instruction(_) => {
+            return Err(CodeGenError::UndefinedVariable(
+                "Function call did not return a value".to_string(),
+            ))
+        }
+    };
+
+    // Integer-returning functions hand back the raw payload: tag it here so the
+    // INT tag is a compile-time constant at the call site
+    match compiler.function_return_type(func) {
+        ReturnType::Int => Ok(compiler.create_pyobject_int(value)),
+        ReturnType::PyObject => Ok(value),
+    }
+}
+
+/// Compiles a call through a variable holding a function value (see
+/// `Compiler::function_value`)
+///
+/// The callee is only known at runtime, so the arguments are passed positionally and
+/// checked against the descriptor's arity (see `build_indirect_call`).
+fn compile_indirect_call<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    func: &str,
+    args: &[IRExpr],
+    keywords: &[(String, IRExpr)],
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    if !keywords.is_empty() {
+        return Err(CodeGenError::InvalidArguments(format!(
+            "calls through variable '{}' take positional arguments only",
+            func
+        )));
+    }
+    let callee = compile_variable(compiler, func)?;
+    let compiled_args = args
+        .iter()
+        .map(|arg| compiler.compile_expression(arg).map(Into::into))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(build_indirect_call(compiler, callee, &compiled_args, func))
+}
+
+/// Calls a function value with compiled positional arguments, raising TypeError if
+/// the value is not a function or takes a different number of arguments
+fn build_indirect_call<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    callee: IntValue<'ctx>,
+    args: &[BasicMetadataValueEnum<'ctx>],
+    name: &str,
+) -> IntValue<'ctx> {
+    let is_function = build_tag_check(compiler, callee, TYPE_TAG_FUNC, "is_function");
+    let not_function = compiler
+        .builder
+        .build_not(is_function, "not_function")
+        .unwrap();
+    compiler.build_raise_if(not_function, "TypeError: object is not callable");
+
+    let i64_type = compiler.context.i64_type();
+    let ptr_type = compiler.context.ptr_type(inkwell::AddressSpace::default());
+    let descriptor_type = compiler.function_descriptor_type();
+    let descriptor = compiler.extract_string_ptr(callee);
+    let arity_ptr = compiler
+        .builder
+        .build_struct_gep(descriptor_type, descriptor, 1, "arity_ptr")
+        .unwrap();
+    let arity = compiler
+        .builder
+        .build_load(i64_type, arity_ptr, "arity")
+        .unwrap()
+        .into_int_value();
+    let wrong_arity = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::NE,
+            arity,
+            i64_type.const_int(args.len() as u64, false),
+            "wrong_arity",
+        )
+        .unwrap();
+    compiler.build_raise_if(
+        wrong_arity,
+        "TypeError: function called with the wrong number of arguments",
+    );
+
+    let function_ptr = compiler
+        .builder
+        .build_struct_gep(descriptor_type, descriptor, 0, "function_ptr")
+        .unwrap();
+    let function = compiler
+        .builder
+        .build_load(ptr_type, function_ptr, "function")
+        .unwrap()
+        .into_pointer_value();
+    let param_types: Vec<_> = args.iter().map(|_| i64_type.into()).collect();
+    let fn_type = i64_type.fn_type(&param_types, false);
+    compiler
+        .builder
+        .build_indirect_call(fn_type, function, args, &format!("{}_result", name))
+        .unwrap()
+        .try_as_basic_value()
+        .basic()
+        .unwrap()
+        .into_int_value()
+}
+
+// ============================================================================
+// Binary Operations
+// ============================================================================
+
+/// Compiles a binary operation expression (arithmetic, bitwise, string concatenation)
+pub fn compile_binary_op<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    op: &BinOp,
+    left: &IRExpr,
+    right: &IRExpr,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let lhs_obj = compiler.compile_expression(left)?;
+    let rhs_obj = compiler.compile_expression(right)?;
+    build_binary_op(compiler, op, lhs_obj, rhs_obj)
+}
+
+/// Applies a binary operator to two already compiled operands
+pub fn build_binary_op<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    op: &BinOp,
+    lhs_obj: IntValue<'ctx>,
+    rhs_obj: IntValue<'ctx>,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    // Extract tags to check types
+    let lhs_tag = compiler.extract_tag(lhs_obj);
+    let rhs_tag = compiler.extract_tag(rhs_obj);
+    let string_tag_const = compiler
+        .context
+        .i64_type()
+        .const_int(TYPE_TAG_STRING as u64, false);
+
+    // Handle string concatenation for Add operator
+    if matches!(op, BinOp::Add) {
+        let lhs_is_string = compiler
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                lhs_tag,
+                string_tag_const,
+                "lhs_is_string",
+            )
+            .unwrap();
+        let rhs_is_string = compiler
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                rhs_tag,
+                string_tag_const,
+                "rhs_is_string",
+            )
+            .unwrap();
+        let both_strings = compiler
+            .builder
+            .build_and(lhs_is_string, rhs_is_string, "both_strings")
+            .unwrap();
+
+        // Get current function for creating basic blocks
+        let current_fn = compiler
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+
+        let concat_block = compiler
+            .context
+            .append_basic_block(current_fn, "str_concat");
+        let not_strings_block = compiler
+            .context
+            .append_basic_block(current_fn, "add_not_strings");
+        let list_concat_block = compiler
+            .context
+            .append_basic_block(current_fn, "list_concat");
+        let arithmetic_block = compiler
+            .context
+            .append_basic_block(current_fn, "arithmetic");
+        let merge_block = compiler.context.append_basic_block(current_fn, "add_merge");
+
+        let pyobject_type = compiler.create_pyobject_type();
+
+        // Branch based on whether both are strings, then on whether both are lists
+        compiler
+            .builder
+            .build_conditional_branch(both_strings, concat_block, not_strings_block)
+            .unwrap();
+
+        compiler.builder.position_at_end(not_strings_block);
+        let list_tag_const = compiler
+            .context
+            .i64_type()
+            .const_int(TYPE_TAG_LIST as u64, false);
+        let lhs_is_list = compiler
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                lhs_tag,
+                list_tag_const,
+                "lhs_is_list",
+            )
+            .unwrap();
+        let rhs_is_list = compiler
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                rhs_tag,
+                list_tag_const,
+                "rhs_is_list",
+            )
+            .unwrap();
+        let both_lists = compiler
+            .builder
+            .build_and(lhs_is_list, rhs_is_list, "both_lists")
+            .unwrap();
+        compiler
+            .builder
+            .build_conditional_branch(both_lists, list_concat_block, arithmetic_block)
+            .unwrap();
+
+        // List concatenation block
+        compiler.builder.position_at_end(list_concat_block);
+        let list_concat_result = build_list_concat(compiler, lhs_obj, rhs_obj)?;
+        compiler
+            .builder
+            .build_unconditional_branch(merge_block)
+            .unwrap();
+
+        // String concatenation block
+        compiler.builder.position_at_end(concat_block);
+        let lhs_str_ptr = compiler.extract_string_ptr(lhs_obj);
+        let rhs_str_ptr = compiler.extract_string_ptr(rhs_obj);
+
+        // Get lengths of both strings using strlen
+        let strlen_fn = compiler.runtime.add_strlen(&compiler.module);
+        let lhs_len_result = compiler
+            .builder
+            .build_call(strlen_fn, &[lhs_str_ptr.into()], "lhs_len")
+            .unwrap();
+        let lhs_len = match lhs_len_result.try_as_basic_value() {
+            inkwell::values::ValueKind::Basic(value) => value.into_int_value(),
+            _ => {
+                return Err(CodeGenError::UndefinedVariable(
+                    "strlen did not return a value".to_string(),
+                ))
+            }
+        };
+        let rhs_len_result = compiler
+            .builder
+            .build_call(strlen_fn, &[rhs_str_ptr.into()], "rhs_len")
+            .unwrap();
+        let rhs_len = match rhs_len_result.try_as_basic_value() {
+            inkwell::values::ValueKind::Basic(value) => value.into_int_value(),
+            _ => {
+                return Err(CodeGenError::UndefinedVariable(
+                    "strlen did not return a value".to_string(),
+                ))
+            }
+        };
+
+        // Calculate total size (lhs_len + rhs_len + 1 for null terminator)
+        let total_len = compiler
+            .builder
+            .build_int_add(lhs_len, rhs_len, "total_len")
+            .unwrap();
+        let total_size = compiler
+            .builder
+            .build_int_add(
+                total_len,
+                compiler.context.i64_type().const_int(1, false),
+                "total_size",
+            )
+            .unwrap();
+
+        // Allocate memory for concatenated string
+        let malloc_fn = compiler.runtime.add_malloc(&compiler.module);
+        let concat_ptr_result = compiler
+            .builder
+            .build_call(malloc_fn, &[total_size.into()], "malloc_concat")
+            .unwrap();
+        let concat_ptr = match concat_ptr_result.try_as_basic_value() {
+            inkwell::values::ValueKind::Basic(value) => value.into_pointer_value(),
+            _ => {
+                return Err(CodeGenError::UndefinedVariable(
+                    "malloc did not return a value".to_string(),
+                ))
+            }
+        };
+
+        // Copy first string
+        let memcpy_fn = compiler.runtime.add_memcpy(&compiler.module);
+        compiler
+            .builder
+            .build_call(
+                memcpy_fn,
+                &[concat_ptr.into(), lhs_str_ptr.into(), lhs_len.into()],
+                "memcpy_lhs",
+            )
+            .unwrap();
+
+        // Copy second string (offset by lhs_len)
+        let rhs_dest = unsafe {
+            compiler
+                .builder
+                .build_gep(
+                    compiler.context.i8_type(),
+                    concat_ptr,
+                    &[lhs_len],
+                    "rhs_dest",
+                )
+                .unwrap()
+        };
+        // Copy rhs_len + 1 to include null terminator
+        let rhs_copy_len = compiler
+            .builder
+            .build_int_add(
+                rhs_len,
+                compiler.context.i64_type().const_int(1, false),
+                "rhs_copy_len",
+            )
+            .unwrap();
+        compiler
+            .builder
+            .build_call(
+                memcpy_fn,
+                &[rhs_dest.into(), rhs_str_ptr.into(), rhs_copy_len.into()],
+                "memcpy_rhs",
+            )
+            .unwrap();
+
+        compiler.track_string_in_arena(concat_ptr);
+
+        // Create PyObject for concatenated string
+        let concat_result = compiler.create_pyobject_string(concat_ptr);
+        compiler
+            .builder
+            .build_unconditional_branch(merge_block)
+            .unwrap();
+
+        // Arithmetic block (for non-string addition)
+        compiler.builder.position_at_end(arithmetic_block);
+        let lhs_payload = compiler.extract_payload(lhs_obj);
+        let rhs_payload = compiler.extract_payload(rhs_obj);
+
+        // Check if either operand is a float (tag == TYPE_TAG_FLOAT)
+        let float_tag_const = compiler
+            .context
+            .i64_type()
+            .const_int(TYPE_TAG_FLOAT as u64, false);
+        let lhs_is_float = compiler
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                lhs_tag,
+                float_tag_const,
+                "lhs_is_float",
+            )
+            .unwrap();
+        let rhs_is_float = compiler
+            .builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                rhs_tag,
+                float_tag_const,
+                "rhs_is_float",
+            )
+            .unwrap();
+
+        // If either is float, result should be float
+        let result_is_float = compiler
+            .builder
+            .build_or(lhs_is_float, rhs_is_float, "result_is_float")
+            .unwrap();
+
+        let result_payload = compiler
+            .builder
+            .build_float_add(lhs_payload, rhs_payload, "addtmp")
+            .unwrap();
+
+        if compiler.checked_arithmetic {
+            build_overflow_check(compiler, op, lhs_obj, rhs_obj, result_is_float)?;
+        }
+
+        // Select the result tag based on whether either operand is float
+        let int_tag = compiler
+            .context
+            .i64_type()
+            .const_int(TYPE_TAG_INT as u64, false);
+        let float_tag = compiler
+            .context
+            .i64_type()
+            .const_int(TYPE_TAG_FLOAT as u64, false);
+        let result_tag = compiler
+            .builder
+            .build_select(result_is_float, float_tag, int_tag, "result_tag")
+            .unwrap()
+            .into_int_value();
+
+        // Create result PyObject
+        let arithmetic_result =
+            compiler.create_pyobject_from_tag_and_payload(result_tag, result_payload);
+        // The overflow check may have split the arithmetic block
+        let arithmetic_end_block = compiler.builder.get_insert_block().unwrap();
+        compiler
+            .builder
+            .build_unconditional_branch(merge_block)
+            .unwrap();
+
+        // Merge block - phi node to select result
+        compiler.builder.position_at_end(merge_block);
+        let phi = compiler
+            .builder
+            .build_phi(pyobject_type, "add_result")
+            .unwrap();
+        phi.add_incoming(&[
+            (&concat_result, concat_block),
+            (&list_concat_result, list_concat_block),
+            (&arithmetic_result, arithmetic_end_block),
+        ]);
+        return Ok(phi.as_basic_value().into_int_value());
+    }
+
+    // Handle string repetition for Mul (str * int or int * str)
+    if matches!(op, BinOp::Mul) {
+        let lhs_is_string = compiler
+            .builder
+            .build_int_compare(IntPredicate::EQ, lhs_tag, string_tag_const, "lhs_is_string")
+            .unwrap();
+        let rhs_is_string = compiler
+            .builder
+            .build_int_compare(IntPredicate::EQ, rhs_tag, string_tag_const, "rhs_is_string")
+            .unwrap();
+        let is_repeat = compiler
+            .builder
+            .build_xor(lhs_is_string, rhs_is_string, "is_repeat")
+            .unwrap();
+
+        let current_fn = compiler
+            .builder
+            .get_insert_block()
+            .unwrap()
+            .get_parent()
+            .unwrap();
+        let repeat_block = compiler
+            .context
+            .append_basic_block(current_fn, "str_repeat");
+        let not_str_repeat_block = compiler
+            .context
+            .append_basic_block(current_fn, "mul_not_str_repeat");
+        let list_repeat_block = compiler
+            .context
+            .append_basic_block(current_fn, "list_repeat");
+        let arithmetic_block = compiler
+            .context
+            .append_basic_block(current_fn, "arithmetic");
+        let merge_block = compiler.context.append_basic_block(current_fn, "mul_merge");
+
+        compiler
+            .builder
+            .build_conditional_branch(is_repeat, repeat_block, not_str_repeat_block)
+            .unwrap();
+
+        // List repetition (list * int or int * list) copies the element references
+        compiler.builder.position_at_end(not_str_repeat_block);
+        let lhs_is_list = build_tag_check(compiler, lhs_obj, TYPE_TAG_LIST, "lhs_is_list");
+        let rhs_is_list = build_tag_check(compiler, rhs_obj, TYPE_TAG_LIST, "rhs_is_list");
+        let is_list_repeat = compiler
+            .builder
+            .build_xor(lhs_is_list, rhs_is_list, "is_list_repeat")
+            .unwrap();
+        compiler
+            .builder
+            .build_conditional_branch(is_list_repeat, list_repeat_block, arithmetic_block)
+            .unwrap();
+
+        compiler.builder.position_at_end(list_repeat_block);
+        let list_obj = compiler
+            .builder
+            .build_select(lhs_is_list, lhs_obj, rhs_obj, "repeat_list")
+            .unwrap()
+            .into_int_value();
+        let count_obj = compiler
+            .builder
+            .build_select(lhs_is_list, rhs_obj, lhs_obj, "list_repeat_count")
+            .unwrap()
+            .into_int_value();
+        let count = extract_repeat_count(compiler, count_obj);
+        let list_repeat_result = build_list_repeat(compiler, list_obj, count)?;
+        let list_repeat_end_block = compiler.builder.get_insert_block().unwrap();
+        compiler
+            .builder
+            .build_unconditional_branch(merge_block)
+            .unwrap();
+
+        // String repetition block: whichever operand is the string gets repeated
+        compiler.builder.position_at_end(repeat_block);
+        let str_obj = compiler
+            .builder
+            .build_select(lhs_is_string, lhs_obj, rhs_obj, "repeat_str")
+            .unwrap()
+            .into_int_value();
+        let count_obj = compiler
+            .builder
+            .build_select(lhs_is_string, rhs_obj, lhs_obj, "repeat_count")
+            .unwrap()
+            .into_int_value();
+        let str_ptr = compiler.extract_string_ptr(str_obj);
+        let count = extract_repeat_count(compiler, count_obj);
+        let repeat_ptr = build_string_repeat(compiler, str_ptr, count)?;
+        let repeat_result = compiler.create_pyobject_string(repeat_ptr);
+        let repeat_end_block = compiler.builder.get_insert_block().unwrap();
+        compiler
+            .builder
+            .build_unconditional_branch(merge_block)
+            .unwrap();
+
+        // Arithmetic block (for numeric multiplication)
+        compiler.builder.position_at_end(arithmetic_block);
+        let arithmetic_result = compile_arithmetic(compiler, op, lhs_obj, rhs_obj)?;
+        let arithmetic_end_block = compiler.builder.get_insert_block().unwrap();
+        compiler
+            .builder
+            .build_unconditional_branch(merge_block)
+            .unwrap();
+
+        compiler.builder.position_at_end(merge_block);
+        let phi = compiler
+            .builder
+            .build_phi(compiler.create_pyobject_type(), "mul_result")
+            .unwrap();
+        phi.add_incoming(&[
+            (&repeat_result, repeat_end_block),
+            (&list_repeat_result, list_repeat_end_block),
+            (&arithmetic_result, arithmetic_end_block),
+        ]);
+        return Ok(phi.as_basic_value().into_int_value());
+    }
+
+    // Handle bitwise operations separately (they require integer operands)
+    match op {
+        BinOp::BitAnd | BinOp::BitOr | BinOp::BitXor | BinOp::LShift | BinOp::RShift => {
+            // Convert payloads to integers
+            let lhs_payload = compiler.extract_payload(lhs_obj);
+            let rhs_payload = compiler.extract_payload(rhs_obj);
+
+            let lhs_int = compiler
+                .builder
+                .build_float_to_signed_int(lhs_payload, compiler.context.i64_type(), "lhs_to_int")
+                .unwrap();
+            let rhs_int = compiler
+                .builder
+                .build_float_to_signed_int(rhs_payload, compiler.context.i64_type(), "rhs_to_int")
+                .unwrap();
+
+            // Perform bitwise operation
+            let result_int = match op {
+                BinOp::BitAnd => compiler.builder.build_and(lhs_int, rhs_int, "and").unwrap(),
+                BinOp::BitOr => compiler.builder.build_or(lhs_int, rhs_int, "or").unwrap(),
+                BinOp::BitXor => compiler.builder.build_xor(lhs_int, rhs_int, "xor").unwrap(),
+                BinOp::LShift => compiler
+                    .builder
+                    .build_left_shift(lhs_int, rhs_int, "shl")
+                    .unwrap(),
+                BinOp::RShift => compiler
+                    .builder
+                    .build_right_shift(lhs_int, rhs_int, true, "shr")
+                    .unwrap(),
+                _ => unreachable!(),
+            };
+
+            let int_result = compiler.create_pyobject_int(result_int);
+            if matches!(op, BinOp::LShift | BinOp::RShift) {
+                // Shifts always produce an int, even on bools
+                return Ok(int_result);
+            }
+
+            // &, | and ^ on two bools produce a bool; any int operand makes it an int
+            let bool_tag = compiler
+                .context
+                .i64_type()
+                .const_int(TYPE_TAG_BOOL as u64, false);
+            let lhs_is_bool = compiler
+                .builder
+                .build_int_compare(IntPredicate::EQ, lhs_tag, bool_tag, "lhs_is_bool")
+                .unwrap();
+            let rhs_is_bool = compiler
+                .builder
+                .build_int_compare(IntPredicate::EQ, rhs_tag, bool_tag, "rhs_is_bool")
+                .unwrap();
+            let both_bools = compiler
+                .builder
+                .build_and(lhs_is_bool, rhs_is_bool, "both_bools")
+                .unwrap();
+            let result_bit = compiler
+                .builder
+                .build_int_truncate(result_int, compiler.context.bool_type(), "result_bit")
+                .unwrap();
+            let bool_result = compiler.create_pyobject_bool(result_bit);
+            Ok(compiler
+                .builder
+                .build_select(both_bools, bool_result, int_result, "bitwise_result")
+                .unwrap()
+                .into_int_value())
+        }
+        // Arithmetic operations (Add, Sub, Mul, Div, FloorDiv, Mod)
+        _ => compile_arithmetic(compiler, op, lhs_obj, rhs_obj),
+    }
+}
+
+/// Compiles numeric arithmetic (Add, Sub, Mul, Div, FloorDiv, Mod) on int/float/bool operands
+/// The result is a float if either operand is a float, otherwise an int
+fn compile_arithmetic<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    op: &BinOp,
+    lhs_obj: IntValue<'ctx>,
+    rhs_obj: IntValue<'ctx>,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    // Extract tags and payloads
+    let lhs_tag = compiler.extract_tag(lhs_obj);
+    let rhs_tag = compiler.extract_tag(rhs_obj);
+    let lhs_payload = compiler.extract_payload(lhs_obj);
+    let rhs_payload = compiler.extract_payload(rhs_obj);
+
+    // Check if either operand is a float (tag == TYPE_TAG_FLOAT)
+    let float_tag_const = compiler
+        .context
+        .i64_type()
+        .const_int(TYPE_TAG_FLOAT as u64, false);
+    let lhs_is_float = compiler
+        .builder
+        .build_int_compare(
+            inkwell::IntPredicate::EQ,
+            lhs_tag,
+            float_tag_const,
+            "lhs_is_float",
+        )
+        .unwrap();
+    let rhs_is_float = compiler
+        .builder
+        .build_int_compare(
+            inkwell::IntPredicate::EQ,
+            rhs_tag,
+            float_tag_const,
+            "rhs_is_float",
+        )
+        .unwrap();
+
+    // True division always yields a float; otherwise the result is a float if
+    // either operand is
+    let result_is_float = if matches!(op, BinOp::Div) {
+        compiler.context.bool_type().const_all_ones()
+    } else {
+        compiler
+            .builder
+            .build_or(lhs_is_float, rhs_is_float, "result_is_float")
+            .unwrap()
+    };
+
+    // A negative exponent makes a power a float even for integer operands (2 ** -2 == 0.25),
+    // and zero cannot be raised to one
+    let result_is_float = if matches!(op, BinOp::Pow) {
+        let zero = compiler.context.f64_type().const_zero();
+        let exponent_is_negative = compiler
+            .builder
+            .build_float_compare(
+                FloatPredicate::OLT,
+                rhs_payload,
+                zero,
+                "exponent_is_negative",
+            )
+            .unwrap();
+        let base_is_zero = compiler
+            .builder
+            .build_float_compare(FloatPredicate::OEQ, lhs_payload, zero, "base_is_zero")
+            .unwrap();
+        let zero_to_negative = compiler
+            .builder
+            .build_and(base_is_zero, exponent_is_negative, "zero_to_negative")
+            .unwrap();
+        compiler.build_raise_if(
+            zero_to_negative,
+            "ZeroDivisionError: 0.0 cannot be raised to a negative power",
+        );
+        compiler
+            .builder
+            .build_or(result_is_float, exponent_is_negative, "pow_is_float")
+            .unwrap()
+    } else {
+        result_is_float
+    };
+
+    // Division and modulo by zero (including False) raise ZeroDivisionError
+    if matches!(op, BinOp::Div | BinOp::FloorDiv | BinOp::Mod) {
+        let is_zero = compiler
+            .builder
+            .build_float_compare(
+                FloatPredicate::OEQ,
+                rhs_payload,
+                compiler.context.f64_type().const_zero(),
+                "divisor_is_zero",
+            )
+            .unwrap();
+        let message = match op {
+            BinOp::Div => "ZeroDivisionError: division by zero",
+            _ => "ZeroDivisionError: integer division or modulo by zero",
+        };
+        compiler.build_raise_if(is_zero, message);
+    }
+
+    // Perform the operation on payloads
+    let result_payload = match op {
+        BinOp::Add => compiler
+            .builder
+            .build_float_add(lhs_payload, rhs_payload, "addtmp")
+            .unwrap(),
+        BinOp::Sub => compiler
+            .builder
+            .build_float_sub(lhs_payload, rhs_payload, "subtmp")
+            .unwrap(),
+        BinOp::Mul => compiler
+            .builder
+            .build_float_mul(lhs_payload, rhs_payload, "multmp")
+            .unwrap(),
+        BinOp::Div => compiler
+            .builder
+            .build_float_div(lhs_payload, rhs_payload, "divtmp")
+            .unwrap(),
+        BinOp::FloorDiv => build_floor_div_mod(compiler, lhs_payload, rhs_payload)?.0,
+        BinOp::Mod => build_floor_div_mod(compiler, lhs_payload, rhs_payload)?.1,
+        BinOp::Pow => {
+            let pow = Intrinsic::find("llvm.pow")
+                .and_then(|intrinsic| {
+                    intrinsic
+                        .get_declaration(&compiler.module, &[compiler.context.f64_type().into()])
+                })
+                .expect("llvm.pow intrinsic should be available");
+            let pow_result = compiler
+                .builder
+                .build_call(pow, &[lhs_payload.into(), rhs_payload.into()], "powtmp")
+                .unwrap();
+            match pow_result.try_as_basic_value() {
+                inkwell::values::ValueKind::Basic(value) => value.into_float_value(),
+                _ => {
+                    return Err(CodeGenError::UndefinedVariable(
+                        "llvm.pow did not return a value".to_string(),
+                    ))
+                }
+            }
+        }
+        _ => unreachable!(),
+    };
+
+    if compiler.checked_arithmetic && matches!(op, BinOp::Add | BinOp::Sub | BinOp::Mul) {
+        build_overflow_check(compiler, op, lhs_obj, rhs_obj, result_is_float)?;
+    }
+    if compiler.checked_arithmetic && matches!(op, BinOp::Pow) {
+        build_pow_overflow_check(compiler, result_payload, result_is_float);
+    }
+
+    // Select the result tag based on whether either operand is float
+    let int_tag = compiler
+        .context
+        .i64_type()
+        .const_int(TYPE_TAG_INT as u64, false);
+    let float_tag = compiler
+        .context
+        .i64_type()
+        .const_int(TYPE_TAG_FLOAT as u64, false);
+    let result_tag = compiler
+        .builder
+        .build_select(result_is_float, float_tag, int_tag, "result_tag")
+        .unwrap()
+        .into_int_value();
+
+    // Create result PyObject
+    let result_obj = compiler.create_pyobject_from_tag_and_payload(result_tag, result_payload);
+
+    // Integer fast path: +, - and * on two ints use the exact sign-extended payloads,
+    // so large products wrap to 48 bits instead of losing precision through f64
+    let int_op = match op {
+        BinOp::Add => Some(
+            compiler
+                .builder
+                .build_int_add(
+                    compiler.extract_int_payload(lhs_obj),
+                    compiler.extract_int_payload(rhs_obj),
+                    "int_addtmp",
+                )
+                .unwrap(),
+        ),
+        BinOp::Sub => Some(
+            compiler
+                .builder
+                .build_int_sub(
+                    compiler.extract_int_payload(lhs_obj),
+                    compiler.extract_int_payload(rhs_obj),
+                    "int_subtmp",
+                )
+                .unwrap(),
+        ),
+        BinOp::Mul => Some(
+            compiler
+                .builder
+                .build_int_mul(
+                    compiler.extract_int_payload(lhs_obj),
+                    compiler.extract_int_payload(rhs_obj),
+                    "int_multmp",
+                )
+                .unwrap(),
+        ),
+        _ => None,
+    };
+    match int_op {
+        Some(int_result) => {
+            let int_obj = compiler.create_pyobject_int(int_result);
+            Ok(compiler
+                .builder
+                .build_select(result_is_float, result_obj, int_obj, "arith_result")
+                .unwrap()
+                .into_int_value())
+        }
+        None => Ok(result_obj),
+    }
+}
+
+/// Builds Python's floored division of two numbers, returning `(a // b, a % b)`
+///
+/// C truncates toward zero; Python rounds the quotient toward negative infinity, so
+/// the remainder takes the divisor's sign (`-7 // 2 == -4`, `-7 % 2 == 1`). Both
+/// results come from the same remainder, as in CPython's `float_divmod`, so that
+/// `a == b * (a // b) + a % b` holds for ints and floats alike. The operands are
+/// payloads, which hold 48-bit ints exactly; the divisor must not be zero.
+fn build_floor_div_mod<'ctx>(
+    compiler: &Compiler<'ctx>,
+    lhs: FloatValue<'ctx>,
+    rhs: FloatValue<'ctx>,
+) -> Result<(FloatValue<'ctx>, FloatValue<'ctx>), CodeGenError> {
+    let f64_type = compiler.context.f64_type();
+    let zero = f64_type.const_zero();
+    let one = f64_type.const_float(1.0);
+    let intrinsic = |name: &str| {
+        Intrinsic::find(name)
+            .and_then(|intrinsic| intrinsic.get_declaration(&compiler.module, &[f64_type.into()]))
+            .unwrap_or_else(|| panic!("{} intrinsic should be available", name))
+    };
+    let builder = &compiler.builder;
+
+    // fmod truncates; move a remainder whose sign differs from the divisor's by one
+    // divisor, taking one off the quotient. A zero remainder takes the divisor's sign
+    let rem = builder.build_float_rem(lhs, rhs, "rem").unwrap();
+    let quotient = builder
+        .build_float_div(
+            builder.build_float_sub(lhs, rem, "exact").unwrap(),
+            rhs,
+            "quotient",
+        )
+        .unwrap();
+    let rem_is_zero = builder
+        .build_float_compare(FloatPredicate::OEQ, rem, zero, "rem_is_zero")
+        .unwrap();
+    let rem_negative = builder
+        .build_float_compare(FloatPredicate::OLT, rem, zero, "rem_negative")
+        .unwrap();
+    let rhs_negative = builder
+        .build_float_compare(FloatPredicate::OLT, rhs, zero, "rhs_negative")
+        .unwrap();
+    let signs_differ = builder
+        .build_xor(rem_negative, rhs_negative, "signs_differ")
+        .unwrap();
+    let adjust = builder
+        .build_and(
+            signs_differ,
+            builder.build_not(rem_is_zero, "rem_nonzero").unwrap(),
+            "adjust",
+        )
+        .unwrap();
+    let signed_zero = build_libc_call(
+        compiler,
+        intrinsic("llvm.copysign"),
+        &[zero.into(), rhs.into()],
+        "signed_zero",
+    )?
+    .into_float_value();
+    let adjusted_rem = builder.build_float_add(rem, rhs, "adjusted_rem").unwrap();
+    let rem = builder
+        .build_select(adjust, adjusted_rem, rem, "floor_rem")
+        .unwrap()
+        .into_float_value();
+    let rem = builder
+        .build_select(rem_is_zero, signed_zero, rem, "floor_mod")
+        .unwrap()
+        .into_float_value();
+    let lowered_quotient = builder
+        .build_float_sub(quotient, one, "lowered_quotient")
+        .unwrap();
+    let quotient = builder
+        .build_select(adjust, lowered_quotient, quotient, "quotient")
+        .unwrap()
+        .into_float_value();
+
+    // The quotient is integral up to rounding error: snap it to the nearest integer
+    // below, or above when it is more than half way there
+    let floored = build_libc_call(
+        compiler,
+        intrinsic("llvm.floor"),
+        &[quotient.into()],
+        "floored",
+    )?
+    .into_float_value();
+    let fraction = builder
+        .build_float_sub(quotient, floored, "fraction")
+        .unwrap();
+    let past_half = builder
+        .build_float_compare(
+            FloatPredicate::OGT,
+            fraction,
+            f64_type.const_float(0.5),
+            "past_half",
+        )
+        .unwrap();
+    let rounded_up = builder.build_float_add(floored, one, "rounded_up").unwrap();
+    let floor_div = builder
+        .build_select(past_half, rounded_up, floored, "floor_div")
+        .unwrap()
+        .into_float_value();
+
+    // A zero quotient takes the sign of the true quotient (`-0.5 // -2` is 0.0)
+    let div_is_zero = builder
+        .build_float_compare(FloatPredicate::OEQ, floor_div, zero, "div_is_zero")
+        .unwrap();
+    let true_quotient = builder.build_float_div(lhs, rhs, "true_quotient").unwrap();
+    let signed_zero_div = build_libc_call(
+        compiler,
+        intrinsic("llvm.copysign"),
+        &[zero.into(), true_quotient.into()],
+        "signed_zero_div",
+    )?
+    .into_float_value();
+    let floor_div = builder
+        .build_select(div_is_zero, signed_zero_div, floor_div, "floor_div")
+        .unwrap()
+        .into_float_value();
+    Ok((floor_div, rem))
+}
+
+/// Builds a new list holding the elements of `list_obj` repeated `count` times. The
+/// elements themselves are not copied, so `[[0] * 3] * 2` repeats one inner list.
+/// A zero or negative count yields the empty list
+fn build_list_repeat<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    list_obj: IntValue<'ctx>,
+    count: IntValue<'ctx>,
+) -> Result<IntValue<'ctx>, CodeGenError> {
+    let i64_type = compiler.context.i64_type();
+    let pyobject_type = compiler.create_pyobject_type();
+    let one = i64_type.const_int(1, false);
+
+    // Clamp the repeat count at zero
+    let is_negative = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::SLT,
+            count,
+            i64_type.const_zero(),
+            "is_negative",
+        )
+        .unwrap();
+    let count = compiler
+        .builder
+        .build_select(is_negative, i64_type.const_zero(), count, "clamped_count")
+        .unwrap()
+        .into_int_value();
+
+    let (src_ptr, len) = compiler.extract_list_ptr_and_len(list_obj);
+    let total_len = compiler
+        .builder
+        .build_int_mul(len, count, "list_repeat_len")
+        .unwrap();
+    let dst_ptr = build_list_alloc(compiler, total_len)?;
+    let src_elems = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, src_ptr, &[one], "src_elems")
+            .unwrap()
+    };
+    let byte_count = compiler
+        .builder
+        .build_int_mul(pyobject_type.size_of(), len, "list_repeat_bytes")
+        .unwrap();
+
+    // Copy the elements `count` times, each copy after the previous one
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let entry_block = compiler.builder.get_insert_block().unwrap();
+    let cond_block = compiler
+        .context
+        .append_basic_block(current_fn, "list_repeat_cond");
+    let body_block = compiler
+        .context
+        .append_basic_block(current_fn, "list_repeat_body");
+    let done_block = compiler
+        .context
+        .append_basic_block(current_fn, "list_repeat_done");
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(cond_block);
+    let counter = compiler
+        .builder
+        .build_phi(i64_type, "list_repeat_i")
+        .unwrap();
+    let i = counter.as_basic_value().into_int_value();
+    let keep_going = compiler
+        .builder
+        .build_int_compare(IntPredicate::SLT, i, count, "list_repeat_keep_going")
+        .unwrap();
+    compiler
+        .builder
+        .build_conditional_branch(keep_going, body_block, done_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(body_block);
+    let offset = compiler
+        .builder
+        .build_int_mul(i, len, "list_repeat_offset")
+        .unwrap();
+    let slot = compiler
+        .builder
+        .build_int_add(offset, one, "list_repeat_slot")
+        .unwrap();
+    let dest = unsafe {
+        compiler
+            .builder
+            .build_in_bounds_gep(pyobject_type, dst_ptr, &[slot], "list_repeat_dest")
+            .unwrap()
+    };
+    let memcpy_fn = compiler.runtime.add_memcpy(&compiler.module);
+    compiler
+        .builder
+        .build_call(
+            memcpy_fn,
+            &[dest.into(), src_elems.into(), byte_count.into()],
+            "memcpy_list_repeat",
+        )
+        .unwrap();
+    let next_i = compiler
+        .builder
+        .build_int_add(i, one, "list_repeat_next_i")
+        .unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+    counter.add_incoming(&[(&i64_type.const_zero(), entry_block), (&next_i, body_block)]);
+
+    compiler.builder.position_at_end(done_block);
+    Ok(compiler.create_pyobject_list(dst_ptr, 0))
+}
+
+/// Extracts the count of a string or list repetition, raising a TypeError that names
+/// the count's type unless it is an int or a bool
+fn extract_repeat_count<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    count_obj: IntValue<'ctx>,
+) -> IntValue<'ctx> {
+    let is_int = build_tag_check(compiler, count_obj, TYPE_TAG_INT, "count_is_int");
+    let is_bool = build_tag_check(compiler, count_obj, TYPE_TAG_BOOL, "count_is_bool");
+    let is_integer = compiler
+        .builder
+        .build_or(is_int, is_bool, "count_is_integer")
+        .unwrap();
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let raise_block = compiler
+        .context
+        .append_basic_block(current_fn, "bad_repeat_count");
+    let count_block = compiler
+        .context
+        .append_basic_block(current_fn, "repeat_count");
+    compiler
+        .builder
+        .build_conditional_branch(is_integer, count_block, raise_block)
+        .unwrap();
+
+    // One raise for every other type, with the message picked by the tag
+    compiler.builder.position_at_end(raise_block);
+    let tag = compiler.extract_tag(count_obj);
+    let mut message = None;
+    for (tag_value, type_name) in TYPE_NAMES.iter().enumerate() {
+        let text = compiler
+            .builder
+            .build_global_string_ptr(
+                &format!(
+                    "TypeError: can't multiply sequence by non-int of type '{}'",
+                    type_name
+                ),
+                "error_message",
+            )
+            .unwrap()
+            .as_pointer_value();
+        message = Some(match message {
+            None => text,
+            Some(previous) => {
+                let matches = compiler
+                    .builder
+                    .build_int_compare(
+                        IntPredicate::EQ,
+                        tag,
+                        compiler
+                            .context
+                            .i64_type()
+                            .const_int(tag_value as u64, false),
+                        "is_type",
+                    )
+                    .unwrap();
+                compiler
+                    .builder
+                    .build_select(matches, text, previous, "error_message")
+                    .unwrap()
+                    .into_pointer_value()
+            }
+        });
+    }
+    let raise_error = compiler.runtime.add_raise_error(&compiler.module);
+    compiler
+        .builder
+        .build_call(raise_error, &[message.unwrap().into()], "")
+        .unwrap();
+    compiler.builder.build_unreachable().unwrap();
+
+    compiler.builder.position_at_end(count_block);
+    compiler.extract_int_payload(count_obj)
+}
+
+/// Builds a new string holding `count` copies of the string at `str_ptr`
+/// A zero or negative count yields the empty string
+fn build_string_repeat<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    str_ptr: inkwell::values::PointerValue<'ctx>,
+    count: IntValue<'ctx>,
+) -> Result<inkwell::values::PointerValue<'ctx>, CodeGenError> {
+    let i64_type = compiler.context.i64_type();
+    let i8_type = compiler.context.i8_type();
+
+    // Clamp the repeat count at zero
+    let is_negative = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::SLT,
+            count,
+            i64_type.const_zero(),
+            "is_negative",
+        )
+        .unwrap();
+    let count = compiler
+        .builder
+        .build_select(is_negative, i64_type.const_zero(), count, "clamped_count")
+        .unwrap()
+        .into_int_value();
+
+    let strlen_fn = compiler.runtime.add_strlen(&compiler.module);
+    let len_result = compiler
+        .builder
+        .build_call(strlen_fn, &[str_ptr.into()], "repeat_len")
+        .unwrap();
+    let len = match len_result.try_as_basic_value() {
+        inkwell::values::ValueKind::Basic(value) => value.into_int_value(),
+        _ => {
+            return Err(CodeGenError::UndefinedVariable(
+                "strlen did not return a value".to_string(),
+            ))
+        }
+    };
+
+    // Allocate len * count bytes plus the null terminator
+    let total_len = compiler
+        .builder
+        .build_int_mul(len, count, "repeat_total_len")
+        .unwrap();
+    let total_size = compiler
+        .builder
+        .build_int_add(total_len, i64_type.const_int(1, false), "repeat_size")
+        .unwrap();
+    let malloc_fn = compiler.runtime.add_malloc(&compiler.module);
+    let result_ptr_result = compiler
+        .builder
+        .build_call(malloc_fn, &[total_size.into()], "malloc_repeat")
+        .unwrap();
+    let result_ptr = match result_ptr_result.try_as_basic_value() {
+        inkwell::values::ValueKind::Basic(value) => value.into_pointer_value(),
+        _ => {
+            return Err(CodeGenError::UndefinedVariable(
+                "malloc did not return a value".to_string(),
+            ))
+        }
+    };
+
+    // Copy the string `count` times
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let entry_block = compiler.builder.get_insert_block().unwrap();
+    let cond_block = compiler
+        .context
+        .append_basic_block(current_fn, "repeat_cond");
+    let body_block = compiler
+        .context
+        .append_basic_block(current_fn, "repeat_body");
+    let done_block = compiler
+        .context
+        .append_basic_block(current_fn, "repeat_done");
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(cond_block);
+    let counter = compiler.builder.build_phi(i64_type, "repeat_i").unwrap();
+    let i = counter.as_basic_value().into_int_value();
+    let keep_going = compiler
+        .builder
+        .build_int_compare(IntPredicate::SLT, i, count, "repeat_keep_going")
+        .unwrap();
+    compiler
+        .builder
+        .build_conditional_branch(keep_going, body_block, done_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(body_block);
+    let offset = compiler
+        .builder
+        .build_int_mul(i, len, "repeat_offset")
+        .unwrap();
+    let dest = unsafe {
+        compiler
+            .builder
+            .build_gep(i8_type, result_ptr, &[offset], "repeat_dest")
+            .unwrap()
+    };
+    let memcpy_fn = compiler.runtime.add_memcpy(&compiler.module);
+    compiler
+        .builder
+        .build_call(
+            memcpy_fn,
+            &[dest.into(), str_ptr.into(), len.into()],
+            "memcpy_repeat",
+        )
+        .unwrap();
+    let next_i = compiler
+        .builder
+        .build_int_add(i, i64_type.const_int(1, false), "repeat_next_i")
+        .unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(cond_block)
+        .unwrap();
+    counter.add_incoming(&[(&i64_type.const_zero(), entry_block), (&next_i, body_block)]);
+
+    // Null-terminate (the empty result is just the terminator)
+    compiler.builder.position_at_end(done_block);
+    let terminator_ptr = unsafe {
+        compiler
+            .builder
+            .build_gep(i8_type, result_ptr, &[total_len], "repeat_terminator")
+            .unwrap()
+    };
+    compiler
+        .builder
+        .build_store(terminator_ptr, i8_type.const_zero())
+        .unwrap();
+
+    Ok(result_ptr)
+}
+
+/// Emits an overflow check for an integer `+`, `-` or `*` (checked arithmetic mode)
+///
+/// The int payloads are shifted into the top bits of an i64 before calling the
+/// `llvm.s*.with.overflow` intrinsics, so the intrinsic's overflow flag is set exactly
+/// when the result leaves the payload range. If it is set and neither operand
+/// is a float, control transfers to `raise_overflow`.
+fn build_overflow_check<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    op: &BinOp,
+    lhs_obj: IntValue<'ctx>,
+    rhs_obj: IntValue<'ctx>,
+    result_is_float: IntValue<'ctx>,
+) -> Result<(), CodeGenError> {
+    let i64_type = compiler.context.i64_type();
+    let payload_bits = compiler.values.payload_width().bits();
+    let shift = i64_type.const_int(u64::from(64 - payload_bits), false);
+    let lhs_int = compiler.extract_int_payload(lhs_obj);
+    let rhs_int = compiler.extract_int_payload(rhs_obj);
+    let lhs_scaled = compiler
+        .builder
+        .build_left_shift(lhs_int, shift, "lhs_scaled")
+        .unwrap();
+    // For multiplication only one operand is scaled: (a << s) * b == (a * b) << s
+    let rhs_operand = match op {
+        BinOp::Mul => rhs_int,
+        _ => compiler
+            .builder
+            .build_left_shift(rhs_int, shift, "rhs_scaled")
+            .unwrap(),
+    };
+
+    let intrinsic_name = match op {
+        BinOp::Add => "llvm.sadd.with.overflow",
+        BinOp::Sub => "llvm.ssub.with.overflow",
+        BinOp::Mul => "llvm.smul.with.overflow",
+        _ => unreachable!(),
+    };
+    let intrinsic = Intrinsic::find(intrinsic_name)
+        .and_then(|intrinsic| intrinsic.get_declaration(&compiler.module, &[i64_type.into()]))
+        .expect("overflow intrinsic should be available");
+    let checked_call = compiler
+        .builder
+        .build_call(
+            intrinsic,
+            &[lhs_scaled.into(), rhs_operand.into()],
+            "checked",
+        )
+        .unwrap();
+    use inkwell::values::ValueKind;
+    let checked = match checked_call.try_as_basic_value() {
+        ValueKind::Basic(value) => value.into_struct_value(),
+        ValueKind::Instruction(_) => {
+            return Err(CodeGenError::UndefinedVariable(
+                "overflow intrinsic did not return a value".to_string(),
+            ))
+        }
+    };
+    let overflowed = compiler
+        .builder
+        .build_extract_value(checked, 1, "overflowed")
+        .unwrap()
+        .into_int_value();
+    build_trap_overflow_if(compiler, overflowed, result_is_float);
+    Ok(())
+}
+
+/// Emits an overflow check for an integer `**` (checked arithmetic mode)
+///
+/// The power is computed in f64, which holds every payload exactly, so the result
+/// overflows when it lies outside the payload range. If it does and the result is
+/// an int, control transfers to `raise_overflow`.
+fn build_pow_overflow_check<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    result_payload: FloatValue<'ctx>,
+    result_is_float: IntValue<'ctx>,
+) {
+    let f64_type = compiler.context.f64_type();
+    let limit = (1u64 << (compiler.values.payload_width().bits() - 1)) as f64;
+    let too_large = compiler
+        .builder
+        .build_float_compare(
+            FloatPredicate::OGE,
+            result_payload,
+            f64_type.const_float(limit),
+            "pow_too_large",
+        )
+        .unwrap();
+    let too_small = compiler
+        .builder
+        .build_float_compare(
+            FloatPredicate::OLT,
+            result_payload,
+            f64_type.const_float(-limit),
+            "pow_too_small",
+        )
+        .unwrap();
+    let overflowed = compiler
+        .builder
+        .build_or(too_large, too_small, "overflowed")
+        .unwrap();
+    build_trap_overflow_if(compiler, overflowed, result_is_float);
+}
+
+/// Calls `raise_overflow` when `overflowed` is set for an int result, and continues
+/// building in a fresh block otherwise
+fn build_trap_overflow_if<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    overflowed: IntValue<'ctx>,
+    result_is_float: IntValue<'ctx>,
+) {
+    let is_int_op = compiler
+        .builder
+        .build_not(result_is_float, "is_int_op")
+        .unwrap();
+    let should_trap = compiler
+        .builder
+        .build_and(is_int_op, overflowed, "should_trap")
+        .unwrap();
+
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let overflow_block = compiler
+        .context
+        .append_basic_block(current_fn, "int_overflow");
+    let continue_block = compiler
+        .context
+        .append_basic_block(current_fn, "no_overflow");
+    compiler
+        .builder
+        .build_conditional_branch(should_trap, overflow_block, continue_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(overflow_block);
+    let raise_overflow = compiler.runtime.add_raise_overflow(&compiler.module);
+    compiler
+        .builder
+        .build_call(raise_overflow, &[], "")
+        .unwrap();
+    compiler.builder.build_unreachable().unwrap();
+
+    compiler.builder.position_at_end(continue_block);
 }
@@ -5,34 +5,54 @@
 //!
 //! ## Architecture
 //! Statement compilation is separated into focused helper functions:
-//! - **Simple statements**: Print, Assign, ExprStmt, Return
+//! - **Simple statements**: Print, Assign, IndexAssign, UnpackAssign, ExprStmt, Return, Raise,
+//!   Assert, Exit
 //! - **Control flow**: If, While, For, Break, Continue (in control.rs)
 //!
 //! ## Usage
 //! These functions are called from `Compiler::compile_statement()` to handle specific
 //! statement types while keeping the main compilation logic clean and maintainable.
 
-use crate::ast::IRExpr;
-use crate::codegen::{CodeGenError, Compiler};
-use inkwell::values::FunctionValue;
+use crate::ast::{BinOp, IRExpr};
+use crate::codegen::{string_append_operand, CodeGenError, Compiler, ReturnType};
+use crate::compiler::generators::expression;
+use crate::compiler::values::{
+    TYPE_TAG_BOOL, TYPE_TAG_DICT, TYPE_TAG_INT, TYPE_TAG_LIST, TYPE_TAG_NONE, TYPE_TAG_STRING,
+    TYPE_TAG_TUPLE,
+};
+use inkwell::values::{FunctionValue, PointerValue, ValueKind};
+use inkwell::IntPredicate;
 
 // ============================================================================
 // Simple Statement Helpers
 // ============================================================================
 
-/// Compiles a print statement: print(expr1, expr2, ...)
+/// Compiles a print statement: print(expr1, expr2, ..., sep=..., end=...)
+///
+/// `sep` and `end` default to a space and a newline. String literals are folded
+/// into the printed text at compile time; other values are checked at runtime.
 pub fn compile_print<'ctx>(
     compiler: &mut Compiler<'ctx>,
     exprs: &[IRExpr],
+    sep: Option<&IRExpr>,
+    end: Option<&IRExpr>,
 ) -> Result<(), CodeGenError> {
-    // Handle print with multiple arguments
-    if exprs.is_empty() {
+    let literal = |keyword: Option<&'_ IRExpr>, default: &'static str| match keyword {
+        None => Some(default.to_string()),
+        Some(IRExpr::StringLiteral(text)) => Some(text.clone()),
+        Some(_) => None,
+    };
+    let (Some(sep), Some(end)) = (literal(sep, " "), literal(end, "\n")) else {
+        compile_print_computed(compiler, exprs, sep, end)?;
+        build_print_flush(compiler);
+        return Ok(());
+    };
+    let (sep, end) = (sep.as_str(), end.as_str());
+
+    if exprs.is_empty() && end == "\n" {
         // print() with no arguments just prints a newline
-        let printf = compiler.runtime.add_printf(&compiler.module);
         compiler
-            .builder
-            .build_call(
-                printf,
+            .build_print_call(
                 &[compiler
                     .format_strings
                     .get_newline_format_string(&compiler.builder)
@@ -40,22 +60,34 @@ pub fn compile_print<'ctx>(
                 "printf_newline",
             )
             .unwrap();
+    } else if let Some(text) = literal_print_text(exprs, sep) {
+        // All arguments are string literals: emit one printf of the joined line
+        build_print_text(compiler, &format!("{}{}", text, end));
     } else {
         // Print each argument
         for (i, expr) in exprs.iter().enumerate() {
-            let value = compiler.compile_expression(expr)?;
             let is_last = i == exprs.len() - 1;
+            let separator = if is_last { end } else { sep };
+
+            // A string literal's type is known statically: print it (and the
+            // separator that follows) with a single printf, skipping the tag dispatch
+            if let IRExpr::StringLiteral(text) = expr {
+                build_print_text(compiler, &format!("{}{}", text, separator));
+                continue;
+            }
+
+            let value = compiler.compile_expression(expr)?;
 
-            // Print the value (with newline only for the last one)
-            compiler.build_print_value(value, is_last);
+            // The default line ending folds into the value's own format string
+            if is_last && end == "\n" {
+                compiler.build_print_value(value, true);
+                continue;
+            }
+            compiler.build_print_value(value, false);
 
-            // Print a space between arguments (but not after the last one)
-            if !is_last {
-                let printf = compiler.runtime.add_printf(&compiler.module);
+            if !is_last && sep == " " {
                 compiler
-                    .builder
-                    .build_call(
-                        printf,
+                    .build_print_call(
                         &[compiler
                             .format_strings
                             .get_space_format_string(&compiler.builder)
@@ -63,12 +95,162 @@ pub fn compile_print<'ctx>(
                         "printf_space",
                     )
                     .unwrap();
+            } else {
+                build_print_text(compiler, separator);
             }
         }
     }
+
+    build_print_flush(compiler);
     Ok(())
 }
 
+/// Compiles a print whose `sep` or `end` is computed: the arguments are evaluated,
+/// then the keywords, before anything is printed. Each keyword must be a string or
+/// None (its default), like Python's.
+fn compile_print_computed<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    exprs: &[IRExpr],
+    sep: Option<&IRExpr>,
+    end: Option<&IRExpr>,
+) -> Result<(), CodeGenError> {
+    let values = exprs
+        .iter()
+        .map(|expr| compiler.compile_expression(expr))
+        .collect::<Result<Vec<_>, _>>()?;
+    let sep = build_print_keyword_text(compiler, sep, " ", "sep")?;
+    let end = build_print_keyword_text(compiler, end, "\n", "end")?;
+
+    let string_format = compiler
+        .format_strings
+        .get_string_format_string_no_newline(&compiler.builder);
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            compiler
+                .build_print_call(&[string_format.into(), sep.into()], "printf_sep")
+                .unwrap();
+        }
+        compiler.build_print_value(*value, false);
+    }
+    compiler
+        .build_print_call(&[string_format.into(), end.into()], "printf_end")
+        .unwrap();
+    Ok(())
+}
+
+/// Returns the text of a `sep=` / `end=` keyword: the default when it is absent or
+/// None at runtime, the string itself otherwise. Other values raise a TypeError.
+fn build_print_keyword_text<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    keyword: Option<&IRExpr>,
+    default: &str,
+    name: &str,
+) -> Result<PointerValue<'ctx>, CodeGenError> {
+    let default_text = compiler
+        .builder
+        .build_global_string_ptr(default, &format!("default_{}", name))
+        .unwrap()
+        .as_pointer_value();
+    let Some(keyword) = keyword else {
+        return Ok(default_text);
+    };
+    let value = compiler.compile_expression(keyword)?;
+    let i64_type = compiler.context.i64_type();
+    let tag = compiler.extract_tag(value);
+    let tag_is = |compiler: &Compiler<'ctx>, type_tag: u8, tag_name: &str| {
+        compiler
+            .builder
+            .build_int_compare(
+                IntPredicate::EQ,
+                tag,
+                i64_type.const_int(type_tag as u64, false),
+                tag_name,
+            )
+            .unwrap()
+    };
+    let is_string = tag_is(compiler, TYPE_TAG_STRING, "is_string");
+    let is_none = tag_is(compiler, TYPE_TAG_NONE, "is_none");
+    let is_valid = compiler
+        .builder
+        .build_or(is_string, is_none, "is_valid_keyword")
+        .unwrap();
+    let is_invalid = compiler
+        .builder
+        .build_not(is_valid, "is_invalid_keyword")
+        .unwrap();
+    compiler.build_raise_if(
+        is_invalid,
+        &format!("TypeError: {} must be None or a string", name),
+    );
+    let text = compiler.extract_string_ptr(value);
+    Ok(compiler
+        .builder
+        .build_select(is_none, default_text, text, name)
+        .unwrap()
+        .into_pointer_value())
+}
+
+/// In unbuffered mode, flushes after each print (fflush(NULL) flushes all output
+/// streams)
+fn build_print_flush(compiler: &mut Compiler<'_>) {
+    if compiler.unbuffered_output {
+        let fflush = compiler.runtime.add_fflush(&compiler.module);
+        let null_stream = compiler
+            .context
+            .ptr_type(inkwell::AddressSpace::default())
+            .const_null();
+        compiler
+            .builder
+            .build_call(fflush, &[null_stream.into()], "fflush")
+            .unwrap();
+    }
+}
+
+/// Returns the text printed by `print(...)` (before the line ending) when every
+/// argument is a string literal, so it can be folded into a single printf at
+/// compile time
+fn literal_print_text(exprs: &[IRExpr], sep: &str) -> Option<String> {
+    let parts = exprs
+        .iter()
+        .map(|expr| match expr {
+            IRExpr::StringLiteral(s) => Some(s.as_str()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(parts.join(sep))
+}
+
+/// Emits a printf of compile-time text, with '%' escaped since the text becomes
+/// the format string, or an fwrite when the text holds a NUL. Empty text emits
+/// nothing.
+fn build_print_text(compiler: &mut Compiler<'_>, text: &str) {
+    if text.is_empty() {
+        return;
+    }
+    if text.contains('\0') {
+        let bytes = compiler.context.const_string(text.as_bytes(), false);
+        let global = compiler
+            .module
+            .add_global(bytes.get_type(), None, "print_bytes");
+        global.set_initializer(&bytes);
+        global.set_constant(true);
+        global.set_linkage(inkwell::module::Linkage::Private);
+        let len = compiler
+            .context
+            .i64_type()
+            .const_int(text.len() as u64, false);
+        compiler.build_print_bytes(global.as_pointer_value(), len);
+        return;
+    }
+    let format = compiler
+        .builder
+        .build_global_string_ptr(&text.replace('%', "%%"), "print_literal")
+        .unwrap();
+    compiler
+        .build_print_call(&[format.as_pointer_value().into()], "printf_literal")
+        .unwrap();
+}
+
 /// Compiles an assignment statement: target = value
 pub fn compile_assign<'ctx>(
     compiler: &mut Compiler<'ctx>,
@@ -76,13 +258,372 @@ pub fn compile_assign<'ctx>(
     value: &IRExpr,
     current_fn: FunctionValue<'ctx>,
 ) -> Result<(), CodeGenError> {
+    if let (Some(&buffer), Some(addend)) = (
+        compiler.string_buffers.get(target),
+        string_append_operand(target, value, &compiler.function_return_types),
+    ) {
+        return compile_string_append(compiler, target, addend, buffer, current_fn);
+    }
+
     let value = compiler.compile_expression(value)?;
     let ptr = compiler.variables.get(target).copied().unwrap_or_else(|| {
         let ptr = compiler.create_entry_block_alloca(target, current_fn);
         compiler.variables.insert(target.to_string(), ptr);
         ptr
     });
-    compiler.builder.build_store(ptr, value).unwrap();
+    compiler.build_variable_store(ptr, value);
+    Ok(())
+}
+
+/// Compiles `target = target + addend` for a variable with a text buffer
+///
+/// When both operands are strings and the buffer still owns the target's string, the
+/// addend is appended in place, growing the buffer geometrically, so building a string
+/// in a loop takes linear time. If the string is not the buffer's (it was assigned or
+/// read since the last append), it is first copied into a fresh buffer; the old data
+/// is left alone since the read may have shared it. Other operands are added as usual.
+fn compile_string_append<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    target: &str,
+    addend: &IRExpr,
+    buffer: PointerValue<'ctx>,
+    current_fn: FunctionValue<'ctx>,
+) -> Result<(), CodeGenError> {
+    let ptr = *compiler
+        .variables
+        .get(target)
+        .ok_or_else(|| CodeGenError::UndefinedVariable(target.to_string()))?;
+    let current = compiler.build_variable_load(ptr, target);
+    compiler.build_bound_check(current, target);
+    let addend = compiler.compile_expression(addend)?;
+
+    let string_tag = compiler
+        .context
+        .i64_type()
+        .const_int(TYPE_TAG_STRING as u64, false);
+    let current_is_string = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::EQ,
+            compiler.extract_tag(current),
+            string_tag,
+            "current_is_string",
+        )
+        .unwrap();
+    let addend_is_string = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::EQ,
+            compiler.extract_tag(addend),
+            string_tag,
+            "addend_is_string",
+        )
+        .unwrap();
+    let both_strings = compiler
+        .builder
+        .build_and(current_is_string, addend_is_string, "both_strings")
+        .unwrap();
+
+    let append_block = compiler
+        .context
+        .append_basic_block(current_fn, "str_append");
+    let adopt_block = compiler
+        .context
+        .append_basic_block(current_fn, "str_append_adopt");
+    let copy_block = compiler
+        .context
+        .append_basic_block(current_fn, "str_append_copy");
+    let add_block = compiler
+        .context
+        .append_basic_block(current_fn, "str_append_add");
+    let merge_block = compiler
+        .context
+        .append_basic_block(current_fn, "str_append_merge");
+    compiler
+        .builder
+        .build_conditional_branch(both_strings, append_block, add_block)
+        .unwrap();
+
+    // Does the buffer still hold the string the variable refers to?
+    compiler.builder.position_at_end(append_block);
+    let buffer_type = compiler.text_buffer_type();
+    let ptr_type = compiler.context.ptr_type(inkwell::AddressSpace::default());
+    let data_ptr = compiler
+        .builder
+        .build_struct_gep(buffer_type, buffer, 0, "buffer_data_ptr")
+        .unwrap();
+    let data = compiler
+        .builder
+        .build_load(ptr_type, data_ptr, "buffer_data")
+        .unwrap()
+        .into_pointer_value();
+    let current_ptr = compiler.extract_string_ptr(current);
+    let owned = compiler
+        .builder
+        .build_int_compare(IntPredicate::EQ, data, current_ptr, "buffer_owns_string")
+        .unwrap();
+    compiler
+        .builder
+        .build_conditional_branch(owned, copy_block, adopt_block)
+        .unwrap();
+
+    // Start a fresh buffer holding a copy of the current string
+    let buffer_append = compiler.get_or_build_buffer_append();
+    compiler.builder.position_at_end(adopt_block);
+    compiler
+        .builder
+        .build_store(buffer, buffer_type.const_zero())
+        .unwrap();
+    compiler
+        .builder
+        .build_call(buffer_append, &[buffer.into(), current_ptr.into()], "")
+        .unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(copy_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(copy_block);
+    let addend_ptr = compiler.extract_string_ptr(addend);
+    compiler
+        .builder
+        .build_call(buffer_append, &[buffer.into(), addend_ptr.into()], "")
+        .unwrap();
+    let data = compiler
+        .builder
+        .build_load(ptr_type, data_ptr, "buffer_data")
+        .unwrap()
+        .into_pointer_value();
+    let appended = compiler.create_pyobject_string(data);
+    compiler
+        .builder
+        .build_unconditional_branch(merge_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(add_block);
+    let sum = expression::build_binary_op(compiler, &BinOp::Add, current, addend)?;
+    let add_end_block = compiler.builder.get_insert_block().unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(merge_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(merge_block);
+    let phi = compiler
+        .builder
+        .build_phi(compiler.create_pyobject_type(), "str_append_result")
+        .unwrap();
+    phi.add_incoming(&[(&appended, copy_block), (&sum, add_end_block)]);
+    compiler.build_variable_store(ptr, phi.as_basic_value().into_int_value());
+    Ok(())
+}
+
+/// Compiles a `del` statement by unbinding each variable
+///
+/// The variable keeps its slot, which now holds the unbound marker: a later read
+/// (e.g. `x += 1`) raises a NameError at runtime, while a plain assignment binds the
+/// name again. Deleting a variable that is already unbound raises too.
+pub fn compile_delete(compiler: &mut Compiler<'_>, names: &[String]) -> Result<(), CodeGenError> {
+    for name in names {
+        let ptr = *compiler
+            .variables
+            .get(name)
+            .ok_or_else(|| CodeGenError::UndefinedVariable(name.clone()))?;
+        let current = compiler.build_variable_load(ptr, name);
+        compiler.build_bound_check(current, name);
+        let unbound = compiler.values.create_unbound();
+        compiler.build_variable_store(ptr, unbound);
+    }
+    Ok(())
+}
+
+/// Compiles a tuple-unpacking assignment: a, b = value
+/// The value must be a tuple or list holding exactly as many elements as targets
+pub fn compile_unpack_assign<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    targets: &[String],
+    value: &IRExpr,
+    current_fn: FunctionValue<'ctx>,
+) -> Result<(), CodeGenError> {
+    let value = compiler.compile_expression(value)?;
+    let i64_type = compiler.context.i64_type();
+
+    let tag = compiler.extract_tag(value);
+    let is_list = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::EQ,
+            tag,
+            i64_type.const_int(TYPE_TAG_LIST as u64, false),
+            "is_list",
+        )
+        .unwrap();
+    let is_tuple = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::EQ,
+            tag,
+            i64_type.const_int(TYPE_TAG_TUPLE as u64, false),
+            "is_tuple",
+        )
+        .unwrap();
+    let is_sequence = compiler
+        .builder
+        .build_or(is_list, is_tuple, "is_sequence")
+        .unwrap();
+    let not_sequence = compiler
+        .builder
+        .build_not(is_sequence, "not_sequence")
+        .unwrap();
+    compiler.build_raise_if(not_sequence, "TypeError: cannot unpack non-sequence");
+
+    let (seq_ptr, seq_len) = compiler.extract_list_ptr_and_len(value);
+    let expected = i64_type.const_int(targets.len() as u64, false);
+    let too_many = compiler
+        .builder
+        .build_int_compare(IntPredicate::SGT, seq_len, expected, "too_many")
+        .unwrap();
+    compiler.build_raise_if(
+        too_many,
+        &format!(
+            "ValueError: too many values to unpack (expected {})",
+            targets.len()
+        ),
+    );
+    let too_few = compiler
+        .builder
+        .build_int_compare(IntPredicate::SLT, seq_len, expected, "too_few")
+        .unwrap();
+    compiler.build_raise_if(
+        too_few,
+        &format!(
+            "ValueError: not enough values to unpack (expected {})",
+            targets.len()
+        ),
+    );
+
+    // Load every element before storing, so `a, b = b, a`-style sources stay intact
+    let pyobject_type = compiler.create_pyobject_type();
+    let mut elements = Vec::with_capacity(targets.len());
+    for i in 0..targets.len() {
+        // Skip the length header: [length: i64][element_0: i64]...[element_n: i64]
+        let index = i64_type.const_int((i + 1) as u64, false);
+        let elem_ptr = unsafe {
+            compiler
+                .builder
+                .build_in_bounds_gep(pyobject_type, seq_ptr, &[index], "elem_ptr")
+                .unwrap()
+        };
+        let elem = compiler
+            .builder
+            .build_load(pyobject_type, elem_ptr, "elem")
+            .unwrap()
+            .into_int_value();
+        elements.push(elem);
+    }
+    for (target, elem) in targets.iter().zip(elements) {
+        let ptr = compiler.variables.get(target).copied().unwrap_or_else(|| {
+            let ptr = compiler.create_entry_block_alloca(target, current_fn);
+            compiler.variables.insert(target.to_string(), ptr);
+            ptr
+        });
+        compiler.build_variable_store(ptr, elem);
+    }
+    Ok(())
+}
+
+/// Compiles an indexed assignment: list[index] = value
+/// The element is stored directly into the list allocation, so aliases observe the change
+pub fn compile_index_assign<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    list: &IRExpr,
+    index: &IRExpr,
+    value: &IRExpr,
+) -> Result<(), CodeGenError> {
+    let list_obj = compiler.compile_expression(list)?;
+    let index_obj = compiler.compile_expression(index)?;
+    let value = compiler.compile_expression(value)?;
+
+    let current_fn = compiler
+        .builder
+        .get_insert_block()
+        .unwrap()
+        .get_parent()
+        .unwrap();
+    let dict_block = compiler
+        .context
+        .append_basic_block(current_fn, "dict_store");
+    let sequence_block = compiler
+        .context
+        .append_basic_block(current_fn, "sequence_store");
+    let done_block = compiler
+        .context
+        .append_basic_block(current_fn, "store_done");
+    let tag = compiler.extract_tag(list_obj);
+    let is_dict = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::EQ,
+            tag,
+            compiler
+                .context
+                .i64_type()
+                .const_int(TYPE_TAG_DICT as u64, false),
+            "is_dict",
+        )
+        .unwrap();
+    compiler
+        .builder
+        .build_conditional_branch(is_dict, dict_block, sequence_block)
+        .unwrap();
+
+    // Dicts update the entry for the key, or add one
+    compiler.builder.position_at_end(dict_block);
+    let dict_set = compiler.get_or_build_dict_set();
+    compiler
+        .builder
+        .build_call(
+            dict_set,
+            &[list_obj.into(), index_obj.into(), value.into()],
+            "",
+        )
+        .unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(done_block)
+        .unwrap();
+
+    // Tuples are immutable, so only lists can be stored into
+    compiler.builder.position_at_end(sequence_block);
+    let is_tuple = compiler
+        .builder
+        .build_int_compare(
+            IntPredicate::EQ,
+            tag,
+            compiler
+                .context
+                .i64_type()
+                .const_int(TYPE_TAG_TUPLE as u64, false),
+            "is_tuple",
+        )
+        .unwrap();
+    compiler.build_raise_if(
+        is_tuple,
+        "TypeError: 'tuple' object does not support item assignment",
+    );
+    let elem_ptr = expression::build_element_ptr(
+        compiler,
+        list_obj,
+        index_obj,
+        "IndexError: list assignment index out of range",
+    );
+    compiler.builder.build_store(elem_ptr, value).unwrap();
+    compiler
+        .builder
+        .build_unconditional_branch(done_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(done_block);
     Ok(())
 }
 
@@ -98,11 +639,302 @@ pub fn compile_expr_stmt<'ctx>(
 }
 
 /// Compiles a return statement: return expr
+/// Functions inferred to return an integer return the raw payload instead of a PyObject
 pub fn compile_return<'ctx>(
     compiler: &mut Compiler<'ctx>,
     expr: &IRExpr,
+    current_fn: FunctionValue<'ctx>,
 ) -> Result<(), CodeGenError> {
     let value = compiler.compile_expression(expr)?;
+    // Returning from inside try blocks: restore the caller's exception handler
+    compiler.restore_exception_handler(0);
+    compiler.build_recursion_exit();
+    let fn_name = current_fn.get_name().to_str().unwrap_or_default();
+    let value = match compiler.function_return_type(fn_name) {
+        ReturnType::Int => {
+            let payload = compiler.extract_payload(value);
+            compiler
+                .builder
+                .build_float_to_signed_int(payload, compiler.context.i64_type(), "ret_int")
+                .unwrap()
+        }
+        ReturnType::PyObject => value,
+    };
     compiler.builder.build_return(Some(&value)).unwrap();
     Ok(())
 }
+
+/// Compiles a raise statement through `raise_error`, so it is caught by an enclosing
+/// `try` or otherwise prints `Name: message` and exits with status 1.
+/// A bare `raise` re-raises the most recent runtime error.
+pub fn compile_raise<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    exception: Option<&str>,
+    message: Option<&IRExpr>,
+    current_fn: FunctionValue<'ctx>,
+) -> Result<(), CodeGenError> {
+    let message_ptr = match (exception, message) {
+        // Literal messages are formatted at compile time
+        (Some(name), Some(IRExpr::StringLiteral(text))) => compiler
+            .builder
+            .build_global_string_ptr(&format!("{}: {}", name, text), "raise_message")
+            .unwrap()
+            .as_pointer_value(),
+        (Some(name), None) => compiler
+            .builder
+            .build_global_string_ptr(name, "raise_message")
+            .unwrap()
+            .as_pointer_value(),
+        (Some(name), Some(expr)) => {
+            let message_obj = compiler.compile_expression(expr)?;
+            let tag = compiler.extract_tag(message_obj);
+            let string_tag = compiler
+                .context
+                .i64_type()
+                .const_int(TYPE_TAG_STRING as u64, false);
+            let not_string = compiler
+                .builder
+                .build_int_compare(IntPredicate::NE, tag, string_tag, "message_not_string")
+                .unwrap();
+            compiler.build_raise_if(not_string, "TypeError: exception message must be a string");
+            let text_ptr = compiler.extract_string_ptr(message_obj);
+            build_prefixed_message(compiler, &format!("{}: ", name), text_ptr)?
+        }
+        (None, _) => {
+            let ptr_type = compiler.context.ptr_type(inkwell::AddressSpace::default());
+            let current_exception = compiler
+                .runtime
+                .add_current_exception(&compiler.module)
+                .as_pointer_value();
+            let current = compiler
+                .builder
+                .build_load(ptr_type, current_exception, "current_exception")
+                .unwrap()
+                .into_pointer_value();
+            let no_exception = compiler
+                .builder
+                .build_is_null(current, "no_active_exception")
+                .unwrap();
+            let reraise_error = compiler
+                .builder
+                .build_global_string_ptr(
+                    "RuntimeError: No active exception to reraise",
+                    "reraise_error_message",
+                )
+                .unwrap()
+                .as_pointer_value();
+            compiler
+                .builder
+                .build_select(no_exception, reraise_error, current, "raise_message")
+                .unwrap()
+                .into_pointer_value()
+        }
+    };
+
+    let raise_error = compiler.runtime.add_raise_error(&compiler.module);
+    compiler
+        .builder
+        .build_call(raise_error, &[message_ptr.into()], "")
+        .unwrap();
+    compiler.builder.build_unreachable().unwrap();
+
+    // Statements after the raise are unreachable; give them a block of their own
+    let after_raise = compiler
+        .context
+        .append_basic_block(current_fn, "after_raise");
+    compiler.builder.position_at_end(after_raise);
+    Ok(())
+}
+
+/// Compiles an assert statement: raises an AssertionError, with the message if given,
+/// when the condition is falsy. The message is only evaluated on failure.
+pub fn compile_assert<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    condition: &IRExpr,
+    message: Option<&IRExpr>,
+    current_fn: FunctionValue<'ctx>,
+) -> Result<(), CodeGenError> {
+    let condition_obj = compiler.compile_expression(condition)?;
+    let holds = compiler.pyobject_to_bool(condition_obj);
+    let fail_block = compiler
+        .context
+        .append_basic_block(current_fn, "assert_fail");
+    let ok_block = compiler.context.append_basic_block(current_fn, "assert_ok");
+    compiler
+        .builder
+        .build_conditional_branch(holds, ok_block, fail_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(fail_block);
+    compile_raise(compiler, Some("AssertionError"), message, current_fn)?;
+    // compile_raise leaves the builder in an unreachable block
+    compiler
+        .builder
+        .build_unconditional_branch(ok_block)
+        .unwrap();
+
+    compiler.builder.position_at_end(ok_block);
+    Ok(())
+}
+
+/// Compiles an exit()/quit() call: ends the program through the C `exit` function,
+/// which also flushes stdout
+///
+/// The status must be an int (or bool), or None for 0; anything else raises a TypeError.
+pub fn compile_exit<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    code: &IRExpr,
+    current_fn: FunctionValue<'ctx>,
+) -> Result<(), CodeGenError> {
+    let code_obj = compiler.compile_expression(code)?;
+    let i64_type = compiler.context.i64_type();
+    let tag = compiler.extract_tag(code_obj);
+    let tag_is = |compiler: &Compiler<'ctx>, type_tag: u8, name: &str| {
+        compiler
+            .builder
+            .build_int_compare(
+                IntPredicate::EQ,
+                tag,
+                i64_type.const_int(type_tag as u64, false),
+                name,
+            )
+            .unwrap()
+    };
+    let is_int = tag_is(compiler, TYPE_TAG_INT, "is_int");
+    let is_bool = tag_is(compiler, TYPE_TAG_BOOL, "is_bool");
+    let is_none = tag_is(compiler, TYPE_TAG_NONE, "is_none");
+    let is_number = compiler
+        .builder
+        .build_or(is_int, is_bool, "is_int_or_bool")
+        .unwrap();
+    let is_valid = compiler
+        .builder
+        .build_or(is_number, is_none, "is_valid_code")
+        .unwrap();
+    let is_invalid = compiler
+        .builder
+        .build_not(is_valid, "is_invalid_code")
+        .unwrap();
+    compiler.build_raise_if(
+        is_invalid,
+        "TypeError: exit() code must be an integer or None",
+    );
+
+    let payload = compiler.extract_payload(code_obj);
+    let status = compiler
+        .builder
+        .build_float_to_signed_int(payload, compiler.context.i32_type(), "exit_status")
+        .unwrap();
+    let status = compiler
+        .builder
+        .build_select(
+            is_none,
+            compiler.context.i32_type().const_zero(),
+            status,
+            "exit_status",
+        )
+        .unwrap();
+    let exit_fn = compiler.runtime.add_exit(&compiler.module);
+    compiler
+        .builder
+        .build_call(exit_fn, &[status.into()], "")
+        .unwrap();
+    compiler.builder.build_unreachable().unwrap();
+
+    // Statements after the exit are unreachable; give them a block of their own
+    let after_exit = compiler
+        .context
+        .append_basic_block(current_fn, "after_exit");
+    compiler.builder.position_at_end(after_exit);
+    Ok(())
+}
+
+/// Allocates `prefix` followed by the null-terminated string at `text`
+fn build_prefixed_message<'ctx>(
+    compiler: &mut Compiler<'ctx>,
+    prefix: &str,
+    text: PointerValue<'ctx>,
+) -> Result<PointerValue<'ctx>, CodeGenError> {
+    let i64_type = compiler.context.i64_type();
+    let prefix_len = i64_type.const_int(prefix.len() as u64, false);
+    let strlen_fn = compiler.runtime.add_strlen(&compiler.module);
+    let text_len = match compiler
+        .builder
+        .build_call(strlen_fn, &[text.into()], "message_len")
+        .unwrap()
+        .try_as_basic_value()
+    {
+        ValueKind::Basic(value) => value.into_int_value(),
+        ValueKind::Instruction(_) => {
+            return Err(CodeGenError::UndefinedVariable(
+                "strlen did not return a value".to_string(),
+            ))
+        }
+    };
+    let total_size = compiler
+        .builder
+        .build_int_add(
+            compiler
+                .builder
+                .build_int_add(prefix_len, text_len, "message_total_len")
+                .unwrap(),
+            i64_type.const_int(1, false),
+            "message_size",
+        )
+        .unwrap();
+
+    let malloc_fn = compiler.runtime.add_malloc(&compiler.module);
+    let buffer = match compiler
+        .builder
+        .build_call(malloc_fn, &[total_size.into()], "message_buffer")
+        .unwrap()
+        .try_as_basic_value()
+    {
+        ValueKind::Basic(value) => value.into_pointer_value(),
+        ValueKind::Instruction(_) => {
+            return Err(CodeGenError::UndefinedVariable(
+                "malloc did not return a value".to_string(),
+            ))
+        }
+    };
+
+    // Copy the prefix, then the text including its null terminator
+    let memcpy_fn = compiler.runtime.add_memcpy(&compiler.module);
+    let prefix_ptr = compiler
+        .builder
+        .build_global_string_ptr(prefix, "raise_prefix")
+        .unwrap()
+        .as_pointer_value();
+    compiler
+        .builder
+        .build_call(
+            memcpy_fn,
+            &[buffer.into(), prefix_ptr.into(), prefix_len.into()],
+            "memcpy_prefix",
+        )
+        .unwrap();
+    let text_dest = unsafe {
+        compiler
+            .builder
+            .build_gep(
+                compiler.context.i8_type(),
+                buffer,
+                &[prefix_len],
+                "message_dest",
+            )
+            .unwrap()
+    };
+    let text_copy_len = compiler
+        .builder
+        .build_int_add(text_len, i64_type.const_int(1, false), "message_copy_len")
+        .unwrap();
+    compiler
+        .builder
+        .build_call(
+            memcpy_fn,
+            &[text_dest.into(), text.into(), text_copy_len.into()],
+            "memcpy_message",
+        )
+        .unwrap();
+    Ok(buffer)
+}
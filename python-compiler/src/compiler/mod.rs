@@ -6,10 +6,12 @@
 //! - `runtime`: External C function declarations (printf, malloc, etc.)
 //! - `values`: NaN-boxing type system for PyObject representation
 //! - `generators`: Code generation modules (expression, statement, control flow)
+//! - `session`: Reusing one LLVM context (and an optional IR cache) across programs
 //!
 //! ## Refactoring Progress
 //! See `/REFACTORING_PROGRESS.md` for detailed progress and next steps.
 
 pub mod generators;
 pub mod runtime;
+pub mod session;
 pub mod values;
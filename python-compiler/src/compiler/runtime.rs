@@ -1,16 +1,36 @@
 //! Runtime and External Functions
 //!
 //! This module manages declarations for external C library functions used by the compiler.
-//! It handles printf, scanf, malloc, free, strlen, and memcpy.
+//! It handles printf, scanf, malloc, free, strlen, memcpy, fflush, exit, setjmp, longjmp,
+//! the stdio file functions (fopen, fclose, fputs, fread, fseek, ftell), the conversions
+//! used to format floats (snprintf, strtod, strchr, atoi), plus the small runtime helpers
+//! (such as `raise_error` and `format_float`) that are emitted into the module.
+//!
+//! ## Runtime errors
+//! Runtime errors call `raise_error(message)`. If a `try` block is active,
+//! `rusthon_exception_handler` points at its `jmp_buf` and `raise_error` longjmps there;
+//! otherwise the message is printed and the program exits with status 1.
 //!
 //! ## Purpose
 //! - Centralizes external function management
 //! - Provides a clean interface for declaring runtime functions
 //! - Respects Single Responsibility Principle (SRP)
 
+use inkwell::attributes::{Attribute, AttributeLoc};
 use inkwell::context::Context;
 use inkwell::module::{Linkage, Module};
-use inkwell::values::{FunctionValue, PointerValue};
+use inkwell::values::{FunctionValue, GlobalValue, PointerValue};
+
+/// Size in bytes reserved for a `jmp_buf` (larger than any supported platform needs)
+pub const JMP_BUF_SIZE: u32 = 512;
+
+/// Size in bytes of the buffer `format_float` writes into (17 significant digits,
+/// sign, point, exponent and terminator fit with room to spare)
+pub const FLOAT_REPR_SIZE: u32 = 32;
+
+/// Size in bytes of the heap buffer a string-mode `input()` reads a line into; longer
+/// lines are truncated to `INPUT_LINE_SIZE - 1` characters
+pub const INPUT_LINE_SIZE: u64 = 1024;
 
 /// Runtime manager for external C functions
 pub struct Runtime<'ctx> {
@@ -35,6 +55,52 @@ impl<'ctx> Runtime<'ctx> {
         module.add_function("printf", printf_type, Some(Linkage::External))
     }
 
+    /// Declares fprintf function if not already declared
+    /// Signature: int fprintf(FILE* stream, const char* format, ...)
+    pub fn add_fprintf(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("fprintf") {
+            return function;
+        }
+        let i32_type = self.context.i32_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fprintf_type = i32_type.fn_type(&[ptr_type.into(), ptr_type.into()], true);
+        module.add_function("fprintf", fprintf_type, Some(Linkage::External))
+    }
+
+    /// Declares the C library's `stdout` stream global if not already declared
+    /// (`__stdoutp` on macOS)
+    pub fn add_stdout(&self, module: &Module<'ctx>) -> GlobalValue<'ctx> {
+        let name = if cfg!(target_os = "macos") {
+            "__stdoutp"
+        } else {
+            "stdout"
+        };
+        if let Some(global) = module.get_global(name) {
+            return global;
+        }
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let global = module.add_global(ptr_type, None, name);
+        global.set_linkage(Linkage::External);
+        global
+    }
+
+    /// Declares the C library's `stderr` stream global if not already declared
+    /// (`__stderrp` on macOS)
+    pub fn add_stderr(&self, module: &Module<'ctx>) -> GlobalValue<'ctx> {
+        let name = if cfg!(target_os = "macos") {
+            "__stderrp"
+        } else {
+            "stderr"
+        };
+        if let Some(global) = module.get_global(name) {
+            return global;
+        }
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let global = module.add_global(ptr_type, None, name);
+        global.set_linkage(Linkage::External);
+        global
+    }
+
     /// Declares scanf function if not already declared
     /// Signature: int scanf(const char* format, ...)
     pub fn add_scanf(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
@@ -86,6 +152,18 @@ impl<'ctx> Runtime<'ctx> {
         module.add_function("free", free_type, Some(Linkage::External))
     }
 
+    /// Declares realloc function if not already declared
+    /// Signature: void* realloc(void* ptr, size_t size)
+    pub fn add_realloc(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("realloc") {
+            return function;
+        }
+        let i8_ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let size_type = self.context.i64_type();
+        let realloc_type = i8_ptr_type.fn_type(&[i8_ptr_type.into(), size_type.into()], false);
+        module.add_function("realloc", realloc_type, Some(Linkage::External))
+    }
+
     /// Declares strlen function if not already declared
     /// Signature: size_t strlen(const char* s)
     pub fn add_strlen(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
@@ -97,6 +175,651 @@ impl<'ctx> Runtime<'ctx> {
         let strlen_type = size_type.fn_type(&[i8_ptr_type.into()], false);
         module.add_function("strlen", strlen_type, Some(Linkage::External))
     }
+
+    /// Declares fflush function if not already declared
+    /// Signature: int fflush(FILE* stream)
+    pub fn add_fflush(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("fflush") {
+            return function;
+        }
+        let i32_type = self.context.i32_type();
+        let i8_ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fflush_type = i32_type.fn_type(&[i8_ptr_type.into()], false);
+        module.add_function("fflush", fflush_type, Some(Linkage::External))
+    }
+
+    /// Declares exit function if not already declared
+    /// Signature: void exit(int status)
+    pub fn add_exit(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("exit") {
+            return function;
+        }
+        let void_type = self.context.void_type();
+        let i32_type = self.context.i32_type();
+        let exit_type = void_type.fn_type(&[i32_type.into()], false);
+        module.add_function("exit", exit_type, Some(Linkage::External))
+    }
+
+    /// Declares setjmp function if not already declared
+    /// Signature: int setjmp(jmp_buf env), marked returns_twice
+    pub fn add_setjmp(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("setjmp") {
+            return function;
+        }
+        let i32_type = self.context.i32_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let setjmp_type = i32_type.fn_type(&[ptr_type.into()], false);
+        let function = module.add_function("setjmp", setjmp_type, Some(Linkage::External));
+        let returns_twice = self
+            .context
+            .create_enum_attribute(Attribute::get_named_enum_kind_id("returns_twice"), 0);
+        function.add_attribute(AttributeLoc::Function, returns_twice);
+        function
+    }
+
+    /// Declares longjmp function if not already declared
+    /// Signature: void longjmp(jmp_buf env, int val)
+    pub fn add_longjmp(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("longjmp") {
+            return function;
+        }
+        let void_type = self.context.void_type();
+        let i32_type = self.context.i32_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let longjmp_type = void_type.fn_type(&[ptr_type.into(), i32_type.into()], false);
+        let function = module.add_function("longjmp", longjmp_type, Some(Linkage::External));
+        let noreturn = self
+            .context
+            .create_enum_attribute(Attribute::get_named_enum_kind_id("noreturn"), 0);
+        function.add_attribute(AttributeLoc::Function, noreturn);
+        function
+    }
+
+    /// Declares snprintf function if not already declared
+    /// Signature: int snprintf(char* buffer, size_t size, const char* format, ...)
+    pub fn add_snprintf(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("snprintf") {
+            return function;
+        }
+        let i32_type = self.context.i32_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let size_type = self.context.i64_type();
+        let snprintf_type =
+            i32_type.fn_type(&[ptr_type.into(), size_type.into(), ptr_type.into()], true);
+        module.add_function("snprintf", snprintf_type, Some(Linkage::External))
+    }
+
+    /// Declares strtod function if not already declared
+    /// Signature: double strtod(const char* s, char** end)
+    pub fn add_strtod(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("strtod") {
+            return function;
+        }
+        let f64_type = self.context.f64_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let strtod_type = f64_type.fn_type(&[ptr_type.into(), ptr_type.into()], false);
+        module.add_function("strtod", strtod_type, Some(Linkage::External))
+    }
+
+    /// Declares strtoll function if not already declared
+    /// Signature: long long strtoll(const char* s, char** end, int base)
+    pub fn add_strtoll(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("strtoll") {
+            return function;
+        }
+        let i32_type = self.context.i32_type();
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let strtoll_type =
+            i64_type.fn_type(&[ptr_type.into(), ptr_type.into(), i32_type.into()], false);
+        module.add_function("strtoll", strtoll_type, Some(Linkage::External))
+    }
+
+    /// Declares strchr function if not already declared
+    /// Signature: char* strchr(const char* s, int c)
+    pub fn add_strchr(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("strchr") {
+            return function;
+        }
+        let i32_type = self.context.i32_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let strchr_type = ptr_type.fn_type(&[ptr_type.into(), i32_type.into()], false);
+        module.add_function("strchr", strchr_type, Some(Linkage::External))
+    }
+
+    /// Declares strstr function if not already declared
+    /// Signature: char* strstr(const char* haystack, const char* needle)
+    pub fn add_strstr(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("strstr") {
+            return function;
+        }
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let strstr_type = ptr_type.fn_type(&[ptr_type.into(), ptr_type.into()], false);
+        module.add_function("strstr", strstr_type, Some(Linkage::External))
+    }
+
+    /// Declares strcmp function if not already declared
+    /// Signature: int strcmp(const char* s1, const char* s2)
+    pub fn add_strcmp(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("strcmp") {
+            return function;
+        }
+        let i32_type = self.context.i32_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let strcmp_type = i32_type.fn_type(&[ptr_type.into(), ptr_type.into()], false);
+        module.add_function("strcmp", strcmp_type, Some(Linkage::External))
+    }
+
+    /// Declares strncmp function if not already declared
+    /// Signature: int strncmp(const char* a, const char* b, size_t n)
+    pub fn add_strncmp(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("strncmp") {
+            return function;
+        }
+        let i32_type = self.context.i32_type();
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let strncmp_type =
+            i32_type.fn_type(&[ptr_type.into(), ptr_type.into(), i64_type.into()], false);
+        module.add_function("strncmp", strncmp_type, Some(Linkage::External))
+    }
+
+    /// Declares atoi function if not already declared
+    /// Signature: int atoi(const char* s)
+    pub fn add_atoi(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("atoi") {
+            return function;
+        }
+        let i32_type = self.context.i32_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let atoi_type = i32_type.fn_type(&[ptr_type.into()], false);
+        module.add_function("atoi", atoi_type, Some(Linkage::External))
+    }
+
+    /// Declares fopen function if not already declared
+    /// Signature: FILE* fopen(const char* path, const char* mode)
+    pub fn add_fopen(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("fopen") {
+            return function;
+        }
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fopen_type = ptr_type.fn_type(&[ptr_type.into(), ptr_type.into()], false);
+        module.add_function("fopen", fopen_type, Some(Linkage::External))
+    }
+
+    /// Declares fclose function if not already declared
+    /// Signature: int fclose(FILE* stream)
+    pub fn add_fclose(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("fclose") {
+            return function;
+        }
+        let i32_type = self.context.i32_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fclose_type = i32_type.fn_type(&[ptr_type.into()], false);
+        module.add_function("fclose", fclose_type, Some(Linkage::External))
+    }
+
+    /// Declares fputs function if not already declared
+    /// Signature: int fputs(const char* str, FILE* stream)
+    pub fn add_fputs(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("fputs") {
+            return function;
+        }
+        let i32_type = self.context.i32_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fputs_type = i32_type.fn_type(&[ptr_type.into(), ptr_type.into()], false);
+        module.add_function("fputs", fputs_type, Some(Linkage::External))
+    }
+
+    /// Declares fwrite function if not already declared
+    /// Signature: size_t fwrite(const void* ptr, size_t size, size_t count, FILE* stream)
+    pub fn add_fwrite(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("fwrite") {
+            return function;
+        }
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fwrite_type = i64_type.fn_type(
+            &[
+                ptr_type.into(),
+                i64_type.into(),
+                i64_type.into(),
+                ptr_type.into(),
+            ],
+            false,
+        );
+        module.add_function("fwrite", fwrite_type, Some(Linkage::External))
+    }
+
+    /// Declares fread function if not already declared
+    /// Signature: size_t fread(void* ptr, size_t size, size_t count, FILE* stream)
+    pub fn add_fread(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("fread") {
+            return function;
+        }
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fread_type = i64_type.fn_type(
+            &[
+                ptr_type.into(),
+                i64_type.into(),
+                i64_type.into(),
+                ptr_type.into(),
+            ],
+            false,
+        );
+        module.add_function("fread", fread_type, Some(Linkage::External))
+    }
+
+    /// Declares fseek function if not already declared
+    /// Signature: int fseek(FILE* stream, long offset, int whence)
+    pub fn add_fseek(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("fseek") {
+            return function;
+        }
+        let i32_type = self.context.i32_type();
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fseek_type =
+            i32_type.fn_type(&[ptr_type.into(), i64_type.into(), i32_type.into()], false);
+        module.add_function("fseek", fseek_type, Some(Linkage::External))
+    }
+
+    /// Declares ftell function if not already declared
+    /// Signature: long ftell(FILE* stream)
+    pub fn add_ftell(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("ftell") {
+            return function;
+        }
+        let i64_type = self.context.i64_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let ftell_type = i64_type.fn_type(&[ptr_type.into()], false);
+        module.add_function("ftell", ftell_type, Some(Linkage::External))
+    }
+
+    /// Defines the global holding the innermost active `try` block's jmp_buf (null if none)
+    pub fn add_exception_handler(&self, module: &Module<'ctx>) -> GlobalValue<'ctx> {
+        if let Some(global) = module.get_global("rusthon_exception_handler") {
+            return global;
+        }
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let global = module.add_global(ptr_type, None, "rusthon_exception_handler");
+        global.set_linkage(Linkage::Internal);
+        global.set_initializer(&ptr_type.const_null());
+        global
+    }
+
+    /// Defines the global holding the message of the most recent runtime error (null if
+    /// none yet), re-raised by a bare `raise`
+    pub fn add_current_exception(&self, module: &Module<'ctx>) -> GlobalValue<'ctx> {
+        if let Some(global) = module.get_global("rusthon_current_exception") {
+            return global;
+        }
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let global = module.add_global(ptr_type, None, "rusthon_current_exception");
+        global.set_linkage(Linkage::Internal);
+        global.set_initializer(&ptr_type.const_null());
+        global
+    }
+
+    /// Defines the global counting active user function calls (for --recursion-limit)
+    pub fn add_recursion_depth(&self, module: &Module<'ctx>) -> GlobalValue<'ctx> {
+        if let Some(global) = module.get_global("rusthon_recursion_depth") {
+            return global;
+        }
+        let i64_type = self.context.i64_type();
+        let global = module.add_global(i64_type, None, "rusthon_recursion_depth");
+        global.set_linkage(Linkage::Internal);
+        global.set_initializer(&i64_type.const_zero());
+        global
+    }
+
+    /// Defines the raise_error helper if not already defined
+    /// Records the message as the current exception, then jumps to the active exception
+    /// handler, or flushes stdout, prints the message to stderr and exits with status 1
+    /// Signature: void raise_error(const char* message)
+    pub fn add_raise_error(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("raise_error") {
+            return function;
+        }
+        let fflush = self.add_fflush(module);
+        let fprintf = self.add_fprintf(module);
+        let stderr = self.add_stderr(module);
+        let exit = self.add_exit(module);
+        let longjmp = self.add_longjmp(module);
+        let handler = self.add_exception_handler(module);
+        let current_exception = self.add_current_exception(module);
+
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fn_type = self.context.void_type().fn_type(&[ptr_type.into()], false);
+        let function = module.add_function("raise_error", fn_type, Some(Linkage::Internal));
+        let builder = self.context.create_builder();
+        let entry = self.context.append_basic_block(function, "entry");
+        let jump_block = self.context.append_basic_block(function, "jump_to_handler");
+        let abort_block = self.context.append_basic_block(function, "abort");
+
+        builder.position_at_end(entry);
+        let message = function.get_first_param().unwrap();
+        builder
+            .build_store(current_exception.as_pointer_value(), message)
+            .unwrap();
+        let handler_ptr = builder
+            .build_load(ptr_type, handler.as_pointer_value(), "handler")
+            .unwrap()
+            .into_pointer_value();
+        let has_handler = builder
+            .build_is_not_null(handler_ptr, "has_handler")
+            .unwrap();
+        builder
+            .build_conditional_branch(has_handler, jump_block, abort_block)
+            .unwrap();
+
+        // Inside a try block: resume at its setjmp with a non-zero value
+        builder.position_at_end(jump_block);
+        builder
+            .build_call(
+                longjmp,
+                &[
+                    handler_ptr.into(),
+                    self.context.i32_type().const_int(1, false).into(),
+                ],
+                "",
+            )
+            .unwrap();
+        builder.build_unreachable().unwrap();
+
+        // Uncaught: print the message after everything already printed, and exit
+        builder.position_at_end(abort_block);
+        builder
+            .build_call(fflush, &[ptr_type.const_null().into()], "")
+            .unwrap();
+        let stderr_stream = builder
+            .build_load(ptr_type, stderr.as_pointer_value(), "stderr")
+            .unwrap();
+        let format = builder
+            .build_global_string_ptr("%s\n", "error_format_string")
+            .unwrap()
+            .as_pointer_value();
+        builder
+            .build_call(
+                fprintf,
+                &[stderr_stream.into(), format.into(), message.into()],
+                "fprintf_error",
+            )
+            .unwrap();
+        builder
+            .build_call(
+                exit,
+                &[self.context.i32_type().const_int(1, false).into()],
+                "",
+            )
+            .unwrap();
+        builder.build_unreachable().unwrap();
+        function
+    }
+
+    /// Defines the raise_overflow helper if not already defined
+    /// Raises an OverflowError through raise_error
+    /// Signature: void raise_overflow()
+    pub fn add_raise_overflow(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("raise_overflow") {
+            return function;
+        }
+        let raise_error = self.add_raise_error(module);
+
+        let fn_type = self.context.void_type().fn_type(&[], false);
+        let function = module.add_function("raise_overflow", fn_type, Some(Linkage::Internal));
+        let builder = self.context.create_builder();
+        let entry = self.context.append_basic_block(function, "entry");
+        builder.position_at_end(entry);
+
+        let message = builder
+            .build_global_string_ptr("OverflowError: integer overflow", "overflow_error_message")
+            .unwrap()
+            .as_pointer_value();
+        builder
+            .build_call(raise_error, &[message.into()], "")
+            .unwrap();
+        builder.build_unreachable().unwrap();
+        function
+    }
+
+    /// Defines the format_float helper if not already defined
+    /// Writes the shortest representation of the value that reads back exactly, laid out
+    /// like Python's `repr(float)`: fixed notation with at least one decimal (`2.0`,
+    /// `-0.0`, `0.0001`) when the decimal exponent is in [-4, 16), scientific notation
+    /// otherwise (`1e+300`, `1e-10`), and `inf` / `nan` as printed by the C library.
+    /// Signature: void format_float(double value, char* buffer), where the buffer holds
+    /// at least `FLOAT_REPR_SIZE` bytes
+    pub fn add_format_float(&self, module: &Module<'ctx>) -> FunctionValue<'ctx> {
+        if let Some(function) = module.get_function("format_float") {
+            return function;
+        }
+        let snprintf = self.add_snprintf(module);
+        let strtod = self.add_strtod(module);
+        let strchr = self.add_strchr(module);
+        let atoi = self.add_atoi(module);
+        let strlen = self.add_strlen(module);
+
+        let i8_type = self.context.i8_type();
+        let i32_type = self.context.i32_type();
+        let ptr_type = self.context.ptr_type(inkwell::AddressSpace::default());
+        let fn_type = self
+            .context
+            .void_type()
+            .fn_type(&[self.context.f64_type().into(), ptr_type.into()], false);
+        let function = module.add_function("format_float", fn_type, Some(Linkage::Internal));
+        let builder = self.context.create_builder();
+        let entry = self.context.append_basic_block(function, "entry");
+        let digits_block = self.context.append_basic_block(function, "digits");
+        let more_digits_block = self.context.append_basic_block(function, "more_digits");
+        let exponent_block = self.context.append_basic_block(function, "exponent");
+        let notation_block = self.context.append_basic_block(function, "notation");
+        let fixed_block = self.context.append_basic_block(function, "fixed");
+        let point_block = self
+            .context
+            .append_basic_block(function, "append_point_zero");
+        let done_block = self.context.append_basic_block(function, "done");
+
+        builder.position_at_end(entry);
+        let value = function.get_nth_param(0).unwrap().into_float_value();
+        let buffer = function.get_nth_param(1).unwrap().into_pointer_value();
+        let buffer_size = self
+            .context
+            .i64_type()
+            .const_int(FLOAT_REPR_SIZE as u64, false);
+        let scientific_format = builder
+            .build_global_string_ptr("%.*e", "float_scientific_format")
+            .unwrap()
+            .as_pointer_value();
+        let fixed_format = builder
+            .build_global_string_ptr("%.*f", "float_fixed_format")
+            .unwrap()
+            .as_pointer_value();
+        builder.build_unconditional_branch(digits_block).unwrap();
+
+        // Try 1..=17 significant digits until the text parses back to the same value
+        // (17 always does; NaN never compares equal and stops there too)
+        builder.position_at_end(digits_block);
+        let digits = builder.build_phi(i32_type, "digits").unwrap();
+        let digit_count = digits.as_basic_value().into_int_value();
+        let one = i32_type.const_int(1, false);
+        let precision = builder
+            .build_int_sub(digit_count, one, "precision")
+            .unwrap();
+        builder
+            .build_call(
+                snprintf,
+                &[
+                    buffer.into(),
+                    buffer_size.into(),
+                    scientific_format.into(),
+                    precision.into(),
+                    value.into(),
+                ],
+                "snprintf_scientific",
+            )
+            .unwrap();
+        let parsed = builder
+            .build_call(
+                strtod,
+                &[buffer.into(), ptr_type.const_null().into()],
+                "parsed",
+            )
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_float_value();
+        let exact = builder
+            .build_float_compare(inkwell::FloatPredicate::OEQ, parsed, value, "exact")
+            .unwrap();
+        let at_max = builder
+            .build_int_compare(
+                inkwell::IntPredicate::SGE,
+                digit_count,
+                i32_type.const_int(17, false),
+                "at_max_digits",
+            )
+            .unwrap();
+        let found = builder.build_or(exact, at_max, "found_digits").unwrap();
+        builder
+            .build_conditional_branch(found, exponent_block, more_digits_block)
+            .unwrap();
+
+        builder.position_at_end(more_digits_block);
+        let next_digits = builder
+            .build_int_add(digit_count, one, "next_digits")
+            .unwrap();
+        builder.build_unconditional_branch(digits_block).unwrap();
+        digits.add_incoming(&[(&one, entry), (&next_digits, more_digits_block)]);
+
+        // inf and nan have no exponent and are kept as formatted
+        builder.position_at_end(exponent_block);
+        let exponent_ptr = builder
+            .build_call(
+                strchr,
+                &[buffer.into(), i32_type.const_int(b'e' as u64, false).into()],
+                "exponent_ptr",
+            )
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_pointer_value();
+        let has_exponent = builder
+            .build_is_not_null(exponent_ptr, "has_exponent")
+            .unwrap();
+        builder
+            .build_conditional_branch(has_exponent, notation_block, done_block)
+            .unwrap();
+
+        builder.position_at_end(notation_block);
+        let exponent_digits = unsafe {
+            builder
+                .build_in_bounds_gep(
+                    i8_type,
+                    exponent_ptr,
+                    &[self.context.i64_type().const_int(1, false)],
+                    "exponent_digits",
+                )
+                .unwrap()
+        };
+        let exponent = builder
+            .build_call(atoi, &[exponent_digits.into()], "exponent")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        let too_small = builder
+            .build_int_compare(
+                inkwell::IntPredicate::SLT,
+                exponent,
+                i32_type.const_int((-4i64) as u64, true),
+                "exponent_too_small",
+            )
+            .unwrap();
+        let too_large = builder
+            .build_int_compare(
+                inkwell::IntPredicate::SGE,
+                exponent,
+                i32_type.const_int(16, false),
+                "exponent_too_large",
+            )
+            .unwrap();
+        let scientific = builder
+            .build_or(too_small, too_large, "scientific")
+            .unwrap();
+        builder
+            .build_conditional_branch(scientific, done_block, fixed_block)
+            .unwrap();
+
+        // Fixed notation keeps the same significant digits: digits - 1 - exponent decimals
+        builder.position_at_end(fixed_block);
+        let decimals = builder
+            .build_int_sub(precision, exponent, "decimals")
+            .unwrap();
+        let no_decimals = builder
+            .build_int_compare(
+                inkwell::IntPredicate::SLE,
+                decimals,
+                i32_type.const_zero(),
+                "no_decimals",
+            )
+            .unwrap();
+        let decimals = builder
+            .build_select(no_decimals, i32_type.const_zero(), decimals, "decimals")
+            .unwrap();
+        builder
+            .build_call(
+                snprintf,
+                &[
+                    buffer.into(),
+                    buffer_size.into(),
+                    fixed_format.into(),
+                    decimals.into(),
+                    value.into(),
+                ],
+                "snprintf_fixed",
+            )
+            .unwrap();
+        builder
+            .build_conditional_branch(no_decimals, point_block, done_block)
+            .unwrap();
+
+        // An integral value still shows as a float: "2" becomes "2.0"
+        builder.position_at_end(point_block);
+        let length = builder
+            .build_call(strlen, &[buffer.into()], "length")
+            .unwrap()
+            .try_as_basic_value()
+            .basic()
+            .unwrap()
+            .into_int_value();
+        for (offset, byte) in [b'.', b'0', 0].into_iter().enumerate() {
+            let index = builder
+                .build_int_add(
+                    length,
+                    self.context.i64_type().const_int(offset as u64, false),
+                    "point_index",
+                )
+                .unwrap();
+            let byte_ptr = unsafe {
+                builder
+                    .build_in_bounds_gep(i8_type, buffer, &[index], "point_ptr")
+                    .unwrap()
+            };
+            builder
+                .build_store(byte_ptr, i8_type.const_int(byte as u64, false))
+                .unwrap();
+        }
+        builder.build_unconditional_branch(done_block).unwrap();
+
+        builder.position_at_end(done_block);
+        builder.build_return(None).unwrap();
+        function
+    }
 }
 
 /// Format string manager for printf/scanf operations
@@ -114,68 +837,50 @@ impl<'ctx> FormatStrings<'ctx> {
         }
     }
 
-    /// Returns a pointer to the "%d\n" format string for integers
+    /// Returns a pointer to the "%lld\n" format string for integers
     pub fn get_int_format_string(
         &self,
         builder: &inkwell::builder::Builder<'ctx>,
     ) -> PointerValue<'ctx> {
         builder
-            .build_global_string_ptr("%d\n", "int_format_string")
-            .unwrap()
-            .as_pointer_value()
-    }
-
-    /// Returns a pointer to the "%f\n" format string for floats
-    pub fn get_float_format_string(
-        &self,
-        builder: &inkwell::builder::Builder<'ctx>,
-    ) -> PointerValue<'ctx> {
-        builder
-            .build_global_string_ptr("%f\n", "float_format_string")
+            .build_global_string_ptr("%lld\n", "int_format_string")
             .unwrap()
             .as_pointer_value()
     }
 
-    /// Returns a pointer to the "%lf" format string for scanf float input
-    pub fn get_scanf_float_format_string(
+    /// Returns a pointer to the scanf format string reading the rest of the current
+    /// line (without its newline) into an `INPUT_LINE_SIZE` buffer and discarding
+    /// any overflow
+    pub fn get_scanf_line_format_string(
         &self,
         builder: &inkwell::builder::Builder<'ctx>,
     ) -> PointerValue<'ctx> {
+        let format = format!("%{}[^\n]%*[^\n]", INPUT_LINE_SIZE - 1);
         builder
-            .build_global_string_ptr("%lf", "scanf_float_format_string")
+            .build_global_string_ptr(&format, "scanf_line_format_string")
             .unwrap()
             .as_pointer_value()
     }
 
-    /// Returns a pointer to the "%s\n" format string for strings
-    pub fn get_string_format_string(
+    /// Returns a pointer to the "%*c" scanf format string, which skips one character
+    pub fn get_scanf_skip_char_format_string(
         &self,
         builder: &inkwell::builder::Builder<'ctx>,
     ) -> PointerValue<'ctx> {
         builder
-            .build_global_string_ptr("%s\n", "string_format_string")
+            .build_global_string_ptr("%*c", "scanf_skip_char_format_string")
             .unwrap()
             .as_pointer_value()
     }
 
-    /// Returns a pointer to the "%d" format string for integers (no newline)
+    /// Returns a pointer to the "%lld" format string for integers (no newline), which
+    /// is also how `str()` writes them
     pub fn get_int_format_string_no_newline(
         &self,
         builder: &inkwell::builder::Builder<'ctx>,
     ) -> PointerValue<'ctx> {
         builder
-            .build_global_string_ptr("%d", "int_format_no_nl")
-            .unwrap()
-            .as_pointer_value()
-    }
-
-    /// Returns a pointer to the "%f" format string for floats (no newline)
-    pub fn get_float_format_string_no_newline(
-        &self,
-        builder: &inkwell::builder::Builder<'ctx>,
-    ) -> PointerValue<'ctx> {
-        builder
-            .build_global_string_ptr("%f", "float_format_no_nl")
+            .build_global_string_ptr("%lld", "int_format_no_nl")
             .unwrap()
             .as_pointer_value()
     }
@@ -0,0 +1,269 @@
+//! Compiler sessions for processes that compile many programs
+//!
+//! A `Compiler` borrows an LLVM `Context` and builds one module, so compiling a
+//! program from scratch means creating a context, initializing the targets and
+//! re-interning every type. A `CompilerSession` owns one context for its lifetime and
+//! compiles each program into a fresh module of it, configured with the same
+//! `Compiler` builder options every time.
+//!
+//! ## Function Cache
+//! With the cache enabled, every user function compiled is kept as bitcode, keyed by
+//! its name and a hash of its definition. A later program defining the same function
+//! only declares it; the cached body is linked into the module before it is verified
+//! and optimized. A call compiles against the callee's parameters and inferred return
+//! type, so the hash also covers those of every function the body may refer to.
+//!
+//! Runtime state (the current exception, `global` variables, ...) lives in internal
+//! globals of each module. While cached functions are linked in, these are made
+//! link-once so each cached copy merges with the program's global of the same name.
+
+use crate::ast::IRStmt;
+use crate::codegen::{infer_return_types, CodeGenError, Compiler};
+use inkwell::context::Context;
+use inkwell::memory_buffer::MemoryBuffer;
+use inkwell::module::{Linkage, Module};
+use inkwell::values::BasicValue;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::time::Instant;
+
+/// Builder options applied to the `Compiler` of every program in a session
+type CompilerOptions = Box<dyn for<'ctx> Fn(Compiler<'ctx>) -> Compiler<'ctx>>;
+
+/// A cached function: its name and the hash of everything its code depends on
+type FunctionKey = (String, u64);
+
+/// Compiles successive programs in one LLVM context, optionally caching their
+/// compiled functions
+pub struct CompilerSession {
+    context: Context,
+    options: CompilerOptions,
+    // Bitcode of each compiled function, when caching is enabled
+    cache: Option<HashMap<FunctionKey, MemoryBuffer>>,
+    cache_hits: usize,
+    cache_misses: usize,
+}
+
+impl CompilerSession {
+    /// Creates a session with no cache, compiling with the default `Compiler` options
+    pub fn new() -> Self {
+        Self {
+            context: Context::create(),
+            options: Box::new(|compiler| compiler),
+            cache: None,
+            cache_hits: 0,
+            cache_misses: 0,
+        }
+    }
+
+    /// Sets the `Compiler` builder options used for every program, e.g.
+    /// `|c| c.with_optimization_level(0).with_string_input(true)`. Functions cached
+    /// under the previous options are dropped.
+    pub fn with_compiler_options(
+        mut self,
+        options: impl for<'ctx> Fn(Compiler<'ctx>) -> Compiler<'ctx> + 'static,
+    ) -> Self {
+        self.options = Box::new(options);
+        if let Some(cache) = &mut self.cache {
+            cache.clear();
+        }
+        self
+    }
+
+    /// Keeps every function compiled, so a later program defining an identical
+    /// function links in its compiled body instead of generating it again
+    pub fn with_cache(mut self, enabled: bool) -> Self {
+        self.cache = enabled.then(HashMap::new);
+        self
+    }
+
+    /// Returns the number of functions whose compiled body is cached
+    pub fn cached_functions(&self) -> usize {
+        self.cache.as_ref().map_or(0, HashMap::len)
+    }
+
+    /// Returns how many function definitions were linked in from the cache
+    pub fn cache_hits(&self) -> usize {
+        self.cache_hits
+    }
+
+    /// Returns how many function definitions were compiled and added to the cache
+    pub fn cache_misses(&self) -> usize {
+        self.cache_misses
+    }
+
+    /// Compiles a program to optimized LLVM IR text, like `Compiler::compile_program`
+    pub fn compile_program(&mut self, program: &[IRStmt]) -> Result<String, CodeGenError> {
+        let mut compiler = (self.options)(Compiler::new(&self.context));
+        let Some(cache) = &mut self.cache else {
+            return compiler.compile_program(program);
+        };
+
+        let codegen_start = Instant::now();
+        let (hits, misses): (Vec<_>, Vec<_>) = function_keys(program)
+            .into_iter()
+            .partition(|key| cache.contains_key(key));
+        compiler.precompiled_functions = hits.iter().map(|(name, _)| name.clone()).collect();
+        compiler.generate_program(program)?;
+
+        for key in &misses {
+            let bitcode = extract_function(&compiler.module, &key.0);
+            cache.insert(key.clone(), bitcode);
+        }
+        link_functions(
+            &self.context,
+            &compiler.module,
+            hits.iter().map(|key| &cache[key]),
+        )?;
+        self.cache_hits += hits.len();
+        self.cache_misses += misses.len();
+
+        compiler.finish_program(codegen_start)
+    }
+}
+
+impl Default for CompilerSession {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Computes the cache key of every function a program defines
+fn function_keys(program: &[IRStmt]) -> Vec<FunctionKey> {
+    let functions: Vec<&IRStmt> = program
+        .iter()
+        .filter(|stmt| matches!(stmt, IRStmt::FunctionDef { .. }))
+        .collect();
+    let return_types = infer_return_types(&functions);
+
+    let mut keys = Vec::new();
+    for stmt in &functions {
+        let IRStmt::FunctionDef { name, .. } = stmt else {
+            continue;
+        };
+        let definition = format!("{:?}", stmt);
+        let mut hasher = DefaultHasher::new();
+        definition.hash(&mut hasher);
+        // Any function whose quoted name appears in the definition may be called from
+        // it (a string literal spelling its name only costs a cache hit)
+        for other in &functions {
+            if let IRStmt::FunctionDef {
+                name: callee,
+                params,
+                defaults,
+                kwonly_params,
+                kwonly_defaults,
+                kwarg,
+                ..
+            } = other
+            {
+                if definition.contains(&format!("{:?}", callee)) {
+                    let signature = format!(
+                        "{:?}",
+                        (
+                            callee,
+                            params,
+                            defaults,
+                            kwonly_params,
+                            kwonly_defaults,
+                            kwarg,
+                            return_types[callee],
+                        )
+                    );
+                    signature.hash(&mut hasher);
+                }
+            }
+        }
+        keys.push((name.clone(), hasher.finish()));
+    }
+    keys
+}
+
+/// Copies one user function out of a module as bitcode, with the other user functions
+/// and the entry point reduced to declarations it links against
+fn extract_function(module: &Module<'_>, name: &str) -> MemoryBuffer {
+    let module = module.clone();
+    for function in module.get_functions().collect::<Vec<_>>() {
+        let function_name = function.get_name().to_str().unwrap().to_string();
+        if function_name == name
+            || function.as_global_value().is_declaration()
+            || function.get_linkage() != Linkage::External
+        {
+            continue;
+        }
+        let declaration = module.add_function("", function.get_type(), Some(Linkage::External));
+        let declaration_ptr = declaration.as_global_value().as_pointer_value();
+        function
+            .as_global_value()
+            .as_pointer_value()
+            .replace_all_uses_with(declaration_ptr);
+        unsafe { function.delete() };
+        declaration_ptr.set_name(&function_name);
+    }
+
+    remove_unreferenced(&module, name);
+    for global in module.get_globals() {
+        if global.get_linkage() == Linkage::Internal {
+            global.set_linkage(Linkage::LinkOnceAny);
+        }
+    }
+    module.write_bitcode_to_memory()
+}
+
+/// Deletes the functions and globals nothing in the module refers to, except `keep`
+fn remove_unreferenced(module: &Module<'_>, keep: &str) {
+    loop {
+        let mut removed = false;
+        for function in module.get_functions().collect::<Vec<_>>() {
+            let unused = function
+                .as_global_value()
+                .as_pointer_value()
+                .get_first_use()
+                .is_none();
+            if unused && function.get_name().to_bytes() != keep.as_bytes() {
+                unsafe { function.delete() };
+                removed = true;
+            }
+        }
+        for global in module.get_globals().collect::<Vec<_>>() {
+            if global.as_pointer_value().get_first_use().is_none() {
+                unsafe { global.delete() };
+                removed = true;
+            }
+        }
+        if !removed {
+            return;
+        }
+    }
+}
+
+/// Links cached functions (see `extract_function`) into a program's module, merging
+/// their runtime state with the program's
+fn link_functions<'a, 'ctx>(
+    context: &'ctx Context,
+    module: &Module<'ctx>,
+    functions: impl Iterator<Item = &'a MemoryBuffer>,
+) -> Result<(), CodeGenError> {
+    let link_error = |message: String| {
+        CodeGenError::ModuleVerification(format!("Failed to link a cached function: {}", message))
+    };
+    for global in module.get_globals() {
+        if global.get_linkage() == Linkage::Internal {
+            global.set_linkage(Linkage::LinkOnceAny);
+        }
+    }
+    for bitcode in functions {
+        let function = Module::parse_bitcode_from_buffer(bitcode, context)
+            .map_err(|e| link_error(e.to_string()))?;
+        module
+            .link_in_module(function)
+            .map_err(|e| link_error(e.to_string()))?;
+    }
+    for global in module.get_globals() {
+        if global.get_linkage() == Linkage::LinkOnceAny {
+            global.set_linkage(Linkage::Internal);
+        }
+    }
+    Ok(())
+}
@@ -23,7 +23,33 @@
 //! - TAG_BOOL = 1: Boolean (1-bit payload)
 //! - TAG_STRING = 2: String pointer (48-bit)
 //! - TAG_LIST = 3: List pointer (48-bit)
+//! - TAG_NONE = 4: None (payload always 0)
+//! - TAG_TUPLE = 5: Tuple pointer (48-bit), same layout as a list
+//! - TAG_DICT = 6: Dict pointer (48-bit), `[length][capacity][entries][index][mask]` with
+//!   the entries alternating keys and values in insertion order, and a hash index of
+//!   entry numbers to find keys by
+//! - TAG_FILE = 7: File object, a pointer (48-bit) to a heap cell holding the C `FILE*`,
+//!   null once the file is closed
 //! - Floats: No tag (stored as canonical float64)
+//!
+//! The three tag bits are all taken, so functions use the sign bit instead: a tagged
+//! value with the sign bit set (and tag bits 0) is a pointer to a function descriptor
+//! `{ ptr function, i64 arity, ptr name }`. No float has this pattern, since negative
+//! NaNs are canonicalized like positive ones. The sign bit with tag bits 1 is a set,
+//! which shares the dict layout with every value slot holding None.
+//!
+//! ## Payload Width
+//! Ints use the low 48 bits by default, everything below the tag. A `ValueManager`
+//! can instead be built with a 32-bit int payload (`PayloadWidth::Bits32`), which
+//! makes ints wrap like a 32-bit target's. Pointers always keep all 48 bits, so heap
+//! values stay valid on a 64-bit host whatever the int width. The tag sits in bits
+//! 48-50, so no payload can grow past 48 bits: a 64-bit int width cannot be boxed.
+//!
+//! ## NaN Canonicalization
+//! A quiet NaN produced by float arithmetic (e.g. `inf - inf`) has the QNAN bits set
+//! and would be mistaken for a tagged value. Every float is therefore boxed through
+//! `create_float`, which maps any NaN to `CANONICAL_NAN`, a signaling NaN pattern
+//! whose quiet bit is clear so it stays outside the tag space.
 
 use inkwell::builder::Builder;
 use inkwell::context::Context;
@@ -33,13 +59,24 @@ use inkwell::values::{FloatValue, IntValue, PointerValue};
 // PyObject is now represented as a single i64 using NaN-boxing
 const QNAN: u64 = 0x7FF8_0000_0000_0000;
 const TAG_MASK: u64 = 0x0007_0000_0000_0000;
-const PAYLOAD_MASK: u64 = 0x0000_FFFF_FFFF_FFFF;
+// Heap pointers keep the full 48 bits below the tag, whatever the int payload width
+const POINTER_MASK: u64 = 0x0000_FFFF_FFFF_FFFF;
+// The single float NaN encoding: all-ones exponent, quiet bit clear, non-zero mantissa
+const CANONICAL_NAN: u64 = 0x7FF4_0000_0000_0000;
 
 // Type tags for NaN-boxing (stored in bits 48-50)
 const TAG_INT: u64 = 0;
 const TAG_BOOL: u64 = 1;
 const TAG_STRING: u64 = 2;
 const TAG_LIST: u64 = 3;
+const TAG_NONE: u64 = 4;
+const TAG_TUPLE: u64 = 5;
+const TAG_DICT: u64 = 6;
+const TAG_FILE: u64 = 7;
+// Function values set the sign bit on top of QNAN
+const FUNC_BIT: u64 = 0x8000_0000_0000_0000;
+// Sets also set the sign bit, with this tag below it
+const TAG_SET: u64 = 1;
 
 // Legacy type tags (for compatibility with print dispatch logic)
 pub const TYPE_TAG_INT: u8 = 0;
@@ -47,6 +84,37 @@ pub const TYPE_TAG_FLOAT: u8 = 1;
 pub const TYPE_TAG_BOOL: u8 = 2;
 pub const TYPE_TAG_STRING: u8 = 3;
 pub const TYPE_TAG_LIST: u8 = 4;
+pub const TYPE_TAG_NONE: u8 = 5;
+pub const TYPE_TAG_TUPLE: u8 = 6;
+pub const TYPE_TAG_DICT: u8 = 7;
+pub const TYPE_TAG_FILE: u8 = 8;
+pub const TYPE_TAG_FUNC: u8 = 9;
+pub const TYPE_TAG_SET: u8 = 10;
+
+/// Number of payload bits holding an int below the tag
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PayloadWidth {
+    /// 32-bit ints, wrapping like a 32-bit target's
+    Bits32,
+    /// 48-bit ints, the widest payload that fits below the tag
+    #[default]
+    Bits48,
+}
+
+impl PayloadWidth {
+    /// Returns the number of payload bits
+    pub const fn bits(self) -> u32 {
+        match self {
+            PayloadWidth::Bits32 => 32,
+            PayloadWidth::Bits48 => 48,
+        }
+    }
+
+    /// Returns the mask selecting the payload bits of a NaN-boxed value
+    pub const fn mask(self) -> u64 {
+        (1 << self.bits()) - 1
+    }
+}
 
 /// Value manager for NaN-boxing operations
 ///
@@ -55,12 +123,26 @@ pub const TYPE_TAG_LIST: u8 = 4;
 /// value representations (e.g., structs vs NaN-boxing) by only modifying this module.
 pub struct ValueManager<'ctx> {
     context: &'ctx Context,
+    payload_width: PayloadWidth,
 }
 
 impl<'ctx> ValueManager<'ctx> {
-    /// Creates a new ValueManager
+    /// Creates a new ValueManager with the default 48-bit payload
     pub fn new(context: &'ctx Context) -> Self {
-        Self { context }
+        Self::with_payload_width(context, PayloadWidth::default())
+    }
+
+    /// Creates a new ValueManager whose ints use the given payload width
+    pub fn with_payload_width(context: &'ctx Context, payload_width: PayloadWidth) -> Self {
+        Self {
+            context,
+            payload_width,
+        }
+    }
+
+    /// Returns the payload width used for ints
+    pub fn payload_width(&self) -> PayloadWidth {
+        self.payload_width
     }
 
     /// Returns the PyObject type: i64 (NaN-boxed value)
@@ -71,9 +153,12 @@ impl<'ctx> ValueManager<'ctx> {
 
     /// Creates a PyObject value from an integer using NaN-boxing
     pub fn create_int(&self, builder: &Builder<'ctx>, value: IntValue<'ctx>) -> IntValue<'ctx> {
-        // NaN-box: QNAN | (TAG_INT << 48) | (value & PAYLOAD_MASK)
-        // Truncate to 48 bits (sign-extended)
-        let payload_mask = self.context.i64_type().const_int(PAYLOAD_MASK, false);
+        // NaN-box: QNAN | (TAG_INT << 48) | (value & payload mask)
+        // Truncate to the payload width (sign-extended on extraction)
+        let payload_mask = self
+            .context
+            .i64_type()
+            .const_int(self.payload_width.mask(), false);
         let payload = builder
             .build_and(value, payload_mask, "int_payload")
             .unwrap();
@@ -94,9 +179,28 @@ impl<'ctx> ValueManager<'ctx> {
     pub fn create_float(&self, builder: &Builder<'ctx>, value: FloatValue<'ctx>) -> IntValue<'ctx> {
         // For floats, we store them directly (not NaN-boxed)
         // Just bitcast f64 to i64
-        builder
+        let bits = builder
             .build_bit_cast(value, self.context.i64_type(), "float_as_i64")
             .unwrap()
+            .into_int_value();
+        self.canonicalize_nan(builder, value, bits)
+    }
+
+    /// Replaces the bits of any NaN float with CANONICAL_NAN so it cannot collide
+    /// with a NaN-boxed tagged value
+    fn canonicalize_nan(
+        &self,
+        builder: &Builder<'ctx>,
+        value: FloatValue<'ctx>,
+        bits: IntValue<'ctx>,
+    ) -> IntValue<'ctx> {
+        let is_nan = builder
+            .build_float_compare(inkwell::FloatPredicate::UNO, value, value, "is_nan")
+            .unwrap();
+        let canonical_nan = self.context.i64_type().const_int(CANONICAL_NAN, false);
+        builder
+            .build_select(is_nan, canonical_nan, bits, "canonical_float")
+            .unwrap()
             .into_int_value()
     }
 
@@ -127,14 +231,14 @@ impl<'ctx> ValueManager<'ctx> {
         builder: &Builder<'ctx>,
         ptr: PointerValue<'ctx>,
     ) -> IntValue<'ctx> {
-        // NaN-box: QNAN | (TAG_STRING << 48) | (ptr & PAYLOAD_MASK)
+        // NaN-box: QNAN | (TAG_STRING << 48) | (ptr & payload mask)
         // Convert pointer to i64
         let ptr_as_int = builder
             .build_ptr_to_int(ptr, self.context.i64_type(), "ptr_to_int")
             .unwrap();
 
-        // Mask to 48 bits
-        let payload_mask = self.context.i64_type().const_int(PAYLOAD_MASK, false);
+        // Mask to the 48-bit pointer payload
+        let payload_mask = self.context.i64_type().const_int(POINTER_MASK, false);
         let payload = builder
             .build_and(ptr_as_int, payload_mask, "ptr_payload")
             .unwrap();
@@ -162,13 +266,13 @@ impl<'ctx> ValueManager<'ctx> {
         _len: usize,
     ) -> IntValue<'ctx> {
         // Store the pointer in the NaN-boxed value
-        // NaN-box: QNAN | (TAG_LIST << 48) | (ptr & PAYLOAD_MASK)
+        // NaN-box: QNAN | (TAG_LIST << 48) | (ptr & payload mask)
         let ptr_as_int = builder
             .build_ptr_to_int(ptr, self.context.i64_type(), "ptr_to_int")
             .unwrap();
 
-        // Mask to 48 bits
-        let payload_mask = self.context.i64_type().const_int(PAYLOAD_MASK, false);
+        // Mask to the 48-bit pointer payload
+        let payload_mask = self.context.i64_type().const_int(POINTER_MASK, false);
         let payload = builder
             .build_and(ptr_as_int, payload_mask, "list_ptr_payload")
             .unwrap();
@@ -186,6 +290,102 @@ impl<'ctx> ValueManager<'ctx> {
             .unwrap()
     }
 
+    /// Creates a PyObject value from a tuple pointer using NaN-boxing
+    /// Tuples share the list memory layout: [length: i64][element_0: i64]...
+    pub fn create_tuple(&self, builder: &Builder<'ctx>, ptr: PointerValue<'ctx>) -> IntValue<'ctx> {
+        // NaN-box: QNAN | (TAG_TUPLE << 48) | (ptr & payload mask)
+        let ptr_as_int = builder
+            .build_ptr_to_int(ptr, self.context.i64_type(), "ptr_to_int")
+            .unwrap();
+        let payload_mask = self.context.i64_type().const_int(POINTER_MASK, false);
+        let payload = builder
+            .build_and(ptr_as_int, payload_mask, "tuple_ptr_payload")
+            .unwrap();
+        let tag_bits = self
+            .context
+            .i64_type()
+            .const_int(QNAN | (TAG_TUPLE << 48), false);
+        builder
+            .build_or(tag_bits, payload, "pyobject_tuple")
+            .unwrap()
+    }
+
+    /// Creates a PyObject value from a dict pointer using NaN-boxing
+    /// The pointer points to: [length: i64][key_0: i64][value_0: i64]...
+    pub fn create_dict(&self, builder: &Builder<'ctx>, ptr: PointerValue<'ctx>) -> IntValue<'ctx> {
+        // NaN-box: QNAN | (TAG_DICT << 48) | (ptr & payload mask)
+        let ptr_as_int = builder
+            .build_ptr_to_int(ptr, self.context.i64_type(), "ptr_to_int")
+            .unwrap();
+        let payload_mask = self.context.i64_type().const_int(POINTER_MASK, false);
+        let payload = builder
+            .build_and(ptr_as_int, payload_mask, "dict_ptr_payload")
+            .unwrap();
+        let tag_bits = self
+            .context
+            .i64_type()
+            .const_int(QNAN | (TAG_DICT << 48), false);
+        builder
+            .build_or(tag_bits, payload, "pyobject_dict")
+            .unwrap()
+    }
+
+    /// Creates a file PyObject from a pointer to the cell holding its C `FILE*`
+    pub fn create_file(&self, builder: &Builder<'ctx>, ptr: PointerValue<'ctx>) -> IntValue<'ctx> {
+        // NaN-box: QNAN | (TAG_FILE << 48) | (ptr & payload mask)
+        let ptr_as_int = builder
+            .build_ptr_to_int(ptr, self.context.i64_type(), "ptr_to_int")
+            .unwrap();
+        let payload_mask = self.context.i64_type().const_int(POINTER_MASK, false);
+        let payload = builder
+            .build_and(ptr_as_int, payload_mask, "file_ptr_payload")
+            .unwrap();
+        let tag_bits = self
+            .context
+            .i64_type()
+            .const_int(QNAN | (TAG_FILE << 48), false);
+        builder
+            .build_or(tag_bits, payload, "pyobject_file")
+            .unwrap()
+    }
+
+    /// Creates a PyObject value from a function descriptor pointer using NaN-boxing
+    pub fn create_function(
+        &self,
+        builder: &Builder<'ctx>,
+        ptr: PointerValue<'ctx>,
+    ) -> IntValue<'ctx> {
+        // NaN-box: FUNC_BIT | QNAN | (ptr & payload mask)
+        let ptr_as_int = builder
+            .build_ptr_to_int(ptr, self.context.i64_type(), "ptr_to_int")
+            .unwrap();
+        let payload_mask = self.context.i64_type().const_int(POINTER_MASK, false);
+        let payload = builder
+            .build_and(ptr_as_int, payload_mask, "function_ptr_payload")
+            .unwrap();
+        let tag_bits = self.context.i64_type().const_int(FUNC_BIT | QNAN, false);
+        builder
+            .build_or(tag_bits, payload, "pyobject_function")
+            .unwrap()
+    }
+
+    /// Creates a PyObject value from a set pointer using NaN-boxing
+    pub fn create_set(&self, builder: &Builder<'ctx>, ptr: PointerValue<'ctx>) -> IntValue<'ctx> {
+        // NaN-box: FUNC_BIT | QNAN | (TAG_SET << 48) | (ptr & payload mask)
+        let ptr_as_int = builder
+            .build_ptr_to_int(ptr, self.context.i64_type(), "ptr_to_int")
+            .unwrap();
+        let payload_mask = self.context.i64_type().const_int(POINTER_MASK, false);
+        let payload = builder
+            .build_and(ptr_as_int, payload_mask, "set_ptr_payload")
+            .unwrap();
+        let tag_bits = self
+            .context
+            .i64_type()
+            .const_int(FUNC_BIT | QNAN | (TAG_SET << 48), false);
+        builder.build_or(tag_bits, payload, "pyobject_set").unwrap()
+    }
+
     /// Extracts a string pointer from a PyObject
     /// Assumes the PyObject has a STRING tag
     pub fn extract_string_ptr(
@@ -193,8 +393,8 @@ impl<'ctx> ValueManager<'ctx> {
         builder: &Builder<'ctx>,
         pyobject: IntValue<'ctx>,
     ) -> PointerValue<'ctx> {
-        // Extract payload (lower 48 bits)
-        let payload_mask = self.context.i64_type().const_int(PAYLOAD_MASK, false);
+        // Extract payload (the bits below the tag)
+        let payload_mask = self.context.i64_type().const_int(POINTER_MASK, false);
         let payload = builder
             .build_and(pyobject, payload_mask, "extract_ptr_payload")
             .unwrap();
@@ -217,8 +417,8 @@ impl<'ctx> ValueManager<'ctx> {
         builder: &Builder<'ctx>,
         pyobject: IntValue<'ctx>,
     ) -> (PointerValue<'ctx>, IntValue<'ctx>) {
-        // Extract payload (lower 48 bits)
-        let payload_mask = self.context.i64_type().const_int(PAYLOAD_MASK, false);
+        // Extract payload (the bits below the tag)
+        let payload_mask = self.context.i64_type().const_int(POINTER_MASK, false);
         let payload = builder
             .build_and(pyobject, payload_mask, "extract_list_payload")
             .unwrap();
@@ -275,12 +475,17 @@ impl<'ctx> ValueManager<'ctx> {
             .build_bit_cast(payload, self.context.i64_type(), "float_to_i64")
             .unwrap()
             .into_int_value();
+        let float_result = self.canonicalize_nan(builder, payload, float_result);
 
         // For non-floats: Convert back from external tag to internal tag, then NaN-box
         // TYPE_TAG_INT (0) -> TAG_INT (0)
         // TYPE_TAG_BOOL (2) -> TAG_BOOL (1)
         // TYPE_TAG_STRING (3) -> TAG_STRING (2)
         // TYPE_TAG_LIST (4) -> TAG_LIST (3)
+        // TYPE_TAG_NONE (5) -> TAG_NONE (4)
+        // TYPE_TAG_TUPLE (6) -> TAG_TUPLE (5)
+        // TYPE_TAG_DICT (7) -> TAG_DICT (6)
+        // TYPE_TAG_FILE (8) -> TAG_FILE (7)
         let bool_tag = self
             .context
             .i64_type()
@@ -303,6 +508,20 @@ impl<'ctx> ValueManager<'ctx> {
         let is_list = builder
             .build_int_compare(inkwell::IntPredicate::EQ, tag, list_tag, "is_list")
             .unwrap();
+        let none_tag = self
+            .context
+            .i64_type()
+            .const_int(TYPE_TAG_NONE as u64, false);
+        let is_none = builder
+            .build_int_compare(inkwell::IntPredicate::EQ, tag, none_tag, "is_none")
+            .unwrap();
+        let tuple_tag = self
+            .context
+            .i64_type()
+            .const_int(TYPE_TAG_TUPLE as u64, false);
+        let is_tuple = builder
+            .build_int_compare(inkwell::IntPredicate::EQ, tag, tuple_tag, "is_tuple")
+            .unwrap();
 
         let internal_tag_1 = self.context.i64_type().const_int(TAG_BOOL, false);
         let internal_tag_2 = self.context.i64_type().const_int(TAG_STRING, false);
@@ -317,8 +536,42 @@ impl<'ctx> ValueManager<'ctx> {
             .build_select(is_string, internal_tag_2, internal_tag_temp1, "tag_temp2")
             .unwrap()
             .into_int_value();
+        let internal_tag_temp3 = builder
+            .build_select(is_list, internal_tag_3, internal_tag_temp2, "tag_temp3")
+            .unwrap()
+            .into_int_value();
+        let internal_tag_4 = self.context.i64_type().const_int(TAG_NONE, false);
+        let internal_tag_temp4 = builder
+            .build_select(is_none, internal_tag_4, internal_tag_temp3, "tag_temp4")
+            .unwrap()
+            .into_int_value();
+        let internal_tag_5 = self.context.i64_type().const_int(TAG_TUPLE, false);
+        let internal_tag_temp5 = builder
+            .build_select(is_tuple, internal_tag_5, internal_tag_temp4, "tag_temp5")
+            .unwrap()
+            .into_int_value();
+        let dict_tag = self
+            .context
+            .i64_type()
+            .const_int(TYPE_TAG_DICT as u64, false);
+        let is_dict = builder
+            .build_int_compare(inkwell::IntPredicate::EQ, tag, dict_tag, "is_dict")
+            .unwrap();
+        let internal_tag_6 = self.context.i64_type().const_int(TAG_DICT, false);
+        let internal_tag_temp6 = builder
+            .build_select(is_dict, internal_tag_6, internal_tag_temp5, "tag_temp6")
+            .unwrap()
+            .into_int_value();
+        let file_tag = self
+            .context
+            .i64_type()
+            .const_int(TYPE_TAG_FILE as u64, false);
+        let is_file = builder
+            .build_int_compare(inkwell::IntPredicate::EQ, tag, file_tag, "is_file")
+            .unwrap();
+        let internal_tag_7 = self.context.i64_type().const_int(TAG_FILE, false);
         let internal_tag = builder
-            .build_select(is_list, internal_tag_3, internal_tag_temp2, "internal_tag")
+            .build_select(is_file, internal_tag_7, internal_tag_temp6, "internal_tag")
             .unwrap()
             .into_int_value();
 
@@ -327,11 +580,33 @@ impl<'ctx> ValueManager<'ctx> {
             .build_float_to_signed_int(payload, self.context.i64_type(), "payload_to_i64")
             .unwrap();
 
-        // Mask to 48 bits
-        let payload_mask = self.context.i64_type().const_int(PAYLOAD_MASK, false);
+        // Mask to the payload width: pointers keep 48 bits, ints wrap to the int width
+        let payload_mask = self.context.i64_type().const_int(POINTER_MASK, false);
         let payload_masked = builder
             .build_and(payload_i64, payload_mask, "payload_masked")
             .unwrap();
+        let payload_masked = if self.payload_width == PayloadWidth::Bits48 {
+            payload_masked
+        } else {
+            let int_tag = self
+                .context
+                .i64_type()
+                .const_int(TYPE_TAG_INT as u64, false);
+            let is_int = builder
+                .build_int_compare(inkwell::IntPredicate::EQ, tag, int_tag, "is_int_tag")
+                .unwrap();
+            let int_mask = self
+                .context
+                .i64_type()
+                .const_int(self.payload_width.mask(), false);
+            let int_masked = builder
+                .build_and(payload_i64, int_mask, "int_payload_masked")
+                .unwrap();
+            builder
+                .build_select(is_int, int_masked, payload_masked, "payload_masked")
+                .unwrap()
+                .into_int_value()
+        };
 
         // Build NaN-boxed value: QNAN | (tag << 48) | payload
         let tag_shifted = builder
@@ -356,6 +631,21 @@ impl<'ctx> ValueManager<'ctx> {
             .into_int_value()
     }
 
+    /// Creates the None PyObject (a single constant encoding)
+    pub fn create_none(&self) -> IntValue<'ctx> {
+        self.context
+            .i64_type()
+            .const_int(QNAN | (TAG_NONE << 48), false)
+    }
+
+    /// Creates the marker a variable's slot holds after `del`. It is None's encoding
+    /// with the sign bit set, which no value uses.
+    pub fn create_unbound(&self) -> IntValue<'ctx> {
+        self.context
+            .i64_type()
+            .const_int(FUNC_BIT | QNAN | (TAG_NONE << 48), false)
+    }
+
     /// Checks if a PyObject is a float (not NaN-boxed)
     pub fn is_float(&self, builder: &Builder<'ctx>, pyobject: IntValue<'ctx>) -> IntValue<'ctx> {
         // A value is a float if (value & QNAN) != QNAN
@@ -393,6 +683,11 @@ impl<'ctx> ValueManager<'ctx> {
         // TAG_BOOL (1) -> TYPE_TAG_BOOL (2)
         // TAG_STRING (2) -> TYPE_TAG_STRING (3)
         // TAG_LIST (3) -> TYPE_TAG_LIST (4)
+        // TAG_NONE (4) -> TYPE_TAG_NONE (5)
+        // TAG_TUPLE (5) -> TYPE_TAG_TUPLE (6)
+        // TAG_DICT (6) -> TYPE_TAG_DICT (7)
+        // TAG_FILE (7) -> TYPE_TAG_FILE (8)
+        // FUNC_BIT set -> TYPE_TAG_FUNC (9), or TYPE_TAG_SET (10) with TAG_SET
         let tag_map_bool = self
             .context
             .i64_type()
@@ -405,6 +700,14 @@ impl<'ctx> ValueManager<'ctx> {
             .context
             .i64_type()
             .const_int(TYPE_TAG_LIST as u64, false);
+        let tag_map_none = self
+            .context
+            .i64_type()
+            .const_int(TYPE_TAG_NONE as u64, false);
+        let tag_map_tuple = self
+            .context
+            .i64_type()
+            .const_int(TYPE_TAG_TUPLE as u64, false);
 
         // Select based on tag value
         let is_bool = builder
@@ -431,6 +734,22 @@ impl<'ctx> ValueManager<'ctx> {
                 "is_list",
             )
             .unwrap();
+        let is_none = builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                tag_shifted,
+                self.context.i64_type().const_int(TAG_NONE, false),
+                "is_none",
+            )
+            .unwrap();
+        let is_tuple = builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                tag_shifted,
+                self.context.i64_type().const_int(TAG_TUPLE, false),
+                "is_tuple",
+            )
+            .unwrap();
 
         // Build the mapped tag
         let mapped_tag = builder
@@ -445,6 +764,75 @@ impl<'ctx> ValueManager<'ctx> {
             .build_select(is_list, tag_map_list, mapped_tag, "map_list")
             .unwrap()
             .into_int_value();
+        let mapped_tag = builder
+            .build_select(is_none, tag_map_none, mapped_tag, "map_none")
+            .unwrap()
+            .into_int_value();
+        let mapped_tag = builder
+            .build_select(is_tuple, tag_map_tuple, mapped_tag, "map_tuple")
+            .unwrap()
+            .into_int_value();
+        let is_dict = builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                tag_shifted,
+                self.context.i64_type().const_int(TAG_DICT, false),
+                "is_dict",
+            )
+            .unwrap();
+        let tag_map_dict = self
+            .context
+            .i64_type()
+            .const_int(TYPE_TAG_DICT as u64, false);
+        let mapped_tag = builder
+            .build_select(is_dict, tag_map_dict, mapped_tag, "map_dict")
+            .unwrap()
+            .into_int_value();
+        let is_file = builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                tag_shifted,
+                self.context.i64_type().const_int(TAG_FILE, false),
+                "is_file",
+            )
+            .unwrap();
+        let tag_map_file = self
+            .context
+            .i64_type()
+            .const_int(TYPE_TAG_FILE as u64, false);
+        let mapped_tag = builder
+            .build_select(is_file, tag_map_file, mapped_tag, "map_file")
+            .unwrap()
+            .into_int_value();
+        let func_bit = self.context.i64_type().const_int(FUNC_BIT, false);
+        let sign = builder.build_and(pyobject, func_bit, "sign").unwrap();
+        let is_func = builder
+            .build_int_compare(inkwell::IntPredicate::EQ, sign, func_bit, "is_func")
+            .unwrap();
+        let is_set_tag = builder
+            .build_int_compare(
+                inkwell::IntPredicate::EQ,
+                tag_shifted,
+                self.context.i64_type().const_int(TAG_SET, false),
+                "is_set_tag",
+            )
+            .unwrap();
+        let tag_map_func = self
+            .context
+            .i64_type()
+            .const_int(TYPE_TAG_FUNC as u64, false);
+        let tag_map_set = self
+            .context
+            .i64_type()
+            .const_int(TYPE_TAG_SET as u64, false);
+        let signed_tag = builder
+            .build_select(is_set_tag, tag_map_set, tag_map_func, "signed_tag")
+            .unwrap()
+            .into_int_value();
+        let mapped_tag = builder
+            .build_select(is_func, signed_tag, mapped_tag, "map_func")
+            .unwrap()
+            .into_int_value();
 
         // If it's a float, return TYPE_TAG_FLOAT, otherwise return mapped tag
         let float_tag = self
@@ -474,13 +862,13 @@ impl<'ctx> ValueManager<'ctx> {
             .unwrap()
             .into_float_value();
 
-        // Otherwise, extract lower 48 bits and convert to f64
-        let payload_mask = self.context.i64_type().const_int(PAYLOAD_MASK, false);
+        // Otherwise, extract the payload bits and convert to f64
+        let payload_mask = self.context.i64_type().const_int(POINTER_MASK, false);
         let payload_int = builder
             .build_and(pyobject, payload_mask, "extract_payload")
             .unwrap();
 
-        // Sign-extend from 48 bits to 64 bits for integers
+        // Sign-extend from bit 47 to 64 bits for integers
         let sign_bit = builder
             .build_right_shift(
                 payload_int,
@@ -499,7 +887,7 @@ impl<'ctx> ValueManager<'ctx> {
             .unwrap();
 
         // If negative, fill upper bits with 1s
-        let sign_extension = self.context.i64_type().const_int(!PAYLOAD_MASK, false);
+        let sign_extension = self.context.i64_type().const_int(!POINTER_MASK, false);
         let extended = builder
             .build_or(payload_int, sign_extension, "sign_extend")
             .unwrap();
@@ -508,6 +896,32 @@ impl<'ctx> ValueManager<'ctx> {
             .unwrap()
             .into_int_value();
 
+        // A narrower int payload is sign-extended from its own width instead
+        let signed_payload = if self.payload_width == PayloadWidth::Bits48 {
+            signed_payload
+        } else {
+            let tag_bits = self
+                .context
+                .i64_type()
+                .const_int(QNAN | TAG_MASK | FUNC_BIT, false);
+            let masked_tag = builder
+                .build_and(pyobject, tag_bits, "int_tag_bits")
+                .unwrap();
+            let is_int = builder
+                .build_int_compare(
+                    inkwell::IntPredicate::EQ,
+                    masked_tag,
+                    self.context.i64_type().const_int(QNAN, false),
+                    "is_int",
+                )
+                .unwrap();
+            let int_payload = self.extract_int_payload(builder, pyobject);
+            builder
+                .build_select(is_int, int_payload, signed_payload, "signed_payload")
+                .unwrap()
+                .into_int_value()
+        };
+
         // Convert to f64
         let payload_as_float = builder
             .build_signed_int_to_float(signed_payload, self.context.f64_type(), "payload_to_f64")
@@ -520,13 +934,35 @@ impl<'ctx> ValueManager<'ctx> {
             .into_float_value()
     }
 
+    /// Extracts the payload of an INT-tagged PyObject as an exact, sign-extended i64
+    /// Unlike `extract_payload`, this does not round-trip through f64
+    pub fn extract_int_payload(
+        &self,
+        builder: &Builder<'ctx>,
+        pyobject: IntValue<'ctx>,
+    ) -> IntValue<'ctx> {
+        // Shift the payload to the top of the word, then arithmetic-shift it back
+        // down so its sign bit is replicated into the upper bits
+        let shift = self
+            .context
+            .i64_type()
+            .const_int(u64::from(64 - self.payload_width.bits()), false);
+        let shifted = builder
+            .build_left_shift(pyobject, shift, "int_payload_hi")
+            .unwrap();
+        builder
+            .build_right_shift(shifted, shift, true, "int_payload")
+            .unwrap()
+    }
+
     /// Converts a PyObject to a boolean (i1) for conditionals
     /// Returns true if the value is non-zero
     pub fn to_bool(&self, builder: &Builder<'ctx>, pyobject: IntValue<'ctx>) -> IntValue<'ctx> {
         let payload = self.extract_payload(builder, pyobject);
         let zero = self.context.f64_type().const_float(0.0);
         builder
-            .build_float_compare(inkwell::FloatPredicate::ONE, payload, zero, "to_bool")
+            // Unordered: NaN is truthy, as in Python
+            .build_float_compare(inkwell::FloatPredicate::UNE, payload, zero, "to_bool")
             .unwrap()
     }
 }
@@ -1,5 +1,5 @@
 use crate::codegen::CodeGenError;
-use crate::lowering::LoweringError;
+use crate::lowering::{LoweringError, LoweringWarning};
 use ariadne::{Color, Label, Report, ReportKind, Source};
 use rustpython_parser::ParseError;
 
@@ -50,6 +50,24 @@ pub fn display_lowering_error(source: &str, filename: &str, error: &LoweringErro
         .unwrap();
 }
 
+/// Display a lowering warning with ariadne formatting
+pub fn display_lowering_warning(source: &str, filename: &str, warning: &LoweringWarning) {
+    let range = warning.range();
+    let start = usize::from(range.start());
+    let end = std::cmp::max(usize::from(range.end()), start + 1);
+
+    Report::build(ReportKind::Warning, filename, start)
+        .with_message("Lowering warning")
+        .with_label(
+            Label::new((filename, start..end))
+                .with_message(warning.to_string())
+                .with_color(Color::Yellow),
+        )
+        .finish()
+        .eprint((filename, Source::from(source)))
+        .unwrap();
+}
+
 /// Display a code generation error with ariadne formatting
 pub fn display_codegen_error(source: &str, filename: &str, error: &CodeGenError) {
     Report::build(ReportKind::Error, filename, 0)
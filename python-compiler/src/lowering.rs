@@ -1,6 +1,11 @@
-use crate::ast::{BinOp, CmpOp, IRExpr, IRStmt, UnaryOp};
+use crate::ast::{
+    BinOp, BoolOp, CmpOp, ComprehensionSource, FStringPart, FormatSpec, IRExpr, IRStmt, UnaryOp,
+};
 use num_traits::ToPrimitive;
 use rustpython_parser::ast;
+use rustpython_parser::text_size::TextRange;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use thiserror::Error;
 
 #[derive(Debug, Error, PartialEq)]
@@ -11,54 +16,641 @@ pub enum LoweringError {
     UnsupportedExpression(Box<ast::Expr>),
     #[error("Unsupported operator: {0:?}")]
     UnsupportedOperator(ast::Operator),
-    #[error("Unsupported comparison operator: {0:?}")]
-    UnsupportedComparisonOperator(ast::CmpOp),
-    #[error("Comparison must have exactly one operator and two operands")]
+    #[error("Comparison must have one operator per right-hand operand")]
     InvalidComparison,
+    #[error("with statements are not supported (context managers are not implemented)")]
+    UnsupportedWith,
+    #[error("cannot assign to {0}")]
+    AssignToKeyword(String),
+    #[error("decorator @{0} is not supported (decorators are not implemented)")]
+    UnsupportedDecorator(String),
+    #[error("range expected 1 to 3 arguments, got {0}")]
+    RangeArgumentCount(usize),
+    #[error("'{0}' outside loop")]
+    LoopControlOutsideLoop(&'static str),
+}
+
+/// A suspicious but compilable construct found while lowering.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LoweringWarning {
+    /// `x = x`, which has no effect
+    SelfAssignment { name: String, range: TextRange },
+    /// `while True:` whose body can never break or return
+    InfiniteLoop { range: TextRange },
+    /// `is` / `is not` where neither side is None: compared by value, not identity
+    IdentityComparison { range: TextRange },
+}
+
+impl LoweringWarning {
+    /// Source range of the statement the warning refers to
+    pub fn range(&self) -> TextRange {
+        match self {
+            LoweringWarning::SelfAssignment { range, .. } => *range,
+            LoweringWarning::InfiniteLoop { range } => *range,
+            LoweringWarning::IdentityComparison { range } => *range,
+        }
+    }
+}
+
+impl fmt::Display for LoweringWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoweringWarning::SelfAssignment { name, .. } => {
+                write!(f, "Assignment of '{}' to itself has no effect", name)
+            }
+            LoweringWarning::InfiniteLoop { .. } => {
+                write!(f, "Infinite loop: 'while True' has no break or return")
+            }
+            LoweringWarning::IdentityComparison { .. } => write!(
+                f,
+                "'is' is only reliable against None; other operands are compared by value"
+            ),
+        }
+    }
 }
 
 /// Lowers a `rustpython-parser` AST to the custom IR.
+///
+/// Names resolve locals first, then user functions, then builtins: a builtin such
+/// as `len` is only lowered to its dedicated IR when no binding in scope shadows it.
 pub fn lower_program(stmts: &[ast::Stmt]) -> Result<Vec<IRStmt>, LoweringError> {
-    stmts.iter().map(lower_statement).collect()
+    check_loop_control(stmts, false)?;
+    let shadowed = &shadowed_builtins(stmts, HashSet::new());
+    let ir = lower_block(skip_docstring(stmts), shadowed)?;
+    Ok(propagate_constants(ir, &[]))
+}
+
+/// Lowers a program like `lower_program`, also returning non-fatal warnings.
+pub fn lower_program_with_warnings(
+    stmts: &[ast::Stmt],
+) -> Result<(Vec<IRStmt>, Vec<LoweringWarning>), LoweringError> {
+    let ir = lower_program(stmts)?;
+    let mut warnings = Vec::new();
+    collect_warnings(stmts, &mut warnings);
+    Ok((ir, warnings))
+}
+
+/// Drops a leading docstring from a module or function body: a bare string
+/// literal has no effect, so compiling it would only allocate a string to discard
+fn skip_docstring(body: &[ast::Stmt]) -> &[ast::Stmt] {
+    match body.split_first() {
+        Some((ast::Stmt::Expr(ast::StmtExpr { value, .. }), rest))
+            if matches!(
+                value.as_ref(),
+                ast::Expr::Constant(ast::ExprConstant {
+                    value: ast::Constant::Str(_),
+                    ..
+                })
+            ) =>
+        {
+            rest
+        }
+        _ => body,
+    }
+}
+
+/// Names of the builtins lowered to dedicated IR rather than called
+const BUILTIN_NAMES: [&str; 23] = [
+    "print", "input", "len", "reversed", "sorted", "zip", "map", "filter", "open", "exit", "quit",
+    "range", "isclose", "hex", "oct", "bin", "sum", "str", "type", "abs", "int", "min", "max",
+];
+
+/// Returns the builtins shadowed in a scope: those among `names` (the bindings
+/// already known, e.g. parameters) or bound by the statements themselves
+fn shadowed_builtins(stmts: &[ast::Stmt], mut names: HashSet<String>) -> HashSet<String> {
+    collect_bound_names(stmts, &mut names);
+    names.retain(|name| BUILTIN_NAMES.contains(&name.as_str()));
+    names
+}
+
+/// Collects the names bound by assignments, `for` targets, `def`, `global` and
+/// `except ... as`, including in nested blocks but not nested function bodies
+fn collect_bound_names(stmts: &[ast::Stmt], names: &mut HashSet<String>) {
+    for stmt in stmts {
+        match stmt {
+            ast::Stmt::Assign(ast::StmtAssign { targets, .. }) => {
+                for target in targets {
+                    collect_target_names(target, names);
+                }
+            }
+            ast::Stmt::AugAssign(ast::StmtAugAssign { target, .. }) => {
+                collect_target_names(target, names)
+            }
+            ast::Stmt::FunctionDef(ast::StmtFunctionDef { name, .. }) => {
+                names.insert(name.to_string());
+            }
+            ast::Stmt::Global(ast::StmtGlobal { names: globals, .. }) => {
+                names.extend(globals.iter().map(|name| name.to_string()))
+            }
+            ast::Stmt::For(ast::StmtFor {
+                target,
+                body,
+                orelse,
+                ..
+            }) => {
+                collect_target_names(target, names);
+                collect_bound_names(body, names);
+                collect_bound_names(orelse, names);
+            }
+            ast::Stmt::If(ast::StmtIf { body, orelse, .. })
+            | ast::Stmt::While(ast::StmtWhile { body, orelse, .. }) => {
+                collect_bound_names(body, names);
+                collect_bound_names(orelse, names);
+            }
+            ast::Stmt::Try(ast::StmtTry {
+                body,
+                handlers,
+                orelse,
+                finalbody,
+                ..
+            }) => {
+                collect_bound_names(body, names);
+                for ast::ExceptHandler::ExceptHandler(handler) in handlers {
+                    names.extend(handler.name.iter().map(|name| name.to_string()));
+                    collect_bound_names(&handler.body, names);
+                }
+                collect_bound_names(orelse, names);
+                collect_bound_names(finalbody, names);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Collects the variable names an assignment target binds
+fn collect_target_names(target: &ast::Expr, names: &mut HashSet<String>) {
+    match target {
+        ast::Expr::Name(ast::ExprName { id, .. }) => {
+            names.insert(id.to_string());
+        }
+        ast::Expr::Tuple(ast::ExprTuple { elts, .. })
+        | ast::Expr::List(ast::ExprList { elts, .. }) => {
+            for elt in elts {
+                collect_target_names(elt, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Walks the statements (including nested bodies) looking for suspicious constructs.
+fn collect_warnings(stmts: &[ast::Stmt], warnings: &mut Vec<LoweringWarning>) {
+    for stmt in stmts {
+        match stmt {
+            ast::Stmt::Expr(ast::StmtExpr { value, .. })
+            | ast::Stmt::AugAssign(ast::StmtAugAssign { value, .. }) => {
+                collect_expr_warnings(value, warnings);
+            }
+            ast::Stmt::Return(ast::StmtReturn {
+                value: Some(value), ..
+            }) => collect_expr_warnings(value, warnings),
+            ast::Stmt::Assign(ast::StmtAssign {
+                targets,
+                value,
+                range,
+                ..
+            }) => {
+                collect_expr_warnings(value, warnings);
+                if let [ast::Expr::Name(target)] = targets.as_slice() {
+                    if let ast::Expr::Name(source) = value.as_ref() {
+                        if target.id == source.id {
+                            warnings.push(LoweringWarning::SelfAssignment {
+                                name: target.id.to_string(),
+                                range: *range,
+                            });
+                        }
+                    }
+                }
+            }
+            ast::Stmt::While(ast::StmtWhile {
+                test,
+                body,
+                orelse,
+                range,
+            }) => {
+                collect_expr_warnings(test, warnings);
+                if is_always_true(test) && !can_exit_loop(body, false) {
+                    warnings.push(LoweringWarning::InfiniteLoop { range: *range });
+                }
+                collect_warnings(body, warnings);
+                collect_warnings(orelse, warnings);
+            }
+            ast::Stmt::For(ast::StmtFor {
+                iter, body, orelse, ..
+            }) => {
+                collect_expr_warnings(iter, warnings);
+                collect_warnings(body, warnings);
+                collect_warnings(orelse, warnings);
+            }
+            ast::Stmt::FunctionDef(ast::StmtFunctionDef { body, .. }) => {
+                collect_warnings(body, warnings);
+            }
+            ast::Stmt::If(ast::StmtIf {
+                test, body, orelse, ..
+            }) => {
+                collect_expr_warnings(test, warnings);
+                collect_warnings(body, warnings);
+                collect_warnings(orelse, warnings);
+            }
+            ast::Stmt::Try(ast::StmtTry {
+                body,
+                handlers,
+                orelse,
+                finalbody,
+                ..
+            }) => {
+                collect_warnings(body, warnings);
+                for ast::ExceptHandler::ExceptHandler(handler) in handlers {
+                    collect_warnings(&handler.body, warnings);
+                }
+                collect_warnings(orelse, warnings);
+                collect_warnings(finalbody, warnings);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Walks an expression looking for suspicious constructs.
+fn collect_expr_warnings(expr: &ast::Expr, warnings: &mut Vec<LoweringWarning>) {
+    match expr {
+        ast::Expr::Compare(ast::ExprCompare {
+            left,
+            ops,
+            comparators,
+            range,
+        }) => {
+            let is_none = |e: &ast::Expr| {
+                matches!(
+                    e,
+                    ast::Expr::Constant(ast::ExprConstant {
+                        value: ast::Constant::None,
+                        ..
+                    })
+                )
+            };
+            let uses_identity = ops
+                .iter()
+                .any(|op| matches!(op, ast::CmpOp::Is | ast::CmpOp::IsNot));
+            if uses_identity && !is_none(left) && !comparators.iter().any(is_none) {
+                warnings.push(LoweringWarning::IdentityComparison { range: *range });
+            }
+            collect_expr_warnings(left, warnings);
+            for comparator in comparators {
+                collect_expr_warnings(comparator, warnings);
+            }
+        }
+        ast::Expr::BinOp(ast::ExprBinOp { left, right, .. }) => {
+            collect_expr_warnings(left, warnings);
+            collect_expr_warnings(right, warnings);
+        }
+        ast::Expr::UnaryOp(ast::ExprUnaryOp { operand, .. }) => {
+            collect_expr_warnings(operand, warnings);
+        }
+        ast::Expr::BoolOp(ast::ExprBoolOp { values, .. }) => {
+            for value in values {
+                collect_expr_warnings(value, warnings);
+            }
+        }
+        ast::Expr::Call(ast::ExprCall {
+            func,
+            args,
+            keywords,
+            ..
+        }) => {
+            collect_expr_warnings(func, warnings);
+            for arg in args {
+                collect_expr_warnings(arg, warnings);
+            }
+            for keyword in keywords {
+                collect_expr_warnings(&keyword.value, warnings);
+            }
+        }
+        ast::Expr::List(ast::ExprList { elts, .. }) | ast::Expr::Set(ast::ExprSet { elts, .. }) => {
+            for elt in elts {
+                collect_expr_warnings(elt, warnings);
+            }
+        }
+        ast::Expr::ListComp(ast::ExprListComp {
+            elt, generators, ..
+        }) => {
+            collect_expr_warnings(elt, warnings);
+            for generator in generators {
+                collect_expr_warnings(&generator.iter, warnings);
+                for condition in &generator.ifs {
+                    collect_expr_warnings(condition, warnings);
+                }
+            }
+        }
+        ast::Expr::IfExp(ast::ExprIfExp {
+            test, body, orelse, ..
+        }) => {
+            collect_expr_warnings(test, warnings);
+            collect_expr_warnings(body, warnings);
+            collect_expr_warnings(orelse, warnings);
+        }
+        ast::Expr::Dict(ast::ExprDict { keys, values, .. }) => {
+            for key in keys.iter().flatten() {
+                collect_expr_warnings(key, warnings);
+            }
+            for value in values {
+                collect_expr_warnings(value, warnings);
+            }
+        }
+        ast::Expr::Subscript(ast::ExprSubscript { value, slice, .. }) => {
+            collect_expr_warnings(value, warnings);
+            collect_expr_warnings(slice, warnings);
+        }
+        ast::Expr::Attribute(ast::ExprAttribute { value, .. })
+        | ast::Expr::NamedExpr(ast::ExprNamedExpr { value, .. })
+        | ast::Expr::FormattedValue(ast::ExprFormattedValue { value, .. }) => {
+            collect_expr_warnings(value, warnings);
+        }
+        ast::Expr::JoinedStr(ast::ExprJoinedStr { values, .. }) => {
+            for value in values {
+                collect_expr_warnings(value, warnings);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns true for loop conditions that are constant truthy literals (`True`, `1`)
+fn is_always_true(test: &ast::Expr) -> bool {
+    match test {
+        ast::Expr::Constant(ast::ExprConstant { value, .. }) => match value {
+            ast::Constant::Bool(b) => *b,
+            ast::Constant::Int(i) => i.to_i64() != Some(0),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Returns true if the loop body contains a break (not belonging to a nested loop),
+/// a return, or a raise
+fn can_exit_loop(stmts: &[ast::Stmt], in_nested_loop: bool) -> bool {
+    stmts.iter().any(|stmt| match stmt {
+        ast::Stmt::Break(_) => !in_nested_loop,
+        ast::Stmt::Return(_) | ast::Stmt::Raise(_) => true,
+        ast::Stmt::If(ast::StmtIf { body, orelse, .. }) => {
+            can_exit_loop(body, in_nested_loop) || can_exit_loop(orelse, in_nested_loop)
+        }
+        ast::Stmt::While(ast::StmtWhile { body, orelse, .. })
+        | ast::Stmt::For(ast::StmtFor { body, orelse, .. }) => {
+            can_exit_loop(body, true) || can_exit_loop(orelse, in_nested_loop)
+        }
+        ast::Stmt::Try(ast::StmtTry {
+            body,
+            handlers,
+            orelse,
+            finalbody,
+            ..
+        }) => {
+            can_exit_loop(body, in_nested_loop)
+                || handlers.iter().any(|ast::ExceptHandler::ExceptHandler(h)| {
+                    can_exit_loop(&h.body, in_nested_loop)
+                })
+                || can_exit_loop(orelse, in_nested_loop)
+                || can_exit_loop(finalbody, in_nested_loop)
+        }
+        _ => false,
+    })
+}
+
+/// Rejects `break` / `continue` outside a loop, as Python's compiler does. A function
+/// body starts outside any loop even when defined inside one, and comprehensions
+/// hold only expressions, so their implicit loops never capture a `break`
+fn check_loop_control(stmts: &[ast::Stmt], in_loop: bool) -> Result<(), LoweringError> {
+    for stmt in stmts {
+        match stmt {
+            ast::Stmt::Break(_) if !in_loop => {
+                return Err(LoweringError::LoopControlOutsideLoop("break"))
+            }
+            ast::Stmt::Continue(_) if !in_loop => {
+                return Err(LoweringError::LoopControlOutsideLoop("continue"))
+            }
+            ast::Stmt::While(ast::StmtWhile { body, orelse, .. })
+            | ast::Stmt::For(ast::StmtFor { body, orelse, .. }) => {
+                check_loop_control(body, true)?;
+                check_loop_control(orelse, in_loop)?;
+            }
+            ast::Stmt::If(ast::StmtIf { body, orelse, .. }) => {
+                check_loop_control(body, in_loop)?;
+                check_loop_control(orelse, in_loop)?;
+            }
+            ast::Stmt::Try(ast::StmtTry {
+                body,
+                handlers,
+                orelse,
+                finalbody,
+                ..
+            }) => {
+                check_loop_control(body, in_loop)?;
+                for ast::ExceptHandler::ExceptHandler(handler) in handlers {
+                    check_loop_control(&handler.body, in_loop)?;
+                }
+                check_loop_control(orelse, in_loop)?;
+                check_loop_control(finalbody, in_loop)?;
+            }
+            ast::Stmt::FunctionDef(ast::StmtFunctionDef { body, .. }) => {
+                check_loop_control(body, false)?;
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Returns the error types an `except` clause catches: a name or a tuple of names,
+/// with the built-in base classes standing for their subclasses. An empty list
+/// (from `Exception` or `BaseException`) catches every error. `None` when the
+/// clause is not made of names
+fn handled_exceptions(type_: &ast::Expr) -> Option<Vec<String>> {
+    let names = match type_ {
+        ast::Expr::Name(ast::ExprName { id, .. }) => vec![id.as_str()],
+        ast::Expr::Tuple(ast::ExprTuple { elts, .. }) => elts
+            .iter()
+            .map(|elt| match elt {
+                ast::Expr::Name(ast::ExprName { id, .. }) => Some(id.as_str()),
+                _ => None,
+            })
+            .collect::<Option<Vec<_>>>()?,
+        _ => return None,
+    };
+    let mut exceptions = Vec::new();
+    for name in names {
+        let subclasses: &[&str] = match name {
+            "Exception" | "BaseException" => return Some(Vec::new()),
+            "ArithmeticError" => &["ZeroDivisionError", "OverflowError"],
+            "LookupError" => &["IndexError", "KeyError"],
+            "RuntimeError" => &["RecursionError"],
+            _ => &[],
+        };
+        exceptions.push(name.to_string());
+        exceptions.extend(subclasses.iter().map(|name| name.to_string()));
+    }
+    Some(exceptions)
+}
+
+/// Lowers a block of statements. `import sys` only makes `sys.stderr` /
+/// `sys.stdout` available to print(file=...), so it is dropped.
+fn lower_block(
+    stmts: &[ast::Stmt],
+    shadowed: &HashSet<String>,
+) -> Result<Vec<IRStmt>, LoweringError> {
+    stmts
+        .iter()
+        .filter(|stmt| !is_sys_import(stmt))
+        .map(|stmt| lower_statement(stmt, shadowed))
+        .collect()
+}
+
+/// Returns true for `import sys` (possibly repeated, as in `import sys, sys`)
+fn is_sys_import(stmt: &ast::Stmt) -> bool {
+    matches!(stmt, ast::Stmt::Import(ast::StmtImport { names, .. })
+        if names
+            .iter()
+            .all(|alias| alias.name.as_str() == "sys" && alias.asname.is_none()))
 }
 
 /// Lowers a single statement.
-fn lower_statement(stmt: &ast::Stmt) -> Result<IRStmt, LoweringError> {
+fn lower_statement(stmt: &ast::Stmt, shadowed: &HashSet<String>) -> Result<IRStmt, LoweringError> {
     match stmt {
         ast::Stmt::Expr(ast::StmtExpr { value, .. }) => {
             // Special handling for print() calls
-            if let ast::Expr::Call(ast::ExprCall { func, args, .. }) = value.as_ref() {
-                if let ast::Expr::Name(ast::ExprName { id, .. }) = func.as_ref() {
+            if let ast::Expr::Call(ast::ExprCall {
+                func,
+                args,
+                keywords,
+                ..
+            }) = value.as_ref()
+            {
+                let builtin = match func.as_ref() {
+                    ast::Expr::Name(ast::ExprName { id, .. })
+                        if !shadowed.contains(id.as_str()) =>
+                    {
+                        Some(id.as_str())
+                    }
+                    _ => None,
+                };
+                if let Some(id) = builtin {
                     if id == "print" {
                         // Lower all arguments
-                        let lowered_args: Result<Vec<IRExpr>, LoweringError> =
-                            args.iter().map(lower_expression).collect();
-                        return Ok(IRStmt::Print(lowered_args?));
+                        let lowered_args: Result<Vec<IRExpr>, LoweringError> = args
+                            .iter()
+                            .map(|expr| lower_expression(expr, shadowed))
+                            .collect();
+                        let mut sep = None;
+                        let mut end = None;
+                        let mut stderr = false;
+                        for keyword in keywords {
+                            let name = keyword.arg.as_ref().map(|arg| arg.as_str());
+                            if name == Some("file") {
+                                stderr = match print_stream(&keyword.value) {
+                                    Some(stream) => stream == "stderr",
+                                    None => {
+                                        return Err(LoweringError::UnsupportedStatement(Box::new(
+                                            stmt.clone(),
+                                        )))
+                                    }
+                                };
+                                continue;
+                            }
+                            let slot = match name {
+                                Some("sep") => &mut sep,
+                                Some("end") => &mut end,
+                                _ => {
+                                    return Err(LoweringError::UnsupportedStatement(Box::new(
+                                        stmt.clone(),
+                                    )))
+                                }
+                            };
+                            // A literal None means the default
+                            *slot = match lower_expression(&keyword.value, shadowed)? {
+                                IRExpr::NoneLiteral => None,
+                                value => Some(value),
+                            };
+                        }
+                        return Ok(IRStmt::Print {
+                            args: lowered_args?,
+                            sep,
+                            end,
+                            stderr,
+                        });
+                    }
+                    // exit() / quit() end the program; the status defaults to 0
+                    if id == "exit" || id == "quit" {
+                        let code = match args.as_slice() {
+                            [] => IRExpr::Constant(0),
+                            [code] => lower_expression(code, shadowed)?,
+                            _ => {
+                                return Err(LoweringError::UnsupportedStatement(Box::new(
+                                    stmt.clone(),
+                                )))
+                            }
+                        };
+                        return Ok(IRStmt::Exit(code));
                     }
                 }
             }
             // General expression statement (e.g., function call without using result)
-            let expr = lower_expression(value)?;
+            let expr = lower_expression(value, shadowed)?;
             Ok(IRStmt::ExprStmt(expr))
         }
         ast::Stmt::Assign(ast::StmtAssign { targets, value, .. }) => {
+            targets.iter().try_for_each(check_assign_target)?;
             if targets.len() != 1 {
                 return Err(LoweringError::UnsupportedStatement(Box::new(stmt.clone())));
             }
-            if let ast::Expr::Name(ast::ExprName { id, .. }) = &targets[0] {
-                let value = lower_expression(value)?;
-                Ok(IRStmt::Assign {
-                    target: id.to_string(),
-                    value,
-                })
-            } else {
-                Err(LoweringError::UnsupportedStatement(Box::new(stmt.clone())))
+            match &targets[0] {
+                ast::Expr::Name(ast::ExprName { id, .. }) => {
+                    let value = lower_expression(value, shadowed)?;
+                    Ok(IRStmt::Assign {
+                        target: id.to_string(),
+                        value,
+                    })
+                }
+                ast::Expr::Tuple(ast::ExprTuple { elts, .. })
+                | ast::Expr::List(ast::ExprList { elts, .. }) => {
+                    // Tuple unpacking: every target must be a plain name
+                    let targets = elts
+                        .iter()
+                        .map(|elt| match elt {
+                            ast::Expr::Name(ast::ExprName { id, .. }) => Ok(id.to_string()),
+                            _ => Err(LoweringError::UnsupportedStatement(Box::new(stmt.clone()))),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let value = lower_expression(value, shadowed)?;
+                    Ok(IRStmt::UnpackAssign { targets, value })
+                }
+                ast::Expr::Subscript(ast::ExprSubscript {
+                    value: list, slice, ..
+                }) => {
+                    // For nested targets the list lowers to an index read that
+                    // yields the inner list, which is then mutated in place
+                    let list = lower_expression(list, shadowed)?;
+                    let index = lower_expression(slice, shadowed)?;
+                    let value = lower_expression(value, shadowed)?;
+                    Ok(IRStmt::IndexAssign { list, index, value })
+                }
+                _ => Err(LoweringError::UnsupportedStatement(Box::new(stmt.clone()))),
             }
         }
         ast::Stmt::FunctionDef(ast::StmtFunctionDef {
-            name, args, body, ..
+            name,
+            args,
+            body,
+            decorator_list,
+            ..
         }) => {
-            let params = args
+            // Rather than silently dropping the decorator, refuse it
+            if let Some(decorator) = decorator_list.first() {
+                return Err(LoweringError::UnsupportedDecorator(decorator_name(
+                    decorator,
+                )));
+            }
+            let params: Vec<String> = args
                 .args
                 .iter()
                 .map(|arg| arg.def.arg.to_string())
@@ -73,37 +665,61 @@ fn lower_statement(stmt: &ast::Stmt) -> Result<IRStmt, LoweringError> {
             // Default values apply to the last N parameters
             let defaults_start = num_params - num_defaults;
             for (i, default_expr) in defaults_vec.iter().enumerate() {
-                let lowered_default = lower_expression(default_expr)?;
+                let lowered_default = lower_expression(default_expr, shadowed)?;
                 defaults[defaults_start + i] = Some(lowered_default);
             }
 
-            let body: Result<Vec<IRStmt>, LoweringError> =
-                body.iter().map(lower_statement).collect();
+            // Keyword-only parameters (after `*`) carry their own defaults
+            let kwonly_params = args
+                .kwonlyargs
+                .iter()
+                .map(|arg| arg.def.arg.to_string())
+                .collect();
+            let kwonly_defaults = args
+                .kwonlyargs
+                .iter()
+                .map(|arg| {
+                    arg.default
+                        .as_deref()
+                        .map(|expr| lower_expression(expr, shadowed))
+                        .transpose()
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+
+            let kwarg = args.kwarg.as_ref().map(|arg| arg.arg.to_string());
+
+            // Defaults are evaluated outside the function, the body sees its own locals
+            let mut locals: HashSet<String> = shadowed.clone();
+            locals.extend(params.iter().chain(&kwonly_params).chain(&kwarg).cloned());
+            let shadowed = &shadowed_builtins(body, locals);
+            let body = lower_block(skip_docstring(body), shadowed);
             Ok(IRStmt::FunctionDef {
                 name: name.to_string(),
                 params,
                 defaults,
+                kwonly_params,
+                kwonly_defaults,
+                kwarg,
                 body: body?,
             })
         }
         ast::Stmt::Return(ast::StmtReturn { value, .. }) => {
-            let value = value
-                .as_ref()
-                .ok_or_else(|| LoweringError::UnsupportedStatement(Box::new(stmt.clone())))?;
-            let expr = lower_expression(value)?;
+            // A bare `return` returns None
+            let expr = match value {
+                Some(value) => lower_expression(value, shadowed)?,
+                None => IRExpr::NoneLiteral,
+            };
             Ok(IRStmt::Return(expr))
         }
         ast::Stmt::If(ast::StmtIf {
             test, body, orelse, ..
         }) => {
-            let condition = lower_expression(test)?;
-            let then_body: Result<Vec<IRStmt>, LoweringError> =
-                body.iter().map(lower_statement).collect();
+            let condition = lower_expression(test, shadowed)?;
+            let then_body = lower_block(body, shadowed);
 
             // Handle else clause (including elif, which is represented as a nested If in orelse)
             let else_body = if !orelse.is_empty() {
-                let else_stmts: Result<Vec<IRStmt>, LoweringError> =
-                    orelse.iter().map(lower_statement).collect();
+                let else_stmts = lower_block(orelse, shadowed);
                 else_stmts?
             } else {
                 Vec::new()
@@ -115,22 +731,26 @@ fn lower_statement(stmt: &ast::Stmt) -> Result<IRStmt, LoweringError> {
                 else_body,
             })
         }
-        ast::Stmt::While(ast::StmtWhile { test, body, .. }) => {
-            let condition = lower_expression(test)?;
-            let body: Result<Vec<IRStmt>, LoweringError> =
-                body.iter().map(lower_statement).collect();
+        ast::Stmt::While(ast::StmtWhile {
+            test, body, orelse, ..
+        }) => {
+            let condition = lower_expression(test, shadowed)?;
+            let body = lower_block(body, shadowed);
+            let else_body = lower_block(orelse, shadowed);
             Ok(IRStmt::While {
                 condition,
                 body: body?,
+                else_body: else_body?,
             })
         }
         ast::Stmt::AugAssign(ast::StmtAugAssign {
             target, op, value, ..
         }) => {
+            check_assign_target(target)?;
             // Desugar augmented assignment: x += y => x = x + y
             if let ast::Expr::Name(ast::ExprName { id, .. }) = target.as_ref() {
                 let current_value = IRExpr::Variable(id.to_string());
-                let new_value = lower_expression(value)?;
+                let new_value = lower_expression(value, shadowed)?;
                 let op = lower_binop(op)?;
                 let result = IRExpr::BinaryOp {
                     op,
@@ -145,74 +765,208 @@ fn lower_statement(stmt: &ast::Stmt) -> Result<IRStmt, LoweringError> {
                 Err(LoweringError::UnsupportedStatement(Box::new(stmt.clone())))
             }
         }
+        ast::Stmt::Try(ast::StmtTry {
+            body,
+            handlers,
+            orelse,
+            finalbody,
+            ..
+        }) => {
+            // Only a single `except:` / `except SomeError:` clause without `as` or `finally`
+            if handlers.len() != 1 || !finalbody.is_empty() {
+                return Err(LoweringError::UnsupportedStatement(Box::new(stmt.clone())));
+            }
+            let ast::ExceptHandler::ExceptHandler(ast::ExceptHandlerExceptHandler {
+                type_,
+                name,
+                body: handler_body,
+                ..
+            }) = &handlers[0];
+            if name.is_some() {
+                return Err(LoweringError::UnsupportedStatement(Box::new(stmt.clone())));
+            }
+            let exceptions = match type_.as_deref() {
+                None => Vec::new(),
+                Some(type_) => handled_exceptions(type_)
+                    .ok_or_else(|| LoweringError::UnsupportedStatement(Box::new(stmt.clone())))?,
+            };
+
+            let body = lower_block(body, shadowed);
+            let handler = lower_block(handler_body, shadowed);
+            let else_body = lower_block(orelse, shadowed);
+            Ok(IRStmt::Try {
+                body: body?,
+                exceptions,
+                handler: handler?,
+                else_body: else_body?,
+            })
+        }
+        ast::Stmt::Global(ast::StmtGlobal { names, .. }) => Ok(IRStmt::Global(
+            names.iter().map(|name| name.to_string()).collect(),
+        )),
+        ast::Stmt::Delete(ast::StmtDelete { targets, .. }) => {
+            // Only plain names can be deleted
+            let names = targets
+                .iter()
+                .map(|target| match target {
+                    ast::Expr::Name(ast::ExprName { id, .. }) => Ok(id.to_string()),
+                    _ => Err(LoweringError::UnsupportedStatement(Box::new(stmt.clone()))),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(IRStmt::Delete(names))
+        }
+        ast::Stmt::With(_) | ast::Stmt::AsyncWith(_) => Err(LoweringError::UnsupportedWith),
+        ast::Stmt::Raise(ast::StmtRaise { exc, cause, .. }) => {
+            // Only `raise`, `raise Name` and `raise Name(message)` are supported
+            let unsupported = || LoweringError::UnsupportedStatement(Box::new(stmt.clone()));
+            if cause.is_some() {
+                return Err(unsupported());
+            }
+            match exc.as_deref() {
+                None => Ok(IRStmt::Raise {
+                    exception: None,
+                    message: None,
+                }),
+                Some(ast::Expr::Name(ast::ExprName { id, .. })) => Ok(IRStmt::Raise {
+                    exception: Some(id.to_string()),
+                    message: None,
+                }),
+                Some(ast::Expr::Call(ast::ExprCall {
+                    func,
+                    args,
+                    keywords,
+                    ..
+                })) if args.len() <= 1 && keywords.is_empty() => match func.as_ref() {
+                    ast::Expr::Name(ast::ExprName { id, .. }) => Ok(IRStmt::Raise {
+                        exception: Some(id.to_string()),
+                        message: args
+                            .first()
+                            .map(|expr| lower_expression(expr, shadowed))
+                            .transpose()?,
+                    }),
+                    _ => Err(unsupported()),
+                },
+                Some(_) => Err(unsupported()),
+            }
+        }
+        ast::Stmt::Assert(ast::StmtAssert { test, msg, .. }) => Ok(IRStmt::Assert {
+            condition: lower_expression(test, shadowed)?,
+            message: msg
+                .as_deref()
+                .map(|expr| lower_expression(expr, shadowed))
+                .transpose()?,
+        }),
         ast::Stmt::Break(_) => Ok(IRStmt::Break),
         ast::Stmt::Continue(_) => Ok(IRStmt::Continue),
+        ast::Stmt::Pass(_) => Ok(IRStmt::Pass),
         ast::Stmt::For(ast::StmtFor {
-            target, iter, body, ..
+            target,
+            iter,
+            body,
+            orelse,
+            ..
         }) => {
-            // Only support for i in range(...) pattern
+            check_assign_target(target)?;
+
+            // Extract the loop variable. A tuple target `for a, b in ...` loops over a
+            // hidden variable, named so that it can't clash with a Python identifier,
+            // which the body starts by unpacking.
+            let (var, unpack_targets) = match target.as_ref() {
+                ast::Expr::Name(ast::ExprName { id, .. }) => (id.to_string(), None),
+                ast::Expr::Tuple(ast::ExprTuple { elts, .. }) => {
+                    let targets = elts
+                        .iter()
+                        .map(|elt| match elt {
+                            ast::Expr::Name(ast::ExprName { id, .. }) => Ok(id.to_string()),
+                            _ => Err(LoweringError::UnsupportedStatement(Box::new(stmt.clone()))),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    (format!("({})", targets.join(", ")), Some(targets))
+                }
+                _ => return Err(LoweringError::UnsupportedStatement(Box::new(stmt.clone()))),
+            };
+
+            let else_body = lower_block(orelse, shadowed);
+            let else_body = else_body?;
+
+            // for i in range(...) gets a dedicated counting loop
             if let ast::Expr::Call(ast::ExprCall { func, args, .. }) = iter.as_ref() {
                 if let ast::Expr::Name(ast::ExprName { id, .. }) = func.as_ref() {
-                    if id == "range" && !args.is_empty() {
-                        // Extract the loop variable
-                        let var = if let ast::Expr::Name(ast::ExprName { id, .. }) = target.as_ref()
-                        {
-                            id.to_string()
-                        } else {
-                            return Err(LoweringError::UnsupportedStatement(Box::new(
-                                stmt.clone(),
-                            )));
-                        };
-
-                        // Handle range(end) or range(start, end)
-                        let (start, end) = if args.len() == 1 {
+                    if id == "range" && !shadowed.contains("range") && unpack_targets.is_none() {
+                        // Handle range(end), range(start, end) or range(start, end, step)
+                        let (start, end, step) = match args.as_slice() {
                             // range(end) - start from 0
-                            (IRExpr::Constant(0), lower_expression(&args[0])?)
-                        } else if args.len() == 2 {
-                            // range(start, end)
-                            (lower_expression(&args[0])?, lower_expression(&args[1])?)
-                        } else {
-                            // range with step is not supported
-                            return Err(LoweringError::UnsupportedStatement(Box::new(
-                                stmt.clone(),
-                            )));
+                            [end] => (
+                                IRExpr::Constant(0),
+                                lower_expression(end, shadowed)?,
+                                IRExpr::Constant(1),
+                            ),
+                            [start, end] => (
+                                lower_expression(start, shadowed)?,
+                                lower_expression(end, shadowed)?,
+                                IRExpr::Constant(1),
+                            ),
+                            [start, end, step] => (
+                                lower_expression(start, shadowed)?,
+                                lower_expression(end, shadowed)?,
+                                lower_expression(step, shadowed)?,
+                            ),
+                            _ => return Err(LoweringError::RangeArgumentCount(args.len())),
                         };
 
                         // Lower the loop body
-                        let body: Result<Vec<IRStmt>, LoweringError> =
-                            body.iter().map(lower_statement).collect();
+                        let body = lower_block(body, shadowed);
 
                         return Ok(IRStmt::For {
                             var,
                             start,
                             end,
+                            step,
                             body: body?,
+                            else_body,
                         });
                     }
                 }
             }
-            Err(LoweringError::UnsupportedStatement(Box::new(stmt.clone())))
+
+            // Any other iterable: for x in <list or string>
+            let iter = lower_expression(iter, shadowed)?;
+            let mut lowered_body = Vec::with_capacity(body.len() + 1);
+            if let Some(targets) = unpack_targets {
+                lowered_body.push(IRStmt::UnpackAssign {
+                    targets,
+                    value: IRExpr::Variable(var.clone()),
+                });
+            }
+            lowered_body.extend(lower_block(body, shadowed)?);
+            Ok(IRStmt::ForEach {
+                var,
+                iter,
+                body: lowered_body,
+                else_body,
+            })
         }
         _ => Err(LoweringError::UnsupportedStatement(Box::new(stmt.clone()))),
     }
 }
 
 /// Lowers a single expression.
-fn lower_expression(expr: &ast::Expr) -> Result<IRExpr, LoweringError> {
+fn lower_expression(expr: &ast::Expr, shadowed: &HashSet<String>) -> Result<IRExpr, LoweringError> {
     match expr {
         ast::Expr::Constant(ast::ExprConstant { value, .. }) => match value {
             ast::Constant::Int(n) => Ok(IRExpr::Constant(n.to_i64().unwrap())),
             ast::Constant::Float(f) => Ok(IRExpr::Float(*f)),
             ast::Constant::Str(s) => Ok(IRExpr::StringLiteral(s.to_string())),
             ast::Constant::Bool(b) => Ok(IRExpr::Bool(*b)),
+            ast::Constant::None => Ok(IRExpr::NoneLiteral),
             _ => Err(LoweringError::UnsupportedExpression(Box::new(expr.clone()))),
         },
         ast::Expr::Name(ast::ExprName { id, .. }) => Ok(IRExpr::Variable(id.to_string())),
         ast::Expr::BinOp(ast::ExprBinOp {
             left, op, right, ..
         }) => {
-            let left = lower_expression(left)?;
-            let right = lower_expression(right)?;
+            let left = lower_expression(left, shadowed)?;
+            let right = lower_expression(right, shadowed)?;
             let op = lower_binop(op)?;
             Ok(IRExpr::BinaryOp {
                 op,
@@ -220,32 +974,54 @@ fn lower_expression(expr: &ast::Expr) -> Result<IRExpr, LoweringError> {
                 right: Box::new(right),
             })
         }
-        ast::Expr::Call(ast::ExprCall { func, args, .. }) => {
+        ast::Expr::Call(ast::ExprCall {
+            func,
+            args,
+            keywords,
+            ..
+        }) => {
             if let ast::Expr::Name(ast::ExprName { id, .. }) = func.as_ref() {
-                // Don't handle print here - it's handled as a statement
-                if id == "print" {
-                    return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
-                }
-                // Handle input() call
-                if id == "input" {
-                    if !args.is_empty() {
-                        return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
-                    }
-                    return Ok(IRExpr::Input);
-                }
-                // Handle len() call
-                if id == "len" {
-                    if args.len() != 1 {
-                        return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+                // A local binding (e.g. a parameter named `len`) shadows the builtin
+                if !shadowed.contains(id.as_str()) {
+                    if let Some(builtin) = lower_builtin_call(expr, id, args, keywords, shadowed)? {
+                        return Ok(builtin);
                     }
-                    let arg = lower_expression(&args[0])?;
-                    return Ok(IRExpr::Len(Box::new(arg)));
                 }
-                let args: Result<Vec<IRExpr>, LoweringError> =
-                    args.iter().map(lower_expression).collect();
+                let args: Result<Vec<IRExpr>, LoweringError> = args
+                    .iter()
+                    .map(|expr| lower_expression(expr, shadowed))
+                    .collect();
+                // `**mapping` expansion has no keyword name and is not supported
+                let keywords = keywords
+                    .iter()
+                    .map(|keyword| match &keyword.arg {
+                        Some(name) => Ok((
+                            name.to_string(),
+                            lower_expression(&keyword.value, shadowed)?,
+                        )),
+                        None => Err(LoweringError::UnsupportedExpression(Box::new(expr.clone()))),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
                 Ok(IRExpr::Call {
                     func: id.to_string(),
                     args: args?,
+                    keywords,
+                })
+            } else if let ast::Expr::Attribute(ast::ExprAttribute { value, attr, .. }) =
+                func.as_ref()
+            {
+                // Method calls take positional arguments only
+                if !keywords.is_empty() {
+                    return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+                }
+                let args: Result<Vec<IRExpr>, LoweringError> = args
+                    .iter()
+                    .map(|expr| lower_expression(expr, shadowed))
+                    .collect();
+                Ok(IRExpr::MethodCall {
+                    object: Box::new(lower_expression(value, shadowed)?),
+                    method: attr.to_string(),
+                    args: args?,
                 })
             } else {
                 Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())))
@@ -255,83 +1031,1663 @@ fn lower_expression(expr: &ast::Expr) -> Result<IRExpr, LoweringError> {
             left,
             ops,
             comparators,
-            ..
+            range,
         }) => {
-            // For simplicity, only support single comparisons (e.g., a < b, not a < b < c)
-            if ops.len() != 1 || comparators.len() != 1 {
+            if ops.is_empty() || ops.len() != comparators.len() {
                 return Err(LoweringError::InvalidComparison);
             }
 
-            let left = lower_expression(left)?;
-            let right = lower_expression(&comparators[0])?;
-            let op = match &ops[0] {
-                ast::CmpOp::Eq => CmpOp::Eq,
-                ast::CmpOp::NotEq => CmpOp::NotEq,
-                ast::CmpOp::Lt => CmpOp::Lt,
-                ast::CmpOp::Gt => CmpOp::Gt,
-                ast::CmpOp::LtE => CmpOp::LtE,
-                ast::CmpOp::GtE => CmpOp::GtE,
-                _ => return Err(LoweringError::UnsupportedComparisonOperator(ops[0])),
-            };
+            // `a < b in c` means `a < b and b in c`, with `b` evaluated only once
+            let mut left = lower_expression(left, shadowed)?;
+            let mut comparisons = Vec::new();
+            for (index, (op, comparator)) in ops.iter().zip(comparators).enumerate() {
+                let op = lower_cmpop(op);
+                let right = lower_expression(comparator, shadowed)?;
+                let is_last = index + 1 == comparators.len();
+                let (right, next_left) = if is_last || is_reevaluable(&right) {
+                    (right.clone(), right)
+                } else {
+                    // A middle operand is also the next comparison's left side, so it
+                    // is stored in a temporary no Python name can clash with
+                    let temp = format!("$chain{}_{}", u32::from(range.start()), index);
+                    (
+                        IRExpr::NamedExpr {
+                            target: temp.clone(),
+                            value: Box::new(right),
+                        },
+                        IRExpr::Variable(temp),
+                    )
+                };
+                comparisons.push(IRExpr::Comparison {
+                    op,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                });
+                left = next_left;
+            }
 
-            Ok(IRExpr::Comparison {
-                op,
-                left: Box::new(left),
-                right: Box::new(right),
+            Ok(if comparisons.len() == 1 {
+                comparisons.remove(0)
+            } else {
+                IRExpr::BoolOp {
+                    op: BoolOp::And,
+                    values: comparisons,
+                }
             })
         }
         ast::Expr::UnaryOp(ast::ExprUnaryOp { op, operand, .. }) => {
-            let operand = lower_expression(operand)?;
+            let operand = lower_expression(operand, shadowed)?;
             let op = match op {
                 ast::UnaryOp::Invert => UnaryOp::Invert,
                 ast::UnaryOp::Not => UnaryOp::Not,
                 ast::UnaryOp::UAdd => UnaryOp::UAdd,
                 ast::UnaryOp::USub => UnaryOp::USub,
             };
+            // Fold a negated integer literal into a negative constant, so that the
+            // most negative int (-2**47) never passes through its out-of-range positive
+            if let (UnaryOp::USub, IRExpr::Constant(n)) = (&op, &operand) {
+                return Ok(IRExpr::Constant(-n));
+            }
             Ok(IRExpr::UnaryOp {
                 op,
                 operand: Box::new(operand),
             })
         }
         ast::Expr::List(ast::ExprList { elts, .. }) => {
-            let elements: Result<Vec<IRExpr>, LoweringError> =
-                elts.iter().map(lower_expression).collect();
+            let elements: Result<Vec<IRExpr>, LoweringError> = elts
+                .iter()
+                .map(|expr| lower_expression(expr, shadowed))
+                .collect();
             Ok(IRExpr::List(elements?))
         }
+        ast::Expr::ListComp(ast::ExprListComp {
+            elt, generators, ..
+        }) => {
+            // Only a single, non-async `for name in ...` clause is supported
+            let unsupported = || LoweringError::UnsupportedExpression(Box::new(expr.clone()));
+            let [generator] = generators.as_slice() else {
+                return Err(unsupported());
+            };
+            let ast::Expr::Name(ast::ExprName { id: var, .. }) = &generator.target else {
+                return Err(unsupported());
+            };
+            if generator.is_async {
+                return Err(unsupported());
+            }
+            let source = match &generator.iter {
+                ast::Expr::Call(ast::ExprCall {
+                    func,
+                    args,
+                    keywords,
+                    ..
+                }) if matches!(func.as_ref(), ast::Expr::Name(ast::ExprName { id, .. }) if id == "range")
+                    && !shadowed.contains("range")
+                    && keywords.is_empty() =>
+                {
+                    match args.as_slice() {
+                        [end] => ComprehensionSource::Range {
+                            start: IRExpr::Constant(0),
+                            end: lower_expression(end, shadowed)?,
+                        },
+                        [start, end] => ComprehensionSource::Range {
+                            start: lower_expression(start, shadowed)?,
+                            end: lower_expression(end, shadowed)?,
+                        },
+                        [] => return Err(LoweringError::RangeArgumentCount(0)),
+                        _ => return Err(unsupported()),
+                    }
+                }
+                iter => ComprehensionSource::Iterable(lower_expression(iter, shadowed)?),
+            };
+            let conditions = generator
+                .ifs
+                .iter()
+                .map(|expr| lower_expression(expr, shadowed))
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(IRExpr::ListComp {
+                element: Box::new(lower_expression(elt, shadowed)?),
+                var: var.to_string(),
+                source: Box::new(source),
+                conditions,
+            })
+        }
+        ast::Expr::Tuple(ast::ExprTuple { elts, .. }) => {
+            let elements: Result<Vec<IRExpr>, LoweringError> = elts
+                .iter()
+                .map(|expr| lower_expression(expr, shadowed))
+                .collect();
+            Ok(IRExpr::Tuple(elements?))
+        }
+        ast::Expr::NamedExpr(ast::ExprNamedExpr { target, value, .. }) => match target.as_ref() {
+            ast::Expr::Name(ast::ExprName { id, .. }) => Ok(IRExpr::NamedExpr {
+                target: id.to_string(),
+                value: Box::new(lower_expression(value, shadowed)?),
+            }),
+            _ => Err(LoweringError::UnsupportedExpression(Box::new(expr.clone()))),
+        },
+        ast::Expr::BoolOp(ast::ExprBoolOp { op, values, .. }) => {
+            let op = match op {
+                ast::BoolOp::And => BoolOp::And,
+                ast::BoolOp::Or => BoolOp::Or,
+            };
+            let values: Result<Vec<IRExpr>, LoweringError> = values
+                .iter()
+                .map(|expr| lower_expression(expr, shadowed))
+                .collect();
+            Ok(IRExpr::BoolOp {
+                op,
+                values: values?,
+            })
+        }
+        ast::Expr::IfExp(ast::ExprIfExp {
+            test, body, orelse, ..
+        }) => Ok(IRExpr::IfExp {
+            condition: Box::new(lower_expression(test, shadowed)?),
+            then_value: Box::new(lower_expression(body, shadowed)?),
+            else_value: Box::new(lower_expression(orelse, shadowed)?),
+        }),
+        ast::Expr::Dict(ast::ExprDict { keys, values, .. }) => {
+            let mut entries: Vec<(IRExpr, IRExpr)> = Vec::new();
+            for (key, value) in keys.iter().zip(values) {
+                // `**mapping` entries have no key and are not supported
+                let key = key
+                    .as_ref()
+                    .ok_or_else(|| LoweringError::UnsupportedExpression(Box::new(expr.clone())))?;
+                let key = lower_expression(key, shadowed)?;
+                let value = lower_expression(value, shadowed)?;
+                // A repeated literal key keeps its first position but takes the last value
+                let is_literal_key = matches!(
+                    key,
+                    IRExpr::Constant(_)
+                        | IRExpr::StringLiteral(_)
+                        | IRExpr::Bool(_)
+                        | IRExpr::NoneLiteral
+                );
+                match entries
+                    .iter_mut()
+                    .find(|(existing, _)| is_literal_key && *existing == key)
+                {
+                    Some(entry) => entry.1 = value,
+                    None => entries.push((key, value)),
+                }
+            }
+            Ok(IRExpr::Dict(entries))
+        }
+        ast::Expr::Set(ast::ExprSet { elts, .. }) => {
+            let elements: Result<Vec<IRExpr>, LoweringError> = elts
+                .iter()
+                .map(|expr| lower_expression(expr, shadowed))
+                .collect();
+            Ok(IRExpr::Set(elements?))
+        }
         ast::Expr::Subscript(ast::ExprSubscript { value, slice, .. }) => {
-            let list = lower_expression(value)?;
-            let index = lower_expression(slice)?;
+            let list = lower_expression(value, shadowed)?;
+            let index = lower_expression(slice, shadowed)?;
             Ok(IRExpr::Index {
                 list: Box::new(list),
                 index: Box::new(index),
             })
         }
+        ast::Expr::JoinedStr(ast::ExprJoinedStr { values, .. }) => {
+            let unsupported = || LoweringError::UnsupportedExpression(Box::new(expr.clone()));
+            let mut parts = Vec::new();
+            for value in values {
+                match value {
+                    ast::Expr::Constant(ast::ExprConstant {
+                        value: ast::Constant::Str(text),
+                        ..
+                    }) => parts.push(FStringPart::Literal(text.to_string())),
+                    ast::Expr::FormattedValue(ast::ExprFormattedValue {
+                        value,
+                        conversion,
+                        format_spec,
+                        ..
+                    }) => {
+                        // `!s` is the default conversion; `!r` / `!a` are not supported
+                        if !matches!(
+                            conversion,
+                            ast::ConversionFlag::None | ast::ConversionFlag::Str
+                        ) {
+                            return Err(unsupported());
+                        }
+                        let spec = match format_spec.as_deref() {
+                            None => None,
+                            Some(spec) => Some(lower_format_spec(spec).ok_or_else(unsupported)?),
+                        };
+                        parts.push(FStringPart::Value {
+                            value: lower_expression(value, shadowed)?,
+                            spec,
+                        });
+                    }
+                    _ => return Err(unsupported()),
+                }
+            }
+            // Without replacement fields the f-string is just its text
+            let literal_text = parts
+                .iter()
+                .map(|part| match part {
+                    FStringPart::Literal(text) => Some(text.as_str()),
+                    FStringPart::Value { .. } => None,
+                })
+                .collect::<Option<String>>();
+            Ok(match literal_text {
+                Some(text) => IRExpr::StringLiteral(text),
+                None => IRExpr::FString(parts),
+            })
+        }
         _ => Err(LoweringError::UnsupportedExpression(Box::new(expr.clone()))),
     }
 }
 
-/// Helper function to convert AST binary operators to IR binary operators.
-fn lower_binop(op: &ast::Operator) -> Result<BinOp, LoweringError> {
-    match op {
-        ast::Operator::Add => Ok(BinOp::Add),
+/// Lowers a call to a builtin the compiler implements directly (`len`, `map`, ...).
+/// Returns None for any other name, which is then called as a user function.
+fn lower_builtin_call(
+    expr: &ast::Expr,
+    id: &str,
+    args: &[ast::Expr],
+    keywords: &[ast::Keyword],
+    shadowed: &HashSet<String>,
+) -> Result<Option<IRExpr>, LoweringError> {
+    // Don't handle print here - it's handled as a statement
+    if id == "print" {
+        return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+    }
+    // Handle input() call
+    if id == "input" {
+        if !args.is_empty() {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        }
+        return Ok(Some(IRExpr::Input));
+    }
+    // Handle len() call
+    if id == "len" {
+        if args.len() != 1 {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        }
+        let arg = lower_expression(&args[0], shadowed)?;
+        return Ok(Some(IRExpr::Len(Box::new(arg))));
+    }
+    // Handle reversed() call
+    if id == "reversed" {
+        if args.len() != 1 {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        }
+        let arg = lower_expression(&args[0], shadowed)?;
+        return Ok(Some(IRExpr::Reversed(Box::new(arg))));
+    }
+    // Handle str() call: the same conversion as the f-string `f"{value}"`
+    if id == "str" {
+        if args.len() > 1 || !keywords.is_empty() {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        }
+        return Ok(Some(match args.first() {
+            Some(arg) => IRExpr::FString(vec![FStringPart::Value {
+                value: lower_expression(arg, shadowed)?,
+                spec: None,
+            }]),
+            None => IRExpr::StringLiteral(String::new()),
+        }));
+    }
+    // Handle type() call
+    if id == "type" {
+        if args.len() != 1 || !keywords.is_empty() {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        }
+        let arg = lower_expression(&args[0], shadowed)?;
+        return Ok(Some(IRExpr::TypeName(Box::new(arg))));
+    }
+    // Handle sum() call
+    if id == "sum" {
+        if args.len() != 1 || !keywords.is_empty() {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        }
+        let arg = lower_expression(&args[0], shadowed)?;
+        return Ok(Some(IRExpr::Sum(Box::new(arg))));
+    }
+    // Handle abs() call
+    if id == "abs" {
+        if args.len() != 1 || !keywords.is_empty() {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        }
+        let arg = lower_expression(&args[0], shadowed)?;
+        return Ok(Some(IRExpr::Abs(Box::new(arg))));
+    }
+    // Handle int() call; a `base` argument is not supported
+    if id == "int" {
+        if args.len() > 1 || !keywords.is_empty() {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        }
+        return Ok(Some(match args.first() {
+            Some(arg) => IRExpr::Int(Box::new(lower_expression(arg, shadowed)?)),
+            None => IRExpr::Constant(0),
+        }));
+    }
+    // Handle min() and max() calls; `key=` and `default=` are not supported
+    if id == "min" || id == "max" {
+        if args.is_empty() || !keywords.is_empty() {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        }
+        let args = args
+            .iter()
+            .map(|arg| lower_expression(arg, shadowed))
+            .collect::<Result<Vec<_>, _>>()?;
+        return Ok(Some(IRExpr::MinMax {
+            is_max: id == "max",
+            args,
+        }));
+    }
+    // Handle sorted(list) and sorted(list, key=f): the key must be named
+    if id == "sorted" {
+        if args.len() != 1 {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        }
+        let mut key = None;
+        for keyword in keywords {
+            match (keyword.arg.as_ref().map(|arg| arg.as_str()), &keyword.value) {
+                (Some("key"), ast::Expr::Name(ast::ExprName { id, .. })) => {
+                    key = Some(id.to_string())
+                }
+                (
+                    Some("key"),
+                    ast::Expr::Constant(ast::ExprConstant {
+                        value: ast::Constant::None,
+                        ..
+                    }),
+                ) => key = None,
+                _ => return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone()))),
+            }
+        }
+        let list = Box::new(lower_expression(&args[0], shadowed)?);
+        return Ok(Some(IRExpr::Sorted { list, key }));
+    }
+    // Handle zip(xs, ys)
+    if id == "zip" {
+        let [left, right] = args else {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        };
+        let left = lower_expression(left, shadowed)?;
+        let right = lower_expression(right, shadowed)?;
+        return Ok(Some(IRExpr::Zip(Box::new(left), Box::new(right))));
+    }
+    // Handle hex(n), oct(n) and bin(n)
+    if let Some(base) = match id {
+        "hex" => Some(16),
+        "oct" => Some(8),
+        "bin" => Some(2),
+        _ => None,
+    } {
+        let [value] = args else {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        };
+        if !keywords.is_empty() {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        }
+        let value = Box::new(lower_expression(value, shadowed)?);
+        return Ok(Some(IRExpr::IntToBase { base, value }));
+    }
+    // Handle isclose(a, b)
+    if id == "isclose" {
+        let [left, right] = args else {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        };
+        if !keywords.is_empty() {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        }
+        let left = lower_expression(left, shadowed)?;
+        let right = lower_expression(right, shadowed)?;
+        return Ok(Some(IRExpr::IsClose(Box::new(left), Box::new(right))));
+    }
+    // Handle map(f, list) and filter(f, list): the function must be named
+    if id == "map" || id == "filter" {
+        let [func, list] = args else {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        };
+        let ast::Expr::Name(ast::ExprName { id: func, .. }) = func else {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        };
+        let func = func.to_string();
+        let list = Box::new(lower_expression(list, shadowed)?);
+        return Ok(Some(if id == "map" {
+            IRExpr::Map { func, list }
+        } else {
+            IRExpr::Filter { func, list }
+        }));
+    }
+    // Handle open() call: open(path) or open(path, mode)
+    if id == "open" {
+        if args.is_empty() || args.len() > 2 || !keywords.is_empty() {
+            return Err(LoweringError::UnsupportedExpression(Box::new(expr.clone())));
+        }
+        let path = lower_expression(&args[0], shadowed)?;
+        let mode = match args.get(1) {
+            Some(mode) => lower_expression(mode, shadowed)?,
+            None => IRExpr::StringLiteral("r".to_string()),
+        };
+        return Ok(Some(IRExpr::Open {
+            path: Box::new(path),
+            mode: Box::new(mode),
+        }));
+    }
+    Ok(None)
+}
+
+/// Parses the format spec of an f-string replacement field, which the parser hands
+/// over as a nested f-string. Returns None for specs with nested replacement fields
+/// or syntax outside `[<|>][0][width][.precision][d|f|e|g|s]`.
+fn lower_format_spec(spec: &ast::Expr) -> Option<FormatSpec> {
+    let ast::Expr::JoinedStr(ast::ExprJoinedStr { values, .. }) = spec else {
+        return None;
+    };
+    let text = match values.as_slice() {
+        [] => return Some(FormatSpec::default()),
+        [ast::Expr::Constant(ast::ExprConstant {
+            value: ast::Constant::Str(text),
+            ..
+        })] => text.as_str(),
+        _ => return None,
+    };
+
+    let mut chars = text.chars().peekable();
+    let mut spec = FormatSpec {
+        align: chars.next_if(|c| matches!(c, '<' | '>')),
+        zero_pad: chars.next_if_eq(&'0').is_some(),
+        ..FormatSpec::default()
+    };
+    let number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+        let mut digits = String::new();
+        while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            digits.push(digit);
+        }
+        digits.parse::<usize>().ok()
+    };
+    spec.width = number(&mut chars);
+    if chars.next_if_eq(&'.').is_some() {
+        spec.precision = Some(number(&mut chars)?);
+    }
+    spec.kind = chars.next_if(|c| matches!(c, 'd' | 'f' | 'e' | 'g' | 's'));
+    if chars.next().is_some() {
+        return None;
+    }
+
+    // Zero padding applies to numbers, and a precision to floats and strings only
+    let numeric = matches!(spec.kind, Some('d' | 'f' | 'e' | 'g'));
+    let precise = matches!(spec.kind, Some('f' | 'e' | 'g' | 's'));
+    if (spec.zero_pad && !numeric) || (spec.precision.is_some() && !precise) {
+        return None;
+    }
+    Some(spec)
+}
+
+/// Returns the source-like name of a decorator expression for error messages:
+/// `name`, `module.name`, or either followed by `(...)` when it is called
+fn decorator_name(decorator: &ast::Expr) -> String {
+    match decorator {
+        ast::Expr::Name(ast::ExprName { id, .. }) => id.to_string(),
+        ast::Expr::Attribute(ast::ExprAttribute { value, attr, .. }) => {
+            format!("{}.{}", decorator_name(value), attr)
+        }
+        ast::Expr::Call(ast::ExprCall { func, .. }) => format!("{}(...)", decorator_name(func)),
+        _ => "...".to_string(),
+    }
+}
+
+/// Rejects assignment to `True`, `False` or `None`, which parse as constants rather
+/// than names, including inside a tuple or list of targets
+fn check_assign_target(target: &ast::Expr) -> Result<(), LoweringError> {
+    match target {
+        ast::Expr::Constant(ast::ExprConstant { value, .. }) => {
+            let keyword = match value {
+                ast::Constant::Bool(true) => "True",
+                ast::Constant::Bool(false) => "False",
+                ast::Constant::None => "None",
+                _ => return Ok(()),
+            };
+            Err(LoweringError::AssignToKeyword(keyword.to_string()))
+        }
+        ast::Expr::Tuple(ast::ExprTuple { elts, .. })
+        | ast::Expr::List(ast::ExprList { elts, .. }) => {
+            elts.iter().try_for_each(check_assign_target)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Returns `"stdout"` or `"stderr"` for print's `file=sys.stdout` / `file=sys.stderr`
+/// keyword (`file=None` means stdout), or `None` for any other file
+fn print_stream(value: &ast::Expr) -> Option<&str> {
+    match value {
+        ast::Expr::Constant(ast::ExprConstant {
+            value: ast::Constant::None,
+            ..
+        }) => Some("stdout"),
+        ast::Expr::Attribute(ast::ExprAttribute { value, attr, .. })
+            if matches!(attr.as_str(), "stdout" | "stderr") =>
+        {
+            match value.as_ref() {
+                ast::Expr::Name(ast::ExprName { id, .. }) if id.as_str() == "sys" => {
+                    Some(attr.as_str())
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Helper function to convert AST binary operators to IR binary operators.
+fn lower_binop(op: &ast::Operator) -> Result<BinOp, LoweringError> {
+    match op {
+        ast::Operator::Add => Ok(BinOp::Add),
         ast::Operator::Sub => Ok(BinOp::Sub),
         ast::Operator::Mult => Ok(BinOp::Mul),
         ast::Operator::Div => Ok(BinOp::Div),
+        ast::Operator::FloorDiv => Ok(BinOp::FloorDiv),
         ast::Operator::Mod => Ok(BinOp::Mod),
         ast::Operator::BitAnd => Ok(BinOp::BitAnd),
         ast::Operator::BitOr => Ok(BinOp::BitOr),
         ast::Operator::BitXor => Ok(BinOp::BitXor),
         ast::Operator::LShift => Ok(BinOp::LShift),
         ast::Operator::RShift => Ok(BinOp::RShift),
+        ast::Operator::Pow => Ok(BinOp::Pow),
         _ => Err(LoweringError::UnsupportedOperator(*op)),
     }
 }
 
+/// Helper function to convert AST comparison operators to IR comparison operators.
+fn lower_cmpop(op: &ast::CmpOp) -> CmpOp {
+    match op {
+        ast::CmpOp::Eq => CmpOp::Eq,
+        ast::CmpOp::NotEq => CmpOp::NotEq,
+        ast::CmpOp::Lt => CmpOp::Lt,
+        ast::CmpOp::Gt => CmpOp::Gt,
+        ast::CmpOp::LtE => CmpOp::LtE,
+        ast::CmpOp::GtE => CmpOp::GtE,
+        ast::CmpOp::Is => CmpOp::Is,
+        ast::CmpOp::IsNot => CmpOp::IsNot,
+        ast::CmpOp::In => CmpOp::In,
+        ast::CmpOp::NotIn => CmpOp::NotIn,
+    }
+}
+
+/// Returns true if evaluating an expression twice is indistinguishable from
+/// evaluating it once, so a chained comparison can repeat it instead of storing it
+fn is_reevaluable(expr: &IRExpr) -> bool {
+    matches!(
+        expr,
+        IRExpr::Constant(_)
+            | IRExpr::Float(_)
+            | IRExpr::Bool(_)
+            | IRExpr::NoneLiteral
+            | IRExpr::Variable(_)
+    )
+}
+
+// ============================================================================
+// Constant Propagation
+// ============================================================================
+
+/// Substitutes the literal for every use of a variable that is assigned a literal
+/// exactly once, at the top level of its scope, and never reassigned or mutated.
+///
+/// Each function body is its own scope (functions cannot see module variables) and
+/// its parameters never qualify. Only uses after the assignment are rewritten, so a
+/// use before definition still reports an undefined variable.
+fn propagate_constants(stmts: Vec<IRStmt>, params: &[String]) -> Vec<IRStmt> {
+    let mut counts = HashMap::new();
+    let mut mutated: HashSet<String> = params.iter().cloned().collect();
+    count_assignments(&stmts, &mut counts, &mut mutated);
+
+    let mut constants = HashMap::new();
+    stmts
+        .into_iter()
+        .map(|stmt| {
+            let stmt = substitute_stmt(stmt, &constants);
+            if let IRStmt::Assign { target, value } = &stmt {
+                let is_literal = matches!(
+                    value,
+                    IRExpr::Constant(_) | IRExpr::Float(_) | IRExpr::Bool(_) | IRExpr::NoneLiteral
+                );
+                if is_literal && counts.get(target) == Some(&1) && !mutated.contains(target) {
+                    constants.insert(target.clone(), value.clone());
+                }
+            }
+            stmt
+        })
+        .collect()
+}
+
+/// Counts assignments to each name in a scope, including nested blocks but not
+/// nested function bodies. Names used as an index-assignment target are `mutated`,
+/// as are globals, which any function declaring them may reassign.
+fn count_assignments(
+    stmts: &[IRStmt],
+    counts: &mut HashMap<String, usize>,
+    mutated: &mut HashSet<String>,
+) {
+    for stmt in stmts {
+        // `:=` assigns from inside an expression, so its target is never a constant
+        for expr in statement_exprs(stmt) {
+            collect_named_targets(expr, mutated);
+        }
+        match stmt {
+            IRStmt::Assign { target, .. } => *counts.entry(target.clone()).or_insert(0) += 1,
+            IRStmt::UnpackAssign { targets, .. } => {
+                for target in targets {
+                    *counts.entry(target.clone()).or_insert(0) += 1;
+                }
+            }
+            IRStmt::IndexAssign {
+                list: IRExpr::Variable(name),
+                ..
+            } => {
+                mutated.insert(name.clone());
+            }
+            // A deleted variable must still be looked up so the read fails
+            IRStmt::Global(names) | IRStmt::Delete(names) => mutated.extend(names.iter().cloned()),
+            IRStmt::FunctionDef { body, .. } => collect_globals(body, mutated),
+            IRStmt::If {
+                then_body,
+                else_body,
+                ..
+            } => {
+                count_assignments(then_body, counts, mutated);
+                count_assignments(else_body, counts, mutated);
+            }
+            IRStmt::While {
+                body, else_body, ..
+            } => {
+                count_assignments(body, counts, mutated);
+                count_assignments(else_body, counts, mutated);
+            }
+            IRStmt::For {
+                var,
+                body,
+                else_body,
+                ..
+            }
+            | IRStmt::ForEach {
+                var,
+                body,
+                else_body,
+                ..
+            } => {
+                *counts.entry(var.clone()).or_insert(0) += 1;
+                count_assignments(body, counts, mutated);
+                count_assignments(else_body, counts, mutated);
+            }
+            IRStmt::Try {
+                body,
+                handler,
+                else_body,
+                ..
+            } => {
+                count_assignments(body, counts, mutated);
+                count_assignments(handler, counts, mutated);
+                count_assignments(else_body, counts, mutated);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns the expressions a statement evaluates itself (not those of nested bodies)
+fn statement_exprs(stmt: &IRStmt) -> Vec<&IRExpr> {
+    match stmt {
+        IRStmt::Print { args, sep, end, .. } => args.iter().chain(sep).chain(end).collect(),
+        IRStmt::Assign { value, .. }
+        | IRStmt::UnpackAssign { value, .. }
+        | IRStmt::ExprStmt(value)
+        | IRStmt::Return(value)
+        | IRStmt::Exit(value) => vec![value],
+        IRStmt::IndexAssign { list, index, value } => vec![list, index, value],
+        IRStmt::If { condition, .. } | IRStmt::While { condition, .. } => vec![condition],
+        IRStmt::For {
+            start, end, step, ..
+        } => vec![start, end, step],
+        IRStmt::ForEach { iter, .. } => vec![iter],
+        IRStmt::Raise { message, .. } => message.iter().collect(),
+        IRStmt::Assert { condition, message } => {
+            std::iter::once(condition).chain(message).collect()
+        }
+        IRStmt::FunctionDef { .. }
+        | IRStmt::Try { .. }
+        | IRStmt::Global(_)
+        | IRStmt::Delete(_)
+        | IRStmt::Break
+        | IRStmt::Continue
+        | IRStmt::Pass => Vec::new(),
+    }
+}
+
+/// Collects the targets of every `:=` inside an expression
+fn collect_named_targets(expr: &IRExpr, targets: &mut HashSet<String>) {
+    let mut visit = |expr: &IRExpr| collect_named_targets(expr, targets);
+    match expr {
+        IRExpr::NamedExpr { target, value } => {
+            targets.insert(target.clone());
+            collect_named_targets(value, targets);
+        }
+        IRExpr::BinaryOp { left, right, .. }
+        | IRExpr::Comparison { left, right, .. }
+        | IRExpr::Zip(left, right)
+        | IRExpr::IsClose(left, right) => {
+            visit(left);
+            visit(right);
+        }
+        IRExpr::Call { args, keywords, .. } => {
+            args.iter().for_each(&mut visit);
+            keywords.iter().for_each(|(_, value)| visit(value));
+        }
+        IRExpr::Len(e)
+        | IRExpr::Reversed(e)
+        | IRExpr::Sum(e)
+        | IRExpr::Abs(e)
+        | IRExpr::Int(e)
+        | IRExpr::TypeName(e)
+        | IRExpr::Sorted { list: e, .. }
+        | IRExpr::IntToBase { value: e, .. } => visit(e),
+        IRExpr::Map { list, .. } | IRExpr::Filter { list, .. } => visit(list),
+        IRExpr::UnaryOp { operand, .. } => visit(operand),
+        IRExpr::IfExp {
+            condition,
+            then_value,
+            else_value,
+        } => {
+            visit(condition);
+            visit(then_value);
+            visit(else_value);
+        }
+        IRExpr::Tuple(elements)
+        | IRExpr::List(elements)
+        | IRExpr::Set(elements)
+        | IRExpr::MinMax { args: elements, .. }
+        | IRExpr::BoolOp {
+            values: elements, ..
+        } => elements.iter().for_each(visit),
+        IRExpr::Dict(entries) => entries.iter().for_each(|(key, value)| {
+            visit(key);
+            visit(value);
+        }),
+        IRExpr::ListComp {
+            element,
+            source,
+            conditions,
+            ..
+        } => {
+            match source.as_ref() {
+                ComprehensionSource::Range { start, end } => {
+                    visit(start);
+                    visit(end);
+                }
+                ComprehensionSource::Iterable(iter) => visit(iter),
+            }
+            conditions.iter().for_each(&mut visit);
+            visit(element);
+        }
+        IRExpr::Index { list, index } => {
+            visit(list);
+            visit(index);
+        }
+        IRExpr::Open { path, mode } => {
+            visit(path);
+            visit(mode);
+        }
+        IRExpr::MethodCall { object, args, .. } => {
+            visit(object);
+            args.iter().for_each(visit);
+        }
+        IRExpr::FString(parts) => parts.iter().for_each(|part| {
+            if let FStringPart::Value { value, .. } = part {
+                visit(value);
+            }
+        }),
+        IRExpr::Constant(_)
+        | IRExpr::Float(_)
+        | IRExpr::Bool(_)
+        | IRExpr::Variable(_)
+        | IRExpr::Input
+        | IRExpr::StringLiteral(_)
+        | IRExpr::NoneLiteral => {}
+    }
+}
+
+/// Collects the names declared `global` anywhere in a function body
+fn collect_globals(stmts: &[IRStmt], globals: &mut HashSet<String>) {
+    for stmt in stmts {
+        match stmt {
+            IRStmt::Global(names) => globals.extend(names.iter().cloned()),
+            IRStmt::If {
+                then_body: body,
+                else_body,
+                ..
+            }
+            | IRStmt::While {
+                body, else_body, ..
+            }
+            | IRStmt::For {
+                body, else_body, ..
+            }
+            | IRStmt::ForEach {
+                body, else_body, ..
+            } => {
+                collect_globals(body, globals);
+                collect_globals(else_body, globals);
+            }
+            IRStmt::Try {
+                body,
+                handler,
+                else_body,
+                ..
+            } => {
+                collect_globals(body, globals);
+                collect_globals(handler, globals);
+                collect_globals(else_body, globals);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Rewrites the expressions of a statement (and its nested blocks) using `constants`.
+/// A function definition starts a new scope with its own propagation.
+fn substitute_stmt(stmt: IRStmt, constants: &HashMap<String, IRExpr>) -> IRStmt {
+    let block = |stmts: Vec<IRStmt>| -> Vec<IRStmt> {
+        stmts
+            .into_iter()
+            .map(|stmt| substitute_stmt(stmt, constants))
+            .collect()
+    };
+    let expr = |expr: IRExpr| substitute_expr(expr, constants);
+    match stmt {
+        IRStmt::Print {
+            args,
+            sep,
+            end,
+            stderr,
+        } => IRStmt::Print {
+            args: args.into_iter().map(expr).collect(),
+            sep: sep.map(expr),
+            end: end.map(expr),
+            stderr,
+        },
+        IRStmt::Assign { target, value } => IRStmt::Assign {
+            target,
+            value: expr(value),
+        },
+        IRStmt::IndexAssign { list, index, value } => IRStmt::IndexAssign {
+            list: expr(list),
+            index: expr(index),
+            value: expr(value),
+        },
+        IRStmt::UnpackAssign { targets, value } => IRStmt::UnpackAssign {
+            targets,
+            value: expr(value),
+        },
+        IRStmt::ExprStmt(e) => IRStmt::ExprStmt(expr(e)),
+        IRStmt::FunctionDef {
+            name,
+            params,
+            defaults,
+            kwonly_params,
+            kwonly_defaults,
+            kwarg,
+            body,
+        } => {
+            let all_params: Vec<String> = params
+                .iter()
+                .chain(&kwonly_params)
+                .chain(&kwarg)
+                .cloned()
+                .collect();
+            let body = propagate_constants(body, &all_params);
+            IRStmt::FunctionDef {
+                name,
+                params,
+                defaults,
+                kwonly_params,
+                kwonly_defaults,
+                kwarg,
+                body,
+            }
+        }
+        IRStmt::Return(e) => IRStmt::Return(expr(e)),
+        IRStmt::Exit(e) => IRStmt::Exit(expr(e)),
+        IRStmt::If {
+            condition,
+            then_body,
+            else_body,
+        } => IRStmt::If {
+            condition: expr(condition),
+            then_body: block(then_body),
+            else_body: block(else_body),
+        },
+        IRStmt::While {
+            condition,
+            body,
+            else_body,
+        } => IRStmt::While {
+            condition: expr(condition),
+            body: block(body),
+            else_body: block(else_body),
+        },
+        IRStmt::For {
+            var,
+            start,
+            end,
+            step,
+            body,
+            else_body,
+        } => IRStmt::For {
+            var,
+            start: expr(start),
+            end: expr(end),
+            step: expr(step),
+            body: block(body),
+            else_body: block(else_body),
+        },
+        IRStmt::ForEach {
+            var,
+            iter,
+            body,
+            else_body,
+        } => IRStmt::ForEach {
+            var,
+            iter: expr(iter),
+            body: block(body),
+            else_body: block(else_body),
+        },
+        IRStmt::Try {
+            body,
+            exceptions,
+            handler,
+            else_body,
+        } => IRStmt::Try {
+            body: block(body),
+            exceptions,
+            handler: block(handler),
+            else_body: block(else_body),
+        },
+        IRStmt::Global(names) => IRStmt::Global(names),
+        IRStmt::Delete(names) => IRStmt::Delete(names),
+        IRStmt::Raise { exception, message } => IRStmt::Raise {
+            exception,
+            message: message.map(expr),
+        },
+        IRStmt::Assert { condition, message } => IRStmt::Assert {
+            condition: expr(condition),
+            message: message.map(expr),
+        },
+        IRStmt::Break => IRStmt::Break,
+        IRStmt::Continue => IRStmt::Continue,
+        IRStmt::Pass => IRStmt::Pass,
+    }
+}
+
+/// Computes `len()` of a string, list or tuple literal at compile time, so no length
+/// is read at runtime. Lists and tuples only fold when their elements are literals,
+/// since folding skips evaluating them.
+fn fold_len(arg: IRExpr) -> IRExpr {
+    fn is_literal(expr: &IRExpr) -> bool {
+        match expr {
+            IRExpr::Constant(_)
+            | IRExpr::Float(_)
+            | IRExpr::Bool(_)
+            | IRExpr::StringLiteral(_)
+            | IRExpr::NoneLiteral => true,
+            IRExpr::List(elements) | IRExpr::Tuple(elements) => elements.iter().all(is_literal),
+            _ => false,
+        }
+    }
+    let len = match &arg {
+        IRExpr::StringLiteral(s) => s.chars().count(),
+        IRExpr::List(elements) | IRExpr::Tuple(elements) if elements.iter().all(is_literal) => {
+            elements.len()
+        }
+        _ => return IRExpr::Len(Box::new(arg)),
+    };
+    IRExpr::Constant(len as i64)
+}
+
+/// Replaces variables bound in `constants` with their literal value.
+fn substitute_expr(expr: IRExpr, constants: &HashMap<String, IRExpr>) -> IRExpr {
+    let sub = |e: Box<IRExpr>| Box::new(substitute_expr(*e, constants));
+    let all = |exprs: Vec<IRExpr>| -> Vec<IRExpr> {
+        exprs
+            .into_iter()
+            .map(|e| substitute_expr(e, constants))
+            .collect()
+    };
+    match expr {
+        IRExpr::Variable(name) => match constants.get(&name) {
+            Some(value) => value.clone(),
+            None => IRExpr::Variable(name),
+        },
+        IRExpr::BinaryOp { op, left, right } => IRExpr::BinaryOp {
+            op,
+            left: sub(left),
+            right: sub(right),
+        },
+        IRExpr::Call {
+            func,
+            args,
+            keywords,
+        } => IRExpr::Call {
+            func,
+            args: all(args),
+            keywords: keywords
+                .into_iter()
+                .map(|(name, value)| (name, substitute_expr(value, constants)))
+                .collect(),
+        },
+        IRExpr::Len(e) => fold_len(substitute_expr(*e, constants)),
+        IRExpr::Comparison { op, left, right } => IRExpr::Comparison {
+            op,
+            left: sub(left),
+            right: sub(right),
+        },
+        IRExpr::Tuple(elements) => IRExpr::Tuple(all(elements)),
+        IRExpr::Set(elements) => IRExpr::Set(all(elements)),
+        IRExpr::UnaryOp { op, operand } => IRExpr::UnaryOp {
+            op,
+            operand: sub(operand),
+        },
+        IRExpr::List(elements) => IRExpr::List(all(elements)),
+        IRExpr::ListComp {
+            element,
+            var,
+            source,
+            conditions,
+        } => {
+            let source = match *source {
+                ComprehensionSource::Range { start, end } => ComprehensionSource::Range {
+                    start: substitute_expr(start, constants),
+                    end: substitute_expr(end, constants),
+                },
+                ComprehensionSource::Iterable(iter) => {
+                    ComprehensionSource::Iterable(substitute_expr(iter, constants))
+                }
+            };
+            // The comprehension variable shadows any constant of the same name
+            let mut inner = constants.clone();
+            inner.remove(&var);
+            IRExpr::ListComp {
+                element: Box::new(substitute_expr(*element, &inner)),
+                conditions: conditions
+                    .into_iter()
+                    .map(|condition| substitute_expr(condition, &inner))
+                    .collect(),
+                var,
+                source: Box::new(source),
+            }
+        }
+        IRExpr::NamedExpr { target, value } => IRExpr::NamedExpr {
+            target,
+            value: sub(value),
+        },
+        IRExpr::BoolOp { op, values } => IRExpr::BoolOp {
+            op,
+            values: all(values),
+        },
+        IRExpr::IfExp {
+            condition,
+            then_value,
+            else_value,
+        } => IRExpr::IfExp {
+            condition: sub(condition),
+            then_value: sub(then_value),
+            else_value: sub(else_value),
+        },
+        IRExpr::Dict(entries) => IRExpr::Dict(
+            entries
+                .into_iter()
+                .map(|(key, value)| {
+                    (
+                        substitute_expr(key, constants),
+                        substitute_expr(value, constants),
+                    )
+                })
+                .collect(),
+        ),
+        IRExpr::Index { list, index } => IRExpr::Index {
+            list: sub(list),
+            index: sub(index),
+        },
+        IRExpr::Reversed(e) => IRExpr::Reversed(sub(e)),
+        IRExpr::Sum(e) => IRExpr::Sum(sub(e)),
+        IRExpr::Abs(e) => IRExpr::Abs(sub(e)),
+        IRExpr::Int(e) => IRExpr::Int(sub(e)),
+        IRExpr::MinMax { is_max, args } => IRExpr::MinMax {
+            is_max,
+            args: all(args),
+        },
+        IRExpr::TypeName(e) => IRExpr::TypeName(sub(e)),
+        IRExpr::IntToBase { base, value } => IRExpr::IntToBase {
+            base,
+            value: sub(value),
+        },
+        IRExpr::Sorted { list, key } => IRExpr::Sorted {
+            list: sub(list),
+            key,
+        },
+        IRExpr::Zip(left, right) => IRExpr::Zip(sub(left), sub(right)),
+        IRExpr::IsClose(left, right) => IRExpr::IsClose(sub(left), sub(right)),
+        IRExpr::FString(parts) => IRExpr::FString(
+            parts
+                .into_iter()
+                .map(|part| match part {
+                    FStringPart::Value { value, spec } => FStringPart::Value {
+                        value: substitute_expr(value, constants),
+                        spec,
+                    },
+                    literal => literal,
+                })
+                .collect(),
+        ),
+        IRExpr::Map { func, list } => IRExpr::Map {
+            func,
+            list: sub(list),
+        },
+        IRExpr::Filter { func, list } => IRExpr::Filter {
+            func,
+            list: sub(list),
+        },
+        IRExpr::Open { path, mode } => IRExpr::Open {
+            path: sub(path),
+            mode: sub(mode),
+        },
+        IRExpr::MethodCall {
+            object,
+            method,
+            args,
+        } => IRExpr::MethodCall {
+            object: sub(object),
+            method,
+            args: all(args),
+        },
+        IRExpr::Constant(_)
+        | IRExpr::Float(_)
+        | IRExpr::Bool(_)
+        | IRExpr::Input
+        | IRExpr::StringLiteral(_)
+        | IRExpr::NoneLiteral => expr,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use rustpython_parser::{ast, Parse};
 
+    #[test]
+    fn test_infinite_loop_warning() {
+        let source = "while True:\n    print(1)\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let (_, warnings) = lower_program_with_warnings(&stmts).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(warnings[0], LoweringWarning::InfiniteLoop { .. }));
+        assert_eq!(usize::from(warnings[0].range().start()), 0);
+
+        // A break in a nested loop does not leave the outer loop
+        let source = "while True:\n    for i in range(3):\n        break\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let (_, warnings) = lower_program_with_warnings(&stmts).unwrap();
+        assert_eq!(warnings.len(), 1);
+
+        let source = "while True:\n    if x:\n        break\nwhile x:\n    print(1)\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let (_, warnings) = lower_program_with_warnings(&stmts).unwrap();
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn test_loop_control_outside_loop_is_rejected() {
+        for (source, keyword) in [
+            ("break\n", "break"),
+            ("if x:\n    continue\n", "continue"),
+            ("for i in range(3):\n    pass\nelse:\n    break\n", "break"),
+            ("while x:\n    def f():\n        continue\n", "continue"),
+        ] {
+            let stmts = ast::Suite::parse(source, "<test>").unwrap();
+            assert_eq!(
+                lower_program(&stmts),
+                Err(LoweringError::LoopControlOutsideLoop(keyword))
+            );
+        }
+
+        let source = "while x:\n    try:\n        break\n    except:\n        continue\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        assert!(lower_program(&stmts).is_ok());
+    }
+
+    #[test]
+    fn test_int_call_lowering() {
+        let source = "print(int(x), int())\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        let IRStmt::Print { args, .. } = &ir[0] else {
+            panic!("expected a print, got {:?}", ir[0]);
+        };
+        assert_eq!(
+            args,
+            &vec![
+                IRExpr::Int(Box::new(IRExpr::Variable("x".to_string()))),
+                IRExpr::Constant(0),
+            ]
+        );
+
+        // A base argument is not supported
+        let stmts = ast::Suite::parse("print(int(\"ff\", 16))\n", "<test>").unwrap();
+        assert!(matches!(
+            lower_program(&stmts),
+            Err(LoweringError::UnsupportedExpression(_))
+        ));
+    }
+
+    #[test]
+    fn test_negated_int_literal_is_folded() {
+        let source = "x = -140737488355328\ny = -x\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        assert_eq!(
+            ir[0],
+            IRStmt::Assign {
+                target: "x".to_string(),
+                value: IRExpr::Constant(-140_737_488_355_328),
+            }
+        );
+        assert!(matches!(
+            &ir[1],
+            IRStmt::Assign {
+                value: IRExpr::UnaryOp {
+                    op: UnaryOp::USub,
+                    ..
+                },
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_identity_comparison() {
+        let source = "x = input()\nprint(x is not None)\nprint(x is 1)\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let (ir, warnings) = lower_program_with_warnings(&stmts).unwrap();
+        assert_eq!(
+            ir[1],
+            IRStmt::print(vec![IRExpr::Comparison {
+                op: CmpOp::IsNot,
+                left: Box::new(IRExpr::Variable("x".to_string())),
+                right: Box::new(IRExpr::NoneLiteral),
+            }])
+        );
+        // Only the comparison without a None operand is flagged
+        assert_eq!(warnings.len(), 1);
+        assert!(matches!(
+            warnings[0],
+            LoweringWarning::IdentityComparison { .. }
+        ));
+    }
+
+    #[test]
+    fn test_chained_comparison_evaluates_middle_operand_once() {
+        let source = "print(1 < f() in [2, 3])\nprint(0 <= x < 10)\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        let call = IRExpr::Call {
+            func: "f".to_string(),
+            args: vec![],
+            keywords: vec![],
+        };
+        assert_eq!(
+            ir[0],
+            IRStmt::print(vec![IRExpr::BoolOp {
+                op: BoolOp::And,
+                values: vec![
+                    IRExpr::Comparison {
+                        op: CmpOp::Lt,
+                        left: Box::new(IRExpr::Constant(1)),
+                        right: Box::new(IRExpr::NamedExpr {
+                            target: "$chain6_0".to_string(),
+                            value: Box::new(call),
+                        }),
+                    },
+                    IRExpr::Comparison {
+                        op: CmpOp::In,
+                        left: Box::new(IRExpr::Variable("$chain6_0".to_string())),
+                        right: Box::new(IRExpr::List(vec![
+                            IRExpr::Constant(2),
+                            IRExpr::Constant(3),
+                        ])),
+                    },
+                ],
+            }])
+        );
+        // A variable in the middle is simply read twice
+        let IRStmt::Print { args, .. } = &ir[1] else {
+            panic!("expected a print");
+        };
+        let IRExpr::BoolOp { values, .. } = &args[0] else {
+            panic!("expected a chain");
+        };
+        assert!(matches!(&values[1], IRExpr::Comparison { left, .. }
+            if **left == IRExpr::Variable("x".to_string())));
+    }
+
+    #[test]
+    fn test_indexing_a_method_call_result() {
+        let source = "print(\"a,b,c\".split(\",\")[1])\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        assert_eq!(
+            ir,
+            vec![IRStmt::print(vec![IRExpr::Index {
+                list: Box::new(IRExpr::MethodCall {
+                    object: Box::new(IRExpr::StringLiteral("a,b,c".to_string())),
+                    method: "split".to_string(),
+                    args: vec![IRExpr::StringLiteral(",".to_string())],
+                }),
+                index: Box::new(IRExpr::Constant(1)),
+            }])]
+        );
+    }
+
+    #[test]
+    fn test_assignment_to_keyword_is_rejected() {
+        for (source, keyword) in [
+            ("True = 1\n", "True"),
+            ("a, None = 1, 2\n", "None"),
+            ("False += 1\n", "False"),
+            ("for None in range(3):\n    pass\n", "None"),
+        ] {
+            let stmts = ast::Suite::parse(source, "<test>").unwrap();
+            let error = lower_program(&stmts).unwrap_err();
+            assert_eq!(error, LoweringError::AssignToKeyword(keyword.to_string()));
+            assert_eq!(error.to_string(), format!("cannot assign to {}", keyword));
+        }
+    }
+
+    #[test]
+    fn test_range_argument_count_is_checked() {
+        for (source, count) in [
+            ("for i in range():\n    pass\n", 0),
+            ("for i in range(1, 2, 3, 4):\n    pass\n", 4),
+            ("x = [i for i in range()]\n", 0),
+        ] {
+            let stmts = ast::Suite::parse(source, "<test>").unwrap();
+            let error = lower_program(&stmts).unwrap_err();
+            assert_eq!(error, LoweringError::RangeArgumentCount(count));
+            assert_eq!(
+                error.to_string(),
+                format!("range expected 1 to 3 arguments, got {}", count)
+            );
+        }
+    }
+
+    #[test]
+    fn test_decorator_is_rejected() {
+        let source = "@functools.lru_cache(maxsize=None)\ndef f(n):\n    return n\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let error = lower_program(&stmts).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "decorator @functools.lru_cache(...) is not supported (decorators are not implemented)"
+        );
+
+        let source = "@staticmethod\ndef f():\n    return 1\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        assert_eq!(
+            lower_program(&stmts).unwrap_err(),
+            LoweringError::UnsupportedDecorator("staticmethod".to_string())
+        );
+    }
+
+    #[test]
+    fn test_self_assignment_warning() {
+        let source = "x = 1\nx = x\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let (ir, warnings) = lower_program_with_warnings(&stmts).unwrap();
+        assert_eq!(ir.len(), 2);
+        assert_eq!(
+            warnings,
+            vec![LoweringWarning::SelfAssignment {
+                name: "x".to_string(),
+                range: TextRange::new(6.into(), 11.into()),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_tuple_unpacking() {
+        let source = "q, r = 1, 2";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        assert_eq!(
+            ir,
+            vec![IRStmt::UnpackAssign {
+                targets: vec!["q".to_string(), "r".to_string()],
+                value: IRExpr::Tuple(vec![IRExpr::Constant(1), IRExpr::Constant(2)]),
+            }]
+        );
+
+        // Only plain names can be unpacked into
+        let stmts = ast::Suite::parse("a[0], b = 1, 2", "<test>").unwrap();
+        assert!(lower_program(&stmts).is_err());
+    }
+
+    #[test]
+    fn test_delete_statement() {
+        let stmts = ast::Suite::parse("x = 1\ndel x", "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        // The deleted variable is not constant-propagated away
+        assert_eq!(
+            ir,
+            vec![
+                IRStmt::Assign {
+                    target: "x".to_string(),
+                    value: IRExpr::Constant(1),
+                },
+                IRStmt::Delete(vec!["x".to_string()]),
+            ]
+        );
+
+        // Only plain names can be deleted
+        let stmts = ast::Suite::parse("del xs[0]", "<test>").unwrap();
+        assert!(lower_program(&stmts).is_err());
+    }
+
+    #[test]
+    fn test_print_sep_and_end_keywords() {
+        let stmts = ast::Suite::parse("print(1, 2, sep=\"-\", end=None)", "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        assert_eq!(
+            ir,
+            vec![IRStmt::Print {
+                args: vec![IRExpr::Constant(1), IRExpr::Constant(2)],
+                sep: Some(IRExpr::StringLiteral("-".to_string())),
+                end: None,
+                stderr: false,
+            }]
+        );
+
+        // A computed separator is kept as an expression
+        let stmts = ast::Suite::parse("def f(s):\n    print(1, sep=s)\n", "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        let IRStmt::FunctionDef { body, .. } = &ir[0] else {
+            panic!("expected a function, got {:?}", ir[0]);
+        };
+        assert!(matches!(
+            &body[0],
+            IRStmt::Print { sep: Some(IRExpr::Variable(name)), .. } if name == "s"
+        ));
+
+        // Other keywords are not supported yet
+        let stmts = ast::Suite::parse("print(1, flush=True)", "<test>").unwrap();
+        assert!(lower_program(&stmts).is_err());
+    }
+
+    #[test]
+    fn test_fstring_format_specs() {
+        let stmts = ast::Suite::parse("print(f\"n={n:05d} {x:<8.2f} {s}\")", "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        let field = |name: &str, spec| FStringPart::Value {
+            value: IRExpr::Variable(name.to_string()),
+            spec,
+        };
+        assert_eq!(
+            ir,
+            vec![IRStmt::print(vec![IRExpr::FString(vec![
+                FStringPart::Literal("n=".to_string()),
+                field(
+                    "n",
+                    Some(FormatSpec {
+                        zero_pad: true,
+                        width: Some(5),
+                        kind: Some('d'),
+                        ..FormatSpec::default()
+                    })
+                ),
+                FStringPart::Literal(" ".to_string()),
+                field(
+                    "x",
+                    Some(FormatSpec {
+                        align: Some('<'),
+                        width: Some(8),
+                        precision: Some(2),
+                        kind: Some('f'),
+                        ..FormatSpec::default()
+                    })
+                ),
+                FStringPart::Literal(" ".to_string()),
+                field("s", None),
+            ])])]
+        );
+
+        // Without replacement fields an f-string is a plain literal
+        let stmts = ast::Suite::parse("print(f\"plain\")", "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        assert_eq!(ir, vec![IRStmt::print(vec![IRExpr::string("plain")])]);
+
+        // Centering, nested fields, `!r` and precision on ints are not supported
+        for source in [
+            "print(f\"{n:^5}\")",
+            "print(f\"{n:{w}}\")",
+            "print(f\"{n!r}\")",
+            "print(f\"{n:.2d}\")",
+        ] {
+            let stmts = ast::Suite::parse(source, "<test>").unwrap();
+            assert!(lower_program(&stmts).is_err(), "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_constant_propagation() {
+        let source = "x = 5\nprint(x + 1)\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        assert_eq!(
+            ir[1],
+            IRStmt::print(vec![IRExpr::BinaryOp {
+                op: BinOp::Add,
+                left: Box::new(IRExpr::Constant(5)),
+                right: Box::new(IRExpr::Constant(1)),
+            }])
+        );
+    }
+
+    #[test]
+    fn test_semicolon_separated_statements() {
+        let source = "a = 1; a = a + 1; print(a)\nif a: b = a; print(b)\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        assert_eq!(ir.len(), 4);
+        assert_eq!(
+            ir[0],
+            IRStmt::Assign {
+                target: "a".to_string(),
+                value: IRExpr::Constant(1),
+            }
+        );
+        assert!(matches!(&ir[1], IRStmt::Assign { target, .. } if target == "a"));
+        assert_eq!(
+            ir[2],
+            IRStmt::print(vec![IRExpr::Variable("a".to_string())])
+        );
+
+        // Semicolons after a compound statement's colon all belong to its body
+        let IRStmt::If { then_body, .. } = &ir[3] else {
+            panic!("Expected If statement");
+        };
+        assert_eq!(then_body.len(), 2);
+    }
+
+    #[test]
+    fn test_constant_propagation_skips_walrus_targets() {
+        let source = "n = 0\nwhile (n := n + 1) < 3:\n    print(n)\n";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        let IRStmt::While { condition, .. } = &ir[1] else {
+            panic!("Expected While statement");
+        };
+        let IRExpr::Comparison { left, .. } = condition else {
+            panic!("Expected comparison");
+        };
+        assert_eq!(
+            **left,
+            IRExpr::NamedExpr {
+                target: "n".to_string(),
+                value: Box::new(IRExpr::BinaryOp {
+                    op: BinOp::Add,
+                    left: Box::new(IRExpr::Variable("n".to_string())),
+                    right: Box::new(IRExpr::Constant(1)),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_constant_propagation_skips_unsafe_variables() {
+        let source = r#"
+print(a)
+a = 1
+b = 2
+b += 1
+if a:
+    c = 3
+print(b, c)
+for i in range(3):
+    d = 4
+    print(d)
+def f(n):
+    e = 6
+    return n + e
+"#;
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        let var = |name: &str| IRExpr::Variable(name.to_string());
+
+        // Used before its assignment
+        assert_eq!(ir[0], IRStmt::print(vec![var("a")]));
+        // Reassigned, and assigned only conditionally
+        assert_eq!(ir[5], IRStmt::print(vec![var("b"), var("c")]));
+        // Assigned inside a loop body
+        let IRStmt::For { body, .. } = &ir[6] else {
+            panic!("Expected For statement");
+        };
+        assert_eq!(body[1], IRStmt::print(vec![var("d")]));
+        // Function bodies propagate on their own; parameters are never constant
+        let IRStmt::FunctionDef { body, .. } = &ir[7] else {
+            panic!("Expected FunctionDef statement");
+        };
+        assert_eq!(
+            body[1],
+            IRStmt::Return(IRExpr::BinaryOp {
+                op: BinOp::Add,
+                left: Box::new(var("n")),
+                right: Box::new(IRExpr::Constant(6)),
+            })
+        );
+    }
+
     #[test]
     fn test_bool_literal() {
         let source = "x = True\ny = False";
@@ -354,6 +2710,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_scientific_float_literals() {
+        let source = "print(1e3, 2.5e-2, 1_000.5)";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+
+        assert_eq!(
+            ir,
+            vec![IRStmt::print(vec![
+                IRExpr::Float(1000.0),
+                IRExpr::Float(0.025),
+                IRExpr::Float(1000.5),
+            ])]
+        );
+    }
+
     #[test]
     fn test_elif_support() {
         let source = r#"
@@ -389,6 +2761,35 @@ else:
         }
     }
 
+    #[test]
+    fn test_elif_without_else() {
+        let source = r#"
+if x == 1:
+    print(1)
+elif x == 2:
+    print(2)
+"#;
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+        assert_eq!(ir.len(), 1);
+
+        // The elif becomes a nested If whose own else body is empty
+        let IRStmt::If { else_body, .. } = &ir[0] else {
+            panic!("Expected If statement");
+        };
+        assert_eq!(else_body.len(), 1);
+        let IRStmt::If {
+            then_body,
+            else_body,
+            ..
+        } = &else_body[0]
+        else {
+            panic!("Expected nested If for elif");
+        };
+        assert_eq!(then_body.len(), 1);
+        assert!(else_body.is_empty());
+    }
+
     #[test]
     fn test_break_continue() {
         let source = r#"
@@ -438,6 +2839,7 @@ while True:
             start,
             end,
             body,
+            ..
         } = &ir[0]
         {
             assert_eq!(var, "i");
@@ -449,6 +2851,156 @@ while True:
         }
     }
 
+    #[test]
+    fn test_for_each_string() {
+        let source = "for c in \"abc\":\n    print(c)";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+
+        assert_eq!(ir.len(), 1);
+        if let IRStmt::ForEach {
+            var, iter, body, ..
+        } = &ir[0]
+        {
+            assert_eq!(var, "c");
+            assert_eq!(iter, &IRExpr::StringLiteral("abc".to_string()));
+            assert_eq!(body.len(), 1);
+        } else {
+            panic!("Expected ForEach statement");
+        }
+    }
+
+    #[test]
+    fn test_for_each_tuple_target_unpacks() {
+        let source = "for a, b in zip(xs, ys):\n    print(a)";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+
+        let IRStmt::ForEach {
+            var, iter, body, ..
+        } = &ir[0]
+        else {
+            panic!("Expected ForEach statement");
+        };
+        assert_eq!(
+            iter,
+            &IRExpr::Zip(
+                Box::new(IRExpr::Variable("xs".to_string())),
+                Box::new(IRExpr::Variable("ys".to_string()))
+            )
+        );
+        assert_eq!(
+            body[0],
+            IRStmt::UnpackAssign {
+                targets: vec!["a".to_string(), "b".to_string()],
+                value: IRExpr::Variable(var.clone()),
+            }
+        );
+        assert_eq!(body.len(), 2);
+    }
+
+    #[test]
+    fn test_len_of_literals_folds() {
+        let source = "print(len(\"abc\"))\nprint(len([1, [2, 3], \"x\"]))\nprint(len([f()]))";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+
+        assert_eq!(ir[0], IRStmt::print(vec![IRExpr::Constant(3)]));
+        assert_eq!(ir[1], IRStmt::print(vec![IRExpr::Constant(3)]));
+        // A call inside the list still has to run
+        assert!(matches!(&ir[2], IRStmt::Print { args, .. } if matches!(args[0], IRExpr::Len(_))));
+    }
+
+    #[test]
+    fn test_import_sys_is_dropped() {
+        let source = "import sys\ndef f():\n    import sys\n    return 1\nprint(1)";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+
+        assert_eq!(ir.len(), 2);
+        let IRStmt::FunctionDef { body, .. } = &ir[0] else {
+            panic!("Expected a function definition, got {:?}", ir[0]);
+        };
+        assert_eq!(body, &vec![IRStmt::Return(IRExpr::Constant(1))]);
+        assert_eq!(ir[1], IRStmt::print(vec![IRExpr::Constant(1)]));
+    }
+
+    #[test]
+    fn test_local_binding_shadows_builtin() {
+        let source = "def f(len):\n    return len(x)\nprint(len(y))";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+
+        let IRStmt::FunctionDef { body, .. } = &ir[0] else {
+            panic!("Expected a function definition, got {:?}", ir[0]);
+        };
+        assert!(matches!(&body[0], IRStmt::Return(IRExpr::Call { func, .. }) if func == "len"));
+        // The parameter does not leak out of the function
+        assert!(matches!(&ir[1], IRStmt::Print { args, .. } if matches!(args[0], IRExpr::Len(_))));
+    }
+
+    #[test]
+    fn test_list_comprehension() {
+        let source = "rows = [[0] * n for _ in range(m) if m > 1]";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+
+        let IRStmt::Assign {
+            value:
+                IRExpr::ListComp {
+                    var,
+                    source,
+                    conditions,
+                    ..
+                },
+            ..
+        } = &ir[0]
+        else {
+            panic!("Expected a list comprehension, got {:?}", ir[0]);
+        };
+        assert_eq!(var, "_");
+        assert_eq!(
+            **source,
+            ComprehensionSource::Range {
+                start: IRExpr::Constant(0),
+                end: IRExpr::var("m"),
+            }
+        );
+        assert_eq!(conditions.len(), 1);
+
+        // Only one `for` clause is supported
+        let stmts = ast::Suite::parse("[x for x in a for y in b]", "<test>").unwrap();
+        assert!(lower_program(&stmts).is_err());
+    }
+
+    #[test]
+    fn test_try_except() {
+        let source = "try:\n    x = 1 // 0\nexcept ZeroDivisionError:\n    x = 0";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        let ir = lower_program(&stmts).unwrap();
+
+        assert_eq!(ir.len(), 1);
+        if let IRStmt::Try {
+            body,
+            exceptions,
+            handler,
+            else_body,
+        } = &ir[0]
+        {
+            assert_eq!(body.len(), 1);
+            assert_eq!(exceptions, &vec!["ZeroDivisionError".to_string()]);
+            assert_eq!(handler.len(), 1);
+            assert!(else_body.is_empty());
+        } else {
+            panic!("Expected Try statement");
+        }
+
+        // Binding the exception to a name is not supported yet
+        let source = "try:\n    x = 1\nexcept ZeroDivisionError as e:\n    x = 0";
+        let stmts = ast::Suite::parse(source, "<test>").unwrap();
+        assert!(lower_program(&stmts).is_err());
+    }
+
     #[test]
     fn test_for_range_start_end() {
         let source = "for j in range(2, 8):\n    print(j)";
@@ -461,6 +3013,7 @@ while True:
             start,
             end,
             body,
+            ..
         } = &ir[0]
         {
             assert_eq!(var, "j");
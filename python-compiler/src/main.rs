@@ -1,69 +1,306 @@
 use inkwell::context::Context;
+use inkwell::memory_buffer::MemoryBuffer;
+use std::collections::HashMap;
 use std::env;
 use std::fs;
 use std::path::Path;
 use std::process::{self, Command};
 
-mod ast;
-mod codegen;
-mod compiler;
-mod error;
-mod lowering;
-mod parser;
+use python_compiler::compiler::values::PayloadWidth;
+use python_compiler::{ast, codegen, error, lowering, parser};
+
+fn print_usage(program: &str) {
+    eprintln!("Usage: {} [options] <python_file.py>...", program);
+    eprintln!("Example: {} example.py", program);
+    eprintln!();
+    eprintln!("Multiple files are compiled into one program, run in the order given;");
+    eprintln!("the executable is named after the first file.");
+    eprintln!();
+    eprintln!("Options:");
+    eprintln!("  --unbuffered          Flush stdout after every print");
+    eprintln!("  --buffered-lists      Format each printed list in memory and print it at once");
+    eprintln!("  --string-input        Make input() return the line read as a string");
+    eprintln!("  --checked-arithmetic  Abort with OverflowError when integer +, -, *, ** overflow");
+    eprintln!("  -O, --no-asserts      Drop assert statements, like python -O");
+    eprintln!("  -O0, -O1, -O2, -O3    LLVM optimization level (default: -O2)");
+    eprintln!(
+        "  --no-optimize         Same as -O0: skip the optimization passes (IR is still verified)"
+    );
+    eprintln!("  --time-passes         Report code generation and optimization times");
+    eprintln!("  --stats               Report the number of functions, blocks and instructions");
+    eprintln!("  --recursion-limit N   Raise RecursionError past N nested function calls");
+    eprintln!("  --int-width 32|48     Wrap integers to 32 or 48 bits (default: 48)");
+    eprintln!("  --dump-tokens         Print the lexer token stream and exit");
+    eprintln!("  --parse-only          Print the parser's AST (before lowering) and exit");
+    eprintln!("  --ast-only            Only parse and lower the program, reporting any errors");
+    eprintln!("  --warnings-as-errors  Abort compilation if any warning is reported");
+    eprintln!("  --emit=exe|lib        Link an executable (default) or a shared library");
+    eprintln!("                        exporting the functions under their Python names");
+    eprintln!(
+        "  --entry-point NAME    Name the function running the top-level code (default: main)"
+    );
+    eprintln!("  --no-entry-point      Generate no such function (with --emit=lib)");
+}
+
+/// Returns whether `name` can name the entry point symbol: a letter or `_` followed
+/// by letters, digits, `_`, `.` or `$`, as C toolchains accept
+fn is_symbol_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|first| first.is_ascii_alphabetic() || first == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '$'))
+}
 
 fn main() {
     let args: Vec<String> = env::args().collect();
 
-    if args.len() != 2 {
-        eprintln!("Usage: {} <python_file.py>", args[0]);
-        eprintln!("Example: {} example.py", args[0]);
+    let mut unbuffered = false;
+    let mut buffered_lists = false;
+    let mut string_input = false;
+    let mut asserts = true;
+    let mut checked_arithmetic = false;
+    let mut optimization_level = 2;
+    let mut time_passes = false;
+    let mut stats = false;
+    let mut recursion_limit: Option<u64> = None;
+    let mut payload_width = PayloadWidth::default();
+    let mut dump_tokens = false;
+    let mut parse_only = false;
+    let mut ast_only = false;
+    let mut warnings_as_errors = false;
+    let mut emit_lib = false;
+    let mut entry_point = Some("main".to_string());
+    let mut input_files: Vec<String> = Vec::new();
+    let mut arg_iter = args[1..].iter();
+    while let Some(arg) = arg_iter.next() {
+        match arg.as_str() {
+            "--unbuffered" => unbuffered = true,
+            "--buffered-lists" => buffered_lists = true,
+            "--string-input" => string_input = true,
+            "--checked-arithmetic" => checked_arithmetic = true,
+            "-O" | "--no-asserts" => asserts = false,
+            "-O0" | "--no-optimize" => optimization_level = 0,
+            "-O1" => optimization_level = 1,
+            "-O2" => optimization_level = 2,
+            "-O3" => optimization_level = 3,
+            "--time-passes" => time_passes = true,
+            "--stats" => stats = true,
+            "--dump-tokens" => dump_tokens = true,
+            "--parse-only" => parse_only = true,
+            "--ast-only" => ast_only = true,
+            "--warnings-as-errors" => warnings_as_errors = true,
+            "--emit=exe" => emit_lib = false,
+            "--emit=lib" => emit_lib = true,
+            "--no-entry-point" => entry_point = None,
+            "--entry-point" => match arg_iter.next() {
+                Some(name) if is_symbol_name(name) => entry_point = Some(name.clone()),
+                _ => {
+                    eprintln!("--entry-point expects a function name, such as rusthon_main");
+                    print_usage(&args[0]);
+                    process::exit(1);
+                }
+            },
+            "--recursion-limit" => match arg_iter.next().map(|n| n.parse::<u64>()) {
+                Some(Ok(limit)) => recursion_limit = Some(limit),
+                _ => {
+                    eprintln!("--recursion-limit expects a non-negative integer");
+                    print_usage(&args[0]);
+                    process::exit(1);
+                }
+            },
+            "--int-width" => match arg_iter.next().map(String::as_str) {
+                Some("32") => payload_width = PayloadWidth::Bits32,
+                Some("48") => payload_width = PayloadWidth::Bits48,
+                _ => {
+                    eprintln!("--int-width expects 32 or 48");
+                    print_usage(&args[0]);
+                    process::exit(1);
+                }
+            },
+            flag if flag.starts_with("--") => {
+                eprintln!("Unknown option: {}", flag);
+                print_usage(&args[0]);
+                process::exit(1);
+            }
+            _ => input_files.push(arg.clone()),
+        }
+    }
+
+    if input_files.is_empty() {
+        print_usage(&args[0]);
         process::exit(1);
     }
 
-    let filename = &args[1];
+    if entry_point.is_none() && !emit_lib {
+        eprintln!("--no-entry-point requires --emit=lib: an executable needs a main function");
+        process::exit(1);
+    }
 
-    let source = match fs::read_to_string(filename) {
-        Ok(content) => content,
-        Err(e) => {
-            eprintln!("Error reading file '{}': {}", filename, e);
-            process::exit(1);
+    let mut sources = Vec::new();
+    for filename in &input_files {
+        match fs::read_to_string(filename) {
+            Ok(content) => sources.push(content),
+            Err(e) => {
+                eprintln!("Error reading file '{}': {}", filename, e);
+                process::exit(1);
+            }
         }
-    };
+    }
 
-    println!("Compiling: {}", filename);
+    if dump_tokens {
+        for (source, filename) in sources.iter().zip(&input_files) {
+            match parser::tokenize(source) {
+                Ok(tokens) => {
+                    for (token, range) in tokens {
+                        println!("{:?} {:?}", range, token);
+                    }
+                }
+                Err(e) => {
+                    error::display_parse_error(source, filename, &e);
+                    process::exit(1);
+                }
+            }
+        }
+        return;
+    }
 
-    let ast = match parser::parse_program(&source) {
-        Ok(ast) => ast,
-        Err(e) => {
-            error::display_parse_error(&source, filename, &e);
-            process::exit(1);
+    if parse_only {
+        for (source, filename) in sources.iter().zip(&input_files) {
+            match parser::dump_ast(source) {
+                Ok(dump) => println!("{}", dump),
+                Err(e) => {
+                    error::display_parse_error(source, filename, &e);
+                    process::exit(1);
+                }
+            }
         }
-    };
+        return;
+    }
 
-    let ir = match lowering::lower_program(&ast) {
-        Ok(ir) => ir,
-        Err(e) => {
-            error::display_lowering_error(&source, filename, &e);
-            process::exit(1);
+    if ast_only {
+        println!("Checking: {}", input_files.join(", "));
+    } else {
+        println!("Compiling: {}", input_files.join(", "));
+    }
+
+    // Each file is parsed and lowered on its own so errors point into the right
+    // source; the resulting statements are concatenated into a single program
+    let mut ir = Vec::new();
+    // The index in `input_files` of each statement's file, to report codegen errors
+    let mut statement_files = Vec::new();
+    let mut function_files: HashMap<String, &String> = HashMap::new();
+    let mut warning_count = 0;
+    for (file_index, (source, filename)) in sources.iter().zip(&input_files).enumerate() {
+        let ast = match parser::parse_program(source) {
+            Ok(ast) => ast,
+            Err(e) => {
+                error::display_parse_error(source, filename, &e);
+                process::exit(1);
+            }
+        };
+
+        let file_ir = match lowering::lower_program_with_warnings(&ast) {
+            Ok((file_ir, warnings)) => {
+                for warning in &warnings {
+                    error::display_lowering_warning(source, filename, warning);
+                }
+                warning_count += warnings.len();
+                file_ir
+            }
+            Err(e) => {
+                error::display_lowering_error(source, filename, &e);
+                process::exit(1);
+            }
+        };
+
+        for stmt in &file_ir {
+            if let ast::IRStmt::FunctionDef { name, .. } = stmt {
+                match function_files.get(name) {
+                    Some(&first) if first != filename => {
+                        eprintln!(
+                            "Error: function '{}' is defined in both '{}' and '{}'",
+                            name, first, filename
+                        );
+                        process::exit(1);
+                    }
+                    _ => {
+                        function_files.insert(name.clone(), filename);
+                    }
+                }
+            }
         }
+        statement_files.extend(std::iter::repeat_n(file_index, file_ir.len()));
+        ir.extend(file_ir);
+    }
+
+    // Every file's warnings are reported before giving up
+    if warnings_as_errors && warning_count > 0 {
+        eprintln!(
+            "Error: {} warning(s) treated as errors (--warnings-as-errors)",
+            warning_count
+        );
+        process::exit(1);
+    }
+
+    // Parsing and lowering succeeded: skip codegen and linking entirely
+    if ast_only {
+        return;
+    }
+
+    // Generate output filenames
+    let filename = &input_files[0];
+    let path = Path::new(filename);
+    let stem = path.file_stem().unwrap().to_str().unwrap();
+    let ll_file = format!("{}.ll", stem);
+    let output_file = if !emit_lib {
+        stem.to_string()
+    } else if cfg!(target_os = "macos") {
+        format!("lib{}.dylib", stem)
+    } else {
+        format!("lib{}.so", stem)
     };
 
     let context = Context::create();
-    let compiler = codegen::Compiler::new(&context);
+    let compiler = codegen::Compiler::new(&context)
+        .with_module_name(stem)
+        .with_unbuffered_output(unbuffered)
+        .with_buffered_list_printing(buffered_lists)
+        .with_string_input(string_input)
+        .with_asserts(asserts)
+        .with_optimization_level(optimization_level)
+        .with_checked_arithmetic(checked_arithmetic)
+        .with_time_passes(time_passes)
+        .with_recursion_limit(recursion_limit)
+        .with_payload_width(payload_width)
+        .with_entry_point(entry_point.as_deref());
 
-    let llvm_ir = match compiler.compile_program(&ir) {
+    let llvm_ir = match compiler.compile_program_locating_errors(&ir) {
         Ok(llvm_ir) => llvm_ir,
-        Err(e) => {
-            error::display_codegen_error(&source, filename, &e);
+        Err((statement, e)) => {
+            let file_index = statement.map_or(0, |index| statement_files[index]);
+            error::display_codegen_error(&sources[file_index], &input_files[file_index], &e);
             process::exit(1);
         }
     };
 
-    // Generate output filenames
-    let path = Path::new(filename);
-    let stem = path.file_stem().unwrap().to_str().unwrap();
-    let ll_file = format!("{}.ll", stem);
-    let output_file = stem.to_string();
+    if stats {
+        // Re-read the emitted IR so the counts reflect the optimized module
+        let buffer = MemoryBuffer::create_from_memory_range_copy(llvm_ir.as_bytes(), stem);
+        match context.create_module_from_ir(buffer) {
+            Ok(module) => {
+                let stats = codegen::module_stats(&module);
+                eprintln!("Module statistics:");
+                eprintln!("  Functions:    {:>8}", stats.functions);
+                eprintln!("  Basic blocks: {:>8}", stats.basic_blocks);
+                eprintln!("  Instructions: {:>8}", stats.instructions);
+            }
+            Err(e) => {
+                eprintln!("Error reading back LLVM IR for --stats: {}", e);
+                process::exit(1);
+            }
+        }
+    }
 
     // Write LLVM IR to .ll file
     if let Err(e) = fs::write(&ll_file, llvm_ir) {
@@ -72,9 +309,16 @@ fn main() {
     }
     println!("Generated LLVM IR: {}", ll_file);
 
-    // Compile LLVM IR to executable using clang
-    println!("Compiling to executable...");
-    let clang_output = Command::new("clang")
+    // Compile LLVM IR to executable using clang. A library keeps the functions'
+    // external symbols, and the entry point if any
+    let mut clang = Command::new("clang");
+    if emit_lib {
+        println!("Compiling to shared library...");
+        clang.arg("-shared").arg("-fPIC");
+    } else {
+        println!("Compiling to executable...");
+    }
+    let clang_output = clang
         .arg(&ll_file)
         .arg("-o")
         .arg(&output_file)
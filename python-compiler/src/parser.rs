@@ -1,6 +1,30 @@
-use rustpython_parser::{ast, Parse, ParseError};
+use rustpython_parser::lexer::{lex, Spanned};
+use rustpython_parser::{ast, Mode, Parse, ParseError, ParseErrorType};
 
+/// Parses a whole module. A leading `#!` shebang line is an ordinary comment to the
+/// lexer, so executable scripts need no preprocessing.
 pub fn parse_program(source: &str) -> Result<ast::Suite, ParseError> {
     let suite = ast::Suite::parse(source, "<input>")?;
     Ok(suite)
 }
+
+/// Parses a whole module and renders the parser's AST, before any lowering, in Rust's
+/// pretty-printed debug form. Shows what the parser produced for constructs lowering
+/// rejects or handles unexpectedly.
+pub fn dump_ast(source: &str) -> Result<String, ParseError> {
+    Ok(format!("{:#?}", parse_program(source)?))
+}
+
+/// Runs the lexer over a whole module and returns the token stream with source
+/// ranges. Lexical errors are reported as a `ParseError` so they display the same way.
+pub fn tokenize(source: &str) -> Result<Vec<Spanned>, ParseError> {
+    lex(source, Mode::Module)
+        .map(|token| {
+            token.map_err(|e| ParseError {
+                error: ParseErrorType::Lexical(e.error),
+                offset: e.location,
+                source_path: "<input>".to_string(),
+            })
+        })
+        .collect()
+}
@@ -250,6 +250,17 @@ mod tests {
         assert_eq!(obj.as_int(), -100);
     }
 
+    #[test]
+    fn test_integer_round_trip_at_48_bit_bounds() {
+        let min = -(1i64 << 47);
+        let max = (1i64 << 47) - 1;
+        for value in [min, min + 1, -1, 0, 1, max - 1, max] {
+            let obj = TaggedPointer::from_int(value);
+            assert!(obj.is_int());
+            assert_eq!(obj.as_int(), value);
+        }
+    }
+
     #[test]
     fn test_float_boxing() {
         let obj = TaggedPointer::from_float(123.456);
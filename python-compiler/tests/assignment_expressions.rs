@@ -0,0 +1,42 @@
+mod common;
+
+use common::run_program;
+
+#[test]
+fn test_walrus_in_while_condition_updates_each_iteration() {
+    let source = r#"
+n = 0
+while (n := n + 1) < 4:
+    print(n)
+print(n)
+"#;
+    assert_eq!(run_program(source), "1\n2\n3\n4\n");
+}
+
+#[test]
+fn test_walrus_condition_uses_value_truthiness() {
+    let source = r#"
+xs = [3, 2, 1, 0, 5]
+i = 0
+while (v := xs[i]):
+    print(v)
+    i += 1
+print(v)
+"#;
+    assert_eq!(run_program(source), "3\n2\n1\n0\n");
+}
+
+#[test]
+fn test_walrus_yields_assigned_value() {
+    let source = r#"
+print((y := 5) + y)
+
+def f():
+    if (k := 7) > 3:
+        return k
+    return 0
+
+print(f())
+"#;
+    assert_eq!(run_program(source), "10\n7\n");
+}
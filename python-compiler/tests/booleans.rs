@@ -0,0 +1,179 @@
+mod common;
+
+use common::{compile_to_ir, run_program};
+
+#[test]
+fn test_print_bool_values() {
+    let source = r#"
+print(True)
+print(False)
+print(3 < 5)
+"#;
+    assert_eq!(run_program(source), "True\nFalse\nTrue\n");
+}
+
+#[test]
+fn test_bitwise_on_bools_returns_bool() {
+    let source = r#"
+print(True ^ False)
+print(True & False)
+print(False | True)
+"#;
+    assert_eq!(run_program(source), "True\nFalse\nTrue\n");
+}
+
+#[test]
+fn test_bitwise_on_bool_and_int_returns_int() {
+    let source = r#"
+print(True & 3)
+print(2 | False)
+print(True << 1)
+"#;
+    assert_eq!(run_program(source), "1\n2\n2\n");
+}
+
+#[test]
+fn test_bitwise_on_comparisons_returns_bool() {
+    let source = r#"
+def both(a, b, c, d):
+    return (a < b) & (c < d)
+
+def masked(n):
+    return n & 1
+
+print((1 < 2) & (3 < 2))
+print((1 < 2) | (3 < 2))
+print(both(1, 2, 3, 4))
+print(both(1, 2, 4, 3))
+print(masked(True))
+"#;
+    assert_eq!(run_program(source), "False\nTrue\nTrue\nFalse\n1\n");
+}
+
+#[test]
+fn test_if_not_swaps_branches() {
+    let source = r#"
+x = 0
+if not x:
+    print(1)
+else:
+    print(2)
+y = 5
+if not y:
+    print(3)
+else:
+    print(4)
+if not not y:
+    print(5)
+if not x < y:
+    print(6)
+else:
+    print(7)
+"#;
+    assert_eq!(run_program(source), "1\n4\n5\n7\n");
+}
+
+#[test]
+fn test_and_or_yield_deciding_operand() {
+    let source = r#"
+x = 1
+y = 0
+print(x and y, x or y, y or "z", y and 5)
+print(1 and 2 and 3, 0 or 0 or 7)
+"#;
+    assert_eq!(run_program(source), "0 1 z 0\n3 7\n");
+}
+
+#[test]
+fn test_and_or_short_circuit() {
+    let source = r#"
+def calls(n):
+    print(n)
+    return n
+if calls(0) and calls(1):
+    print("no")
+if calls(2) or calls(3):
+    print("yes")
+i = 0
+while i < 5 and not i == 3:
+    i += 1
+print(i)
+print("a" if i and not calls(0) else "b")
+"#;
+    assert_eq!(run_program(source), "0\n2\nyes\n3\n0\na\n");
+}
+
+#[test]
+fn test_and_condition_branches_without_boxing() {
+    let source = r#"
+def both(a, b):
+    if a > 0 and b > 0:
+        return 1
+    return 0
+"#;
+    let llvm_ir = compile_to_ir(source);
+    let start = llvm_ir.find("define i64 @both").expect("both is defined");
+    let end = start + llvm_ir[start..].find("\n}").unwrap();
+    let body = &llvm_ir[start..end];
+
+    // Each comparison branches straight to the next operand or out of the `if`;
+    // no PyObject result of the `and` is merged and tested
+    assert!(body.contains("cond_next:"), "{}", body);
+    assert!(!body.contains("boolop_result"), "{}", body);
+
+    assert_eq!(
+        run_program(&format!(
+            "{}print(both(1, 2), both(1, -2), both(-1, 2))\n",
+            source
+        )),
+        "1 0 0\n"
+    );
+}
+
+#[test]
+fn test_not_uses_truthiness_of_none_and_containers() {
+    let source = r#"
+print(not None)
+print(not [1])
+print(not [])
+print(not "")
+print(not (1, 2))
+"#;
+    assert_eq!(run_program(source), "True\nFalse\nTrue\nTrue\nFalse\n");
+}
+
+#[test]
+fn test_empty_containers_are_falsy_in_conditions() {
+    let source = r#"
+items = []
+if items:
+    print("items")
+else:
+    print("no items")
+name = "x"
+while name:
+    print(name)
+    name = ""
+"#;
+    assert_eq!(run_program(source), "no items\nx\n");
+}
+
+#[test]
+fn test_bool_returning_function_in_conditions() {
+    let source = r#"
+def even(n):
+    return n % 2 == 0
+if even(4):
+    print("yes")
+if even(3):
+    print("no")
+else:
+    print("odd")
+print(even(10), even(7))
+x = 0
+while not even(x + 1):
+    x = x + 1
+print(x, [n for n in range(6) if even(n)])
+"#;
+    assert_eq!(run_program(source), "yes\nodd\nTrue False\n1 [0, 2, 4]\n");
+}
@@ -0,0 +1,208 @@
+mod common;
+
+use common::{run_cli, run_cli_files, run_cli_files_with_stderr};
+use std::process::Command;
+
+#[test]
+fn test_ast_only_accepts_supported_program() {
+    let source = r#"
+def double(x):
+    return x * 2
+print(double(21))
+"#;
+    let (stdout, status) = run_cli(source, &["--ast-only"]);
+    assert_eq!(stdout, "Checking: program.py\n");
+    assert_eq!(status, Some(0));
+}
+
+#[test]
+fn test_ast_only_rejects_unsupported_program() {
+    // Lambdas parse but are not supported by lowering
+    let (_, status) = run_cli("f = lambda x: x\n", &["--ast-only"]);
+    assert_eq!(status, Some(1));
+
+    // Syntax errors fail at the parse stage
+    let (_, status) = run_cli("def broken(:\n", &["--ast-only"]);
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_multiple_files_compile_into_one_program() {
+    let helpers = r#"
+def greet(name):
+    return "Hello, " + name
+"#;
+    let main = r#"
+print(greet("world"))
+"#;
+    let (stdout, status, dir) = run_cli_files(&[("helpers.py", helpers), ("main.py", main)], &[]);
+    assert_eq!(status, Some(0), "{}", stdout);
+    assert!(stdout.starts_with("Compiling: helpers.py, main.py\n"));
+
+    // The executable is named after the first file
+    let output = Command::new(dir.join("helpers"))
+        .output()
+        .expect("Failed to run program");
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "Hello, world\n");
+}
+
+#[test]
+fn test_duplicate_function_across_files_is_rejected() {
+    let source = "def f():\n    return 1\n";
+    let (_, status, _) = run_cli_files(&[("a.py", source), ("b.py", source)], &["--ast-only"]);
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_codegen_error_points_into_its_own_file() {
+    let files = [
+        ("first.py", "print(1)\n"),
+        ("second.py", "x = 1\nprint(undefined_name)\n"),
+    ];
+    let (_, stderr, status, _) = run_cli_files_with_stderr(&files, &[]);
+    assert_eq!(status, Some(1));
+    assert!(stderr.contains("second.py"), "{}", stderr);
+    assert!(!stderr.contains("first.py"), "{}", stderr);
+    assert!(stderr.contains("undefined_name"), "{}", stderr);
+}
+
+#[test]
+fn test_warnings_as_errors() {
+    // `x = x` is reported as a self-assignment warning
+    let source = "x = 1\nx = x\nprint(x)\n";
+    let (_, status) = run_cli(source, &["--ast-only"]);
+    assert_eq!(status, Some(0));
+    let (_, status) = run_cli(source, &["--ast-only", "--warnings-as-errors"]);
+    assert_eq!(status, Some(1));
+
+    // A clean program is unaffected
+    let (_, status) = run_cli("print(1)\n", &["--ast-only", "--warnings-as-errors"]);
+    assert_eq!(status, Some(0));
+}
+
+#[test]
+fn test_dash_o_drops_asserts() {
+    let source = "assert 0\nprint(\"ok\")\n";
+    for args in [&["-O"][..], &["--no-asserts"][..]] {
+        let (stdout, status, dir) = run_cli_files(&[("program.py", source)], args);
+        assert_eq!(status, Some(0), "{}", stdout);
+        let llvm_ir = std::fs::read_to_string(dir.join("program.ll")).unwrap();
+        assert!(!llvm_ir.contains("AssertionError"), "{}", llvm_ir);
+        let output = Command::new(dir.join("program"))
+            .output()
+            .expect("Failed to run program");
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "ok\n");
+    }
+}
+
+#[test]
+fn test_no_optimize_skips_passes() {
+    let source = r#"
+x = 20
+print(x + 22)
+"#;
+    for (args, optimized) in [
+        (&[][..], true),
+        (&["--no-optimize"][..], false),
+        (&["-O0"][..], false),
+    ] {
+        let (stdout, status, dir) = run_cli_files(&[("program.py", source)], args);
+        assert_eq!(status, Some(0), "{}", stdout);
+        let output = Command::new(dir.join("program"))
+            .output()
+            .expect("Failed to run program");
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n");
+
+        // Unoptimized IR keeps the variable's stack slot that mem2reg would remove
+        let llvm_ir = std::fs::read_to_string(dir.join("program.ll")).unwrap();
+        assert_eq!(
+            llvm_ir.contains("alloca i64"),
+            !optimized,
+            "{:?}: {}",
+            args,
+            llvm_ir
+        );
+    }
+}
+
+#[test]
+fn test_parse_only_prints_the_parser_ast() {
+    // Lowering rejects lambdas, but the parser's view of them is still shown
+    let (stdout, status) = run_cli("f = lambda x: x + 1\n", &["--parse-only"]);
+    assert_eq!(status, Some(0));
+    for node in ["Assign", "Lambda", "BinOp", "Add"] {
+        assert!(stdout.contains(node), "missing {}: {}", node, stdout);
+    }
+    assert!(!stdout.contains("Compiling"));
+
+    let (_, status) = run_cli("def broken(:\n", &["--parse-only"]);
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_emit_lib_exports_functions() {
+    let source = r#"
+def add(a, b):
+    return a + b
+print(add(1, 2))
+"#;
+    let (stdout, status, dir) = run_cli_files(&[("mathlib.py", source)], &["--emit=lib"]);
+    assert_eq!(status, Some(0), "{}", stdout);
+    let library = if cfg!(target_os = "macos") {
+        "libmathlib.dylib"
+    } else {
+        "libmathlib.so"
+    };
+    assert!(stdout.contains(library), "{}", stdout);
+
+    let output = Command::new("nm")
+        .args(["-g", "--defined-only"])
+        .arg(dir.join(library))
+        .output()
+        .expect("Failed to run nm");
+    let symbols = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        symbols
+            .lines()
+            .any(|line| line.ends_with(" T add") || line.ends_with(" T _add")),
+        "{}",
+        symbols
+    );
+}
+
+#[test]
+fn test_no_entry_point_requires_emit_lib() {
+    let source = "def add(a, b):\n    return a + b\n";
+    let (stdout, status, dir) = run_cli_files(&[("program.py", source)], &["--no-entry-point"]);
+    assert_eq!(status, Some(1), "{}", stdout);
+    assert!(!dir.join("program").exists());
+
+    let (stdout, status, _) = run_cli_files(
+        &[("program.py", source)],
+        &["--emit=lib", "--no-entry-point"],
+    );
+    assert_eq!(status, Some(0), "{}", stdout);
+}
+
+#[test]
+fn test_invalid_entry_point_names_are_rejected() {
+    let source = "print(1)\n";
+    for name in ["", "1main", "my main", "-main", "main()"] {
+        let (_, stderr, status, dir) =
+            run_cli_files_with_stderr(&[("program.py", source)], &["--entry-point", name]);
+        assert_eq!(status, Some(1), "{:?}", name);
+        assert!(
+            stderr.starts_with("--entry-point expects a function name"),
+            "{:?}: {}",
+            name,
+            stderr
+        );
+        assert!(!dir.join("program.ll").exists());
+    }
+
+    let (stdout, status, _) = run_cli_files(
+        &[("program.py", source)],
+        &["--emit=lib", "--entry-point", "_rusthon.main$1"],
+    );
+    assert_eq!(status, Some(0), "{}", stdout);
+}
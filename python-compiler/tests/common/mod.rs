@@ -0,0 +1,190 @@
+//! Shared helpers for execution-based tests.
+//!
+//! These compile a Python program to LLVM IR, link it with `clang` (as `main.rs` does)
+//! and run the resulting binary so tests can assert on the program's actual output.
+
+#![allow(dead_code)]
+
+use inkwell::context::Context;
+use python_compiler::*;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static PROGRAM_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Compiles Python source to optimized LLVM IR
+pub fn compile_to_ir(source: &str) -> String {
+    compile_to_ir_with(source, |c| c)
+}
+
+/// Compiles Python source to optimized LLVM IR with a configured compiler
+pub fn compile_to_ir_with<F>(source: &str, configure: F) -> String
+where
+    F: for<'ctx> FnOnce(codegen::Compiler<'ctx>) -> codegen::Compiler<'ctx>,
+{
+    let ast = parser::parse_program(source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    let context = Context::create();
+    let compiler = configure(codegen::Compiler::new(&context));
+    compiler.compile_program(&ir).unwrap()
+}
+
+/// Compiles Python source to a native executable and returns its path
+pub fn build_executable(source: &str) -> PathBuf {
+    link_executable(&compile_to_ir(source))
+}
+
+/// Links LLVM IR into a native executable and returns its path
+fn link_executable(llvm_ir: &str) -> PathBuf {
+    let id = PROGRAM_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("rusthon_test_{}_{}", std::process::id(), id));
+    std::fs::create_dir_all(&dir).unwrap();
+    let ll_file = dir.join("program.ll");
+    let exe_file = dir.join("program");
+    std::fs::write(&ll_file, llvm_ir).unwrap();
+
+    let output = Command::new("clang")
+        .arg(&ll_file)
+        .arg("-o")
+        .arg(&exe_file)
+        .arg("-lm")
+        .output()
+        .expect("Failed to run clang");
+    assert!(
+        output.status.success(),
+        "clang failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    exe_file
+}
+
+/// Compiles and runs a Python program, returning its stdout
+pub fn run_program(source: &str) -> String {
+    let exe = build_executable(source);
+    let output = Command::new(&exe).output().expect("Failed to run program");
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// Compiles and runs a Python program with the given stdin, returning its stdout
+pub fn run_program_with_input(source: &str, stdin: &str) -> String {
+    run_executable_with_input(&build_executable(source), stdin).0
+}
+
+/// Compiles and runs a Python program with the given stdin, returning its stdout,
+/// stderr and exit code
+pub fn run_program_with_input_and_status(
+    source: &str,
+    stdin: &str,
+) -> (String, String, Option<i32>) {
+    run_executable_with_input(&build_executable(source), stdin)
+}
+
+/// Compiles with a configured compiler and runs the program with the given stdin,
+/// returning its stdout
+pub fn run_program_with_input_using<F>(source: &str, stdin: &str, configure: F) -> String
+where
+    F: for<'ctx> FnOnce(codegen::Compiler<'ctx>) -> codegen::Compiler<'ctx>,
+{
+    run_executable_with_input(
+        &link_executable(&compile_to_ir_with(source, configure)),
+        stdin,
+    )
+    .0
+}
+
+/// Runs an executable with the given stdin, returning its stdout, stderr and exit
+/// code
+fn run_executable_with_input(exe: &Path, stdin: &str) -> (String, String, Option<i32>) {
+    let mut child = Command::new(exe)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed to run program");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().expect("Failed to run program");
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+        output.status.code(),
+    )
+}
+
+/// Compiles and runs a Python program, returning its stdout, stderr (where an
+/// uncaught error's message goes) and exit code
+pub fn run_program_with_status(source: &str) -> (String, String, Option<i32>) {
+    run_executable_with_status(&build_executable(source))
+}
+
+/// Compiles with a configured compiler and runs the program, returning its stdout,
+/// stderr and exit code
+pub fn run_program_with<F>(source: &str, configure: F) -> (String, String, Option<i32>)
+where
+    F: for<'ctx> FnOnce(codegen::Compiler<'ctx>) -> codegen::Compiler<'ctx>,
+{
+    run_executable_with_status(&link_executable(&compile_to_ir_with(source, configure)))
+}
+
+/// Links already compiled LLVM IR and runs it, returning its stdout, stderr and exit
+/// code
+pub fn run_ir(llvm_ir: &str) -> (String, String, Option<i32>) {
+    run_executable_with_status(&link_executable(llvm_ir))
+}
+
+/// Runs an executable, returning its stdout, stderr and exit code
+fn run_executable_with_status(exe: &Path) -> (String, String, Option<i32>) {
+    let output = Command::new(exe).output().expect("Failed to run program");
+    (
+        String::from_utf8_lossy(&output.stdout).into_owned(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+        output.status.code(),
+    )
+}
+
+/// Writes `source` to a temporary `program.py` and runs the compiler binary on it
+/// with the given flags, returning its stdout and exit status
+pub fn run_cli(source: &str, args: &[&str]) -> (String, Option<i32>) {
+    let (stdout, status, _) = run_cli_files(&[("program.py", source)], args);
+    (stdout, status)
+}
+
+/// Writes each `(name, source)` file to a fresh temporary directory and runs the
+/// compiler binary on all of them in order, returning its stdout, exit status and
+/// the directory (where any executable is produced)
+pub fn run_cli_files(files: &[(&str, &str)], args: &[&str]) -> (String, Option<i32>, PathBuf) {
+    let (stdout, _, status, dir) = run_cli_files_with_stderr(files, args);
+    (stdout, status, dir)
+}
+
+/// Like `run_cli_files`, also returning the compiler's stderr (where diagnostics go)
+pub fn run_cli_files_with_stderr(
+    files: &[(&str, &str)],
+    args: &[&str],
+) -> (String, String, Option<i32>, PathBuf) {
+    let id = PROGRAM_COUNTER.fetch_add(1, Ordering::SeqCst);
+    let dir = std::env::temp_dir().join(format!("rusthon_cli_{}_{}", std::process::id(), id));
+    std::fs::create_dir_all(&dir).unwrap();
+    for (name, source) in files {
+        std::fs::write(dir.join(name), source).unwrap();
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_python-compiler"))
+        .args(args)
+        .args(files.iter().map(|(name, _)| name))
+        .current_dir(&dir)
+        .output()
+        .expect("Failed to run the compiler");
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+        output.status.code(),
+        dir,
+    )
+}
@@ -0,0 +1,252 @@
+mod common;
+
+use common::{compile_to_ir, run_program, run_program_with_status};
+
+#[test]
+fn test_large_distinct_ints_compare_unequal() {
+    // Both values are exactly representable 48-bit ints and must compare exactly
+    let source = r#"
+a = 140737488355327
+b = 140737488355326
+if a == b:
+    print(1)
+else:
+    print(0)
+if a > b:
+    print(1)
+else:
+    print(0)
+"#;
+    assert_eq!(run_program(source), "0\n1\n");
+}
+
+#[test]
+fn test_int_float_comparison() {
+    let source = r#"
+if 1 == 1.0:
+    print(1)
+else:
+    print(0)
+if 2 < 2.5:
+    print(1)
+else:
+    print(0)
+"#;
+    assert_eq!(run_program(source), "1\n1\n");
+}
+
+#[test]
+fn test_mixed_type_equality() {
+    let source = r#"
+print(5 == "a")
+print(5 != "a")
+print("a" == [1])
+print(1 == True)
+print(2.0 == 2)
+"#;
+    assert_eq!(run_program(source), "False\nTrue\nFalse\nTrue\nTrue\n");
+}
+
+#[test]
+fn test_list_equality_with_non_list() {
+    // The tag mismatch decides the result; no element comparison is attempted
+    let source = r#"
+xs = [1, 2]
+print([1] == 1)
+print(xs == 5)
+print(xs != 5)
+print(1.5 == xs)
+print(xs == (1, 2))
+"#;
+    assert_eq!(run_program(source), "False\nFalse\nTrue\nFalse\nFalse\n");
+}
+
+#[test]
+fn test_bool_number_comparison() {
+    // Bools compare as 0 and 1 against ints and floats
+    let source = r#"
+print(True < 2)
+print(False == 0)
+print(True > False)
+print(False >= 0.5)
+print(1 != True)
+"#;
+    assert_eq!(run_program(source), "True\nTrue\nTrue\nFalse\nFalse\n");
+
+    // The result is a bool: & between two bools stays a bool rather than an int
+    let source = r#"
+x = True < 2
+print(x)
+print(x & (False == 0))
+"#;
+    assert_eq!(run_program(source), "True\nTrue\n");
+}
+
+#[test]
+fn test_mixed_type_ordering_raises_type_error() {
+    let source = r#"
+print(1)
+print(5 < "a")
+print(2)
+"#;
+    let (stdout, stderr, status) = run_program_with_status(source);
+    assert_eq!(stdout, "1\n");
+    assert_eq!(
+        stderr,
+        "TypeError: '<' not supported between instances of different types\n"
+    );
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_is_none_comparisons() {
+    let source = r#"
+x = 5
+print(x is not None)
+print(x is None)
+y = None
+print(y is None)
+print(y)
+def nothing():
+    z = 1
+print(nothing() is None)
+if y:
+    print("truthy")
+else:
+    print("falsy")
+print(y == None)
+"#;
+    assert_eq!(
+        run_program(source),
+        "True\nFalse\nTrue\nNone\nTrue\nfalsy\nTrue\n"
+    );
+}
+
+#[test]
+fn test_len_condition_compiles_to_integer_compare() {
+    let source = r#"
+def nonempty(xs):
+    if len(xs) > 0:
+        return 1
+    return 0
+"#;
+    let llvm_ir = compile_to_ir(source);
+    let start = llvm_ir
+        .find("define i64 @nonempty")
+        .expect("nonempty is defined");
+    let end = start + llvm_ir[start..].find("\n}").unwrap();
+    let body = &llvm_ir[start..end];
+
+    // The length is compared as an integer and branched on directly: no f64
+    // conversion of the length and no bool boxing of the comparison
+    assert!(body.contains("icmp sgt"), "{}", body);
+    assert!(!body.contains("fcmp"), "{}", body);
+    assert!(!body.contains("sitofp"), "{}", body);
+
+    assert_eq!(
+        run_program(&format!(
+            "{}print(nonempty([1]))\nprint(nonempty([]))\n",
+            source
+        )),
+        "1\n0\n"
+    );
+}
+
+#[test]
+fn test_membership() {
+    let source = r#"
+names = ["ada", "bob"]
+print("bob" in names, "eve" not in names)
+print("ell" in "hello", "z" in "hello")
+print(2.0 in (1, 2), True in [1], None in [0])
+ages = {"ada": 36}
+print("ada" in ages, 36 in ages)
+"#;
+    assert_eq!(
+        run_program(source),
+        "True True\nTrue False\nTrue True False\nTrue False\n"
+    );
+}
+
+#[test]
+fn test_membership_in_non_container_raises_type_error() {
+    let (stdout, stderr, status) = run_program_with_status("print(3 in 4)\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "TypeError: argument is not iterable\n");
+    assert_eq!(status, Some(1));
+
+    let (stdout, stderr, status) = run_program_with_status("print(1 in \"abc\")\n");
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "TypeError: 'in <string>' requires string as left operand\n"
+    );
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_chained_comparisons_mixing_in() {
+    let source = r#"
+def middle():
+    print("middle")
+    return 2
+
+x = 5
+print(1 < 2 in [2, 3])
+print(1 < 5 in [2, 3])
+print(1 < middle() in [2, 3])
+print(0 <= x < 10, 0 < x > 10)
+"#;
+    assert_eq!(
+        run_program(source),
+        "True\nFalse\nmiddle\nTrue\nTrue False\n"
+    );
+}
+
+#[test]
+fn test_chained_comparison_calls_each_function_once() {
+    // g() feeds both comparisons but runs once; h() is skipped when the first fails
+    let source = r#"
+def f():
+    print("f")
+    return 1
+
+def g():
+    print("g")
+    return 2
+
+def h():
+    print("h")
+    return 3
+
+def check():
+    return f() < g() < h()
+
+print(check())
+print(g() < f() < h())
+"#;
+    assert_eq!(run_program(source), "f\ng\nh\nTrue\ng\nf\nFalse\n");
+}
+
+#[test]
+fn test_strings_and_lists_compare_by_content() {
+    let source = r#"
+print(("a" + "b") == "ab", "a,b".split(",")[0] == "a")
+print([c for c in "abc" if c != "b"])
+print("b" < "ab", "ab" <= "ab", "abc" > "abd")
+print([1, 2] == [1, 2], [1, 2] != [1, 2], [1, 3] > [1, 2], [1] < [1, 0])
+print(["x" + "y"] == ["xy"], "x" + "" in ["x"])
+print([1] == (1,), "1" == 1)
+"#;
+    assert_eq!(
+        run_program(source),
+        "True True\n['a', 'c']\nFalse True False\nTrue False True True\nTrue True\nFalse False\n"
+    );
+
+    let (_, stderr, status) = run_program_with_status("print([1] < (1,))\n");
+    assert_eq!(
+        stderr,
+        "TypeError: '<' not supported between instances of different types\n"
+    );
+    assert_eq!(status, Some(1));
+}
@@ -0,0 +1,188 @@
+mod common;
+
+use common::compile_to_ir_with;
+use inkwell::context::Context;
+use python_compiler::*;
+
+#[test]
+fn test_unbuffered_output_flushes_after_print() {
+    let source = "print(1)\nprint(2)";
+    let llvm_ir = compile_to_ir_with(source, |c| c.with_unbuffered_output(true));
+    assert!(
+        llvm_ir.contains("@fflush"),
+        "Unbuffered mode should flush after print"
+    );
+}
+
+#[test]
+fn test_buffered_output_by_default() {
+    let source = "print(1)\nprint(2)";
+    let llvm_ir = compile_to_ir_with(source, |c| c);
+    assert!(
+        !llvm_ir.contains("@fflush"),
+        "Default mode should not flush after print"
+    );
+}
+
+#[test]
+fn test_checked_arithmetic_emits_overflow_intrinsics() {
+    let source = r#"
+def combine(a, b):
+    return a * b + a - b
+print(combine(3, 4))
+"#;
+    let llvm_ir = compile_to_ir_with(source, |c| c.with_checked_arithmetic(true));
+    assert!(
+        llvm_ir.contains("@llvm.sadd.with.overflow.i64"),
+        "Checked mode should use the overflow-checked add intrinsic"
+    );
+    assert!(llvm_ir.contains("@llvm.smul.with.overflow.i64"));
+    assert!(llvm_ir.contains("@raise_overflow"));
+}
+
+#[test]
+fn test_unchecked_arithmetic_by_default() {
+    let source = r#"
+def combine(a, b):
+    return a + b
+print(combine(3, 4))
+"#;
+    let llvm_ir = compile_to_ir_with(source, |c| c);
+    assert!(!llvm_ir.contains("with.overflow"));
+    assert!(!llvm_ir.contains("@raise_overflow"));
+}
+
+#[test]
+fn test_module_name_defaults_to_main() {
+    let llvm_ir = compile_to_ir_with("print(1)", |c| c);
+    assert!(llvm_ir.starts_with("; ModuleID = 'main'"));
+}
+
+#[test]
+fn test_custom_module_name() {
+    let llvm_ir = compile_to_ir_with("print(1)", |c| c.with_module_name("geometry"));
+    assert!(
+        llvm_ir.starts_with("; ModuleID = 'geometry'"),
+        "Module ID should reflect the chosen name"
+    );
+    assert!(llvm_ir.contains("source_filename = \"geometry\""));
+}
+
+#[test]
+fn test_custom_entry_point() {
+    let source = "def double(x):\n    return x * 2\nprint(double(21))\n";
+    let llvm_ir = compile_to_ir_with(source, |c| c.with_entry_point(Some("rusthon_main")));
+    assert!(llvm_ir.contains("define i32 @rusthon_main()"));
+    assert!(!llvm_ir.contains("@main("));
+
+    // Without an entry point only the user functions are left
+    let llvm_ir = compile_to_ir_with(source, |c| c.with_entry_point(None));
+    assert!(llvm_ir.contains("@double("));
+    assert!(!llvm_ir.contains("define i32"));
+}
+
+#[test]
+fn test_program_of_only_functions_compiles() {
+    let source = "def add(a, b):\n    return a + b\n\ndef twice(x):\n    return add(x, x)\n";
+    let ast = parser::parse_program(source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    let context = Context::create();
+    codegen::Compiler::new(&context).verify_only(&ir).unwrap();
+
+    // The entry point has nothing to run but still exits with 0
+    let llvm_ir = compile_to_ir_with(source, |c| c);
+    assert!(llvm_ir.contains("define i32 @main()"), "{}", llvm_ir);
+    assert!(llvm_ir.contains("ret i32 0"), "{}", llvm_ir);
+
+    let llvm_ir = compile_to_ir_with(source, |c| c.with_entry_point(None));
+    assert!(llvm_ir.contains("@add("), "{}", llvm_ir);
+    assert!(llvm_ir.contains("@twice("), "{}", llvm_ir);
+}
+
+#[test]
+fn test_time_passes_does_not_change_ir() {
+    let source = r#"
+def square(n):
+    return n * n
+for i in range(3):
+    print(square(i))
+"#;
+    let timed = compile_to_ir_with(source, |c| c.with_time_passes(true));
+    let untimed = compile_to_ir_with(source, |c| c);
+    assert_eq!(timed, untimed);
+}
+
+#[test]
+fn test_recursion_limit_tracks_call_depth() {
+    let source = r#"
+def countdown(n):
+    if n == 0:
+        return 0
+    return countdown(n - 1)
+print(countdown(3))
+"#;
+    let llvm_ir = compile_to_ir_with(source, |c| c.with_recursion_limit(Some(500)));
+    assert!(llvm_ir.contains("@rusthon_recursion_depth"));
+    assert!(llvm_ir.contains("RecursionError: maximum recursion depth exceeded"));
+
+    let llvm_ir = compile_to_ir_with(source, |c| c);
+    assert!(!llvm_ir.contains("@rusthon_recursion_depth"));
+}
+
+#[test]
+fn test_module_stats_counts_defined_functions() {
+    let context = Context::create();
+    let module = context.create_module("stats");
+    let builder = context.create_builder();
+    let i64_type = context.i64_type();
+
+    // A declaration without a body is not counted
+    module.add_function("external", i64_type.fn_type(&[], false), None);
+
+    let function = module.add_function("f", i64_type.fn_type(&[i64_type.into()], false), None);
+    let entry = context.append_basic_block(function, "entry");
+    let exit = context.append_basic_block(function, "exit");
+    builder.position_at_end(entry);
+    let param = function.get_first_param().unwrap().into_int_value();
+    let doubled = builder.build_int_add(param, param, "doubled").unwrap();
+    builder.build_unconditional_branch(exit).unwrap();
+    builder.position_at_end(exit);
+    builder.build_return(Some(&doubled)).unwrap();
+
+    let stats = codegen::module_stats(&module);
+    assert_eq!(
+        stats,
+        codegen::ModuleStats {
+            functions: 1,
+            basic_blocks: 2,
+            instructions: 3,
+        }
+    );
+}
+
+#[test]
+fn test_module_stats_for_compiled_program() {
+    let source = r#"
+def total(n):
+    s = 0
+    i = 0
+    while i < n:
+        s = s + i
+        i = i + 1
+    return s
+print(total(10))
+"#;
+    let llvm_ir = compile_to_ir_with(source, |c| c);
+    let context = Context::create();
+    let buffer = inkwell::memory_buffer::MemoryBuffer::create_from_memory_range_copy(
+        llvm_ir.as_bytes(),
+        "program",
+    );
+    let module = context.create_module_from_ir(buffer).unwrap();
+    let stats = codegen::module_stats(&module);
+
+    // At least main, plus the loop's blocks, each holding at least a terminator
+    assert!(stats.functions >= 1, "{:?}", stats);
+    assert!(stats.basic_blocks > stats.functions, "{:?}", stats);
+    assert!(stats.instructions >= stats.basic_blocks, "{:?}", stats);
+}
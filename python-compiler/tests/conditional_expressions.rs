@@ -0,0 +1,97 @@
+mod common;
+
+use common::{compile_to_ir, compile_to_ir_with, run_program};
+
+#[test]
+fn test_conditional_expression_selects_arm() {
+    let source = r#"
+def pick(p, q):
+    return 1 if p else (2 if q else 3)
+
+print(pick(True, False), pick(False, True), pick(False, False))
+n = 5
+print("big" if n > 3 else "small")
+"#;
+    assert_eq!(run_program(source), "1 2 3\nbig\n");
+}
+
+#[test]
+fn test_conditional_expression_as_call_argument() {
+    // Arms that call functions need real branches; the arguments around them must
+    // still be evaluated in order and passed to the right parameters
+    let source = r#"
+def show(x):
+    print("eval", x)
+    return x
+
+def combine(a, b, c=0):
+    return a * 100 + b * 10 + c
+
+c = False
+print(combine(show(1), show(2) if c else show(3), c=4 if not c else 5))
+print(combine(1 if c else 2, show(6)))
+"#;
+    assert_eq!(run_program(source), "eval 1\neval 3\n134\neval 6\n260\n");
+}
+
+#[test]
+fn test_conditional_expression_only_evaluates_selected_arm() {
+    let source = r#"
+print(10 // 0 if False else 7)
+print(1 if True else 10 // 0)
+"#;
+    assert_eq!(run_program(source), "7\n1\n");
+}
+
+#[test]
+fn test_nested_conditional_expression_leaves_no_empty_blocks() {
+    let source = r#"
+def pick(p, q):
+    return 1 if p else (2 if q else 3)
+
+print(pick(True, False))
+"#;
+    let llvm_ir = compile_to_ir(source);
+    let pick: Vec<&str> = llvm_ir
+        .split("@pick(")
+        .nth(1)
+        .expect("Should have pick function")
+        .lines()
+        .take_while(|l| *l != "}")
+        .collect();
+
+    // Each block label is followed by at least one instruction before its terminator
+    let blocks: Vec<&[&str]> = pick
+        .split(|l| l.is_empty())
+        .filter(|block| !block.is_empty())
+        .collect();
+    assert!(
+        blocks.len() <= 5,
+        "Nested ternary should need at most five blocks, got:\n{}",
+        pick.join("\n")
+    );
+    for block in blocks {
+        let instructions = block.iter().filter(|l| l.starts_with("  ")).count();
+        assert!(
+            !(instructions == 1 && block.iter().any(|l| l.trim_start().starts_with("br label"))),
+            "Found an empty forwarding block:\n{}",
+            block.join("\n")
+        );
+    }
+}
+
+#[test]
+fn test_scalar_conditional_expression_uses_select() {
+    let source = r#"
+a = 7
+b = 3
+d = 0
+x = a if a > b else d
+y = 1.5 if b > a else None
+print(x, y)
+"#;
+    let llvm_ir = compile_to_ir_with(source, |c| c.with_optimization_level(0));
+    assert!(llvm_ir.contains("select i1"), "{}", llvm_ir);
+    assert!(!llvm_ir.contains("ifexp_then"), "{}", llvm_ir);
+    assert_eq!(run_program(source), "7 None\n");
+}
@@ -196,3 +196,31 @@ print(result)
 "#;
     insta::assert_snapshot!(compile_source(source));
 }
+
+#[test]
+fn test_elif_without_else() {
+    // Each value takes a different path: the if branch, the elif branch, or
+    // falls through the empty innermost else
+    for x in 1..=3 {
+        let source = format!(
+            r#"
+x = {}
+if x == 1:
+    print(1)
+elif x == 2:
+    print(2)
+
+def classify(n):
+    if n == 1:
+        print(1)
+    elif n == 2:
+        print(2)
+
+classify(x)
+"#,
+            x
+        );
+        let ir = compile_source(&source);
+        assert!(ir.contains("define i32 @main"));
+    }
+}
@@ -1,6 +1,10 @@
 use inkwell::context::Context;
 use python_compiler::*;
 
+mod common;
+
+use common::run_program;
+
 #[test]
 fn test_default_argument_simple() {
     let source = r#"
@@ -20,7 +24,7 @@ print(greet("World", "Hi"))
             name,
             params,
             defaults,
-            body: _,
+            ..
         } => {
             assert_eq!(name, "greet");
             assert_eq!(params.len(), 2);
@@ -63,7 +67,7 @@ print(add(5, 15, 25))
             name,
             params,
             defaults,
-            body: _,
+            ..
         } => {
             assert_eq!(name, "add");
             assert_eq!(params.len(), 3);
@@ -111,3 +115,93 @@ print(multiply(5, 3))
     let llvm_ir = compiler.compile_program(&ir).unwrap();
     insta::assert_snapshot!(llvm_ir);
 }
+
+#[test]
+fn test_keyword_only_arguments() {
+    let source = r#"
+def scale(a, *, factor, offset=1):
+    return a * factor + offset
+
+print(scale(2, factor=3))
+print(scale(2, offset=0, factor=5))
+print(scale(a=4, factor=2))
+"#;
+    let ast = parser::parse_program(source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    match &ir[0] {
+        ast::IRStmt::FunctionDef {
+            params,
+            kwonly_params,
+            kwonly_defaults,
+            ..
+        } => {
+            assert_eq!(params, &["a"]);
+            assert_eq!(kwonly_params, &["factor", "offset"]);
+            assert!(kwonly_defaults[0].is_none());
+            assert!(matches!(kwonly_defaults[1], Some(ast::IRExpr::Constant(1))));
+        }
+        _ => panic!("Expected FunctionDef"),
+    }
+
+    assert_eq!(run_program(source), "7\n10\n9\n");
+}
+
+#[test]
+fn test_keyword_only_argument_passed_positionally_is_rejected() {
+    let source = r#"
+def f(a, *, b):
+    return a + b
+
+print(f(1, 2))
+"#;
+    let ast = parser::parse_program(source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    let context = Context::create();
+    let compiler = codegen::Compiler::new(&context);
+    match compiler.compile_program(&ir) {
+        Err(codegen::CodeGenError::InvalidArguments(message)) => {
+            assert!(message.contains("1 positional arguments but 2 were given"));
+        }
+        other => panic!(
+            "Expected InvalidArguments error, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+}
+
+#[test]
+fn test_keyword_argument_errors() {
+    for (call, expected) in [
+        ("f(1, c=2)", "unexpected keyword argument 'c'"),
+        ("f(1, a=2)", "multiple values for argument 'a'"),
+        ("f(b=2)", "missing required argument 'a'"),
+    ] {
+        let source = format!("def f(a, b=0):\n    return a + b\n\nprint({})\n", call);
+        let ast = parser::parse_program(&source).unwrap();
+        let ir = lowering::lower_program(&ast).unwrap();
+        let context = Context::create();
+        let compiler = codegen::Compiler::new(&context);
+        match compiler.compile_program(&ir) {
+            Err(codegen::CodeGenError::InvalidArguments(message)) => {
+                assert!(message.contains(expected), "{}: {}", call, message);
+            }
+            _ => panic!("Expected InvalidArguments error for {}", call),
+        }
+    }
+}
+
+#[test]
+fn test_default_is_evaluated_when_the_function_is_defined() {
+    // Later assignments to `x` don't change the default, including for calls made
+    // from inside another function
+    let source = r#"
+x = 10
+def f(a=x * 2):
+    return a
+def g():
+    return f()
+x = 20
+print(f(), g(), f(1), x)
+"#;
+    assert_eq!(run_program(source), "20 20 1 20\n");
+}
@@ -0,0 +1,175 @@
+mod common;
+
+use common::{run_program, run_program_with_status};
+use python_compiler::*;
+
+#[test]
+fn test_dict_literal_len() {
+    let source = r#"
+d = {"a": 1, "b": 2, "c": 3}
+print(len(d))
+print(len({}))
+"#;
+    assert_eq!(run_program(source), "3\n0\n");
+}
+
+#[test]
+fn test_repeated_literal_key_keeps_last_value() {
+    let source = r#"d = {"x": 1, "y": 2, "x": 3}"#;
+    let ast = parser::parse_program(source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    assert_eq!(
+        ir,
+        vec![ast::IRStmt::Assign {
+            target: "d".to_string(),
+            value: ast::IRExpr::Dict(vec![
+                (
+                    ast::IRExpr::StringLiteral("x".to_string()),
+                    ast::IRExpr::Constant(3)
+                ),
+                (
+                    ast::IRExpr::StringLiteral("y".to_string()),
+                    ast::IRExpr::Constant(2)
+                ),
+            ]),
+        }]
+    );
+}
+
+#[test]
+fn test_kwargs_collects_extra_keywords() {
+    let source = r#"
+def f(**o):
+    return len(o)
+
+def g(a, *, b=2, **rest):
+    return a + b + len(rest)
+
+print(f(x=1, y=2))
+print(f())
+print(g(1, b=5, c=1, d=2))
+print(g(a=1, b=3))
+"#;
+    assert_eq!(run_program(source), "2\n0\n8\n4\n");
+}
+
+#[test]
+fn test_iterating_keys_values_and_items() {
+    let source = r#"
+d = {1: "a", 2: "b"}
+for k in d.keys():
+    print(k)
+for v in d.values():
+    print(v)
+for k, v in d.items():
+    print(k, v)
+print(d.items())
+print(len({}.keys()))
+"#;
+    assert_eq!(
+        run_program(source),
+        "1\n2\na\nb\n1 a\n2 b\n[(1, 'a'), (2, 'b')]\n0\n"
+    );
+}
+
+#[test]
+fn test_item_assignment_inserts_and_updates() {
+    let source = r#"
+d = {}
+d[1] = "a"
+print(d[1])
+alias = d
+for i in range(10):
+    d[i] = i * i
+print(len(alias), alias[1], alias[9])
+d["x"] = 2.5
+print(d["x"], "x" in alias)
+
+def mark(m, key):
+    m[key] = True
+
+mark(d, "y")
+print(len(d), d["y"])
+"#;
+    assert_eq!(run_program(source), "a\n10 1 81\n2.5 True\n12 True\n");
+}
+
+#[test]
+fn test_missing_key_raises_key_error() {
+    let (stdout, stderr, status) = run_program_with_status("d = {\"a\": 1}\nprint(d[\"b\"])\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "KeyError: key not found\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_get_falls_back_to_default() {
+    let source = r#"
+d = {1: "a"}
+print(d.get(2, "x"))
+print(d.get(1, "x"))
+print(d.get(3))
+counts = {}
+for word in ["a", "b", "a"]:
+    counts[word] = counts.get(word, 0) + 1
+print(counts["a"], counts["b"])
+"#;
+    assert_eq!(run_program(source), "x\na\nNone\n2 1\n");
+}
+
+#[test]
+fn test_print_dict_as_python_literal() {
+    let source = r#"
+print({1: "a", 2: "b"})
+print({})
+d = {"x": [1, "y"], "n": None}
+d["w"] = {"k": 1.5}
+print(d)
+print([{1: 2}], str({True: "v"}) + "!")
+"#;
+    assert_eq!(
+        run_program(source),
+        "{1: 'a', 2: 'b'}\n{}\n{'x': [1, 'y'], 'n': None, 'w': {'k': 1.5}}\n[{1: 2}] {True: 'v'}!\n"
+    );
+}
+
+#[test]
+fn test_dict_grows_past_its_hash_index() {
+    // Thousands of inserts rehash the index several times; every key stays findable
+    let source = r#"
+squares = {}
+for i in range(5000):
+    squares[i] = i * i
+found = 0
+for i in range(5000):
+    if squares[i] == i * i:
+        found += 1
+print(len(squares), found, 5000 in squares)
+grid = {}
+for x in range(40):
+    for y in range(40):
+        grid[(x, y)] = x * y
+print(len(grid), grid[(39, 38)], (40, 0) in grid)
+words = {}
+for i in range(300):
+    words["w" + str(i)] = i
+print(words["w299"], "w300" in words)
+"#;
+    assert_eq!(
+        run_program(source),
+        "5000 5000 False\n1600 1482 False\n299 False\n"
+    );
+}
+
+#[test]
+fn test_equal_keys_share_an_entry() {
+    // 1, 1.0 and True are equal, so they hash alike and find the same entry
+    let source = r#"
+d = {1: "int", "a": 1, "a": 2}
+d[1.0] = "float"
+print(d[True], len(d))
+print(d)
+print({-0.0: "zero"}[0])
+"#;
+    assert_eq!(run_program(source), "float 2\n{1: 'float', 'a': 2}\nzero\n");
+}
@@ -327,3 +327,31 @@ print(sum_many(1, 2, 3, 4, 5, 6, 7, 8))
     let llvm_ir = compiler.compile_program(&ir).unwrap();
     insta::assert_snapshot!(llvm_ir);
 }
+
+#[test]
+fn test_shebang_line_is_ignored() {
+    // A `#!` first line lexes as an ordinary comment, so executable scripts parse as-is
+    let source = "#!/usr/bin/env rusthon\nprint(1)\n";
+    let ast = parser::parse_program(source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    assert_eq!(ir, vec![ast::IRStmt::print(vec![ast::IRExpr::Constant(1)])]);
+
+    let context = Context::create();
+    let compiler = codegen::Compiler::new(&context);
+    assert!(compiler.compile_program(&ir).is_ok());
+}
+
+#[test]
+fn test_tokenize_small_snippet() {
+    use rustpython_parser::Tok;
+
+    let tokens = parser::tokenize("if x:\n    y = 1\n").unwrap();
+    let kinds: Vec<&Tok> = tokens.iter().map(|(token, _)| token).collect();
+    assert!(matches!(kinds[0], Tok::If));
+    assert!(matches!(kinds[1], Tok::Name { name } if name == "x"));
+    assert!(kinds.iter().any(|token| matches!(token, Tok::Indent)));
+    assert!(matches!(kinds.last(), Some(Tok::Dedent)));
+
+    let (_, range) = &tokens[1];
+    assert_eq!(usize::from(range.start()), 3);
+}
@@ -0,0 +1,23 @@
+mod common;
+
+use common::{run_program, run_program_with_input};
+
+#[test]
+fn test_calculator_example() {
+    // Functions, recursion, while loops, elif chains, tuple unpacking, list
+    // indexing and assignment, and input() together in one program
+    let source = include_str!("../examples/calculator.py");
+    assert_eq!(
+        run_program_with_input(source, "3\n5\n"),
+        "16\n40\n3\n2\n1\n1\n"
+    );
+}
+
+#[test]
+fn test_bundled_examples_run() {
+    let output = run_program(include_str!("../examples/strings.py"));
+    assert!(output.starts_with("Hello, World!\n"), "{}", output);
+
+    let output = run_program(include_str!("../examples/control_flow.py"));
+    assert!(output.ends_with("1\n2\n"), "{}", output);
+}
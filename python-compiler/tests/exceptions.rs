@@ -0,0 +1,373 @@
+mod common;
+
+use common::{run_program, run_program_with, run_program_with_status};
+
+#[test]
+fn test_try_except_catches_zero_division() {
+    let source = r#"
+try:
+    print(1 // 0)
+except:
+    print("caught")
+"#;
+    assert_eq!(run_program(source), "caught\n");
+}
+
+#[test]
+fn test_uncaught_zero_division_exits() {
+    let source = r#"
+print(1)
+print(5 % 0)
+print(2)
+"#;
+    let (stdout, stderr, status) = run_program_with_status(source);
+    assert_eq!(stdout, "1\n");
+    assert_eq!(
+        stderr,
+        "ZeroDivisionError: integer division or modulo by zero\n"
+    );
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_try_else_and_nested_handlers() {
+    let source = r#"
+try:
+    print(1)
+except:
+    print("no")
+else:
+    print("else")
+try:
+    try:
+        print(1 / 0)
+    except:
+        print("inner")
+        print(1 / 0)
+except:
+    print("outer")
+"#;
+    assert_eq!(run_program(source), "1\nelse\ninner\nouter\n");
+}
+
+#[test]
+fn test_error_raised_in_called_function_is_caught() {
+    let source = r#"
+def divide(a, b):
+    return a // b
+def safe_divide(a, b):
+    try:
+        return divide(a, b)
+    except:
+        return -1
+print(safe_divide(7, 2))
+print(safe_divide(7, 0))
+"#;
+    assert_eq!(run_program(source), "3\n-1\n");
+}
+
+#[test]
+fn test_handler_sees_assignments_made_in_try_body() {
+    let source = r#"
+x = 1
+try:
+    x = 2
+    print(10 // (x - 2))
+except:
+    print(x)
+"#;
+    assert_eq!(run_program(source), "2\n");
+}
+
+#[test]
+fn test_break_out_of_try_restores_handler() {
+    let source = r#"
+for i in range(3):
+    try:
+        if i == 1:
+            break
+    except:
+        print("no")
+print(1 // 0)
+"#;
+    let (stdout, stderr, status) = run_program_with_status(source);
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "ZeroDivisionError: integer division or modulo by zero\n"
+    );
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_recursion_limit_raises_recursion_error() {
+    let source = r#"
+def forever(n):
+    return forever(n + 1)
+print(1)
+forever(0)
+"#;
+    let (stdout, stderr, status) = run_program_with(source, |c| c.with_recursion_limit(Some(1000)));
+    assert_eq!(stdout, "1\n");
+    assert_eq!(stderr, "RecursionError: maximum recursion depth exceeded\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_recursion_within_limit_and_caught_overflow() {
+    let source = r#"
+def depth(n):
+    if n == 0:
+        return 0
+    return depth(n - 1) + 1
+print(depth(50))
+try:
+    depth(100)
+except:
+    print("too deep")
+print(depth(50))
+"#;
+    let (stdout, _, status) = run_program_with(source, |c| c.with_recursion_limit(Some(60)));
+    assert_eq!(stdout, "50\ntoo deep\n50\n");
+    assert_eq!(status, Some(0));
+}
+
+#[test]
+fn test_raise_aborts_with_message() {
+    let source = r#"
+print("before")
+raise ValueError("bad")
+print("after")
+"#;
+    let (stdout, stderr, status) = run_program_with_status(source);
+    assert_eq!(stdout, "before\n");
+    assert_eq!(stderr, "ValueError: bad\n");
+    assert_eq!(status, Some(1));
+
+    let (stdout, stderr, status) = run_program_with_status("raise KeyError\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "KeyError\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_raise_is_caught_by_try() {
+    let source = r#"
+def check(n):
+    if n < 0:
+        raise ValueError("negative")
+    return n
+
+try:
+    check(-1)
+    print("unreachable")
+except:
+    print("caught")
+print(check(3))
+"#;
+    assert_eq!(run_program(source), "caught\n3\n");
+}
+
+#[test]
+fn test_raise_with_computed_message() {
+    let (stdout, stderr, status) =
+        run_program_with_status("name = \"x\"\nraise NameError(\"no \" + name)\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "NameError: no x\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_fstring_messages_interpolate_values() {
+    let source = r#"
+n = 2.5
+try:
+    raise ValueError(f"bad {n}")
+except ValueError:
+    print("caught")
+x = -2
+assert x > 0, f"got {x}, expected more than {n:.1f}"
+"#;
+    let (stdout, stderr, status) = run_program_with_status(source);
+    assert_eq!(stdout, "caught\n");
+    assert_eq!(stderr, "AssertionError: got -2, expected more than 2.5\n");
+    assert_eq!(status, Some(1));
+
+    let (stdout, stderr, status) = run_program_with_status("xs = [1]\nraise KeyError(f\"{xs}\")\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "KeyError: [1]\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_bare_raise_reraises_current_error() {
+    let source = r#"
+try:
+    raise TypeError("inner")
+except:
+    print("handling")
+    raise
+"#;
+    let (stdout, stderr, status) = run_program_with_status(source);
+    assert_eq!(stdout, "handling\n");
+    assert_eq!(stderr, "TypeError: inner\n");
+    assert_eq!(status, Some(1));
+
+    let (stdout, stderr, status) = run_program_with_status("raise\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "RuntimeError: No active exception to reraise\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_assert_raises_assertion_error() {
+    let source = r#"
+x = 3
+assert x == 3
+assert x > 1, "unused"
+try:
+    assert x < 1, "x is " + "too big"
+except:
+    print("caught")
+assert x == 4
+print("unreachable")
+"#;
+    let (stdout, stderr, status) = run_program_with_status(source);
+    assert_eq!(stdout, "caught\n");
+    assert_eq!(stderr, "AssertionError\n");
+    assert_eq!(status, Some(1));
+
+    let (stdout, stderr, status) = run_program_with_status("assert 1 > 2, \"order\"\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "AssertionError: order\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_assert_inside_function() {
+    // Asserts nested in a function's loop and branch blocks, caught by the caller
+    // and then left to abort the program
+    let source = r#"
+def check(x):
+    for i in range(2):
+        if i == 1:
+            assert x > 0, f"check: x > 0 failed for {x}"
+    return x
+
+print(check(5))
+try:
+    check(0)
+except AssertionError:
+    print("caught")
+check(-1)
+print("unreachable")
+"#;
+    let (stdout, stderr, status) = run_program_with_status(source);
+    assert_eq!(stdout, "5\ncaught\n");
+    assert_eq!(stderr, "AssertionError: check: x > 0 failed for -1\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_disabled_asserts_are_dropped() {
+    // Neither the condition nor the message is evaluated
+    let source = r#"
+print("before")
+assert 0, undefined_name
+print("after")
+"#;
+    let (stdout, _, status) = run_program_with(source, |c| c.with_asserts(false));
+    assert_eq!(stdout, "before\nafter\n");
+    assert_eq!(status, Some(0));
+}
+
+#[test]
+fn test_handler_only_catches_its_exception_types() {
+    let source = r#"
+try:
+    print([1][5])
+except LookupError:
+    print("lookup")
+try:
+    raise ValueError("bad")
+except (TypeError, ValueError):
+    print("tuple")
+try:
+    raise KeyError
+except Exception:
+    print("any")
+"#;
+    assert_eq!(run_program(source), "lookup\ntuple\nany\n");
+
+    // A handler for another type lets the error through to the enclosing try
+    let source = r#"
+def check():
+    try:
+        raise ValueError("a")
+    except ZeroDivisionError:
+        print("wrong")
+
+try:
+    check()
+except ValueError:
+    print("outer")
+try:
+    raise ValueError("a")
+except ZeroDivisionError:
+    print("wrong")
+print("unreachable")
+"#;
+    let (stdout, stderr, status) = run_program_with_status(source);
+    assert_eq!(stdout, "outer\n");
+    assert_eq!(stderr, "ValueError: a\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_reading_a_deleted_variable_raises() {
+    let (stdout, stderr, status) = run_program_with_status("x = 1\ndel x\nx += 1\nprint(x)\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "NameError: name 'x' is not defined\n");
+    assert_eq!(status, Some(1));
+
+    let source = r#"
+def f(a):
+    del a
+    return a
+print(f(1))
+"#;
+    let (_, stderr, status) = run_program_with_status(source);
+    assert_eq!(
+        stderr,
+        "UnboundLocalError: cannot access local variable 'a' where it is not associated with a value\n"
+    );
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_del_follows_control_flow() {
+    // A del that never runs leaves the variable bound, and one inside a loop does not
+    // affect the variable after the loop
+    let source = r#"
+x = 1
+while x < 3:
+    x = x + 1
+    if x == 5:
+        del x
+        x = 9
+print(x)
+y = 1
+if y == 2:
+    del y
+print(y)
+z = 1
+del z
+try:
+    print(z)
+except NameError:
+    print("unbound")
+z = 4
+print(z)
+"#;
+    assert_eq!(run_program(source), "3\n1\nunbound\n4\n");
+}
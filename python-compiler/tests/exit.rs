@@ -0,0 +1,58 @@
+mod common;
+
+use common::{compile_to_ir, run_program_with_status};
+
+#[test]
+fn test_exit_ends_program_with_status() {
+    let source = r#"
+print("before")
+exit(3)
+print("after")
+"#;
+    assert_eq!(
+        run_program_with_status(source),
+        ("before\n".to_string(), String::new(), Some(3))
+    );
+
+    let llvm_ir = compile_to_ir(source);
+    assert!(llvm_ir.contains("call void @exit(i32 3)"), "{}", llvm_ir);
+    // The print after the exit is unreachable and optimized away
+    assert!(!llvm_ir.contains("after"), "{}", llvm_ir);
+}
+
+#[test]
+fn test_bare_exit_and_quit() {
+    assert_eq!(
+        run_program_with_status("exit()\nprint(1)\n"),
+        (String::new(), String::new(), Some(0))
+    );
+    assert_eq!(
+        run_program_with_status("quit(None)\n"),
+        (String::new(), String::new(), Some(0))
+    );
+
+    let source = r#"
+def stop(n):
+    if n > 2:
+        quit(n)
+    return n
+print(stop(1))
+print(stop(5))
+print("unreachable")
+"#;
+    assert_eq!(
+        run_program_with_status(source),
+        ("1\n".to_string(), String::new(), Some(5))
+    );
+}
+
+#[test]
+fn test_exit_rejects_non_integer_code() {
+    let (stdout, stderr, status) = run_program_with_status("exit(\"bye\")\n");
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "TypeError: exit() code must be an integer or None\n"
+    );
+    assert_eq!(status, Some(1));
+}
@@ -0,0 +1,111 @@
+use inkwell::context::Context;
+use python_compiler::*;
+
+mod common;
+
+use common::{run_program, run_program_with_status};
+
+fn temp_path(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!("rusthon_{}_{}.txt", name, std::process::id()))
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn test_write_then_read_round_trip() {
+    let path = temp_path("round_trip");
+    let source = format!(
+        r#"
+text = "first line\nsecond line\n"
+f = open("{path}", "w")
+written = f.write(text)
+f.close()
+g = open("{path}")
+contents = g.read()
+g.close()
+print(written)
+print(len(contents))
+print(contents)
+"#
+    );
+    assert_eq!(run_program(&source), "23\n23\nfirst line\nsecond line\n\n");
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "first line\nsecond line\n"
+    );
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_read_file_written_outside_program() {
+    let path = temp_path("external");
+    std::fs::write(&path, "hello from rust").unwrap();
+    let source = format!(
+        r#"
+f = open("{path}", "r")
+print(f.read())
+# Reading again at end of file yields an empty string
+print(len(f.read()))
+f.close()
+"#
+    );
+    assert_eq!(run_program(&source), "hello from rust\n0\n");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_open_missing_file_raises() {
+    let source = r#"
+try:
+    f = open("/nonexistent/rusthon/file.txt")
+except:
+    print("caught")
+f = open("/nonexistent/rusthon/file.txt")
+"#;
+    let (stdout, stderr, status) = run_program_with_status(source);
+    assert_eq!(stdout, "caught\n");
+    assert_eq!(stderr, "OSError: could not open file\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_unknown_method_is_rejected() {
+    let ast = parser::parse_program("f = open(\"x.txt\")\nf.readlines()\n").unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    let context = Context::create();
+    let compiler = codegen::Compiler::new(&context);
+    match compiler.compile_program(&ir) {
+        Err(codegen::CodeGenError::UnsupportedMethod(method)) => assert_eq!(method, "readlines"),
+        other => panic!(
+            "Expected UnsupportedMethod error, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+}
+
+#[test]
+fn test_closed_file_rejects_io() {
+    let path = temp_path("closed");
+    std::fs::write(&path, "data").unwrap();
+    let source = format!(
+        r#"
+f = open("{path}")
+g = f
+f.close()
+# Closing again, through any reference, does nothing
+g.close()
+f.close()
+try:
+    g.read()
+except ValueError:
+    print("read refused")
+f.write("more")
+"#
+    );
+    let (stdout, stderr, status) = run_program_with_status(&source);
+    assert_eq!(stdout, "read refused\n");
+    assert_eq!(stderr, "ValueError: I/O operation on closed file\n");
+    assert_eq!(status, Some(1));
+    std::fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,152 @@
+mod common;
+
+use common::{run_program, run_program_with_status};
+
+#[test]
+fn test_call_function_through_variable() {
+    let source = r#"
+def add(a, b):
+    return a + b
+
+def apply(f, x, y):
+    return f(x, y)
+
+f = add
+print(f(1, 2))
+print(apply(add, 3, 4))
+print(f)
+"#;
+    assert_eq!(run_program(source), "3\n7\n<function add>\n");
+}
+
+#[test]
+fn test_int_returning_function_as_value() {
+    let source = r#"
+def one():
+    return 1
+
+k = one
+print(k() + 1)
+"#;
+    assert_eq!(run_program(source), "2\n");
+}
+
+#[test]
+fn test_int_function_falling_off_the_end_returns_none() {
+    // Every return is an int, but a path without one (or a bare return) gives None
+    let source = r#"
+def f(n):
+    if n > 0:
+        return 1
+
+def g(n):
+    if n > 0:
+        return 2
+    return
+
+def h(n):
+    if n > 0:
+        return 3
+    else:
+        raise ValueError("negative")
+
+print(f(1))
+print(f(0))
+print(g(0))
+print(f(0) is None)
+print(h(1) + 1)
+"#;
+    assert_eq!(run_program(source), "1\nNone\nNone\nTrue\n4\n");
+}
+
+#[test]
+fn test_calling_non_function_raises_type_error() {
+    let source = r#"
+n = 3
+n(1)
+"#;
+    let (_, stderr, status) = run_program_with_status(source);
+    assert!(stderr.contains("TypeError"), "{}", stderr);
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_wrong_argument_count_through_variable_raises_type_error() {
+    let source = r#"
+def add(a, b):
+    return a + b
+
+f = add
+f(1)
+"#;
+    let (_, stderr, status) = run_program_with_status(source);
+    assert!(stderr.contains("TypeError"), "{}", stderr);
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_function_arguments_drive_map_filter_and_sorted() {
+    let source = r#"
+def inc(x):
+    return x + 1
+
+def odd(x):
+    return x % 2
+
+def size(w):
+    return len(w)
+
+def transform(f, xs):
+    return map(f, xs)
+
+print(map(inc, [1, 2, 3]))
+print(transform(inc, [5, 6]))
+keep = odd
+print(filter(keep, [1, 2, 3, 4, 5]))
+print(sorted(["ccc", "a", "bb"], key=size))
+print(sorted([2, 1], key=None))
+"#;
+    assert_eq!(
+        run_program(source),
+        "[2, 3, 4]\n[6, 7]\n[1, 3, 5]\n['a', 'bb', 'ccc']\n[1, 2]\n"
+    );
+}
+
+#[test]
+fn test_locals_shadow_functions_and_builtins() {
+    let source = r#"
+def double(x):
+    return x * 2
+
+def one():
+    return 1
+
+def measure(len):
+    return len(3)
+
+def call(one):
+    return one(5)
+
+print(measure(double))
+print(call(double))
+print(len("abc"), one())
+"#;
+    assert_eq!(run_program(source), "6\n10\n3 1\n");
+}
+
+#[test]
+fn test_calling_shadowed_builtin_reports_type_error() {
+    let source = r#"
+def measure(len):
+    return len("abc")
+
+measure(3)
+"#;
+    let (_, stderr, status) = run_program_with_status(source);
+    assert!(
+        stderr.contains("TypeError: object is not callable"),
+        "{}",
+        stderr
+    );
+    assert_eq!(status, Some(1));
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use common::{run_program, run_program_with_status};
+
+#[test]
+fn test_fstring_interpolates_values() {
+    let source = r#"
+name = "bob"
+n = 3
+ratio = 0.5
+print(f"hi {name}, n={n} ratio={ratio} big={n > 2} none={None} 100%")
+label = f"{n}-{n * 2}"
+print(label + "!", len(label))
+"#;
+    assert_eq!(
+        run_program(source),
+        "hi bob, n=3 ratio=0.5 big=True none=None 100%\n3-6! 3\n"
+    );
+}
+
+#[test]
+fn test_fstring_format_specs() {
+    let source = r#"
+print(f"{3:03d}")
+print(f"{3.14159:.2f}")
+print(f"{-7:05d} {2.5:e} {1e20:g} {True:d} {4:f}")
+"#;
+    assert_eq!(
+        run_program(source),
+        "003\n3.14\n-0007 2.500000e+00 1e+20 1 4.000000\n"
+    );
+}
+
+#[test]
+fn test_fstring_width_and_alignment() {
+    // Without a type, strings align left and numbers right, as in Python
+    let source = r#"
+s = "bob"
+n = 3
+print(f"[{s:6}] [{n:6}] [{s:>6}] [{n:<4d}] [{2.0:8.3f}] [{s:.2s}]")
+"#;
+    assert_eq!(
+        run_program(source),
+        "[bob   ] [     3] [   bob] [3   ] [   2.000] [bo]\n"
+    );
+}
+
+#[test]
+fn test_fstring_format_code_type_errors() {
+    let source = r#"
+s = "a"
+print(f"{s:d}")
+"#;
+    assert_eq!(
+        run_program_with_status(source),
+        (
+            String::new(),
+            "ValueError: format code 'd' requires an int\n".to_string(),
+            Some(1)
+        )
+    );
+}
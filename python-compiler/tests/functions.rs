@@ -109,3 +109,115 @@ print(z)
     let llvm_ir = compiler.compile_program(&ir).unwrap();
     insta::assert_snapshot!(llvm_ir);
 }
+
+#[test]
+fn test_int_return_type_call_site_is_branch_free() {
+    let source = r#"
+def count(n):
+    if n == 0:
+        return 0
+    return count(n - 1) + 1
+
+print(count(5))
+"#;
+    let ast = parser::parse_program(source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    let context = Context::create();
+    let compiler = codegen::Compiler::new(&context);
+    let llvm_ir = compiler.compile_program(&ir).unwrap();
+
+    // count() always returns an int, so main re-tags its result with a constant
+    // INT tag and the print type dispatch folds away
+    let main_body: String = llvm_ir
+        .split("@main()")
+        .nth(1)
+        .expect("Should have main function")
+        .lines()
+        .take_while(|l| *l != "}")
+        .collect::<Vec<_>>()
+        .join("\n");
+    assert!(main_body.contains("@count"), "main should call count");
+    assert!(
+        !main_body.contains("br i1"),
+        "Call site of an int-returning function should be branch-free, got:\n{}",
+        main_body
+    );
+}
+
+#[test]
+fn test_many_calls_compile_quickly() {
+    // Thousands of call sites must not make result naming (or anything else)
+    // blow up compile time
+    let mut source = String::from("def inc(n):\n    return n + 1\n\nx = 0\n");
+    for _ in 0..3000 {
+        source.push_str("x = inc(x)\n");
+    }
+    source.push_str("print(x)\n");
+
+    let start = std::time::Instant::now();
+    let ast = parser::parse_program(&source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    let context = Context::create();
+    let compiler = codegen::Compiler::new(&context);
+    let llvm_ir = compiler.compile_program(&ir).unwrap();
+    let elapsed = start.elapsed();
+
+    assert!(
+        elapsed < std::time::Duration::from_secs(30),
+        "compiling 3000 calls took {:?}",
+        elapsed
+    );
+    assert!(llvm_ir.contains("define i32 @main"));
+}
+
+#[test]
+fn test_call_results_are_named_after_callee() {
+    let source = r#"
+def fib(n):
+    if n < 2:
+        return n
+    return fib(n - 1) + fib(n - 2)
+
+print(fib(10))
+"#;
+    let ast = parser::parse_program(source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    let context = Context::create();
+    let compiler = codegen::Compiler::new(&context);
+    let llvm_ir = compiler.compile_program(&ir).unwrap();
+    assert!(llvm_ir.contains("%fib_result"), "{}", llvm_ir);
+    assert!(!llvm_ir.contains("%calltmp"));
+}
+
+#[test]
+fn test_docstrings_are_not_compiled() {
+    let source = r#"
+"""Module docstring."""
+def decrement(x):
+    """Returns x minus one."""
+    return x - 1
+
+print(decrement(43))
+"#;
+    let ast = parser::parse_program(source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    let context = Context::create();
+    // Unoptimized, so a discarded string would still show up
+    let compiler = codegen::Compiler::new(&context).with_optimization_level(0);
+    let llvm_ir = compiler.compile_program(&ir).unwrap();
+    assert!(!llvm_ir.contains("docstring"), "{}", llvm_ir);
+    assert!(!llvm_ir.contains("minus one"), "{}", llvm_ir);
+
+    // Neither body allocates a string for its docstring
+    for function in ["@decrement(", "@main()"] {
+        let body: String = llvm_ir
+            .split(function)
+            .nth(1)
+            .expect("Should have the function")
+            .lines()
+            .take_while(|l| *l != "}")
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(!body.contains("@malloc"), "{}", body);
+    }
+}
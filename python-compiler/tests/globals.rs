@@ -0,0 +1,74 @@
+mod common;
+
+use common::run_program;
+
+#[test]
+fn test_global_read_before_assignment_is_none() {
+    let source = r#"
+def show():
+    global counter
+    print(counter)
+
+show()
+counter = 10
+show()
+"#;
+    assert_eq!(run_program(source), "None\n10\n");
+}
+
+#[test]
+fn test_function_assigns_global() {
+    // counter is assigned a literal once at the top level, but bump() rebinds it,
+    // so it must not be constant-propagated
+    let source = r#"
+def bump():
+    global counter
+    counter = counter + 1
+
+counter = 10
+bump()
+bump()
+print(counter)
+"#;
+    assert_eq!(run_program(source), "12\n");
+}
+
+#[test]
+fn test_global_declared_in_nested_block() {
+    let source = r#"
+def set_flag(on):
+    if on:
+        global flag
+        flag = True
+
+flag = False
+set_flag(True)
+print(flag)
+"#;
+    assert_eq!(run_program(source), "True\n");
+}
+
+#[test]
+fn test_functions_share_a_global_counter() {
+    // Every function declaring the name, and the top level, use the same global
+    let source = r#"
+count = 0
+
+def inc():
+    global count
+    count += 1
+
+def take(n):
+    global count
+    count = count - n
+    return count
+
+inc()
+inc()
+print(count)
+print(take(5))
+inc()
+print(count)
+"#;
+    assert_eq!(run_program(source), "2\n-3\n-2\n");
+}
@@ -0,0 +1,44 @@
+use inkwell::context::Context;
+use python_compiler::ast::{BinOp, CmpOp, IRExpr, IRStmt};
+use python_compiler::*;
+
+#[test]
+fn test_constructors_match_lowered_ir() {
+    let ast = parser::parse_program("x = (a + 1) * f(2)").unwrap();
+    let lowered = lowering::lower_program(&ast).unwrap();
+    let built = vec![IRStmt::assign(
+        "x",
+        IRExpr::binary(
+            BinOp::Mul,
+            IRExpr::binary(BinOp::Add, IRExpr::var("a"), IRExpr::int(1)),
+            IRExpr::call("f", vec![IRExpr::int(2)]),
+        ),
+    )];
+    assert_eq!(lowered, built);
+}
+
+#[test]
+fn test_compile_hand_built_program() {
+    let program = vec![
+        IRStmt::function(
+            "square",
+            &["n"],
+            vec![IRStmt::Return(IRExpr::binary(
+                BinOp::Mul,
+                IRExpr::var("n"),
+                IRExpr::var("n"),
+            ))],
+        ),
+        IRStmt::assign("x", IRExpr::call("square", vec![IRExpr::int(7)])),
+        IRStmt::if_then(
+            IRExpr::compare(CmpOp::Gt, IRExpr::var("x"), IRExpr::int(40)),
+            vec![IRStmt::print(vec![IRExpr::var("x")])],
+        ),
+    ];
+
+    let context = Context::create();
+    let compiler = codegen::Compiler::new(&context);
+    let llvm_ir = compiler.compile_program(&program).unwrap();
+    assert!(llvm_ir.contains("define i32 @main()"));
+    assert!(llvm_ir.contains("i64 49"), "square(7) should fold to 49");
+}
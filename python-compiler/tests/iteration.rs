@@ -0,0 +1,269 @@
+mod common;
+
+use common::{compile_to_ir, run_program, run_program_with_status};
+
+#[test]
+fn test_for_over_string_binds_characters() {
+    let source = r#"
+for c in "abc":
+    print(c)
+"#;
+    assert_eq!(run_program(source), "a\nb\nc\n");
+}
+
+#[test]
+fn test_for_over_string_variable_with_break() {
+    let source = r#"
+word = "hello"
+count = 0
+for c in word:
+    if count == 2:
+        break
+    print(c)
+    count = count + 1
+"#;
+    assert_eq!(run_program(source), "h\ne\n");
+}
+
+#[test]
+fn test_for_over_list() {
+    let source = r#"
+total = 0
+for x in [1, 2, 3, 4]:
+    total = total + x
+print(total)
+"#;
+    assert_eq!(run_program(source), "10\n");
+}
+
+#[test]
+fn test_for_else_skipped_on_break() {
+    let source = r#"
+for i in range(3):
+    if i == 1:
+        break
+else:
+    print("done")
+print(i)
+"#;
+    assert_eq!(run_program(source), "1\n");
+}
+
+#[test]
+fn test_for_else_runs_on_normal_exit() {
+    let source = r#"
+for i in range(3):
+    if i == 1:
+        continue
+else:
+    print("done")
+for c in "ab":
+    print(c)
+else:
+    print("end")
+"#;
+    assert_eq!(run_program(source), "done\na\nb\nend\n");
+}
+
+#[test]
+fn test_while_else_with_break_and_continue() {
+    let source = r#"
+n = 0
+while n < 5:
+    n = n + 1
+    if n == 2:
+        continue
+    if n == 4:
+        break
+else:
+    print("no break")
+print(n)
+m = 0
+while m < 2:
+    m = m + 1
+else:
+    print("finished")
+"#;
+    assert_eq!(run_program(source), "4\nfinished\n");
+}
+
+#[test]
+fn test_assignment_before_break_is_kept() {
+    // The store ahead of `break` must land before the branch out of the loop, also
+    // inside a function where the variable lives in a local slot
+    let source = r#"
+found = -1
+i = 0
+while i < 10:
+    if i == 5:
+        found = i
+        break
+    i += 1
+print(found)
+
+def first_over(xs, limit):
+    result = None
+    for x in xs:
+        if x > limit:
+            result = x
+            break
+    return result
+
+print(first_over([1, 7, 9], 5), first_over([1, 2], 5))
+"#;
+    assert_eq!(run_program(source), "5\n7 None\n");
+}
+
+#[test]
+fn test_range_step_with_continue() {
+    let source = r#"
+for i in range(0, 10, 2):
+    if i == 4:
+        continue
+    print(i)
+for j in range(10, 0, -3):
+    if j == 7:
+        continue
+    print(j)
+"#;
+    assert_eq!(run_program(source), "0\n2\n6\n8\n10\n4\n1\n");
+}
+
+#[test]
+fn test_range_step_of_zero_raises_value_error() {
+    let (stdout, stderr, status) =
+        run_program_with_status("for i in range(0, 3, 0):\n    print(i)\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "ValueError: range() arg 3 must not be zero\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_range_with_non_integer_arguments_raises_type_error() {
+    for source in [
+        "for i in range(\"a\"):\n    print(i)\n",
+        "for i in range(0, 3, 1.5):\n    print(i)\n",
+        "print([i for i in range(0.5, 3)])\n",
+    ] {
+        let (stdout, stderr, status) = run_program_with_status(source);
+        assert_eq!(stdout, "");
+        assert_eq!(
+            stderr,
+            "TypeError: object cannot be interpreted as an integer\n"
+        );
+        assert_eq!(status, Some(1));
+    }
+}
+
+#[test]
+fn test_reassigning_range_variable_does_not_change_iterations() {
+    // Python rebinds the loop variable from the range on every iteration
+    let source = r#"
+count = 0
+for i in range(5):
+    i = i + 10
+    print(i)
+    count = count + 1
+print(count)
+for j in range(6, 0, -2):
+    j = 0
+    print(j)
+print(j)
+"#;
+    assert_eq!(run_program(source), "10\n11\n12\n13\n14\n5\n0\n0\n0\n0\n");
+}
+
+#[test]
+fn test_pass_as_whole_function_and_loop_body() {
+    let source = r#"
+def nothing():
+    pass
+
+def maybe(x):
+    if x:
+        pass
+    else:
+        pass
+
+print(nothing())
+print(maybe(1))
+i = 0
+while (i := i + 1) < 3:
+    pass
+for j in range(4):
+    pass
+print(i, j)
+"#;
+    assert_eq!(run_program(source), "None\nNone\n3 3\n");
+}
+
+#[test]
+fn test_infinite_loop_with_pass_body_compiles() {
+    // The pass-only body must still branch back to the condition
+    let ir = compile_to_ir("while True:\n    pass\n");
+    assert!(ir.contains("define i32 @main()"));
+}
+
+#[test]
+fn test_break_after_comprehension_leaves_enclosing_loop() {
+    // A comprehension's implicit loop is not a break/continue target
+    let source = r#"
+for i in range(5):
+    evens = [j for j in range(i) if j % 2 == 0]
+    if len(evens) == 2:
+        break
+    print(i, evens)
+print("stopped at", i)
+n = 0
+while len([k for k in range(n)]) < 10:
+    n = n + 1
+    if n % 2 == 1:
+        continue
+    if n == 4:
+        break
+    print(n)
+print(n)
+"#;
+    assert_eq!(
+        run_program(source),
+        "0 []\n1 [0]\n2 [0]\nstopped at 3\n2\n4\n"
+    );
+}
+
+#[test]
+fn test_for_over_dict_and_set_binds_keys() {
+    let source = r#"
+ages = {"ann": 31, "bob": 27}
+for name in ages:
+    print(name, ages[name])
+total = 0
+for n in {1, 2, 2, 3}:
+    total += n
+print(total)
+"#;
+    assert_eq!(run_program(source), "ann 31\nbob 27\n6\n");
+}
+
+#[test]
+fn test_for_over_non_iterable_raises_type_error() {
+    let source = r#"
+try:
+    for x in 5:
+        print(x)
+except TypeError:
+    print("caught")
+for x in None:
+    print(x)
+"#;
+    let (stdout, stderr, status) = run_program_with_status(source);
+    assert_eq!(stdout, "caught\n");
+    assert_eq!(stderr, "TypeError: object is not iterable\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_for_over_string_does_not_allocate_per_character() {
+    // Characters come from a constant table rather than a malloc per iteration
+    let ir = compile_to_ir("for c in \"abc\":\n    print(c)\n");
+    assert!(ir.contains("one_char_strings"));
+}
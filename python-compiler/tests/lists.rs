@@ -1,3 +1,6 @@
+mod common;
+
+use common::{compile_to_ir_with, run_program, run_program_with, run_program_with_status};
 use inkwell::context::Context;
 use python_compiler::*;
 
@@ -128,3 +131,423 @@ print(x)
     let llvm_ir = compiler.compile_program(&ir).unwrap();
     insta::assert_snapshot!(llvm_ir);
 }
+
+#[test]
+fn test_nested_index_assignment_lowering() {
+    let source = "grid[1][2] = 5\n";
+    let ast = parser::parse_program(source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+
+    match &ir[0] {
+        ast::IRStmt::IndexAssign { list, index, value } => {
+            assert_eq!(
+                *list,
+                ast::IRExpr::Index {
+                    list: Box::new(ast::IRExpr::Variable("grid".to_string())),
+                    index: Box::new(ast::IRExpr::Constant(1)),
+                }
+            );
+            assert_eq!(*index, ast::IRExpr::Constant(2));
+            assert_eq!(*value, ast::IRExpr::Constant(5));
+        }
+        _ => panic!("Expected IndexAssign statement"),
+    }
+}
+
+#[test]
+fn test_index_assignment() {
+    let source = r#"
+x = [1, 2, 3]
+x[1] = 20
+print(x[0])
+print(x[1])
+print(x[2])
+"#;
+    assert_eq!(run_program(source), "1\n20\n3\n");
+}
+
+#[test]
+fn test_nested_index_assignment_grid() {
+    let source = r#"
+grid = [[0, 0, 0], [0, 0, 0]]
+for i in range(2):
+    for j in range(3):
+        grid[i][j] = i * 3 + j
+row = grid[1]
+print(row[0])
+print(grid[0][2])
+print(grid[1][2])
+"#;
+    assert_eq!(run_program(source), "3\n2\n5\n");
+}
+
+#[test]
+fn test_print_list() {
+    let source = r#"
+print([1, 2, 3])
+print([])
+print([[1, 2], [3]])
+"#;
+    assert_eq!(run_program(source), "[1, 2, 3]\n[]\n[[1, 2], [3]]\n");
+}
+
+#[test]
+fn test_reversed_list() {
+    let source = r#"
+x = [1, 2, 3]
+print(reversed(x))
+print(x)
+for v in reversed(x):
+    print(v)
+"#;
+    assert_eq!(run_program(source), "[3, 2, 1]\n[1, 2, 3]\n3\n2\n1\n");
+}
+
+#[test]
+fn test_sorted_list() {
+    let source = r#"
+x = [3, 1, 2]
+print(sorted(x))
+print(x)
+print(sorted([2.5, -1, 0, 7, 2]))
+print(sorted([]))
+"#;
+    assert_eq!(
+        run_program(source),
+        "[1, 2, 3]\n[3, 1, 2]\n[-1, 0, 2, 2.5, 7]\n[]\n"
+    );
+}
+
+#[test]
+fn test_list_concatenation() {
+    let source = r#"
+x = [1, 2] + [3]
+print(len(x))
+print(x)
+a = [1, 2]
+b = a + [3, 4]
+print(len(b), b)
+print(a)
+y = []
+for i in range(3):
+    y = y + [i]
+print(y)
+"#;
+    assert_eq!(
+        run_program(source),
+        "3\n[1, 2, 3]\n4 [1, 2, 3, 4]\n[1, 2]\n[0, 1, 2]\n"
+    );
+}
+
+#[test]
+fn test_zip_pairs_elements() {
+    let source = r#"
+for a, b in zip([1, 2], [3, 4]):
+    print(a + b)
+print(zip([1, 2, 3], ["x", "y"]))
+for a, b in zip([], [1]):
+    print(a, b)
+else:
+    print("done")
+"#;
+    assert_eq!(run_program(source), "4\n6\n[(1, 'x'), (2, 'y')]\ndone\n");
+}
+
+#[test]
+fn test_list_repetition() {
+    let source = r#"
+print([0] * 3)
+print(2 * [1, 2])
+print([1] * 0, [1] * -2)
+"#;
+    assert_eq!(run_program(source), "[0, 0, 0]\n[1, 2, 1, 2]\n[] []\n");
+}
+
+#[test]
+fn test_list_comprehension() {
+    let source = r#"
+print([x * x for x in range(1, 6) if x % 2 == 1])
+print([c for c in "abc"])
+x = 10
+print([x + 1 for x in [1, 2, 3]], x)
+print([[i * j for j in range(3)] for i in range(3)])
+def evens(xs):
+    return [v for v in xs if v % 2 == 0 if v > 2]
+print(evens([1, 2, 3, 4, 5, 6]))
+print([v for v in range(5, 2)])
+"#;
+    assert_eq!(
+        run_program(source),
+        "[1, 9, 25]\n['a', 'b', 'c']\n[2, 3, 4] 10\n[[0, 0, 0], [0, 1, 2], [0, 2, 4]]\n[4, 6]\n[]\n"
+    );
+}
+
+#[test]
+fn test_membership_in_comprehension_condition() {
+    // The membership scan is a loop of its own, nested in the comprehension's loop
+    let source = r#"
+print([x for x in range(10) if x in [2, 4, 6]])
+wanted = "aeiou"
+print([c for c in "comprehension" if c not in wanted if c in "mn"])
+print([[y for y in row if y in [1, 4]] for row in [[1, 2, 1], [3, 4]]])
+"#;
+    assert_eq!(
+        run_program(source),
+        "[2, 4, 6]\n['m', 'n', 'n']\n[[1, 1], [4]]\n"
+    );
+}
+
+#[test]
+fn test_comprehension_rows_are_fresh_but_repeated_rows_are_shared() {
+    let source = r#"
+grid = [[0] * 3 for _ in range(2)]
+grid[0][1] = 5
+print(grid)
+shared = [[0] * 3] * 2
+shared[0][1] = 5
+print(shared)
+"#;
+    assert_eq!(
+        run_program(source),
+        "[[0, 5, 0], [0, 0, 0]]\n[[0, 5, 0], [0, 5, 0]]\n"
+    );
+}
+
+#[test]
+fn test_map_and_filter_builtins() {
+    let source = r#"
+print(map(abs, [-1, -2]))
+print(filter(bool, [0, 1, 0, 2]))
+print(map(abs, [-1.5, True]))
+print(filter(bool, []))
+"#;
+    assert_eq!(run_program(source), "[1, 2]\n[1, 2]\n[1.5, 1]\n[]\n");
+}
+
+#[test]
+fn test_map_and_filter_user_functions() {
+    let source = r#"
+def inc(x):
+    return x + 1
+def scale(x, k=10):
+    return x * k
+def even(n):
+    return n % 2 == 0
+xs = [1, 2, 3, 4]
+print(map(inc, xs))
+print(map(scale, xs))
+print(filter(even, xs))
+print(len(filter(even, [1, 3])))
+for v in map(inc, [10, 20]):
+    print(v)
+"#;
+    assert_eq!(
+        run_program(source),
+        "[2, 3, 4, 5]\n[10, 20, 30, 40]\n[2, 4]\n0\n11\n21\n"
+    );
+}
+
+#[test]
+fn test_print_list_of_strings_uses_repr() {
+    let source = r#"
+print(["a", "b"])
+print("a")
+print([1, ["x"], "y z"])
+"#;
+    assert_eq!(run_program(source), "['a', 'b']\na\n[1, ['x'], 'y z']\n");
+}
+
+#[test]
+fn test_index_list_passed_as_parameter() {
+    let source = r#"
+def first(xs):
+    return xs[0]
+def total(xs):
+    s = 0
+    for x in xs:
+        s = s + x
+    return s
+nums = [10, 20]
+print(first(nums))
+print(first([10, 20]))
+print(total(nums) + len(nums))
+"#;
+    assert_eq!(run_program(source), "10\n10\n32\n");
+}
+
+#[test]
+fn test_returned_list_stays_valid() {
+    let source = r#"
+def make():
+    return [1, 2, 3]
+def pair(x):
+    ys = [x, x * 2]
+    return ys
+print(make()[1])
+a = make()
+b = make()
+print(a[2] + b[0])
+print(pair(5)[1])
+print(len(make()))
+"#;
+    assert_eq!(run_program(source), "2\n4\n10\n3\n");
+}
+
+#[test]
+fn test_comparisons_as_list_elements() {
+    let source = r#"
+print([1 < 2, 3 < 2])
+a = 1
+b = 1
+flags = [a == b, a != b]
+print(flags[0])
+print(flags)
+"#;
+    assert_eq!(run_program(source), "[True, False]\nTrue\n[True, False]\n");
+}
+
+#[test]
+fn test_negative_index_read_and_assignment() {
+    let source = r#"
+x = [1, 2, 3]
+x[-1] = 99
+print(x[2], x[-3], (4, 5)[-1])
+def bump_last(xs):
+    xs[-1] = xs[-1] + 1
+bump_last(x)
+print(x)
+try:
+    x[-4] = 0
+except IndexError:
+    print("caught")
+print(x[3])
+"#;
+    let (stdout, stderr, status) = run_program_with(source, |c| c);
+    assert_eq!(stdout, "99 1 5\n[1, 2, 100]\ncaught\n");
+    assert_eq!(stderr, "IndexError: list index out of range\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_bools_in_lists_print_apart_from_ints() {
+    let source = r#"
+print([True, False, 1, 0])
+print([(False, 1), [True]])
+"#;
+    let expected = "[True, False, 1, 0]\n[(False, 1), [True]]\n";
+    assert_eq!(run_program(source), expected);
+    let (stdout, _, _) = run_program_with(source, |c| c.with_buffered_list_printing(true));
+    assert_eq!(stdout, expected);
+}
+
+#[test]
+fn test_assigned_list_shares_storage() {
+    // Lists are reference values: assignment and argument passing alias, not copy
+    let source = r#"
+a = [1, 2, 3]
+b = a
+b[0] = 99
+print(a[0])
+def set_second(xs):
+    xs[1] = 7
+set_second(a)
+print(b[1])
+print(a)
+"#;
+    assert_eq!(run_program(source), "99\n7\n[99, 7, 3]\n");
+}
+
+#[test]
+fn test_buffered_list_printing_matches_default() {
+    let source = r#"
+xs = [1, 2.5, "a", True, None, [3, ("b",)], (4, 5)]
+print(xs)
+print((7,), [], ())
+print("end")
+"#;
+    let expected = "[1, 2.5, 'a', True, None, [3, ('b',)], (4, 5)]\n(7,) [] ()\nend\n";
+    assert_eq!(run_program(source), expected);
+    let (stdout, _, status) = run_program_with(source, |c| c.with_buffered_list_printing(true));
+    assert_eq!(stdout, expected);
+    assert_eq!(status, Some(0));
+
+    let llvm_ir = compile_to_ir_with(source, |c| c.with_buffered_list_printing(true));
+    assert!(llvm_ir.contains("@format_list"), "{}", llvm_ir);
+    assert!(!llvm_ir.contains("@print_list"), "{}", llvm_ir);
+}
+
+#[test]
+fn test_buffered_printing_of_large_list() {
+    // The text buffer starts empty and is regrown many times along the way
+    let elements: Vec<String> = (0..1000).map(|i| (i * 1001).to_string()).collect();
+    let literal = format!("[{}]", elements.join(", "));
+    let source = format!("xs = {}\nprint(len(xs))\nprint(xs)\n", literal);
+    let (stdout, _, status) = run_program_with(&source, |c| c.with_buffered_list_printing(true));
+    assert_eq!(stdout, format!("1000\n{}\n", literal));
+    assert_eq!(status, Some(0));
+}
+
+#[test]
+fn test_sum_widens_to_float_only_when_needed() {
+    let source = r#"
+xs = [1, 2.5, 3]
+print(sum(xs))
+print(xs)
+print(sum([1, 2, 3]))
+print(sum((True, 2)))
+print(sum([]))
+"#;
+    assert_eq!(run_program(source), "6.5\n[1, 2.5, 3]\n6\n3\n0\n");
+}
+
+#[test]
+fn test_sum_of_non_numbers_raises_type_error() {
+    let (stdout, stderr, status) = run_program_with_status("print(sum([1, \"a\"]))\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "TypeError: sum() can only add numbers\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_trailing_commas_add_no_elements() {
+    let source = r#"
+def count(a, b, c=0):
+    return a + b + c
+
+print(count(
+    1,
+    2,
+))
+xs = [
+    1,
+    2,
+]
+t = (1, 2,)
+one = (5,)
+print(len(xs), len(t), len(one))
+print(xs, t, one)
+"#;
+    assert_eq!(run_program(source), "3\n2 2 1\n[1, 2] (1, 2) (5,)\n");
+}
+
+#[test]
+fn test_index_finds_first_match() {
+    let source = r#"
+print([10, 20, 30].index(20))
+print([1, 2, 1].index(1), (1, "a").index("a"), "banana".index("na"))
+"#;
+    assert_eq!(run_program(source), "1\n0 1 2\n");
+}
+
+#[test]
+fn test_index_of_missing_value_raises() {
+    let (stdout, stderr, status) = run_program_with_status("print([10, 20, 30].index(99))\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "ValueError: list.index(x): x not in list\n");
+    assert_eq!(status, Some(1));
+
+    let (stdout, stderr, status) = run_program_with_status("print(\"abc\".index(\"z\"))\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "ValueError: substring not found\n");
+    assert_eq!(status, Some(1));
+}
@@ -0,0 +1,319 @@
+mod common;
+
+use common::{run_program, run_program_with_status};
+
+#[test]
+fn test_floor_division() {
+    let source = r#"
+print(7 // 2)
+print(-7 // 2)
+print(7.5 // 2)
+"#;
+    assert_eq!(run_program(source), "3\n-4\n3.0\n");
+}
+
+#[test]
+fn test_floor_division_and_modulo_round_toward_negative_infinity() {
+    // The remainder takes the divisor's sign, and a == b * (a // b) + a % b
+    let source = r#"
+a = -7
+b = 2
+print(a // b, a % b, 7 // -2, 7 % -2, -7 // -2, -7 % -2)
+print(-7.5 // 2, -7.5 % 2, 6.0 % -3, -0.5 // -2, 1.0 // 0.1)
+x = 10
+x //= -3
+y = -10
+y %= 3
+print(x, y, -140737488355327 // 2, -140737488355327 % 10)
+for i in range(5, -6, -4):
+    print(i)
+"#;
+    assert_eq!(
+        run_program(source),
+        "-4 1 -4 -1 3 -1\n\
+         -4.0 0.5 -0.0 0.0 9.0\n\
+         -4 2 -70368744177664 3\n\
+         5\n1\n-3\n"
+    );
+}
+
+#[test]
+fn test_int_truncates_where_floor_division_floors() {
+    // int() rounds toward zero by design, so the two differ on negative values
+    let source = r#"
+print(int(-2.9), -2.9 // 1, int(2.9), 2.9 // 1)
+print(int(-7 / 2), -7 // 2, int(-0.5), int(7), int(True), int())
+print(type(int(3.5)), int(-140737488355328.0))
+"#;
+    assert_eq!(
+        run_program(source),
+        "-2 -3.0 2 2.0\n\
+         -3 -4 0 7 1 0\n\
+         <class 'int'> -140737488355328\n"
+    );
+
+    for (source, error) in [
+        (
+            "print(int(\"5\"))\n",
+            "TypeError: int() argument must be a number\n",
+        ),
+        (
+            "print(int(1e308 * 10))\n",
+            "OverflowError: cannot convert float infinity to integer\n",
+        ),
+        (
+            "big = 1e308 * 10\nprint(int(big - big))\n",
+            "ValueError: cannot convert float NaN to integer\n",
+        ),
+    ] {
+        let (stdout, stderr, status) = run_program_with_status(source);
+        assert_eq!(stdout, "");
+        assert_eq!(stderr, error);
+        assert_eq!(status, Some(1));
+    }
+}
+
+#[test]
+fn test_scientific_notation_and_separators() {
+    let source = r#"
+print(1e3)
+print(2.5e-2)
+print(1_000.5)
+print(1e3 + 1)
+"#;
+    assert_eq!(run_program(source), "1000.0\n0.025\n1000.5\n1001.0\n");
+}
+
+#[test]
+fn test_float_printing_matches_python_repr() {
+    let source = r#"
+print(-0.0)
+print(1e300)
+print(1e-10)
+print(0.1 + 0.2, 1 / 3)
+print(1e16, 1e15, 0.0001, 0.00001)
+print(-1.5e-7, [2.0, -0.5])
+"#;
+    assert_eq!(
+        run_program(source),
+        "-0.0\n1e+300\n1e-10\n0.30000000000000004 0.3333333333333333\n\
+         1e+16 1000000000000000.0 0.0001 1e-05\n-1.5e-07 [2.0, -0.5]\n"
+    );
+}
+
+#[test]
+fn test_computed_nan_stays_a_float() {
+    // inf - inf yields a quiet NaN whose bits would otherwise read as a tagged int 0
+    let source = r#"
+x = 1e308 * 10.0
+y = x - x
+print(y)
+print(y == y)
+print(y + 1.5)
+print(y < 1.0)
+"#;
+    assert_eq!(run_program(source), "nan\nFalse\nnan\nFalse\n");
+}
+
+#[test]
+fn test_nan_comparisons_follow_ieee() {
+    // Every comparison with NaN is False except !=, and NaN is truthy
+    let source = r#"
+x = 1e400 - 1e400
+print(x == x, x != x, 1 != x)
+print(x < 1, x > 1, x <= x, x >= 0.0)
+if x:
+    print("truthy")
+print(not x)
+"#;
+    assert_eq!(
+        run_program(source),
+        "False True True\nFalse False False False\ntruthy\nFalse\n"
+    );
+}
+
+#[test]
+fn test_signed_zeros_compare_equal() {
+    // -0.0 has only the sign bit set, which must not read as a tagged value
+    let source = r#"
+z = -0.0
+print(0.0 == -0.0, z == 0, z != 0.0, z < 0.0)
+print(z, 0.0 in [z], not z)
+"#;
+    assert_eq!(
+        run_program(source),
+        "True True False False\n-0.0 True True\n"
+    );
+}
+
+#[test]
+fn test_true_division_always_yields_float() {
+    let source = r#"
+print(4 / 2)
+print(7 / 2)
+print(-6 / 3)
+"#;
+    assert_eq!(run_program(source), "2.0\n3.5\n-2.0\n");
+}
+
+#[test]
+fn test_augmented_division_promotes_to_float() {
+    let source = r#"
+x = 4
+x /= 2
+print(x)
+y = 7
+y //= 2
+print(y)
+z = 7.5
+z //= 2
+print(z)
+"#;
+    assert_eq!(run_program(source), "2.0\n3\n3.0\n");
+}
+
+#[test]
+fn test_division_in_function_returns_float() {
+    let source = r#"
+def half(x):
+    return x / 2
+
+def ratio(a, b):
+    result = a
+    result /= b
+    return result
+
+print(half(5))
+print(half(4))
+print([half(4), ratio(9, 3)])
+"#;
+    assert_eq!(run_program(source), "2.5\n2.0\n[2.0, 3.0]\n");
+}
+
+#[test]
+fn test_int_arithmetic_is_exact_at_48_bit_bounds() {
+    // Results are compared rather than printed, so only the arithmetic is checked
+    let source = r#"
+lo = -140737488355328
+hi = 140737488355327
+print(lo + 1 == -140737488355327)
+print(hi - 1 == 140737488355326)
+print(lo + hi == -1)
+print(hi + 1 == lo)
+print(hi * hi == 1)
+"#;
+    assert_eq!(run_program(source), "True\nTrue\nTrue\nTrue\nTrue\n");
+}
+
+#[test]
+fn test_power() {
+    let source = r#"
+print(2 ** 10)
+print(-2 ** 2)
+print(3 ** 2.0)
+x = 2
+x **= 3
+print(x)
+"#;
+    assert_eq!(run_program(source), "1024\n-4\n9.0\n8\n");
+}
+
+#[test]
+fn test_negative_exponent_yields_float() {
+    let source = r#"
+print(2 ** -2)
+e = -1
+print(10 ** e)
+"#;
+    assert_eq!(run_program(source), "0.25\n0.1\n");
+
+    let (stdout, stderr, status) = run_program_with_status("print(0 ** -1)\n");
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "ZeroDivisionError: 0.0 cannot be raised to a negative power\n"
+    );
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_isclose_compares_floats_with_tolerance() {
+    let source = r#"
+print(0.1 + 0.2 == 0.3, isclose(0.1 + 0.2, 0.3))
+print(isclose(1, 1.0), isclose(1.0, 1.001), isclose(0.0, 1e-300))
+inf = 1e308 * 10
+print(isclose(inf, inf), isclose(inf - inf, inf - inf))
+"#;
+    assert_eq!(
+        run_program(source),
+        "False True\nTrue False False\nTrue False\n"
+    );
+}
+
+#[test]
+fn test_integral_float_prints_with_point_zero() {
+    let source = r#"
+print(2.0)
+print(2)
+print(4 / 2, 100.0, -3.0)
+print(f"{6 / 3}", [2.0])
+"#;
+    assert_eq!(run_program(source), "2.0\n2\n2.0 100.0 -3.0\n2.0 [2.0]\n");
+}
+
+#[test]
+fn test_exact_powers_stay_ints() {
+    let source = r#"
+a = 3
+print(2 ** 10, type(2 ** 10))
+print(a ** 2, type(a ** 0))
+print(type(2 ** -1), type(2.0 ** 3), type(a ** 2.0))
+print(type(True), type("a"), type([]), type(None), type(()), type({}))
+"#;
+    assert_eq!(
+        run_program(source),
+        "1024 <class 'int'>\n9 <class 'int'>\n\
+         <class 'float'> <class 'float'> <class 'float'>\n\
+         <class 'bool'> <class 'str'> <class 'list'> <class 'NoneType'> <class 'tuple'> <class 'dict'>\n"
+    );
+}
+
+#[test]
+fn test_numeric_builtins_keep_result_types() {
+    let source = r#"
+print(sum([1, 2, 3]), sum([1.0, 2]), type(sum([1, 2])), type(sum([1, 2.0])))
+print(abs(-3), abs(-2.5), abs(True), abs(-0.0), type(abs(-3)), type(abs(-3.0)))
+print(max(1, 2.0), max(1, 1.0), min(1.0, 1), min(3, 1, 2), type(max(1, 2.0)))
+print(max([1, 2.5]), min((3, 1.5)), max(True, 0), type(min([2, 1])))
+print(max("apple", "pear"), min([(2, "b"), (1, "z")]))
+"#;
+    assert_eq!(
+        run_program(source),
+        "6 3.0 <class 'int'> <class 'float'>\n\
+         3 2.5 1 0.0 <class 'int'> <class 'float'>\n\
+         2.0 1 1.0 1 <class 'float'>\n\
+         2.5 1.5 True <class 'int'>\n\
+         pear (1, 'z')\n"
+    );
+}
+
+#[test]
+fn test_min_max_errors() {
+    let (stdout, stderr, status) = run_program_with_status("print(max([]))\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "ValueError: max() iterable argument is empty\n");
+    assert_eq!(status, Some(1));
+
+    let (stdout, stderr, status) = run_program_with_status("print(min(1, \"a\"))\n");
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "TypeError: '<' not supported between instances of different types\n"
+    );
+    assert_eq!(status, Some(1));
+
+    let (stdout, stderr, status) = run_program_with_status("print(abs(\"a\"))\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "TypeError: bad operand type for abs()\n");
+    assert_eq!(status, Some(1));
+}
@@ -0,0 +1,143 @@
+mod common;
+
+use common::run_program_with;
+use python_compiler::compiler::values::PayloadWidth;
+
+#[test]
+fn test_payload_width_masks() {
+    assert_eq!(PayloadWidth::default(), PayloadWidth::Bits48);
+    assert_eq!(PayloadWidth::Bits48.mask(), 0x0000_FFFF_FFFF_FFFF);
+    assert_eq!(PayloadWidth::Bits32.mask(), 0x0000_0000_FFFF_FFFF);
+    // The payload always stays below the tag in bits 48-50
+    for width in [PayloadWidth::Bits32, PayloadWidth::Bits48] {
+        assert_eq!(width.mask() & 0x0007_0000_0000_0000, 0);
+        assert_eq!(width.mask().count_ones(), width.bits());
+    }
+}
+
+#[test]
+fn test_32_bit_payload_round_trips_and_wraps() {
+    // Ints stay exact inside the 32-bit range (negatives sign-extend back) and wrap
+    // outside it; floats and bools do not use the payload width
+    let source = r#"
+big = 2147483647
+print(big)
+print(-5 * 3)
+print(big + 1)
+print(big + 1 < 0)
+print(1.5 * 2)
+print(True)
+"#;
+    let (stdout, _, status) =
+        run_program_with(source, |c| c.with_payload_width(PayloadWidth::Bits32));
+    assert_eq!(stdout, "2147483647\n-15\n-2147483648\nTrue\n3.0\nTrue\n");
+    assert_eq!(status, Some(0));
+
+    let (stdout, _, _) = run_program_with("print(2147483647 + 1 > 0)\n", |c| c);
+    assert_eq!(stdout, "True\n");
+}
+
+#[test]
+fn test_32_bit_payload_keeps_heap_pointers() {
+    // Only ints narrow: strings, lists and dicts live on a 64-bit heap and keep
+    // their full pointers, including through a concatenation and a for loop
+    let source = r#"
+s = "ab"
+t = s + "cd"
+print(t)
+items = [1, -2, "x", t]
+print(items)
+for item in items:
+    print(item)
+d = {"k": [3, 4]}
+print(d["k"][1])
+print(len(t + t))
+"#;
+    let (stdout, stderr, status) =
+        run_program_with(source, |c| c.with_payload_width(PayloadWidth::Bits32));
+    assert_eq!(stdout, "abcd\n[1, -2, 'x', 'abcd']\n1\n-2\nx\nabcd\n4\n8\n");
+    assert_eq!(stderr, "");
+    assert_eq!(status, Some(0));
+}
+
+#[test]
+fn test_32_bit_payload_checked_arithmetic() {
+    let source = r#"
+big = 2147483647
+print(big - 1 + 1)
+print(big + 1)
+"#;
+    let (stdout, stderr, status) = run_program_with(source, |c| {
+        c.with_payload_width(PayloadWidth::Bits32)
+            .with_checked_arithmetic(true)
+    });
+    assert_eq!(stdout, "2147483647\n");
+    assert_eq!(stderr, "OverflowError: integer overflow\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_48_bit_boundary_ints_print_exactly() {
+    // Printing reads the sign-extended payload, never a rounded float
+    let source = r#"
+print(140737488355327)
+print(-140737488355328)
+x = 140737488355326
+print(x + 1)
+print([x + 1], f"{x + 1}", -x - 2)
+"#;
+    let (stdout, _, status) = run_program_with(source, |c| c);
+    assert_eq!(
+        stdout,
+        "140737488355327\n-140737488355328\n140737488355327\n\
+         [140737488355327] 140737488355327 -140737488355328\n"
+    );
+    assert_eq!(status, Some(0));
+}
+
+#[test]
+fn test_48_bit_minimum_round_trips_through_assignment() {
+    // The literal is folded to a negative constant, so it fits the payload exactly
+    // and checked arithmetic only trips once a result leaves the range
+    let source = r#"
+low = -140737488355328
+copy = low
+print(copy)
+print(copy == -140737488355327 - 1, copy < 0, [copy], str(copy))
+print(copy + 1)
+print(copy - 1)
+"#;
+    let (stdout, stderr, status) = run_program_with(source, |c| c.with_checked_arithmetic(true));
+    assert_eq!(
+        stdout,
+        "-140737488355328\nTrue True [-140737488355328] -140737488355328\n-140737488355327\n"
+    );
+    assert_eq!(stderr, "OverflowError: integer overflow\n");
+    assert_eq!(status, Some(1));
+
+    let (stdout, _, _) = run_program_with("low = -2147483648\nprint(low, low + 1)\n", |c| {
+        c.with_payload_width(PayloadWidth::Bits32)
+    });
+    assert_eq!(stdout, "-2147483648 -2147483647\n");
+}
+
+#[test]
+fn test_checked_power_overflow() {
+    // Powers are checked against the payload range like +, - and *; float and
+    // negative-exponent powers are floats and never overflow
+    let source = r#"
+print(2 ** 46, (-2) ** 47, 2.0 ** 60, 2 ** -1)
+try:
+    print(2 ** 47)
+except OverflowError:
+    print("caught")
+print(2 ** 60)
+"#;
+    let (stdout, stderr, status) = run_program_with(source, |c| c.with_checked_arithmetic(true));
+    assert_eq!(
+        stdout,
+        "70368744177664 -140737488355328 1.152921504606847e+18 0.5\ncaught\n"
+    );
+    assert_eq!(stderr, "OverflowError: integer overflow\n");
+    assert_eq!(status, Some(1));
+}
@@ -0,0 +1,204 @@
+mod common;
+
+use common::run_ir;
+use inkwell::context::Context;
+use python_compiler::compiler::session::CompilerSession;
+use python_compiler::*;
+
+fn lower(source: &str) -> Vec<ast::IRStmt> {
+    let ast = parser::parse_program(source).unwrap();
+    lowering::lower_program(&ast).unwrap()
+}
+
+fn small_program(n: usize) -> Vec<ast::IRStmt> {
+    lower(&format!(
+        "def f(x):\n    return x * 3 + 1\n\nprint(f({}), \"done\")\n",
+        n
+    ))
+}
+
+#[test]
+fn test_session_matches_fresh_compilers() {
+    let mut session = CompilerSession::new();
+    let mut unoptimized = CompilerSession::new().with_compiler_options(|c| {
+        c.with_optimization_level(0)
+            .with_module_name("script")
+            .with_string_input(true)
+    });
+    for n in 0..5 {
+        let program = small_program(n);
+        let context = Context::create();
+        let fresh = codegen::Compiler::new(&context)
+            .compile_program(&program)
+            .unwrap();
+        assert_eq!(session.compile_program(&program).unwrap(), fresh);
+
+        let context = Context::create();
+        let fresh = codegen::Compiler::new(&context)
+            .with_optimization_level(0)
+            .with_module_name("script")
+            .with_string_input(true)
+            .compile_program(&program)
+            .unwrap();
+        assert_eq!(unoptimized.compile_program(&program).unwrap(), fresh);
+    }
+}
+
+#[test]
+fn test_session_cache_reuses_functions() {
+    let mut session = CompilerSession::new().with_cache(true);
+    let ir = session
+        .compile_program(&lower(
+            r#"
+def square(x):
+    return x * x
+
+def greet(name):
+    return "hi " + name
+
+print(square(3), greet("a"))
+"#,
+        ))
+        .unwrap();
+    assert_eq!(run_ir(&ir).0, "9 hi a\n");
+    assert_eq!((session.cache_hits(), session.cache_misses()), (0, 2));
+
+    // `square` is linked in from the cache; `twice` is new
+    let ir = session
+        .compile_program(&lower(
+            r#"
+def square(x):
+    return x * x
+
+def twice(x):
+    return square(x) + square(x)
+
+print(twice(4), square(5))
+"#,
+        ))
+        .unwrap();
+    assert_eq!(run_ir(&ir).0, "32 25\n");
+    assert_eq!((session.cache_hits(), session.cache_misses()), (1, 3));
+
+    // A different body under the same name is compiled again
+    let ir = session
+        .compile_program(&lower(
+            "def square(x):\n    return x * x * x\n\nprint(square(2))\n",
+        ))
+        .unwrap();
+    assert_eq!(run_ir(&ir).0, "8\n");
+    assert_eq!((session.cache_hits(), session.cache_misses()), (1, 4));
+    assert_eq!(session.cached_functions(), 4);
+
+    assert_eq!(CompilerSession::new().cached_functions(), 0);
+}
+
+#[test]
+fn test_session_cache_keys_on_callee_signatures() {
+    // `f` is unchanged, but `g` now returns a float, so `f` (specialized to raw ints
+    // the first time) must be compiled again
+    let mut session = CompilerSession::new().with_cache(true);
+    let ir = session
+        .compile_program(&lower(
+            "def f(x):\n    return g(x)\n\ndef g(x):\n    return 7\n\nprint(f(1))\n",
+        ))
+        .unwrap();
+    assert_eq!(run_ir(&ir).0, "7\n");
+
+    let ir = session
+        .compile_program(&lower(
+            "def f(x):\n    return g(x)\n\ndef g(x):\n    return 1.5\n\nprint(f(1))\n",
+        ))
+        .unwrap();
+    assert_eq!(run_ir(&ir).0, "1.5\n");
+    assert_eq!((session.cache_hits(), session.cache_misses()), (0, 4));
+}
+
+#[test]
+fn test_cached_functions_share_runtime_state() {
+    // A cached function still updates the program's globals, and its errors reach the
+    // program's try blocks
+    let bump = r#"
+def bump():
+    global counter
+    counter = counter + 1
+    if counter > 2:
+        raise ValueError("too many")
+    return counter
+"#;
+    let mut session = CompilerSession::new().with_cache(true);
+    let ir = session
+        .compile_program(&lower(&format!("{}\ncounter = 0\nprint(bump())\n", bump)))
+        .unwrap();
+    assert_eq!(run_ir(&ir).0, "1\n");
+
+    let ir = session
+        .compile_program(&lower(&format!(
+            "{}\ncounter = 0\nprint(bump())\nprint(bump())\ntry:\n    bump()\nexcept ValueError:\n    print(\"caught\", counter)\nbump()\n",
+            bump
+        )))
+        .unwrap();
+    assert_eq!(session.cache_hits(), 1);
+    let (stdout, stderr, status) = run_ir(&ir);
+    assert_eq!(stdout, "1\n2\ncaught 3\n");
+    assert_eq!(stderr, "ValueError: too many\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_session_compiles_many_programs() {
+    // 100 programs sharing one function: it is compiled once and linked in 99 times
+    let mut session = CompilerSession::new().with_cache(true);
+    let outputs: Vec<String> = (0..100)
+        .map(|n| session.compile_program(&small_program(n)).unwrap())
+        .collect();
+    assert_eq!(session.cache_misses(), 1);
+    assert_eq!(session.cache_hits(), 99);
+    assert_eq!(session.cached_functions(), 1);
+    assert_eq!(run_ir(&outputs[0]).0, "1 done\n");
+    assert_eq!(run_ir(&outputs[99]).0, "298 done\n");
+}
+
+#[test]
+#[ignore = "timing comparison; run with `cargo test --release --test session -- --ignored --nocapture`"]
+fn test_session_timing_against_fresh_compilers() {
+    // Compiles the same 100 small programs with fresh compilers and with sessions. The
+    // optimizer dominates for programs this small, so the session must at least not
+    // fall far behind (its shared context keeps growing with each module)
+    let programs: Vec<_> = (0..100).map(small_program).collect();
+    let time = |compile: &mut dyn FnMut(&[ast::IRStmt])| {
+        let start = std::time::Instant::now();
+        for program in &programs {
+            compile(program);
+        }
+        start.elapsed()
+    };
+
+    let mut session = CompilerSession::new();
+    let plain = time(&mut |program| {
+        session.compile_program(program).unwrap();
+    });
+    let mut session = CompilerSession::new().with_cache(true);
+    let cached = time(&mut |program| {
+        session.compile_program(program).unwrap();
+    });
+    let fresh = time(&mut |program| {
+        let context = Context::create();
+        codegen::Compiler::new(&context)
+            .compile_program(program)
+            .unwrap();
+    });
+    println!(
+        "100 programs: fresh compilers {:?}, session {:?}, cached session {:?}",
+        fresh, plain, cached
+    );
+    for (name, elapsed) in [("session", plain), ("cached session", cached)] {
+        assert!(
+            elapsed.as_secs_f64() < fresh.as_secs_f64() * 1.5,
+            "the {} ({:?}) fell far behind fresh compilers ({:?})",
+            name,
+            elapsed,
+            fresh
+        );
+    }
+}
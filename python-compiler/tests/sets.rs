@@ -0,0 +1,44 @@
+mod common;
+
+use common::{run_program, run_program_with_status};
+use python_compiler::*;
+
+#[test]
+fn test_set_literal_lowers_to_set() {
+    let source = "s = {1, 2}";
+    let ast = parser::parse_program(source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    match &ir[0] {
+        ast::IRStmt::Assign { value, .. } => assert_eq!(
+            value,
+            &ast::IRExpr::Set(vec![ast::IRExpr::Constant(1), ast::IRExpr::Constant(2)])
+        ),
+        other => panic!("expected an assignment, got {:?}", other),
+    }
+}
+
+#[test]
+fn test_set_dedupes_and_checks_membership() {
+    let source = r#"
+s = {1, 2, 2, 3}
+print(len(s))
+print(2 in s, 5 in s, 5 not in s)
+t = {"a", "b", "a", 1.0, 1, True, (1, 2), (1, 2)}
+print(len(t), "a" in t, (1, 2) in t)
+print(type(s))
+if s:
+    print("non-empty")
+"#;
+    assert_eq!(
+        run_program(source),
+        "3\nTrue False True\n4 True True\n<class 'set'>\nnon-empty\n"
+    );
+}
+
+#[test]
+fn test_printing_a_set_raises() {
+    let (stdout, stderr, status) = run_program_with_status("print({1})\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "TypeError: sets cannot be converted to str\n");
+    assert_eq!(status, Some(1));
+}
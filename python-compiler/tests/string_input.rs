@@ -0,0 +1,151 @@
+mod common;
+
+use common::{
+    run_program_with, run_program_with_input, run_program_with_input_and_status,
+    run_program_with_input_using, run_program_with_status,
+};
+
+#[test]
+fn test_string_input_reads_a_word() {
+    let source = r#"
+word = input()
+print(word)
+print("hello, " + word)
+print(len(word))
+"#;
+    assert_eq!(
+        run_program_with_input_using(source, "world\n", |c| c.with_string_input(true)),
+        "world\nhello, world\n5\n"
+    );
+}
+
+#[test]
+fn test_string_input_reads_whole_lines() {
+    // Spaces are kept, an empty line is the empty string
+    let source = r#"
+a = input()
+b = input()
+c = input()
+print(a)
+print(len(b), len(c))
+"#;
+    assert_eq!(
+        run_program_with_input_using(source, "two words\n\nx", |c| c.with_string_input(true)),
+        "two words\n0 1\n"
+    );
+}
+
+#[test]
+fn test_string_input_at_end_of_input_raises_eof_error() {
+    let source = r#"
+try:
+    line = input()
+except EOFError:
+    print("no input")
+line = input()
+print(line)
+"#;
+    assert_eq!(
+        run_program_with(source, |c| c.with_string_input(true)),
+        (
+            "no input\n".to_string(),
+            "EOFError: EOF when reading a line\n".to_string(),
+            Some(1)
+        )
+    );
+}
+
+#[test]
+fn test_input_reads_numbers_by_default() {
+    let source = r#"
+x = input()
+print(x + 1)
+"#;
+    assert_eq!(run_program_with_input(source, "41\n"), "42\n");
+    assert_eq!(run_program_with_input(source, "5\n"), "6\n");
+    // The last line need not end in a newline
+    assert_eq!(run_program_with_input(source, "5"), "6\n");
+}
+
+#[test]
+fn test_successive_inputs_read_successive_lines() {
+    let source = r#"
+a = input()
+b = input()
+print(a * b, a - b)
+"#;
+    assert_eq!(run_program_with_input(source, "6\n7\n"), "42 -1\n");
+}
+
+#[test]
+fn test_input_number_type_follows_the_text() {
+    let source = r#"
+a = input()
+b = input()
+c = input()
+print(a, b, c)
+print(a // 2, b // 2, c)
+"#;
+    assert_eq!(
+        run_program_with_input(source, "5\n5.5\n-2e1\n"),
+        "5 5.5 -20.0\n2 2.0 -20.0\n"
+    );
+}
+
+#[test]
+fn test_input_that_is_not_a_number_raises_value_error() {
+    let source = r#"
+x = input()
+print(x)
+"#;
+    assert_eq!(
+        run_program_with_input_and_status(source, "abc\n"),
+        (
+            String::new(),
+            "ValueError: input is not a number\n".to_string(),
+            Some(1)
+        )
+    );
+}
+
+#[test]
+fn test_numeric_input_at_end_of_input_raises_eof_error() {
+    let source = r#"
+x = input()
+print(x)
+y = input()
+print(y)
+"#;
+    assert_eq!(
+        run_program_with_input_and_status(source, "5\n"),
+        (
+            "5\n".to_string(),
+            "EOFError: EOF when reading a line\n".to_string(),
+            Some(1)
+        )
+    );
+    // No input at all is the end of input too, not an empty line
+    assert_eq!(
+        run_program_with_status(source),
+        (
+            String::new(),
+            "EOFError: EOF when reading a line\n".to_string(),
+            Some(1)
+        )
+    );
+}
+
+#[test]
+fn test_string_input_compares_by_content() {
+    let source = r#"
+answer = input()
+if answer == "yes":
+    print("confirmed")
+else:
+    print("declined")
+"#;
+    assert_eq!(
+        run_program_with_input_using(source, "yes\n", |c| c.with_string_input(true)),
+        "confirmed\n"
+    );
+}
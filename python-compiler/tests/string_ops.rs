@@ -0,0 +1,377 @@
+mod common;
+
+use common::{compile_to_ir, compile_to_ir_with, run_program, run_program_with_status};
+
+#[test]
+fn test_string_repeat_zero_is_empty() {
+    let source = r#"
+s = "x" * 0
+print(len(s))
+print("[" + s + "]")
+"#;
+    assert_eq!(run_program(source), "0\n[]\n");
+}
+
+#[test]
+fn test_repeat_count_must_be_an_integer() {
+    let source = r#"
+print("ab" * True, [1] * 2)
+try:
+    print([0] * 1.5)
+except TypeError:
+    print("list refused")
+n = 2.5
+print("ab" * n)
+"#;
+    let (stdout, stderr, status) = run_program_with_status(source);
+    assert_eq!(stdout, "ab [1, 1]\nlist refused\n");
+    assert_eq!(
+        stderr,
+        "TypeError: can't multiply sequence by non-int of type 'float'\n"
+    );
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_string_repeat_negative_is_empty() {
+    let source = r#"
+s = "x" * -3
+print(len(s))
+print("[" + s + "]")
+"#;
+    assert_eq!(run_program(source), "0\n[]\n");
+}
+
+#[test]
+fn test_string_repeat_either_operand_order() {
+    let source = r#"
+print("x" * 3)
+print(3 * "x")
+n = 2
+print(n * "ab")
+"#;
+    assert_eq!(run_program(source), "xxx\nxxx\nabab\n");
+}
+
+#[test]
+fn test_augmented_string_concatenation() {
+    let source = r#"
+s = "a"
+s += "b"
+print(s)
+"#;
+    // The original "a" is freed by the string arena exactly once at exit
+    let (stdout, _, status) = run_program_with_status(source);
+    assert_eq!(stdout, "ab\n");
+    assert_eq!(status, Some(0));
+}
+
+#[test]
+fn test_augmented_string_concatenation_in_loop() {
+    let source = r#"
+t = ""
+for i in range(3):
+    t += "xy"
+print(t)
+"#;
+    let (stdout, _, status) = run_program_with_status(source);
+    assert_eq!(stdout, "xyxyxy\n");
+    assert_eq!(status, Some(0));
+}
+
+#[test]
+fn test_folded_literal_print_keeps_percent_signs() {
+    let source = r#"
+print("100%", "done")
+print("%d", "%s")
+"#;
+    assert_eq!(run_program(source), "100% done\n%d %s\n");
+}
+
+#[test]
+fn test_mixed_print_with_literal_arguments() {
+    let source = r#"
+n = 3
+print("got", n, "items at 50%")
+print(n, "left")
+"#;
+    assert_eq!(run_program(source), "got 3 items at 50%\n3 left\n");
+}
+
+#[test]
+fn test_print_sep_keeps_escapes() {
+    let source = r#"
+print("a", "b", sep="\t")
+n = 2
+print(n, n + 1, "x", sep=", ")
+print("100%", n, sep="%")
+"#;
+    assert_eq!(run_program(source), "a\tb\n2, 3, x\n100%%2\n");
+}
+
+#[test]
+fn test_print_end_keyword() {
+    let source = r#"
+for i in range(3):
+    print(i, end=" ")
+print(end="")
+print("done", end="!\n")
+print(1.5, end=None)
+"#;
+    assert_eq!(run_program(source), "0 1 2 done!\n1.5\n");
+}
+
+#[test]
+fn test_print_computed_sep_and_end() {
+    let source = r#"
+s = "-"
+print(1, 2, 3, sep=s)
+def show(a, b, sep, end):
+    print(a, b, sep=sep, end=end)
+show("x", [1, "y"], ", ", "!\n")
+show(1, 2, None, None)
+print(sep=s, end=s + "\n")
+"#;
+    assert_eq!(run_program(source), "1-2-3\nx, [1, 'y']!\n1 2\n-\n");
+
+    let (stdout, stderr, status) =
+        run_program_with_status("def f(e):\n    print(1, end=e)\nf(2)\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "TypeError: end must be None or a string\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_print_to_stderr() {
+    let source = r#"
+import sys
+print("out")
+print("err", [1, 2], file=sys.stderr)
+print("also out", file=sys.stdout)
+"#;
+    // Optimization may turn the fprintf into fputs, so check the unoptimized IR
+    let llvm_ir = compile_to_ir_with(source, |c| c.with_optimization_level(0));
+    assert!(llvm_ir.contains("@stderr = external global ptr"));
+    assert!(llvm_ir.contains("call i32 (ptr, ptr, ...) @fprintf(ptr %stderr"));
+    assert_eq!(run_program(source), "out\nalso out\n");
+}
+
+#[test]
+fn test_len_of_literal_is_folded() {
+    let source = r#"
+print(len("abc"))
+print(len([1, 2, 3, 4]))
+"#;
+    let llvm_ir = compile_to_ir(source);
+    assert!(!llvm_ir.contains("strlen"), "{}", llvm_ir);
+    assert_eq!(run_program(source), "3\n4\n");
+}
+
+#[test]
+fn test_string_built_in_loop_appends_in_place() {
+    let source = r#"
+s = ""
+i = 0
+while i < 10000:
+    s = s + "x"
+    i += 1
+print(len(s))
+"#;
+    let llvm_ir = compile_to_ir(source);
+    assert!(llvm_ir.contains("buffer_append"), "{}", llvm_ir);
+    assert_eq!(run_program(source), "10000\n");
+}
+
+#[test]
+fn test_string_append_does_not_change_shared_copies() {
+    let source = r#"
+t = "ab"
+u = t
+t += "c"
+print(t, u)
+w = "a"
+for k in range(2):
+    v = w
+    w += "b"
+    print(v, w)
+z = "q"
+z = z + z
+z += z
+print(z)
+n = 1
+n += 2.5
+print(n)
+"#;
+    assert_eq!(run_program(source), "abc ab\na ab\nab abb\nqqqq\n3.5\n");
+}
+
+#[test]
+fn test_hex_oct_bin() {
+    let source = r#"
+print(hex(255), bin(5), oct(8))
+print(hex(0), bin(-5), hex(-255), bin(True))
+def label(n):
+    return hex(n) + "/" + oct(n)
+print(label(4096), len(bin(255)))
+"#;
+    assert_eq!(
+        run_program(source),
+        "0xff 0b101 0o10\n0x0 -0b101 -0xff 0b1\n0x1000/0o10000 10\n"
+    );
+
+    let (stdout, stderr, status) = run_program_with_status("print(hex(1.5))\n");
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "TypeError: object cannot be interpreted as an integer\n"
+    );
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_strip_methods() {
+    let source = r#"
+s = "  hi  \t\n"
+print("[" + s.strip() + "]", "[" + s.lstrip() + "]")
+print("[" + s.rstrip() + "]", "[" + "   ".strip() + "]")
+def clean(text):
+    return text.strip()
+print("[" + clean("\r\n a b \f") + "]")
+"#;
+    assert_eq!(run_program(source), "[hi] [hi  \t\n]\n[  hi] []\n[a b]\n");
+
+    let (stdout, stderr, status) = run_program_with_status("x = 5\nprint(x.strip())\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "AttributeError: object has no attribute 'strip'\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_replace_method() {
+    let source = r#"
+print("aaa".replace("a", "b"))
+print("hello".replace("l", "L"))
+print("hello".replace("ll", ""), "hello".replace("x", "yy"), "aaaa".replace("aa", "b"))
+def fix(text):
+    return text.replace("-", " :: ")
+print(fix("a-b-c"))
+"#;
+    assert_eq!(
+        run_program(source),
+        "bbb\nheLLo\nheo hello bb\na :: b :: c\n"
+    );
+
+    let (stdout, stderr, status) = run_program_with_status("print(\"x\".replace(1, \"y\"))\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "TypeError: replace() arguments must be str\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_find_startswith_endswith() {
+    let source = r#"
+print("hello".find("ll"), "hello".find("z"), "hello".find(""))
+print("hello".startswith("he"), "hello".startswith("el"), "hi".startswith("hii"))
+print("hello".endswith("lo"), "hello".endswith("l"), "lo".endswith("hello"))
+"#;
+    assert_eq!(
+        run_program(source),
+        "2 -1 0\nTrue False False\nTrue False False\n"
+    );
+
+    let (stdout, stderr, status) = run_program_with_status("print(\"x\".find(3))\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "TypeError: find() argument must be str\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_split_and_index_the_pieces() {
+    let source = r#"
+print("a,b,c".split(",")[1])
+print(" x y ".strip().split(" ")[-1], len("ab-c".split("-")[0]))
+print("a::b::".split("::"), "".split(","), "abc".split("abc"))
+"#;
+    assert_eq!(
+        run_program(source),
+        "b\ny 2\n['a', 'b', ''] [''] ['', '']\n"
+    );
+
+    let (stdout, stderr, status) = run_program_with_status("print(\"x\".split(\"\"))\n");
+    assert_eq!(stdout, "");
+    assert_eq!(stderr, "ValueError: empty separator\n");
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_str_matches_printed_text() {
+    let source = r#"
+def f():
+    return 1
+
+for v in [2.5, True, None, [1, "a", (2.0,)], (), 7, "s", f]:
+    print(v)
+    print(str(v))
+print(str(10) + "!", len(str([1, 2])), len(str()))
+"#;
+    let mut expected = String::new();
+    for text in [
+        "2.5",
+        "True",
+        "None",
+        "[1, 'a', (2.0,)]",
+        "()",
+        "7",
+        "s",
+        "<function f>",
+    ] {
+        expected.push_str(&format!("{}\n{}\n", text, text));
+    }
+    expected.push_str("10! 6 0\n");
+    assert_eq!(run_program(source), expected);
+}
+
+#[test]
+fn test_count_substrings_and_elements() {
+    let source = r#"
+print("banana".count("a"))
+print([1, 2, 1, 1].count(1))
+print("aaaa".count("aa"), "abc".count(""), "".count("x"))
+print((1, 1.0, True, "x").count(1), ["a", "b", "a"].count("a"))
+"#;
+    assert_eq!(run_program(source), "3\n3\n2 4 0\n3 2\n");
+}
+
+#[test]
+fn test_print_writes_embedded_nul_bytes() {
+    let source = r#"
+print("a\0b")
+print("x\x00y", "z", sep="-")
+s = "abc"
+print(s, 1.5)
+"#;
+    assert_eq!(run_program(source), "a\0b\nx\0y-z\nabc 1.5\n");
+}
+
+#[test]
+fn test_returned_strings_outlive_the_call() {
+    // Both a literal and a string built in the function's own buffer stay valid in
+    // the caller, and concatenating them makes a new string
+    let source = r#"
+def greet():
+    return "hi"
+
+def digits(n):
+    out = ""
+    for i in range(n):
+        out = out + str(i)
+    return out
+
+print(greet() + "!")
+word = greet()
+print(word + " there", word)
+built = digits(4)
+print(built + digits(2) + greet(), built)
+"#;
+    assert_eq!(run_program(source), "hi!\nhi there hi\n012301hi 0123\n");
+}
@@ -367,3 +367,35 @@ while i < 3:
     let llvm_ir = compiler.compile_program(&ir).unwrap();
     insta::assert_snapshot!(llvm_ir);
 }
+
+#[test]
+fn test_literal_print_folds_into_single_printf() {
+    let source = r#"print("a", "b")"#;
+    let ast = parser::parse_program(source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    let context = Context::create();
+    let compiler = codegen::Compiler::new(&context);
+    let llvm_ir = compiler.compile_program(&ir).unwrap();
+    // The optimizer may turn the folded printf into puts; either way it is one call
+    assert_eq!(llvm_ir.matches(" call ").count(), 1);
+    assert!(llvm_ir.contains("a b"));
+}
+
+#[test]
+fn test_literal_argument_prints_without_tag_dispatch() {
+    let source = r#"
+def count(n):
+    return n + 1
+print("total:", count(2), "items")
+"#;
+    let ast = parser::parse_program(source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    let context = Context::create();
+    let compiler = codegen::Compiler::new(&context);
+    let llvm_ir = compiler.compile_program(&ir).unwrap();
+    // Each literal becomes its own format string, separator included (the optimizer
+    // may turn the last one into puts, which drops the newline)
+    assert!(llvm_ir.contains(r#"c"total: \00""#));
+    assert!(llvm_ir.contains(r#"c"items"#));
+    assert!(!llvm_ir.contains("string_format"));
+}
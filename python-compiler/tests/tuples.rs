@@ -0,0 +1,104 @@
+mod common;
+
+use common::{run_program, run_program_with_status};
+
+#[test]
+fn test_multiple_return_values() {
+    let source = r#"
+def divmod2(a, b):
+    return a // b, a % b
+q, r = divmod2(17, 5)
+print(q)
+print(r)
+"#;
+    assert_eq!(run_program(source), "3\n2\n");
+}
+
+#[test]
+fn test_print_and_len_of_tuples() {
+    let source = r#"
+t = (1, "a", 2.5)
+print(t)
+print(len(t))
+print((7,))
+print([(1, 2), (3, 4)])
+"#;
+    assert_eq!(
+        run_program(source),
+        "(1, 'a', 2.5)\n3\n(7,)\n[(1, 2), (3, 4)]\n"
+    );
+}
+
+#[test]
+fn test_unpack_swaps_and_lists() {
+    let source = r#"
+a = 1
+b = 2
+a, b = b, a
+print(a, b)
+x, y, z = [10, 20, 30]
+print(x + y + z)
+"#;
+    assert_eq!(run_program(source), "2 1\n60\n");
+}
+
+#[test]
+fn test_unpack_length_mismatch() {
+    let (stdout, stderr, status) = run_program_with_status("a, b = (1, 2, 3)\n");
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "ValueError: too many values to unpack (expected 2)\n"
+    );
+    assert_eq!(status, Some(1));
+
+    let (stdout, stderr, status) = run_program_with_status("a, b, c = (1, 2)\n");
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "ValueError: not enough values to unpack (expected 3)\n"
+    );
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_tuples_compare_lexicographically() {
+    let source = r#"
+print((1, 2) < (1, 3))
+print((1, 2) == (1, 2))
+print((1, 2) != (1, 2), (1, 2) < (1, 2, 0), (2,) > (1, 9), () < (1,))
+print((1, "b") < (1, "c"), ("a", (1, 2)) == ("a", (1, 2)), (1, 2.0) == (1.0, 2))
+print((1, "a") == (1, 2), (1, 2) <= (1, 2), (3, 1) >= (3, 2))
+"#;
+    assert_eq!(
+        run_program(source),
+        "True\nTrue\nFalse True True True\nTrue True True\nFalse True False\n"
+    );
+
+    let (stdout, stderr, status) = run_program_with_status("print((1, \"a\") < (1, 2))\n");
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "TypeError: '<' not supported between instances of different types\n"
+    );
+    assert_eq!(status, Some(1));
+}
+
+#[test]
+fn test_tuple_indexing_and_immutability() {
+    let source = r#"
+t = (10, 20, 30)
+print(t[1])
+print(t[-1], t[0] + t[2])
+"#;
+    assert_eq!(run_program(source), "20\n30 40\n");
+
+    let (stdout, stderr, status) =
+        run_program_with_status("t = (10, 20, 30)\nt[0] = 5\nprint(t)\n");
+    assert_eq!(stdout, "");
+    assert_eq!(
+        stderr,
+        "TypeError: 'tuple' object does not support item assignment\n"
+    );
+    assert_eq!(status, Some(1));
+}
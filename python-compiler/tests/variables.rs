@@ -33,3 +33,14 @@ fn test_reassignment() {
     let llvm_ir = compiler.compile_program(&ir).unwrap();
     insta::assert_snapshot!(llvm_ir);
 }
+
+#[test]
+fn test_constant_variable_folds_into_print() {
+    let source = "x = 5\nprint(x + 1)";
+    let ast = parser::parse_program(source).unwrap();
+    let ir = lowering::lower_program(&ast).unwrap();
+    let context = Context::create();
+    let compiler = codegen::Compiler::new(&context);
+    let llvm_ir = compiler.compile_program(&ir).unwrap();
+    assert!(llvm_ir.contains("@printf(ptr nonnull @int_format_string, i64 6)"));
+}